@@ -0,0 +1,250 @@
+use std::error::Error;
+
+use btleplug::{
+    api::{Central, Manager as _},
+    platform::{Adapter, Manager},
+};
+use serde::Serialize;
+
+use crate::{cli::AdaptersArgs, error::BluscanError, oneshot::format_table};
+
+/// An adapter as `btlescan adapters` lists it. Capabilities the platform does not report
+/// are `None`.
+#[derive(Serialize)]
+struct AdapterSummary {
+    /// The adapter's position in `platform_adapters`.
+    index: usize,
+    identifier: String,
+    address: Option<String>,
+    powered: Option<bool>,
+    br_edr: Option<bool>,
+    le: Option<bool>,
+}
+
+/// Returns the adapters btleplug can use, in the order the platform lists them.
+pub async fn platform_adapters() -> Result<Vec<Adapter>, btleplug::Error> {
+    Manager::new().await?.adapters().await
+}
+
+/// Returns the first of `platform_adapters`, which `--auto` connects with.
+pub async fn default_adapter() -> Result<Adapter, BluscanError> {
+    adapter(0).await
+}
+
+/// Returns the adapter at `index` in `platform_adapters`.
+pub async fn adapter(index: usize) -> Result<Adapter, BluscanError> {
+    platform_adapters()
+        .await
+        .map_err(BluscanError::Adapter)?
+        .into_iter()
+        .nth(index)
+        .ok_or(BluscanError::NoAdapter)
+}
+
+/// Runs `btlescan adapters`: prints the adapters as a table, or as a JSON array with
+/// `--json`. Having no adapter is not an error.
+pub async fn run(args: &AdaptersArgs) -> Result<(), Box<dyn Error>> {
+    let mut adapters = Vec::new();
+    for (index, adapter) in platform_adapters().await?.into_iter().enumerate() {
+        adapters.push(AdapterSummary {
+            index,
+            identifier: adapter.adapter_info().await?,
+            address: None,
+            powered: None,
+            br_edr: None,
+            le: None,
+        });
+    }
+    #[cfg(target_os = "linux")]
+    describe_bluez(&mut adapters).await;
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&adapters)?);
+    } else if adapters.is_empty() {
+        println!("No adapters found.");
+    } else {
+        let flag = |value: Option<bool>| match value {
+            Some(true) => "yes",
+            Some(false) => "no",
+            None => "unknown",
+        };
+        let rows: Vec<Vec<String>> = adapters
+            .iter()
+            .map(|adapter| {
+                vec![
+                    adapter.index.to_string(),
+                    adapter.identifier.clone(),
+                    adapter.address.clone().unwrap_or_else(|| "n/a".to_string()),
+                    flag(adapter.powered).to_string(),
+                    flag(adapter.br_edr).to_string(),
+                    flag(adapter.le).to_string(),
+                ]
+            })
+            .collect();
+        print!(
+            "{}",
+            format_table(
+                &["Index", "Identifier", "Address", "Powered", "BR/EDR", "LE"],
+                &rows
+            )
+        );
+    }
+    Ok(())
+}
+
+/// Fills in what BlueZ knows about each adapter: btleplug identifies them as
+/// `hci0 (<modalias>)`, which is shortened to `hci0`, and BlueZ has their address and
+/// powered state. The supported transports come from the kernel's management interface.
+#[cfg(target_os = "linux")]
+async fn describe_bluez(adapters: &mut [AdapterSummary]) {
+    let bluez = match bluez_async::BluetoothSession::new().await {
+        Ok((_, session)) => session.get_adapters().await.unwrap_or_default(),
+        Err(_) => Vec::new(),
+    };
+    for adapter in adapters {
+        let Some(id) = adapter
+            .identifier
+            .split_whitespace()
+            .next()
+            .map(str::to_string)
+        else {
+            continue;
+        };
+        if let Some(info) = bluez.iter().find(|info| info.id.to_string() == id) {
+            adapter.address = Some(info.mac_address.to_string());
+            adapter.powered = Some(info.powered);
+        }
+        let transports = id
+            .strip_prefix("hci")
+            .and_then(|index| index.parse().ok())
+            .and_then(|index| mgmt::supported_transports(index).ok());
+        if let Some((br_edr, le)) = transports {
+            adapter.br_edr = Some(br_edr);
+            adapter.le = Some(le);
+        }
+        adapter.identifier = id;
+    }
+}
+
+/// A minimal client of the Linux Bluetooth management interface, which reports what a
+/// controller supports where BlueZ's D-Bus API does not.
+#[cfg(target_os = "linux")]
+mod mgmt {
+    use std::{
+        fs::File,
+        io::{self, Read, Write},
+        os::fd::{FromRawFd, OwnedFd},
+    };
+
+    const AF_BLUETOOTH: libc::c_int = 31;
+    const BTPROTO_HCI: libc::c_int = 1;
+    const HCI_DEV_NONE: u16 = 0xffff;
+    const HCI_CHANNEL_CONTROL: u16 = 3;
+    const MGMT_OP_READ_INFO: u16 = 0x0004;
+    const MGMT_EV_CMD_COMPLETE: u16 = 0x0001;
+    const MGMT_EV_CMD_STATUS: u16 = 0x0002;
+    const SETTING_BREDR: u32 = 1 << 7;
+    const SETTING_LE: u32 = 1 << 9;
+
+    /// The offset of the supported settings in a Read Controller Information reply: after
+    /// the event header (6 bytes), the opcode and status (3), the address (6), the version
+    /// (1) and the manufacturer (2).
+    const SUPPORTED_SETTINGS_OFFSET: usize = 18;
+
+    /// How many unrelated events to skip while waiting for the reply, since every
+    /// management event is delivered to every control socket.
+    const MAX_EVENTS: usize = 64;
+
+    #[repr(C)]
+    struct SockaddrHci {
+        hci_family: libc::sa_family_t,
+        hci_dev: u16,
+        hci_channel: u16,
+    }
+
+    /// Returns whether the controller `hci<index>` supports BR/EDR and LE.
+    pub fn supported_transports(index: u16) -> io::Result<(bool, bool)> {
+        let mut socket = open_control_socket()?;
+        let mut request = Vec::with_capacity(6);
+        request.extend_from_slice(&MGMT_OP_READ_INFO.to_le_bytes());
+        request.extend_from_slice(&index.to_le_bytes());
+        request.extend_from_slice(&0u16.to_le_bytes());
+        socket.write_all(&request)?;
+        let mut buffer = [0u8; 512];
+        for _ in 0..MAX_EVENTS {
+            let length = socket.read(&mut buffer)?;
+            let event = &buffer[..length];
+            if length < 9 || u16::from_le_bytes([event[2], event[3]]) != index {
+                continue;
+            }
+            let code = u16::from_le_bytes([event[0], event[1]]);
+            let opcode = u16::from_le_bytes([event[6], event[7]]);
+            if opcode != MGMT_OP_READ_INFO
+                || (code != MGMT_EV_CMD_COMPLETE && code != MGMT_EV_CMD_STATUS)
+            {
+                continue;
+            }
+            let status = event[8];
+            if code == MGMT_EV_CMD_STATUS || status != 0 || length < SUPPORTED_SETTINGS_OFFSET + 4 {
+                return Err(io::Error::other(format!(
+                    "controller information unavailable (status {})",
+                    status
+                )));
+            }
+            let settings = u32::from_le_bytes(
+                event[SUPPORTED_SETTINGS_OFFSET..SUPPORTED_SETTINGS_OFFSET + 4]
+                    .try_into()
+                    .unwrap(),
+            );
+            return Ok((settings & SETTING_BREDR != 0, settings & SETTING_LE != 0));
+        }
+        Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "no reply from the management interface",
+        ))
+    }
+
+    /// Opens a socket on the management channel, reads from which time out after a second.
+    fn open_control_socket() -> io::Result<File> {
+        // SAFETY: plain socket calls on a descriptor owned by this function; the address and
+        // timeout outlive the calls they are passed to.
+        unsafe {
+            let fd = libc::socket(
+                AF_BLUETOOTH,
+                libc::SOCK_RAW | libc::SOCK_CLOEXEC,
+                BTPROTO_HCI,
+            );
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let socket = OwnedFd::from_raw_fd(fd);
+            let address = SockaddrHci {
+                hci_family: AF_BLUETOOTH as libc::sa_family_t,
+                hci_dev: HCI_DEV_NONE,
+                hci_channel: HCI_CHANNEL_CONTROL,
+            };
+            if libc::bind(
+                fd,
+                &address as *const SockaddrHci as *const libc::sockaddr,
+                std::mem::size_of::<SockaddrHci>() as libc::socklen_t,
+            ) < 0
+            {
+                return Err(io::Error::last_os_error());
+            }
+            let timeout = libc::timeval {
+                tv_sec: 1,
+                tv_usec: 0,
+            };
+            if libc::setsockopt(
+                fd,
+                libc::SOL_SOCKET,
+                libc::SO_RCVTIMEO,
+                &timeout as *const libc::timeval as *const libc::c_void,
+                std::mem::size_of::<libc::timeval>() as libc::socklen_t,
+            ) < 0
+            {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(File::from(socket))
+        }
+    }
+}
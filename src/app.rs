@@ -1,99 +1,2549 @@
 use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     error::Error,
+    net::SocketAddr,
+    path::{Path, PathBuf},
     sync::{
         atomic::{AtomicBool, Ordering},
-        Arc,
+        Arc, Mutex,
     },
+    time::{Duration, Instant},
 };
 
-use ratatui::widgets::TableState;
-use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use btleplug::api::ScanFilter;
+use ratatui::{style::Color, widgets::TableState};
+use tokio::{
+    sync::{
+        mpsc::{self, UnboundedReceiver, UnboundedSender},
+        oneshot, Notify,
+    },
+    task::JoinHandle,
+};
+use uuid::Uuid;
 
+#[cfg(feature = "db")]
+use crate::db::DeviceDb;
+#[cfg(feature = "influx")]
+use crate::influx::InfluxWriter;
+#[cfg(feature = "mqtt")]
+use crate::mqtt::MqttPublisher;
+#[cfg(feature = "websocket")]
+use crate::ws::WsHub;
 use crate::{
-    scan::{bluetooth_scan, get_characteristics},
-    structs::{Characteristic, DeviceCsv, DeviceInfo},
+    capture::CaptureWriter,
+    category::classify,
+    channel_health::ChannelHealth,
+    cli::Args,
+    commands::{self, ExportTarget},
+    decoders::{
+        device_tags,
+        exposure::{decode_exposure_notification, EXPOSURE_NOTIFICATION_UUID},
+        trackers::detect_tracker,
+        DecoderRegistry, TAGS,
+    },
+    error::BluscanError,
+    event_log::EventLog,
+    intake::Intake,
+    inventory::{run_inventory, Inventory, InventoryEntry},
+    known_devices::KnownDevices,
+    line_format::LineFormat,
+    oneshot::devices_text,
+    pause_changes::{PauseChange, PauseChanges, PauseSnapshot},
+    redact::Redactor,
+    rssi_history::RssiHistory,
+    scan::{adapter_addresses, get_characteristics},
+    scanner::ScanConfig,
+    session::SessionRecorder,
+    source::{BtleplugSource, DeviceSource, ReplaySource, ScanCommand},
+    state::{load_state, save_state},
+    structs::{
+        AddressTypeFilter, Characteristic, CharacteristicJson, ConnectProgress, CsvColumn,
+        DeviceCategory, DeviceDetailJson, DeviceExport, DeviceHistory, DeviceInfo, DeviceJson,
+        EvictionPolicy, ExportFilters, ExportSession, IdentityDisplay, IdentityKey, MqttStatus,
+        NameFallback, PersistedState, ProximityFeedback, ProximityZone, RssiBarStyle,
+        RssiSampleJson, RssiStats, SeenDevice, SessionMode, SortKey, TrackedDevice,
+        EXPORT_SCHEMA_VERSION,
+    },
+    syslog::{PresenceEvent, Priority, SyslogWriter},
+    utils::{
+        bytes_to_hex, company_name, device_zone, extract_manufacturer_data, format_reading,
+        normalize_address, pulse_interval,
+    },
+    watch::{AlertKind, WatchList},
 };
 
+/// The default background of striped rows: a dark grey that stays subtle on dark themes.
+pub const DEFAULT_STRIPE_COLOR: Color = Color::Rgb(0x26, 0x26, 0x26);
+
+/// The lowest value the minimum RSSI threshold can be set to before it turns off.
+pub const MIN_RSSI_FLOOR: i16 = -100;
+
+/// How long the capped indicator stays up after the device cap last evicted a device.
+pub const CAP_INDICATOR_DURATION: Duration = Duration::from_secs(10);
+
+/// How long a status message stays in the status bar.
+pub const STATUS_DURATION: Duration = Duration::from_secs(10);
+
+/// How long a device may go without advertising before it is considered stale.
+pub const STALE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The number of manufacturers shown in the chip bar.
+pub const MAX_CHIPS: usize = 8;
+
+/// The weight of each new reading in the tracked device's smoothed RSSI.
+const RSSI_SMOOTHING: f64 = 0.3;
+
+/// How long the proximity gauge stays highlighted after a pulse.
+pub const PULSE_FLASH_DURATION: Duration = Duration::from_millis(150);
+
+/// A request from the HTTP API, which the application answers from its current state.
+// Without the `http-api` feature nothing sends requests, though the application still
+// listens for them.
+#[cfg_attr(not(feature = "http-api"), allow(dead_code))]
+pub enum ApiRequest {
+    /// Every device in the list.
+    Devices,
+    /// One device by address or platform id, with its history.
+    Device(String),
+    /// Pauses or resumes the scan.
+    SetPaused(bool),
+}
+
+/// A request sent to the application, with the channel its answer goes back on. The answer
+/// is the JSON body, or `None` when the requested device is not in the list.
+pub struct ApiQuery {
+    pub request: ApiRequest,
+    pub reply: oneshot::Sender<Option<Vec<u8>>>,
+}
+
 pub enum DeviceData {
-    DeviceInfo(DeviceInfo),
-    #[allow(dead_code)]
+    /// Scan results coalesced over one window, with the number of observations they merge.
+    DeviceBatch {
+        devices: Vec<DeviceInfo>,
+        observations: usize,
+    },
     Characteristics(Vec<Characteristic>),
+    /// The result of restarting the scan with a new filter.
+    ScanReconfigured(Result<(), String>),
+    /// The result of a disconnect-all request: the identities of the devices it tore down,
+    /// and those it could not disconnect with the error.
+    Disconnected {
+        disconnected: Vec<String>,
+        failed: Vec<String>,
+    },
+    /// The progress of connecting to a device.
+    Connecting(ConnectProgress),
+    /// Connecting to a device failed after every attempt.
+    ConnectFailed(String),
+    /// An inventory run started reading the device with this identity.
+    InventoryReading(String),
+    /// What an inventory run read from a device.
+    InventoryEntry(Box<InventoryEntry>),
+    /// An inventory run finished or was cancelled.
+    InventoryDone,
+    /// A replay ran out of events, having skipped the lines it could not parse.
+    ReplayFinished {
+        replayed: usize,
+        skipped: usize,
+    },
+    /// A watch-list alert that could not be shown as a desktop notification.
+    WatchAlert(String),
+    /// A WebSocket client connected or disconnected.
+    WsClient {
+        peer: SocketAddr,
+        connected: bool,
+    },
+    /// A change in the connection to the MQTT broker.
+    MqttStatus(MqttStatus),
+    /// The scan could not start or ended with an error; nothing more will arrive from it.
+    ScanFailed(BluscanError),
     Error(String),
 }
 
-#[allow(dead_code)]
 pub struct App {
     pub rx: UnboundedReceiver<DeviceData>,
     pub tx: UnboundedSender<DeviceData>,
-    pub loading_status: Arc<AtomicBool>,
     pub pause_status: Arc<AtomicBool>,
+    /// The adapter, coalescing window and services the scan runs with.
+    pub scan_config: ScanConfig,
+    /// The key devices are merged and remembered by, from `scan_config`.
+    pub identity_key: IdentityKey,
+    pub scan_shutdown: Arc<Notify>,
+    pub scan_task: Option<JoinHandle<()>>,
+    pub scan_commands: Option<UnboundedSender<ScanCommand>>,
+    pub service_filter_enabled: bool,
+    pub reconfiguring: bool,
+    pub observations: usize,
+    /// How long the scan may run without results before a warning is shown.
+    pub watchdog: Option<Duration>,
+    /// When the scan was last started or resumed.
+    pub watching_since: Instant,
+    pub last_batch: Option<Instant>,
+    /// What the interface drains from the scan channel, for the status bar.
+    pub channel_health: ChannelHealth,
+    /// How long the interface may go without a key press before the scan is paused.
+    pub idle_pause: Option<Duration>,
+    pub last_input: Instant,
+    /// Whether the scan was paused for being idle rather than by the user.
+    pub idle_paused: bool,
+    /// The device list as it was when the scan was paused, while it is.
+    pub pause_snapshot: Option<PauseSnapshot>,
+    /// What changed during the last pause, shown for a few seconds after resuming.
+    pub pause_changes: Option<PauseChanges>,
+    /// The speed of the replay driving the application, if any.
+    pub replay_speed: Option<f64>,
+    pub replay_finished: bool,
     pub table_state: TableState,
     pub devices: Vec<DeviceInfo>,
+    pub rssi_stats: HashMap<String, RssiStats>,
+    /// Every RSSI reading of the session, kept when RSSI history is enabled.
+    pub rssi_history: Option<RssiHistory>,
+    /// The names each device advertised before its current one, oldest first.
+    pub name_history: HashMap<String, Vec<String>>,
+    /// The devices whose name changed since the change was last acknowledged.
+    pub renamed_devices: HashSet<String>,
+    /// How many previous names are kept per device; 0 turns the detector off.
+    pub name_history_cap: usize,
+    pub marked_devices: HashSet<String>,
     pub inspect_view: bool,
+    pub raw_view: bool,
+    pub stats_view: bool,
+    pub session_start: Instant,
+    pub session_started_at: chrono::DateTime<chrono::Local>,
+    pub adapter: Option<String>,
+    pub session_mode: SessionMode,
+    pub max_devices: Option<usize>,
+    pub eviction_policy: EvictionPolicy,
+    pub capped_evictions: usize,
+    pub last_capped: Option<Instant>,
+    /// The latest status message and when it was set.
+    pub status: Option<(String, Instant)>,
+    pub name_fallback: NameFallback,
+    pub identity_display: IdentityDisplay,
+    pub tag_filter: Option<&'static str>,
+    pub category_filter: Option<DeviceCategory>,
+    pub manufacturer_filter: Option<u16>,
+    pub address_type_filter: AddressTypeFilter,
+    pub chip_cursor: usize,
+    pub category_popup: Option<usize>,
+    pub min_rssi: Option<i16>,
+    pub group_by_zone: bool,
+    pub sort_key: SortKey,
+    /// Whether the table is sorted against the sort key's natural order.
+    pub sort_reversed: bool,
+    /// Only devices whose name, alias, address or platform id contain this text, ignoring
+    /// case, are shown.
+    pub search: Option<String>,
+    /// The search being typed, opened with `/`.
+    pub search_input: Option<String>,
+    /// The command being typed, opened with `:`.
+    pub command_input: Option<String>,
+    /// Why the last command failed or a key was refused, shown in the status line until the
+    /// next key press.
+    pub command_error: Option<String>,
+    pub named_only: bool,
+    /// Whether the detail pane is hidden to give the device table the room.
+    pub compact: bool,
+    /// Whether the address column also shows the vendor the address is assigned to.
+    pub show_vendor: bool,
+    /// Whether signal strength is shown as a percentage rather than in dBm.
+    pub rssi_percent: bool,
+    /// Whether names advertised by more than one listed device are marked, on top of the
+    /// short hash that tells them apart.
+    pub flag_duplicate_names: bool,
+    /// The identity of the highlighted device, so the highlight follows it when the table
+    /// is reordered.
+    pub selected_identity: Option<String>,
+    pub striped: bool,
+    pub stripe_color: Color,
+    pub rssi_bar: RssiBarStyle,
+    pub tracked: Option<TrackedDevice>,
+    /// The identity of the device pinned for comparison with the highlighted one.
+    pub compare_pin: Option<String>,
+    pub proximity_feedback: ProximityFeedback,
+    /// Every device seen during the session by identity, for the statistics. Eviction and
+    /// the device cap leave it alone.
+    pub session_seen: HashMap<String, SeenDevice>,
+    pub exposure_rpis: HashSet<[u8; 16]>,
+    pub trackers: HashSet<String>,
+    pub excluded_addresses: HashSet<String>,
+    pub exclude_self: bool,
+    /// Set with --redact to hide addresses, and optionally names, from display and exports.
+    pub redactor: Option<Redactor>,
+    pub state: PersistedState,
+    /// Set with --known-devices to read aliases and categories from a file.
+    pub known_devices: Option<KnownDevices>,
+    pub decoders: DecoderRegistry,
+    pub csv_columns: Vec<CsvColumn>,
+    pub session_recorder: Option<Arc<Mutex<SessionRecorder>>>,
+    /// Shared with the intake, which writes the device updates to it.
+    pub event_log: Option<Arc<Mutex<EventLog>>>,
+    /// The capture being recorded, toggled with `R`.
+    pub capture: Option<CaptureWriter>,
+    /// Where captures are recorded; a timestamped file in the current directory when unset.
+    pub capture_path: Option<PathBuf>,
+    #[cfg(feature = "db")]
+    pub db: Option<DeviceDb>,
+    #[cfg(feature = "mqtt")]
+    pub mqtt: Option<MqttPublisher>,
+    #[cfg(feature = "influx")]
+    pub influx: Option<InfluxWriter>,
+    /// The `--format` template the headless and one-shot modes print devices with.
+    pub line_format: Option<LineFormat>,
+    pub mqtt_status: Option<MqttStatus>,
+    /// The devices last announced as online over MQTT.
+    #[cfg(feature = "mqtt")]
+    pub mqtt_online: HashSet<String>,
+    #[cfg(feature = "websocket")]
+    pub ws: Option<WsHub>,
+    /// The number of connected WebSocket clients, or `None` without a WebSocket feed.
+    pub ws_clients: Option<usize>,
+    /// What the database knew about each device of the session before it was first seen
+    /// in it; `None` for devices it did not know.
+    pub db_history: HashMap<String, Option<DeviceHistory>>,
+    /// The updates merged from the current batch, written to the database together once
+    /// it is merged.
+    #[cfg(feature = "db")]
+    pub db_updates: Vec<(DeviceInfo, bool)>,
+    /// Whether the last database write failed, so a failure is reported only once.
+    #[cfg(feature = "db")]
+    pub db_failing: bool,
+    /// Requests from the HTTP API, answered between frames.
+    pub api_queries: Option<UnboundedReceiver<ApiQuery>>,
+    /// Whether the last event log write failed, so a failure is reported only once.
+    pub event_log_failing: bool,
+    pub alias_input: Option<String>,
+    pub note_input: Option<String>,
     pub inspect_overlay_scroll: usize,
+    /// Whether the full service list of the selected device is shown over the tables.
+    pub services_view: bool,
+    pub services_scroll: usize,
     pub selected_characteristics: Vec<Characteristic>,
+    /// The identity of the device last connected to, which the characteristics belong to.
+    pub connected_identity: Option<String>,
     pub frame_count: usize,
+    /// Whether the terminal window is focused. Terminals that do not report focus changes
+    /// are always treated as focused.
+    pub focused: bool,
     pub is_loading: bool,
+    pub connect_timeout: Duration,
+    pub connect_retries: u32,
+    pub connect_progress: Option<ConnectProgress>,
+    pub watch_list: WatchList,
+    /// Whether watch-list alerts are shown as desktop notifications.
+    #[cfg(feature = "notifications")]
+    pub desktop_notify: bool,
+    pub syslog: Option<SyslogWriter>,
+    pub syslog_failing: bool,
+    /// Whether the scan was paused before connecting, to restore it if connecting fails.
+    pub paused_before_connect: bool,
+    pub inventory: Option<Inventory>,
+    #[cfg(feature = "clipboard")]
+    pub clipboard: Option<arboard::Clipboard>,
+    /// Where inventory reports are written; a timestamped file in the current directory
+    /// when unset.
+    pub inventory_out: Option<PathBuf>,
     pub error_view: bool,
     pub error_message: String,
 }
 
 impl App {
-    pub fn new() -> Self {
+    pub fn new(args: &Args, scan_config: ScanConfig, decoders: DecoderRegistry) -> Self {
         let (tx, rx) = mpsc::unbounded_channel();
+        let redactor = (args.redact || args.redact_names).then(|| Redactor::new(args.redact_names));
+        // Devices are redacted as they arrive, so the watch list has to be as well.
+        let watch_list = WatchList::new(
+            args.watch.iter().map(|address| match &redactor {
+                Some(redactor) => redactor.address(address),
+                None => address.clone(),
+            }),
+            args.watch_rssi,
+        );
         Self {
             tx,
             rx,
-            loading_status: Arc::new(AtomicBool::default()),
             pause_status: Arc::new(AtomicBool::default()),
+            service_filter_enabled: !scan_config.services().is_empty(),
+            min_rssi: scan_config.min_rssi(),
+            identity_key: scan_config.identity(),
+            scan_config,
+            scan_shutdown: Arc::new(Notify::new()),
+            scan_task: None,
+            scan_commands: None,
+            reconfiguring: false,
+            observations: 0,
+            watchdog: (!args.watchdog.is_zero()).then_some(args.watchdog),
+            idle_pause: args.idle_pause,
+            last_input: Instant::now(),
+            idle_paused: false,
+            pause_snapshot: None,
+            pause_changes: None,
+            replay_speed: None,
+            replay_finished: false,
+            watching_since: Instant::now(),
+            last_batch: None,
+            channel_health: ChannelHealth::default(),
             table_state: TableState::default(),
             devices: Vec::new(),
+            rssi_stats: HashMap::new(),
+            rssi_history: args.rssi_history.map(RssiHistory::new),
+            name_history: HashMap::new(),
+            renamed_devices: HashSet::new(),
+            name_history_cap: args.name_history,
+            marked_devices: HashSet::new(),
             inspect_view: false,
+            raw_view: false,
+            stats_view: false,
+            session_start: Instant::now(),
+            session_started_at: chrono::Local::now(),
+            adapter: None,
+            session_mode: args.session_mode,
+            max_devices: args.max_devices,
+            eviction_policy: args.eviction_policy,
+            capped_evictions: 0,
+            last_capped: None,
+            status: None,
+            name_fallback: NameFallback::default(),
+            identity_display: IdentityDisplay::default(),
+            tag_filter: None,
+            address_type_filter: AddressTypeFilter::All,
+            category_filter: None,
+            manufacturer_filter: None,
+            chip_cursor: 0,
+            category_popup: None,
+            group_by_zone: false,
+            sort_key: args.sort.key,
+            sort_reversed: args.sort.reversed,
+            search: args.filter.clone().filter(|query| !query.is_empty()),
+            search_input: None,
+            command_input: None,
+            command_error: None,
+            named_only: args.named_only,
+            compact: args.compact,
+            show_vendor: args.show_vendor,
+            rssi_percent: args.rssi_percent,
+            flag_duplicate_names: args.flag_duplicate_names,
+            selected_identity: None,
+            striped: args.stripe_color.is_some(),
+            stripe_color: args.stripe_color.unwrap_or(DEFAULT_STRIPE_COLOR),
+            rssi_bar: args.rssi_bar,
+            tracked: None,
+            compare_pin: None,
+            proximity_feedback: ProximityFeedback::default(),
+            session_seen: HashMap::new(),
+            exposure_rpis: HashSet::new(),
+            trackers: HashSet::new(),
+            excluded_addresses: args
+                .excluded_addresses
+                .iter()
+                .filter_map(|address| normalize_address(address))
+                .collect(),
+            exclude_self: !args.include_self,
+            redactor,
+            state: load_state(),
+            known_devices: None,
+            decoders,
+            csv_columns: args.csv_columns.clone(),
+            session_recorder: None,
+            event_log: None,
+            capture: None,
+            capture_path: args.record.clone(),
+            #[cfg(feature = "db")]
+            db: None,
+            #[cfg(feature = "mqtt")]
+            mqtt: None,
+            #[cfg(feature = "influx")]
+            influx: None,
+            line_format: None,
+            mqtt_status: None,
+            #[cfg(feature = "mqtt")]
+            mqtt_online: HashSet::new(),
+            #[cfg(feature = "websocket")]
+            ws: None,
+            ws_clients: None,
+            db_history: HashMap::new(),
+            #[cfg(feature = "db")]
+            db_updates: Vec::new(),
+            #[cfg(feature = "db")]
+            db_failing: false,
+            api_queries: None,
+            event_log_failing: false,
+            alias_input: None,
+            note_input: None,
             inspect_overlay_scroll: 0,
+            services_view: false,
+            services_scroll: 0,
             selected_characteristics: Vec::new(),
+            connected_identity: None,
             frame_count: 0,
+            focused: true,
             is_loading: false,
+            connect_timeout: args.connect_timeout,
+            connect_retries: args.connect_retries,
+            connect_progress: None,
+            paused_before_connect: false,
+            watch_list,
+            #[cfg(feature = "notifications")]
+            desktop_notify: args.desktop_notify,
+            syslog: None,
+            syslog_failing: false,
+            inventory: None,
+            #[cfg(feature = "clipboard")]
+            clipboard: None,
+            inventory_out: args.inventory_out.clone(),
             error_view: false,
             error_message: String::new(),
         }
     }
 
     pub async fn scan(&mut self) {
+        let adapters = adapter_addresses().await;
+        self.adapter =
+            adapters
+                .get(self.scan_config.adapter())
+                .map(|address| match &self.redactor {
+                    Some(redactor) => redactor.address(address),
+                    None => address.clone(),
+                });
+        if self.exclude_self {
+            self.excluded_addresses.extend(
+                adapters
+                    .iter()
+                    .filter_map(|address| normalize_address(address)),
+            );
+        }
+        self.scan_with(Box::new(BtleplugSource {
+            filter: self.scan_filter(),
+            ..self.scan_config.source()
+        }));
+    }
+
+    /// Drives the application from a recorded event log instead of the adapter. The
+    /// watchdog is turned off, since a quiet stretch in a recording says nothing about the
+    /// adapter.
+    pub fn replay(&mut self, source: ReplaySource, speed: f64) {
+        self.replay_speed = Some(speed);
+        self.watchdog = None;
+        self.scan_with(Box::new(source));
+    }
+
+    /// Reports the end of a replay in the notification overlay.
+    pub fn finish_replay(&mut self, replayed: usize, skipped: usize) {
+        self.replay_finished = true;
+        self.error_message = format!(
+            "Replay finished: {} updates replayed, {} malformed line(s) skipped.",
+            replayed, skipped
+        );
+        self.error_view = true;
+    }
+
+    /// Returns the platform-level filter the scan should run with.
+    pub fn scan_filter(&self) -> ScanFilter {
+        ScanFilter {
+            services: if self.service_filter_enabled {
+                self.scan_config.services().to_vec()
+            } else {
+                Vec::new()
+            },
+        }
+    }
+
+    /// Turns the service filter on or off and restarts the scan with the new platform
+    /// filter. The device list is kept.
+    pub fn toggle_service_filter(&mut self) {
+        if self.scan_config.services().is_empty() {
+            self.error_message =
+                "No services to filter by; start btlescan with --service <UUID>.".to_string();
+            self.error_view = true;
+            return;
+        }
+        self.service_filter_enabled = !self.service_filter_enabled;
+        if let Some(commands) = &self.scan_commands {
+            if commands
+                .send(ScanCommand::Restart(self.scan_filter()))
+                .is_ok()
+            {
+                self.reconfiguring = true;
+            }
+        }
+    }
+
+    /// Starts receiving scan results from `source`, through an intake that drops the
+    /// excluded addresses, redacts the rest and writes them to the event log.
+    pub fn scan_with(&mut self, source: Box<dyn DeviceSource>) {
+        let source = Box::new(Intake {
+            source,
+            excluded_addresses: self.excluded_addresses.clone(),
+            redactor: self.redactor.clone(),
+            event_log: self.event_log.clone(),
+            identity: self.identity_key,
+        });
         let pause_signal_clone = Arc::clone(&self.pause_status);
         let tx_clone = self.tx.clone();
-        tokio::spawn(async move { bluetooth_scan(tx_clone, pause_signal_clone).await });
+        let shutdown = Arc::clone(&self.scan_shutdown);
+        let (commands_tx, commands_rx) = mpsc::unbounded_channel();
+        self.watching_since = Instant::now();
+        self.last_batch = None;
+        self.scan_commands = Some(commands_tx);
+        self.scan_task = Some(tokio::spawn(source.run(
+            tx_clone,
+            pause_signal_clone,
+            shutdown,
+            commands_rx,
+        )));
     }
 
-    pub async fn connect(&mut self) {
-        let selected_device = self
+    /// Stops the scan and merges the results it had not sent yet, so the final device
+    /// list is complete.
+    pub async fn stop_scan(&mut self) {
+        self.scan_shutdown.notify_one();
+        // A paused scan is blocked before it can see the shutdown, so resume it first.
+        self.pause_status.store(false, Ordering::SeqCst);
+        if let Some(task) = self.scan_task.take() {
+            let _ = task.await;
+        }
+        while let Ok(data) = self.rx.try_recv() {
+            if let DeviceData::DeviceBatch {
+                devices,
+                observations,
+            } = data
+            {
+                self.merge_batch(devices, observations);
+            }
+        }
+        self.flush_capture(true);
+        #[cfg(feature = "influx")]
+        if let Some(influx) = self.influx.take() {
+            influx.close().await;
+        }
+    }
+
+    /// Flushes the buffered capture lines to the file. Unless `force` is set, they are only
+    /// flushed once the flush interval has passed.
+    pub fn flush_capture(&mut self, force: bool) {
+        if let Some(capture) = self.capture.as_mut() {
+            let result = if force {
+                capture.flush()
+            } else {
+                capture.flush_if_due()
+            };
+            if let Err(e) = result {
+                self.stop_capture_on_error(e);
+            }
+        }
+    }
+
+    /// Starts recording a capture, or stops the one being recorded.
+    pub fn toggle_capture(&mut self) {
+        if let Some(mut capture) = self.capture.take() {
+            self.error_message = match capture.flush() {
+                Ok(()) => format!("Capture saved to {}", capture.path.display()),
+                Err(e) => format!("Unable to write the capture: {}", e),
+            };
+            self.error_view = true;
+            return;
+        }
+        let path = self.capture_path.clone().unwrap_or_else(|| {
+            let timestamp = chrono::Local::now().format("%Y-%m-%d_%H-%M-%S");
+            PathBuf::from(format!("btlescan_capture_{}.ndjson", timestamp))
+        });
+        match CaptureWriter::open(&path) {
+            Ok(capture) => self.capture = Some(capture),
+            Err(e) => {
+                self.error_message = format!("Unable to open {}: {}", path.display(), e);
+                self.error_view = true;
+            }
+        }
+    }
+
+    /// Stops recording after a write failure and shows it in the notification overlay.
+    fn stop_capture_on_error(&mut self, error: std::io::Error) {
+        self.capture = None;
+        self.error_message = format!("Recording stopped, unable to write the capture: {}", error);
+        self.error_view = true;
+    }
+
+    /// Shows an event log write failure in the notification overlay. Scanning carries on,
+    /// and the failure is reported again only after a write has succeeded in between.
+    fn report_event_log(&mut self, result: std::io::Result<()>) {
+        match result {
+            Ok(()) => self.event_log_failing = false,
+            Err(e) if !self.event_log_failing => {
+                self.event_log_failing = true;
+                self.error_message = format!("Unable to write the event log: {}", e);
+                self.error_view = true;
+            }
+            Err(_) => {}
+        }
+    }
+
+    /// Pauses or resumes the scan. Resuming restarts the watchdog.
+    pub fn toggle_pause(&mut self) {
+        self.set_paused(!self.pause_status.load(Ordering::SeqCst));
+    }
+
+    /// Pauses or resumes the scan, doing nothing if it already is. Pausing takes a
+    /// snapshot of the list, which the devices are compared with after resuming. Resuming
+    /// restarts the watchdog.
+    pub fn set_paused(&mut self, paused: bool) {
+        if self.pause_status.swap(paused, Ordering::SeqCst) == paused {
+            return;
+        }
+        if paused {
+            self.pause_snapshot = Some(PauseSnapshot::take(
+                &self.devices,
+                self.identity_key,
+                |device| self.label_for(device),
+            ));
+            self.pause_changes = None;
+        } else {
+            self.watching_since = Instant::now();
+            self.last_batch = None;
+            self.pause_changes = self.pause_snapshot.take().map(PauseChanges::new);
+        }
+    }
+
+    /// Returns the devices that changed during the last pause and are still highlighted.
+    pub fn pause_highlights(&mut self) -> HashMap<String, PauseChange> {
+        if self
+            .pause_changes
+            .as_ref()
+            .is_some_and(PauseChanges::is_expired)
+        {
+            self.pause_changes = None;
+        }
+        self.pause_changes
+            .as_ref()
+            .map(PauseChanges::highlights)
+            .unwrap_or_default()
+    }
+
+    /// Pauses the scan once no key has been pressed for the idle timeout. Nothing happens
+    /// while the scan is already paused or a connection needs it paused.
+    pub fn check_idle(&mut self) {
+        let paused = self.pause_status.load(Ordering::SeqCst);
+        if self.idle_paused && !paused {
+            // Something other than a key press resumed the scan, e.g. the HTTP API.
+            self.idle_paused = false;
+            self.last_input = Instant::now();
+        }
+        let Some(idle) = self.idle_pause else {
+            return;
+        };
+        if paused || self.is_loading || self.inventory.is_some() {
+            return;
+        }
+        if self.last_input.elapsed() >= idle {
+            self.set_paused(true);
+            self.idle_paused = true;
+        }
+    }
+
+    /// Records a key press, resuming the scan if it was paused for being idle. Returns
+    /// `true` if it was, so the key press only wakes the interface.
+    pub fn wake(&mut self) -> bool {
+        self.last_input = Instant::now();
+        if !self.idle_paused {
+            return false;
+        }
+        self.idle_paused = false;
+        self.set_paused(false);
+        true
+    }
+
+    /// Returns how long the scan has run without results if that exceeds the watchdog
+    /// window. Only the start of a scan is watched: once results have arrived, a quiet
+    /// stretch is taken to mean nothing is nearby.
+    pub fn watchdog_tripped(&self) -> Option<Duration> {
+        let window = self.watchdog?;
+        let elapsed = self.watching_since.elapsed();
+        (self.scan_task.is_some()
+            && self.last_batch.is_none()
+            && !self.pause_status.load(Ordering::SeqCst)
+            && elapsed >= window)
+            .then_some(elapsed)
+    }
+
+    /// Describes the active platform filters, which can keep results from arriving.
+    pub fn scan_filter_descriptions(&self) -> Vec<String> {
+        let mut filters = Vec::new();
+        if self.service_filter_enabled {
+            filters.push(format!(
+                "--service {}",
+                self.scan_config
+                    .services()
+                    .iter()
+                    .map(Uuid::to_string)
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ));
+        }
+        filters
+    }
+
+    /// Shows a database write failure in the notification overlay, once until a write
+    /// succeeds again.
+    #[cfg(feature = "db")]
+    fn report_db(&mut self, result: rusqlite::Result<()>) {
+        match result {
+            Ok(()) => self.db_failing = false,
+            Err(e) if !self.db_failing => {
+                self.db_failing = true;
+                self.error_message = format!("Unable to write the device database: {}", e);
+                self.error_view = true;
+            }
+            Err(_) => {}
+        }
+    }
+
+    /// Merges a batch of scan results into the device list, writing each one to the
+    /// capture being recorded first.
+    pub fn merge_batch(&mut self, devices: Vec<DeviceInfo>, observations: usize) {
+        self.last_batch = Some(Instant::now());
+        self.observations += observations;
+        for device in devices {
+            if let Some(capture) = self.capture.as_mut() {
+                if let Err(e) = capture.record(&device) {
+                    self.stop_capture_on_error(e);
+                }
+            }
+            self.merge_device(device);
+        }
+        #[cfg(feature = "db")]
+        if let Some(db) = self.db.as_mut() {
+            if !self.db_updates.is_empty() {
+                let result = db.record(&self.db_updates, self.identity_key);
+                self.db_updates.clear();
+                self.report_db(result);
+            }
+        }
+    }
+
+    /// Merges a scan result into the device list. A device seen before is updated in
+    /// place, keeping the time it was first detected and, unless it had gone stale, the
+    /// start of its presence episode. Every device, Exposure Notification RPIs and
+    /// trackers are recorded for the session statistics.
+    fn merge_device(&mut self, mut device: DeviceInfo) {
+        if let Some(Ok(exposure)) = device
+            .service_data
+            .get(&EXPOSURE_NOTIFICATION_UUID)
+            .map(|data| decode_exposure_notification(data))
+        {
+            self.exposure_rpis.insert(exposure.rpi);
+        }
+        if let Some(recorder) = &self.session_recorder {
+            recorder
+                .lock()
+                .unwrap()
+                .record(&device, self.identity_key, &self.decoders);
+        }
+        if self.identity_key.excludes(&device) {
+            return;
+        }
+        let identity = device.get_id(self.identity_key);
+        if let Some(changes) = self.pause_changes.as_mut() {
+            changes.observe(&identity, &device);
+        }
+        if let Some(rssi) = device.rssi {
+            self.rssi_stats
+                .entry(identity.clone())
+                .and_modify(|stats| stats.add(rssi))
+                .or_insert_with(|| RssiStats::new(rssi));
+            if let Some(history) = self.rssi_history.as_mut() {
+                history.record(&identity, device.last_seen, rssi);
+            }
+        }
+        if let Some(tracker) = detect_tracker(&device) {
+            // Trackers that expose an identifier are counted by it, the rest by identity.
+            let detected = self.trackers.insert(
+                tracker
+                    .identifier
+                    .clone()
+                    .map_or_else(|| identity.clone(), |(_, id)| id),
+            );
+            if detected {
+                let message = format!(
+                    "Tracker detected: {} ({}, {} dBm)",
+                    tracker.label(),
+                    identity,
+                    format_reading(device.rssi)
+                );
+                self.log_presence(PresenceEvent {
+                    event: "tracker",
+                    identity: &identity,
+                    name: device.name.as_deref(),
+                    rssi: device.rssi,
+                    message: &message,
+                    priority: Priority::Warning,
+                });
+            }
+        }
+        if let (Some(tracked), Some(rssi)) = (
+            self.tracked.as_mut().filter(|t| t.identity == identity),
+            device.rssi.map(f64::from),
+        ) {
+            tracked.smoothed_rssi = Some(tracked.smoothed_rssi.map_or(rssi, |smoothed| {
+                smoothed + RSSI_SMOOTHING * (rssi - smoothed)
+            }));
+        }
+        #[cfg(feature = "db")]
+        if let Some(db) = &self.db {
+            let previous = self
+                .devices
+                .iter()
+                .find(|d| d.get_id(self.identity_key) == identity);
+            let new_sighting = previous.is_none_or(|previous| previous.is_stale(STALE_TIMEOUT));
+            if !self.db_history.contains_key(&identity) {
+                let history = db.lookup(&identity).ok().flatten();
+                self.db_history.insert(identity.clone(), history);
+            }
+            self.db_updates.push((device.clone(), new_sighting));
+        }
+        match self
             .devices
+            .iter_mut()
+            .find(|d| d.get_id(self.identity_key) == identity)
+        {
+            Some(existing) => {
+                device.detected_at = std::mem::take(&mut existing.detected_at);
+                // A device that stops advertising its name has not been renamed.
+                if let (Some(previous), Some(name)) = (&existing.name, &device.name) {
+                    if previous != name && self.name_history_cap > 0 {
+                        let history = self.name_history.entry(identity.clone()).or_default();
+                        history.push(previous.clone());
+                        if history.len() > self.name_history_cap {
+                            history.remove(0);
+                        }
+                        self.renamed_devices.insert(identity.clone());
+                    }
+                }
+                // Advertisements that do not tell whether the device is connectable keep
+                // what was learnt from earlier ones or from connecting.
+                device.is_connectable = device.is_connectable.or(existing.is_connectable);
+                // A device returning after going stale starts a new presence episode.
+                if !existing.is_stale(STALE_TIMEOUT) {
+                    device.first_seen = existing.first_seen;
+                }
+                *existing = device;
+            }
+            None => self.devices.push(device),
+        }
+        if let Some(device) = self
+            .devices
+            .iter()
+            .find(|d| d.get_id(self.identity_key) == identity)
+        {
+            self.session_seen
+                .entry(identity.clone())
+                .and_modify(|seen| seen.update(device))
+                .or_insert_with(|| SeenDevice::new(device));
+        }
+        #[cfg(feature = "mqtt")]
+        if let Some(mqtt) = &self.mqtt {
+            let device = self
+                .devices
+                .iter()
+                .find(|d| d.get_id(self.identity_key) == identity);
+            // Trackers are announced first, so their presence goes out with the availability.
+            if let Some(device) = device.filter(|device| self.watch_list.contains(device)) {
+                mqtt.announce_tracker(
+                    &identity,
+                    &device.display_name(self.name_fallback),
+                    normalize_address(&device.address).as_deref(),
+                );
+            }
+            if self.mqtt_online.insert(identity.clone()) {
+                mqtt.publish_availability(&identity, true);
+            }
+            if let Some(device) = device {
+                if let Ok(payload) = serde_json::to_vec(&self.device_json(device)) {
+                    mqtt.publish_device(&identity, payload);
+                }
+                mqtt.publish_sensors(
+                    &identity,
+                    &device.display_name(self.name_fallback),
+                    normalize_address(&device.address).as_deref(),
+                    &self.decoders.decode_measurements(device),
+                );
+            }
+        }
+        #[cfg(feature = "influx")]
+        if let Some(influx) = &self.influx {
+            if let Some(device) = self
+                .devices
+                .iter()
+                .find(|d| d.get_id(self.identity_key) == identity)
+            {
+                influx.record(device);
+            }
+        }
+        #[cfg(feature = "websocket")]
+        if let Some(ws) = &self.ws {
+            if let Some(device) = self
+                .devices
+                .iter()
+                .find(|d| d.get_id(self.identity_key) == identity)
+            {
+                if let Ok(value) = serde_json::to_value(self.device_json(device)) {
+                    ws.publish_device(&identity, value);
+                }
+            }
+        }
+    }
+
+    /// Tells WebSocket clients about the devices that have left the list.
+    pub fn publish_removed(&self) {
+        #[cfg(feature = "websocket")]
+        if let Some(ws) = &self.ws {
+            ws.remove_absent(
+                &self
+                    .devices
+                    .iter()
+                    .map(|device| device.get_id(self.identity_key))
+                    .collect(),
+            );
+        }
+    }
+
+    /// Counts a WebSocket client connecting or disconnecting, and logs it to the event log.
+    pub fn ws_client(&mut self, peer: SocketAddr, connected: bool) {
+        if let Some(clients) = self.ws_clients.as_mut() {
+            *clients = if connected {
+                *clients + 1
+            } else {
+                clients.saturating_sub(1)
+            };
+        }
+        if let Some(event_log) = &self.event_log {
+            let result = event_log.lock().unwrap().record_client(peer, connected);
+            self.report_event_log(result);
+        }
+    }
+
+    /// Removes stale devices when running in live mode. In accumulate mode stale devices
+    /// are kept so the list covers the whole session. Nothing is evicted while the scan
+    /// is paused, since no device can refresh itself then.
+    pub fn evict_stale(&mut self) {
+        #[cfg(feature = "mqtt")]
+        self.publish_expired();
+        if self.session_mode == SessionMode::Live && !self.pause_status.load(Ordering::SeqCst) {
+            self.devices
+                .retain(|device| !device.is_stale(STALE_TIMEOUT));
+            self.prune_evicted();
+        }
+    }
+
+    /// Announces over MQTT that devices which went stale or left the list are offline.
+    #[cfg(feature = "mqtt")]
+    fn publish_expired(&mut self) {
+        let Some(mqtt) = &self.mqtt else {
+            return;
+        };
+        let present: HashSet<String> = self
+            .devices
+            .iter()
+            .filter(|device| !device.is_stale(STALE_TIMEOUT))
+            .map(|device| device.get_id(self.identity_key))
+            .collect();
+        self.mqtt_online.retain(|identity| {
+            let online = present.contains(identity);
+            if !online {
+                mqtt.publish_availability(identity, false);
+            }
+            online
+        });
+    }
+
+    /// Drops the RSSI statistics and name history of devices that are no longer in the
+    /// list.
+    fn prune_evicted(&mut self) {
+        let present: HashSet<String> = self
+            .devices
+            .iter()
+            .map(|device| device.get_id(self.identity_key))
+            .collect();
+        self.rssi_stats
+            .retain(|identity, _| present.contains(identity));
+        self.name_history
+            .retain(|identity, _| present.contains(identity));
+        self.renamed_devices
+            .retain(|identity| present.contains(identity));
+    }
+
+    /// Evicts devices by the eviction policy until the list fits under the device cap.
+    /// Marked, aliased, tracked, pinned and watched devices are never evicted, so the list
+    /// may stay over the cap when they alone exceed it.
+    pub fn enforce_device_cap(&mut self) {
+        let Some(max) = self.max_devices else {
+            return;
+        };
+        if self.devices.len() <= max {
+            return;
+        }
+        let tracked = self.tracked.as_ref().map(|tracked| &tracked.identity);
+        let mut candidates: Vec<(usize, &DeviceInfo)> = self
+            .devices
+            .iter()
+            .enumerate()
+            .filter(|(_, device)| {
+                let identity = device.get_id(self.identity_key);
+                !self.marked_devices.contains(&identity)
+                    && tracked != Some(&identity)
+                    && self.compare_pin.as_ref() != Some(&identity)
+                    && !self.watch_list.contains(device)
+                    && self.alias_for(device).is_none()
+            })
+            .collect();
+        match self.eviction_policy {
+            EvictionPolicy::OldestLastSeen => {
+                candidates.sort_by_key(|(_, device)| device.last_seen)
+            }
+            EvictionPolicy::WeakestRssi => {
+                candidates.sort_by_key(|(_, device)| device.rssi.unwrap_or(i16::MIN))
+            }
+        }
+        let mut evicted: Vec<usize> = candidates
+            .iter()
+            .take(self.devices.len() - max)
+            .map(|(index, _)| *index)
+            .collect();
+        if evicted.is_empty() {
+            return;
+        }
+        evicted.sort_unstable();
+        for index in evicted.iter().rev() {
+            self.devices.remove(*index);
+        }
+        self.prune_evicted();
+        self.capped_evictions += evicted.len();
+        self.last_capped = Some(Instant::now());
+    }
+
+    /// Returns the number of devices the cap has evicted if it did so recently.
+    pub fn capped_indicator(&self) -> Option<usize> {
+        self.last_capped
+            .filter(|at| at.elapsed() < CAP_INDICATOR_DURATION)
+            .map(|_| self.capped_evictions)
+    }
+
+    /// Returns the devices shown in the table, after applying the active filters, in the
+    /// order of the sort key. When grouping by zone, the devices are then ordered from the
+    /// closest zone to the farthest, with devices without an RSSI reading last.
+    pub fn visible_devices(&self) -> Vec<&DeviceInfo> {
+        let mut devices: Vec<_> = self
+            .devices
+            .iter()
+            .filter(|device| {
+                self.tag_filter
+                    .is_none_or(|tag| device_tags(device).contains(&tag))
+            })
+            .filter(|device| {
+                self.manufacturer_filter
+                    .is_none_or(|code| device.manufacturer_data.contains_key(&code))
+            })
+            .filter(|device| {
+                self.category_filter
+                    .is_none_or(|category| self.category_for(device) == category)
+            })
+            .filter(|device| self.address_type_filter.matches(device))
+            .filter(|device| !self.below_min_rssi(device))
+            .filter(|device| !self.named_only || device.name.is_some())
+            .filter(|device| self.matches_search(device))
+            .collect();
+        match (self.sort_key, self.sort_reversed) {
+            (SortKey::Discovered, false) => {}
+            (SortKey::Discovered, true) => devices.reverse(),
+            (SortKey::LastSeen, false) => {
+                devices.sort_by_key(|device| std::cmp::Reverse(device.last_seen))
+            }
+            (SortKey::LastSeen, true) => devices.sort_by_key(|device| device.last_seen),
+            (SortKey::Rssi, false) => {
+                devices.sort_by_key(|device| std::cmp::Reverse(device.rssi.unwrap_or(i16::MIN)))
+            }
+            // Devices without an RSSI reading still go last.
+            (SortKey::Rssi, true) => devices.sort_by_key(|device| device.rssi.unwrap_or(i16::MAX)),
+        }
+        if self.group_by_zone {
+            devices.sort_by_key(|device| {
+                device_zone(device).map_or(ProximityZone::ALL.len(), |z| z as usize)
+            });
+        }
+        devices
+    }
+
+    /// Returns the number of present devices in each proximity zone. Stale devices and
+    /// devices without an RSSI reading are not counted.
+    pub fn zone_counts(&self) -> Vec<(ProximityZone, usize)> {
+        ProximityZone::ALL
+            .iter()
+            .map(|zone| {
+                let count = self
+                    .devices
+                    .iter()
+                    .filter(|device| !device.is_stale(STALE_TIMEOUT))
+                    .filter(|device| device_zone(device) == Some(*zone))
+                    .count();
+                (*zone, count)
+            })
+            .collect()
+    }
+
+    /// Returns `true` if there is no search, or the device's name, alias, address or
+    /// platform id contains it, ignoring case.
+    fn matches_search(&self, device: &DeviceInfo) -> bool {
+        let Some(search) = &self.search else {
+            return true;
+        };
+        let search = search.to_lowercase();
+        [
+            device.name.as_deref(),
+            self.alias_for(device),
+            Some(device.address.as_str()),
+            Some(device.id.as_str()),
+        ]
+        .into_iter()
+        .flatten()
+        .any(|field| field.to_lowercase().contains(&search))
+    }
+
+    /// Returns the label of the table order, with the direction when it is reversed,
+    /// e.g. `rssi:asc`.
+    pub fn sort_label(&self) -> String {
+        if self.sort_reversed {
+            let direction = if self.sort_key.descending() {
+                "asc"
+            } else {
+                "desc"
+            };
+            format!("{}:{}", self.sort_key.label(), direction)
+        } else {
+            self.sort_key.label().to_string()
+        }
+    }
+
+    /// Moves to the next sort key, in its natural order.
+    pub fn cycle_sort(&mut self) {
+        self.sort_key = self.sort_key.next();
+        self.sort_reversed = false;
+    }
+
+    /// Opens the search input with the current search.
+    pub fn start_search_input(&mut self) {
+        self.search_input = Some(self.search.clone().unwrap_or_default());
+    }
+
+    /// Applies the search being typed; an empty search shows every device again.
+    pub fn commit_search_input(&mut self) {
+        if let Some(search) = self.search_input.take() {
+            self.search = (!search.is_empty()).then_some(search);
+        }
+    }
+
+    /// Completes the command name being typed at the `:` prompt.
+    pub fn complete_command_input(&mut self) {
+        if let Some(input) = self.command_input.as_mut() {
+            if let Some(completed) = commands::complete(input) {
+                *input = completed;
+            }
+        }
+    }
+
+    /// Writes an export and shows where it went, or why it failed, in the notification
+    /// overlay.
+    pub fn export(&mut self, target: ExportTarget) {
+        let result = match target {
+            ExportTarget::Csv => self.get_devices_csv(),
+            ExportTarget::Json => self.get_devices_json(),
+            ExportTarget::Device => self.get_device_detail_json(),
+            ExportTarget::Text => self.get_devices_text(),
+            ExportTarget::RssiHistory => self.get_rssi_history_csv(),
+        };
+        self.error_message = match result {
+            Ok(success_message) => success_message,
+            Err(e) => e.to_string(),
+        };
+        self.error_view = true;
+    }
+
+    /// Returns `true` if the device's RSSI is below the minimum RSSI threshold.
+    /// Devices without an RSSI reading are never hidden by the threshold.
+    pub fn below_min_rssi(&self, device: &DeviceInfo) -> bool {
+        match (self.min_rssi, device.rssi) {
+            (Some(threshold), Some(rssi)) => rssi < threshold,
+            _ => false,
+        }
+    }
+
+    /// Moves the minimum RSSI threshold by `step` dBm. Lowering it past the floor turns
+    /// the threshold off, and raising it from off starts at the floor.
+    pub fn adjust_min_rssi(&mut self, step: i16) {
+        self.min_rssi = match self.min_rssi {
+            None if step > 0 => Some(MIN_RSSI_FLOOR),
+            None => None,
+            Some(threshold) if threshold + step < MIN_RSSI_FLOOR => None,
+            Some(threshold) => Some((threshold + step).min(0)),
+        };
+    }
+
+    /// Cycles the tag filter through every tag, then back to showing all devices.
+    pub fn cycle_tag_filter(&mut self) {
+        self.tag_filter = match self.tag_filter {
+            None => TAGS.first().copied(),
+            Some(tag) => TAGS
+                .iter()
+                .position(|t| *t == tag)
+                .and_then(|i| TAGS.get(i + 1))
+                .copied(),
+        };
+        self.table_state.select(Some(0));
+    }
+
+    /// Cycles the address type filter, then back to showing all devices.
+    pub fn cycle_address_type_filter(&mut self) {
+        self.address_type_filter = self.address_type_filter.next();
+        self.table_state.select(Some(0));
+    }
+
+    /// Returns the manufacturers with the most devices, with their counts, for the chip
+    /// bar. The active manufacturer filter is always included.
+    pub fn top_manufacturers(&self) -> Vec<(u16, usize)> {
+        let mut counts: HashMap<u16, usize> = HashMap::new();
+        for device in &self.devices {
+            for code in device.manufacturer_data.keys() {
+                *counts.entry(*code).or_default() += 1;
+            }
+        }
+        let mut counts: Vec<_> = counts.into_iter().collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        if let Some(active) = self.manufacturer_filter {
+            if let Some(index) = counts.iter().position(|(code, _)| *code == active) {
+                if index >= MAX_CHIPS {
+                    counts.swap(MAX_CHIPS - 1, index);
+                }
+            } else {
+                counts.insert(0, (active, 0));
+            }
+        }
+        counts.truncate(MAX_CHIPS);
+        counts
+    }
+
+    /// Moves the chip bar cursor by `step` chips, wrapping around.
+    pub fn move_chip_cursor(&mut self, step: isize) {
+        let chips = self.top_manufacturers().len() as isize;
+        if chips > 0 {
+            self.chip_cursor = (self.chip_cursor as isize + step).rem_euclid(chips) as usize;
+        }
+    }
+
+    /// Applies the manufacturer under the chip bar cursor as the filter, or removes the
+    /// filter if it is already applied.
+    pub fn toggle_chip(&mut self) {
+        let Some((code, _)) = self.top_manufacturers().get(self.chip_cursor).copied() else {
+            return;
+        };
+        self.manufacturer_filter = (self.manufacturer_filter != Some(code)).then_some(code);
+        self.table_state.select(Some(0));
+    }
+
+    /// Returns the number of devices in each category, in the order of
+    /// [`DeviceCategory::ALL`].
+    pub fn category_counts(&self) -> Vec<(DeviceCategory, usize)> {
+        DeviceCategory::ALL
+            .iter()
+            .map(|category| {
+                let count = self
+                    .devices
+                    .iter()
+                    .filter(|device| self.category_for(device) == *category)
+                    .count();
+                (*category, count)
+            })
+            .collect()
+    }
+
+    /// Opens the category filter popup with the active filter highlighted.
+    pub fn open_category_popup(&mut self) {
+        let selected = self
+            .category_filter
+            .and_then(|filter| DeviceCategory::ALL.iter().position(|c| *c == filter))
+            .map_or(0, |i| i + 1);
+        self.category_popup = Some(selected);
+    }
+
+    /// Moves the highlight in the category popup by `step` entries, wrapping around.
+    pub fn move_category_popup(&mut self, step: isize) {
+        let entries = DeviceCategory::ALL.len() as isize + 1;
+        if let Some(selected) = self.category_popup.as_mut() {
+            *selected = (*selected as isize + step).rem_euclid(entries) as usize;
+        }
+    }
+
+    /// Applies the highlighted entry of the category popup as the filter and closes it.
+    pub fn select_category_popup(&mut self) {
+        if let Some(selected) = self.category_popup.take() {
+            self.category_filter = selected
+                .checked_sub(1)
+                .and_then(|i| DeviceCategory::ALL.get(i))
+                .copied();
+            self.table_state.select(Some(0));
+        }
+    }
+
+    /// Locks the locator onto the highlighted device, or releases it if it is already
+    /// tracked.
+    pub fn toggle_tracking(&mut self) {
+        let Some(device) = self.selected_device() else {
+            return;
+        };
+        let identity = device.get_id(self.identity_key);
+        if self
+            .tracked
+            .as_ref()
+            .is_some_and(|t| t.identity == identity)
+        {
+            self.tracked = None;
+        } else {
+            self.tracked = Some(TrackedDevice {
+                smoothed_rssi: device.rssi.map(f64::from),
+                identity,
+                last_pulse: None,
+            });
+        }
+    }
+
+    /// Pins the highlighted device for comparison, or unpins it if it is already pinned.
+    pub fn toggle_compare_pin(&mut self) {
+        let Some(identity) = self
+            .selected_device()
+            .map(|device| device.get_id(self.identity_key))
+        else {
+            return;
+        };
+        self.compare_pin = (self.compare_pin.as_ref() != Some(&identity)).then_some(identity);
+    }
+
+    /// Returns the device pinned for comparison if it is still in the list.
+    pub fn pinned_device(&self) -> Option<&DeviceInfo> {
+        let pinned = self.compare_pin.as_ref()?;
+        self.devices
+            .iter()
+            .find(|device| device.get_id(self.identity_key) == *pinned)
+    }
+
+    /// Returns the tracked device if it is still in the list.
+    pub fn tracked_device(&self) -> Option<&DeviceInfo> {
+        let tracked = self.tracked.as_ref()?;
+        self.devices
+            .iter()
+            .find(|device| device.get_id(self.identity_key) == tracked.identity)
+    }
+
+    /// Returns `true` when proximity feedback is due for the tracked device, and records
+    /// the pulse. Feedback repeats faster as the smoothed RSSI gets stronger.
+    pub fn proximity_pulse(&mut self) -> bool {
+        // A device that stopped advertising gives no feedback rather than its last reading.
+        let present = self
+            .tracked_device()
+            .is_some_and(|device| !device.is_stale(STALE_TIMEOUT));
+        if self.proximity_feedback == ProximityFeedback::Off || !present {
+            return false;
+        }
+        let Some(tracked) = self.tracked.as_mut() else {
+            return false;
+        };
+        let Some(rssi) = tracked.smoothed_rssi else {
+            return false;
+        };
+        if tracked
+            .last_pulse
+            .is_some_and(|at| at.elapsed() < pulse_interval(rssi))
+        {
+            return false;
+        }
+        tracked.last_pulse = Some(Instant::now());
+        true
+    }
+
+    /// Returns `true` while the proximity gauge should be highlighted after a pulse.
+    pub fn pulse_active(&self) -> bool {
+        self.proximity_feedback != ProximityFeedback::Off
+            && self
+                .tracked
+                .as_ref()
+                .and_then(|t| t.last_pulse)
+                .is_some_and(|at| at.elapsed() < PULSE_FLASH_DURATION)
+    }
+
+    /// Switches between the most-recently-seen order and discovery order.
+    pub fn toggle_last_seen_sort(&mut self) {
+        self.sort_key = if self.sort_key == SortKey::LastSeen {
+            SortKey::Discovered
+        } else {
+            SortKey::LastSeen
+        };
+        self.sort_reversed = false;
+    }
+
+    /// Records the identity of the highlighted device.
+    pub fn remember_selection(&mut self) {
+        self.selected_identity = self
+            .selected_device()
+            .map(|device| device.get_id(self.identity_key));
+    }
+
+    /// Moves the highlight back onto the remembered device after the table was reordered.
+    /// If the device is no longer shown, the highlight stays at the same row.
+    pub fn anchor_selection(&mut self) {
+        let Some(identity) = &self.selected_identity else {
+            return;
+        };
+        if let Some(index) = self
+            .visible_devices()
+            .iter()
+            .position(|device| device.get_id(self.identity_key) == *identity)
+        {
+            self.table_state.select(Some(index));
+        }
+    }
+
+    /// Returns the highlighted device, if any.
+    pub fn selected_device(&self) -> Option<&DeviceInfo> {
+        self.visible_devices()
             .get(self.table_state.selected().unwrap_or(0))
-            .unwrap();
+            .copied()
+    }
 
-        self.pause_status.store(true, Ordering::SeqCst);
+    /// Returns the services found by connecting to the device, in UUID order, or nothing if
+    /// it was not the device last connected to.
+    pub fn gatt_services(&self, device: &DeviceInfo) -> Vec<Uuid> {
+        if self.connected_identity.as_ref() != Some(&device.get_id(self.identity_key)) {
+            return Vec::new();
+        }
+        let services: BTreeSet<Uuid> = self
+            .selected_characteristics
+            .iter()
+            .map(|characteristic| characteristic.service)
+            .collect();
+        services.into_iter().collect()
+    }
 
-        let device = Arc::new(selected_device.clone());
+    pub async fn connect(&mut self) {
+        // The inventory run holds the adapter's connections.
+        if self.inventory.is_some() {
+            return;
+        }
+        let Some(selected_device) = self.selected_device().cloned() else {
+            return;
+        };
+        if selected_device.is_connectable == Some(false) {
+            self.command_error = Some(format!(
+                "{} advertises as not connectable; connecting would fail",
+                selected_device.display_name(self.name_fallback)
+            ));
+            return;
+        }
+
+        self.is_loading = true;
+        self.paused_before_connect = self.pause_status.swap(true, Ordering::SeqCst);
+        self.connected_identity = Some(selected_device.get_id(self.identity_key));
+
+        let device = Arc::new(selected_device);
         let tx_clone = self.tx.clone();
+        let (connect_timeout, retries) = (self.connect_timeout, self.connect_retries);
+
+        tokio::spawn(async move {
+            get_characteristics(tx_clone, device, connect_timeout, retries).await
+        });
+    }
+
+    /// Records that the connected device accepts connections, once its characteristics
+    /// were read.
+    pub fn connect_succeeded(&mut self) {
+        let Some(identity) = &self.connected_identity else {
+            return;
+        };
+        if let Some(device) = self
+            .devices
+            .iter_mut()
+            .find(|d| &d.get_id(self.identity_key) == identity)
+        {
+            device.is_connectable = Some(true);
+        }
+    }
+
+    /// Shows why connecting failed and resumes the scan if connecting paused it.
+    pub fn connect_failed(&mut self, error: String) {
+        self.is_loading = false;
+        self.connect_progress = None;
+        self.pause_status
+            .store(self.paused_before_connect, Ordering::SeqCst);
+        self.error_message = error;
+        self.error_view = true;
+    }
+
+    /// Raises the alerts of the watch list. They are shown as desktop notifications with
+    /// `--desktop-notify`, falling back to the notification overlay when that fails, e.g.
+    /// without a notification daemon.
+    pub fn check_watch_list(&mut self) {
+        if self.watch_list.is_empty() {
+            return;
+        }
+        let aliases: HashMap<String, String> = self
+            .devices
+            .iter()
+            .filter_map(|device| {
+                Some((
+                    device.get_id(self.identity_key),
+                    self.alias_for(device)?.to_string(),
+                ))
+            })
+            .collect();
+        let alerts = self.watch_list.check(
+            &self.devices,
+            self.identity_key,
+            self.name_fallback,
+            &aliases,
+        );
+        if alerts.is_empty() {
+            return;
+        }
+        for alert in &alerts {
+            self.log_presence(PresenceEvent {
+                event: alert.kind.label(),
+                identity: &alert.identity,
+                name: alert.name.as_deref(),
+                rssi: alert.rssi,
+                message: &alert.message,
+                priority: match alert.kind {
+                    AlertKind::Appeared => Priority::Notice,
+                    AlertKind::Near => Priority::Info,
+                    AlertKind::Disappeared => Priority::Warning,
+                },
+            });
+        }
+        let alerts: Vec<String> = alerts.into_iter().map(|alert| alert.message).collect();
+        #[cfg(feature = "notifications")]
+        if self.desktop_notify {
+            for alert in alerts {
+                let tx_clone = self.tx.clone();
+                // Showing a notification blocks on the platform's notification service.
+                tokio::task::spawn_blocking(move || {
+                    let shown = notify_rust::Notification::new()
+                        .appname("btlescan")
+                        .summary("btlescan")
+                        .body(&alert)
+                        .show();
+                    if shown.is_err() {
+                        let _ = tx_clone.send(DeviceData::WatchAlert(alert));
+                    }
+                });
+            }
+            return;
+        }
+        self.error_message = alerts.join("\n");
+        self.error_view = true;
+    }
+
+    /// Sends a presence event to `--syslog`. A failure is shown once, and again only after
+    /// an event has been logged in between.
+    fn log_presence(&mut self, event: PresenceEvent) {
+        let Some(syslog) = self.syslog.as_mut() else {
+            return;
+        };
+        match syslog.send(&event) {
+            Ok(()) => self.syslog_failing = false,
+            Err(e) if !self.syslog_failing => {
+                self.syslog_failing = true;
+                self.error_message = format!("Unable to write to syslog: {}", e);
+                self.error_view = true;
+            }
+            Err(_) => {}
+        }
+    }
+
+    /// Starts an inventory run over the visible devices, or cancels the one in progress
+    /// after its current device. The scan is paused while the run connects.
+    pub fn toggle_inventory(&mut self) {
+        if let Some(inventory) = &self.inventory {
+            inventory.cancel.store(true, Ordering::SeqCst);
+            self.error_message = "Inventory cancelled after the current device.".to_string();
+            self.error_view = true;
+            return;
+        }
+        if self.is_loading {
+            return;
+        }
+        let devices: Vec<DeviceInfo> = self
+            .visible_devices()
+            .into_iter()
+            .filter(|device| device.device.is_some())
+            .cloned()
+            .collect();
+        if devices.is_empty() {
+            self.error_message = "No devices to inventory.".to_string();
+            self.error_view = true;
+            return;
+        }
+        let path = self.inventory_out.clone().unwrap_or_else(|| {
+            let timestamp = chrono::Local::now().format("%Y-%m-%d_%H-%M-%S");
+            PathBuf::from(format!("btlescan_inventory_{}.json", timestamp))
+        });
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.inventory = Some(Inventory {
+            path,
+            entries: Vec::new(),
+            total: devices.len(),
+            current: None,
+            cancel: cancel.clone(),
+            paused_before: self.pause_status.swap(true, Ordering::SeqCst),
+        });
+        let tx_clone = self.tx.clone();
+        let (identity_key, connect_timeout) = (self.identity_key, self.connect_timeout);
+        tokio::spawn(async move {
+            run_inventory(tx_clone, devices, identity_key, connect_timeout, cancel).await
+        });
+    }
+
+    /// Adds what an inventory run read from a device to the report.
+    pub fn record_inventory_entry(&mut self, entry: InventoryEntry) {
+        let Some(inventory) = &mut self.inventory else {
+            return;
+        };
+        inventory.entries.push(entry);
+        if let Err(e) = inventory.write_report() {
+            self.error_message = format!(
+                "Unable to write the inventory to {}: {}",
+                inventory.path.display(),
+                e
+            );
+            self.error_view = true;
+        }
+    }
+
+    /// Ends the inventory run, restoring the scan's pause state and reporting the result.
+    pub fn finish_inventory(&mut self) {
+        let Some(inventory) = self.inventory.take() else {
+            return;
+        };
+        self.pause_status
+            .store(inventory.paused_before, Ordering::SeqCst);
+        self.error_message = format!(
+            "Inventory of {} device(s) written to {} ({} failed).",
+            inventory.entries.len(),
+            inventory.path.display(),
+            inventory.failed()
+        );
+        self.error_view = true;
+    }
+
+    /// Asks the running scan to disconnect every device its adapter is connected to. Safe
+    /// to call at any time; the result is shown in the status bar.
+    pub fn disconnect_all(&mut self) {
+        let sent = self
+            .scan_commands
+            .as_ref()
+            .is_some_and(|commands| commands.send(ScanCommand::DisconnectAll).is_ok());
+        if !sent {
+            self.disconnected(Vec::new(), Vec::new());
+        }
+    }
+
+    /// Shows the result of a disconnect-all request in the status bar.
+    pub fn disconnected(&mut self, disconnected: Vec<String>, failed: Vec<String>) {
+        let mut parts = Vec::new();
+        if !disconnected.is_empty() {
+            parts.push(format!(
+                "disconnected {}: {}",
+                disconnected.len(),
+                disconnected.join(", ")
+            ));
+        }
+        if !failed.is_empty() {
+            parts.push(format!(
+                "failed to disconnect {}: {}",
+                failed.len(),
+                failed.join(", ")
+            ));
+        }
+        if parts.is_empty() {
+            parts.push("no active connections".to_string());
+        }
+        self.status = Some((parts.join("; "), Instant::now()));
+        self.inspect_view = false;
+    }
+
+    /// Returns the status message if it was set recently.
+    pub fn status_indicator(&self) -> Option<&str> {
+        self.status
+            .as_ref()
+            .filter(|(_, at)| at.elapsed() < STATUS_DURATION)
+            .map(|(message, _)| message.as_str())
+    }
+
+    /// Toggles the mark on the highlighted device. Marks are keyed by identity so they
+    /// follow the device when the table changes.
+    pub fn toggle_mark(&mut self) {
+        if let Some(device) = self.selected_device() {
+            let identity = device.get_id(self.identity_key);
+            if !self.marked_devices.remove(&identity) {
+                self.marked_devices.insert(identity);
+            }
+        }
+    }
+
+    /// Returns the label the table shows for a device: its alias, or its name with the
+    /// fallback for unnamed devices.
+    pub fn label_for(&self, device: &DeviceInfo) -> String {
+        self.alias_for(device)
+            .map_or_else(|| device.display_name(self.name_fallback), str::to_owned)
+    }
+
+    /// Returns the local alias of a device: the one in the known-devices file, if any,
+    /// otherwise the one saved in the state. Saved aliases are keyed by platform id, which
+    /// stays the same on platforms that track a device across address rotation; the address
+    /// identity is checked as well for aliases set where the two coincide.
+    pub fn alias_for(&self, device: &DeviceInfo) -> Option<&str> {
+        self.known_devices
+            .as_ref()
+            .and_then(|known| known.get(device)?.alias.as_deref())
+            .or_else(|| {
+                self.state
+                    .aliases
+                    .get(&device.id)
+                    .or_else(|| self.state.aliases.get(&device.get_id(self.identity_key)))
+                    .map(String::as_str)
+            })
+    }
+
+    /// Returns the category of a device: the user's override if one is set, otherwise the
+    /// one in the known-devices file, otherwise the classified category. Overrides are keyed
+    /// like aliases.
+    pub fn category_for(&self, device: &DeviceInfo) -> DeviceCategory {
+        self.state
+            .categories
+            .get(&device.id)
+            .or_else(|| self.state.categories.get(&device.get_id(self.identity_key)))
+            .copied()
+            .or_else(|| self.known_devices.as_ref()?.get(device)?.category)
+            .unwrap_or_else(|| classify(device))
+    }
 
-        tokio::spawn(async move { get_characteristics(tx_clone, device).await });
+    /// Moves the highlighted device's category to the next one and saves it as an
+    /// override. Cycling back to the classified category removes the override.
+    pub fn cycle_category_override(&mut self) -> Result<(), Box<dyn Error>> {
+        let Some(device) = self.selected_device() else {
+            return Ok(());
+        };
+        let next = self.category_for(device).next();
+        let classified = classify(device);
+        let (id, identity) = (device.id.clone(), device.get_id(self.identity_key));
+        self.state.categories.remove(&identity);
+        if next == classified {
+            self.state.categories.remove(&id);
+        } else {
+            self.state.categories.insert(id, next);
+        }
+        save_state(&self.state)
+    }
+
+    /// Clears the name change flag of the highlighted device. Its name history is kept.
+    pub fn acknowledge_rename(&mut self) {
+        if let Some(identity) = self
+            .selected_device()
+            .map(|device| device.get_id(self.identity_key))
+        {
+            self.renamed_devices.remove(&identity);
+        }
+    }
+
+    /// Opens the alias input for the highlighted device, prefilled with its current alias.
+    pub fn start_alias_input(&mut self) {
+        if let Some(device) = self.selected_device() {
+            self.alias_input = Some(self.alias_for(device).unwrap_or_default().to_string());
+        }
+    }
+
+    /// Assigns the typed alias to the highlighted device and saves it, to the known-devices
+    /// file if one is used and to the state otherwise. An empty alias removes the device's
+    /// alias.
+    pub fn commit_alias_input(&mut self) -> Result<(), Box<dyn Error>> {
+        let Some(alias) = self.alias_input.take() else {
+            return Ok(());
+        };
+        let Some(device) = self.selected_device() else {
+            return Ok(());
+        };
+        let (id, identity) = (device.id.clone(), device.get_id(self.identity_key));
+        self.state.aliases.remove(&identity);
+        let alias = alias.trim();
+        if let Some(known) = &mut self.known_devices {
+            // The file takes the alias over, so an older one in the state would only
+            // resurface once the file's is removed.
+            self.state.aliases.remove(&id);
+            known.set_alias(&identity, (!alias.is_empty()).then(|| alias.to_string()))?;
+        } else if alias.is_empty() {
+            self.state.aliases.remove(&id);
+        } else {
+            self.state.aliases.insert(id, alias.to_string());
+        }
+        save_state(&self.state)
+    }
+
+    /// Returns the note taken on a device. Notes are saved in the state keyed like aliases,
+    /// so they follow a device across address rotation where the platform id does.
+    pub fn note_for(&self, device: &DeviceInfo) -> Option<&str> {
+        self.state
+            .notes
+            .get(&device.id)
+            .or_else(|| self.state.notes.get(&device.get_id(self.identity_key)))
+            .map(String::as_str)
+    }
+
+    /// Opens the note input for the highlighted device, prefilled with its current note.
+    pub fn start_note_input(&mut self) {
+        if let Some(device) = self.selected_device() {
+            self.note_input = Some(self.note_for(device).unwrap_or_default().to_string());
+        }
+    }
+
+    /// Saves the typed note on the highlighted device. An empty note removes the device's
+    /// note.
+    pub fn commit_note_input(&mut self) -> Result<(), Box<dyn Error>> {
+        let Some(note) = self.note_input.take() else {
+            return Ok(());
+        };
+        let Some(device) = self.selected_device() else {
+            return Ok(());
+        };
+        let (id, identity) = (device.id.clone(), device.get_id(self.identity_key));
+        self.state.notes.remove(&identity);
+        let note = note.trim();
+        if note.is_empty() {
+            self.state.notes.remove(&id);
+        } else {
+            self.state.notes.insert(id, note.to_string());
+        }
+        save_state(&self.state)
+    }
+
+    /// Returns the devices an export should operate on: the marked devices if any are
+    /// marked, otherwise every device.
+    pub fn export_devices(&self) -> Vec<&DeviceInfo> {
+        self.devices
+            .iter()
+            .filter(|device| {
+                self.marked_devices.is_empty()
+                    || self
+                        .marked_devices
+                        .contains(&device.get_id(self.identity_key))
+            })
+            .collect()
     }
 
     pub fn get_devices_csv(&self) -> Result<String, Box<dyn Error>> {
-        let now = chrono::Local::now();
-        let timestamp = now.format("%Y-%m-%d_%H-%M-%S").to_string();
+        let timestamp = chrono::Local::now().format("%Y-%m-%d_%H-%M-%S").to_string();
         let file_path = format!("btlescan_{}.csv", timestamp);
-        let file = std::fs::File::create(file_path).expect("Unable to create file");
-        let mut wtr = csv::Writer::from_writer(file);
-        for device in &self.devices {
-            wtr.serialize(DeviceCsv {
-                id: device.id.clone(),
-                name: device.name.clone(),
-                tx_power: device.tx_power.clone(),
-                address: device.address.clone(),
-                rssi: device.rssi.clone(),
-            })?;
+        let devices = self.export_devices();
+        self.write_devices_csv(Path::new(&file_path), &devices)?;
+        Ok(format!(
+            "{} devices exported to a CSV file in the current directory.",
+            devices.len()
+        ))
+    }
+
+    /// Exports the RSSI readings of the session to a CSV file in the current directory,
+    /// limited to the marked devices if any are marked.
+    pub fn get_rssi_history_csv(&self) -> Result<String, Box<dyn Error>> {
+        let Some(history) = &self.rssi_history else {
+            return Ok(
+                "RSSI history is off; start btlescan with --rssi-log <PATH> or --rssi-history <N>."
+                    .to_string(),
+            );
+        };
+        let timestamp = chrono::Local::now().format("%Y-%m-%d_%H-%M-%S").to_string();
+        let file_path = format!("btlescan_rssi_{}.csv", timestamp);
+        let only = (!self.marked_devices.is_empty()).then_some(&self.marked_devices);
+        let written = history.write_csv(Path::new(&file_path), only)?;
+        Ok(format!(
+            "{} RSSI readings exported to a CSV file in the current directory.",
+            written
+        ))
+    }
+
+    /// Writes the given devices to a CSV file at `path`, one row per device with the
+    /// configured columns.
+    pub fn write_devices_csv(
+        &self,
+        path: &Path,
+        devices: &[&DeviceInfo],
+    ) -> Result<(), Box<dyn Error>> {
+        let mut wtr = csv::Writer::from_path(path)?;
+        wtr.write_record(self.csv_columns.iter().map(CsvColumn::header))?;
+        for device in devices {
+            wtr.write_record(
+                self.csv_columns
+                    .iter()
+                    .map(|column| self.csv_value(device, column)),
+            )?;
         }
         wtr.flush()?;
-        Ok("Devices exported to a CSV file in the current directory.".to_string())
+        Ok(())
+    }
+
+    /// Returns the value of a CSV column for a device. Timestamps are ISO 8601.
+    fn csv_value(&self, device: &DeviceInfo, column: &CsvColumn) -> String {
+        let uuids = |uuids: &[Uuid]| {
+            uuids
+                .iter()
+                .map(Uuid::to_string)
+                .collect::<Vec<String>>()
+                .join(" ")
+        };
+        match column {
+            CsvColumn::Id => device.id.clone(),
+            CsvColumn::Name => device.name.clone().unwrap_or_default(),
+            CsvColumn::Label => self.label_for(device),
+            CsvColumn::Alias => self.alias_for(device).unwrap_or_default().to_string(),
+            CsvColumn::Note => self.note_for(device).unwrap_or_default().to_string(),
+            CsvColumn::TxPower => device
+                .tx_power
+                .map_or_else(|| "n/a".to_string(), |tx| tx.to_string()),
+            CsvColumn::Address => device.address.clone(),
+            CsvColumn::DeviceType => device.device_type_label().to_string(),
+            CsvColumn::Rssi => device
+                .rssi
+                .map_or_else(|| "n/a".to_string(), |rssi| rssi.to_string()),
+            CsvColumn::Company => company_name(&device.manufacturer_data).unwrap_or_default(),
+            CsvColumn::DetectedAt => device.detected_at.clone(),
+            CsvColumn::FirstSeen => device.first_seen.to_rfc3339(),
+            CsvColumn::LastSeen => device.last_seen.to_rfc3339(),
+            CsvColumn::Services => uuids(&device.services),
+            CsvColumn::ServiceCount => device.services.len().to_string(),
+            CsvColumn::SolicitedServices => uuids(&device.solicited_services),
+            CsvColumn::DwellSecs => device.dwell().as_secs().to_string(),
+            CsvColumn::ManufacturerData => extract_manufacturer_data(&device.manufacturer_data)
+                .iter()
+                .map(|entry| format!("0x{:04X}: {}", entry.company_id, entry.data))
+                .collect::<Vec<String>>()
+                .join("; "),
+            CsvColumn::Category => self.category_for(device).label().to_string(),
+            CsvColumn::Tags => device_tags(device).join(" "),
+            CsvColumn::Decoded => self
+                .decoders
+                .decode(device)
+                .iter()
+                .map(|field| format!("{}: {}", field.label, field.value))
+                .collect::<Vec<String>>()
+                .join("; "),
+            CsvColumn::DecodedField(label) => self
+                .decoders
+                .decode(device)
+                .into_iter()
+                .filter(|field| field.label == *label)
+                .map(|field| field.value)
+                .collect::<Vec<String>>()
+                .join("; "),
+        }
+    }
+
+    /// Copies the devices that pass the filters to the clipboard as a plain-text table with
+    /// the interface's columns. Without a clipboard, such as over SSH or in a build without
+    /// the `clipboard` feature, the table is written to a text file in the current directory
+    /// instead.
+    pub fn get_devices_text(&mut self) -> Result<String, Box<dyn Error>> {
+        let text = devices_text(self);
+        let count = self.visible_devices().len();
+        #[cfg(feature = "clipboard")]
+        {
+            // On Linux the clipboard contents are served by this process, so the handle is
+            // kept.
+            if self.clipboard.is_none() {
+                self.clipboard = arboard::Clipboard::new().ok();
+            }
+            if let Some(clipboard) = self.clipboard.as_mut() {
+                if clipboard.set_text(text.clone()).is_ok() {
+                    return Ok(format!(
+                        "{} devices copied to the clipboard as text.",
+                        count
+                    ));
+                }
+            }
+        }
+        let timestamp = chrono::Local::now().format("%Y-%m-%d_%H-%M-%S").to_string();
+        let file_path = format!("btlescan_{}.txt", timestamp);
+        std::fs::write(&file_path, text)?;
+        Ok(format!(
+            "No clipboard available: {} devices written as text to {}.",
+            count, file_path
+        ))
+    }
+
+    /// Writes everything known about the highlighted device, in the format of the HTTP
+    /// API's device detail, to a JSON file in the current directory.
+    pub fn get_device_detail_json(&self) -> Result<String, Box<dyn Error>> {
+        let device = self.selected_device().ok_or("No device selected.")?;
+        let identity = device.get_id(self.identity_key);
+        let detail = self
+            .device_detail(&identity)
+            .ok_or("The device is no longer in the list.")?;
+        let timestamp = chrono::Local::now().format("%Y-%m-%d_%H-%M-%S").to_string();
+        let file_path = device_file_name(&device.address_or_id(), &timestamp);
+        let file = std::fs::File::create(&file_path)?;
+        serde_json::to_writer_pretty(file, &detail)?;
+        Ok(format!("Device exported to {}", file_path))
+    }
+
+    pub fn get_devices_json(&self) -> Result<String, Box<dyn Error>> {
+        let timestamp = chrono::Local::now().format("%Y-%m-%d_%H-%M-%S").to_string();
+        let file_path = format!("btlescan_{}.json", timestamp);
+        let devices = self.export_devices();
+        self.write_devices_json(Path::new(&file_path), &devices)?;
+        Ok(format!(
+            "{} devices exported to a JSON file in the current directory.",
+            devices.len()
+        ))
+    }
+
+    /// Writes the given devices to a JSON export at `path`, along with the session they
+    /// were seen in and the filters that were active.
+    pub fn write_devices_json(
+        &self,
+        path: &Path,
+        devices: &[&DeviceInfo],
+    ) -> Result<(), Box<dyn Error>> {
+        let file = std::fs::File::create(path)?;
+        serde_json::to_writer_pretty(file, &self.devices_export(devices))?;
+        Ok(())
+    }
+
+    /// Builds the JSON export of the given devices.
+    pub fn devices_export(&self, devices: &[&DeviceInfo]) -> DeviceExport {
+        let mut excluded_addresses: Vec<String> = self
+            .excluded_addresses
+            .iter()
+            .map(|address| match &self.redactor {
+                Some(redactor) => redactor.address(address),
+                None => address.clone(),
+            })
+            .collect();
+        excluded_addresses.sort();
+        DeviceExport {
+            schema_version: EXPORT_SCHEMA_VERSION,
+            crate_version: env!("CARGO_PKG_VERSION"),
+            exported_at: chrono::Local::now().to_rfc3339(),
+            session: ExportSession {
+                started_at: self.session_started_at.to_rfc3339(),
+                adapter: self.adapter.clone(),
+                filters: ExportFilters {
+                    session_mode: self.session_mode.label(),
+                    min_rssi: self.min_rssi,
+                    tag: self.tag_filter,
+                    category: self.category_filter,
+                    manufacturer: self.manufacturer_filter,
+                    address_type: (self.address_type_filter != AddressTypeFilter::All)
+                        .then(|| self.address_type_filter.label()),
+                    excluded_addresses,
+                },
+            },
+            devices: devices
+                .iter()
+                .map(|device| self.device_json(device))
+                .collect(),
+        }
+    }
+
+    /// Answers the requests the HTTP API has received since the last call.
+    pub fn answer_api_queries(&mut self) {
+        while let Some(query) = self
+            .api_queries
+            .as_mut()
+            .and_then(|queries| queries.try_recv().ok())
+        {
+            self.answer_api_query(query);
+        }
+    }
+
+    /// Answers a request from the HTTP API. A client that went away is ignored.
+    pub fn answer_api_query(&mut self, query: ApiQuery) {
+        let body = match query.request {
+            ApiRequest::Devices => {
+                let devices: Vec<_> = self.devices.iter().collect();
+                serde_json::to_vec(&self.devices_export(&devices)).ok()
+            }
+            ApiRequest::Device(address) => self
+                .device_detail(&address)
+                .and_then(|detail| serde_json::to_vec(&detail).ok()),
+            ApiRequest::SetPaused(paused) => {
+                self.set_paused(paused);
+                serde_json::to_vec(&serde_json::json!({ "paused": paused })).ok()
+            }
+        };
+        let _ = query.reply.send(body);
+    }
+
+    /// Builds the JSON representation of a device with its history, looked up by address
+    /// or platform id. Returns `None` if the device is not in the list.
+    pub fn device_detail(&self, address: &str) -> Option<DeviceDetailJson> {
+        let normalized = normalize_address(address);
+        let device = self.devices.iter().find(|device| {
+            device.id == address
+                || normalized.as_deref() == Some(device.get_id(self.identity_key).as_str())
+        })?;
+        let identity = device.get_id(self.identity_key);
+        Some(DeviceDetailJson {
+            device: self.device_json(device),
+            previous_names: self
+                .name_history
+                .get(&identity)
+                .cloned()
+                .unwrap_or_default(),
+            rssi_history: self
+                .rssi_history
+                .iter()
+                .flat_map(|history| history.samples(&identity))
+                .map(|(at, rssi)| RssiSampleJson {
+                    timestamp: at.to_rfc3339(),
+                    rssi: *rssi,
+                })
+                .collect(),
+            history: self.db_history.get(&identity).cloned().flatten(),
+            raw_advertisement: bytes_to_hex(&device.raw_advertisement),
+            raw_reconstructed: device.raw_reconstructed,
+            characteristics: if self.connected_identity.as_ref() == Some(&identity) {
+                self.selected_characteristics
+                    .iter()
+                    .map(|characteristic| CharacteristicJson {
+                        service: characteristic.service.to_string(),
+                        uuid: characteristic.uuid.to_string(),
+                        properties: characteristic
+                            .properties
+                            .iter_names()
+                            .map(|(name, _)| name.to_string())
+                            .collect(),
+                        descriptors: characteristic
+                            .descriptors
+                            .iter()
+                            .map(Uuid::to_string)
+                            .collect(),
+                    })
+                    .collect()
+            } else {
+                Vec::new()
+            },
+        })
+    }
+
+    /// Builds the JSON representation of a device, as used in exports.
+    pub fn device_json(&self, device: &DeviceInfo) -> DeviceJson {
+        let stats = self.rssi_stats.get(&device.get_id(self.identity_key));
+        DeviceJson {
+            id: device.id.clone(),
+            address: device.address.clone(),
+            address_type: device.address_type_label(),
+            device_type: device.device_type_label(),
+            class: device.class,
+            name: device.name.clone(),
+            alias: self.alias_for(device).map(str::to_string),
+            note: self.note_for(device).map(str::to_string),
+            tx_power: device.tx_power,
+            rssi: device.rssi,
+            rssi_min: stats.map(|stats| stats.min),
+            rssi_max: stats.map(|stats| stats.max),
+            rssi_mean: stats.map(|stats| stats.mean()),
+            rssi_samples: stats.map_or(0, |stats| stats.count),
+            detected_at: device.detected_at.clone(),
+            first_seen: device.first_seen.to_rfc3339(),
+            dwell_secs: device.dwell().as_secs(),
+            last_seen: device.last_seen.to_rfc3339(),
+            manufacturer_data: extract_manufacturer_data(&device.manufacturer_data),
+            service_data: device
+                .service_data
+                .iter()
+                .map(|(uuid, data)| (uuid.to_string(), bytes_to_hex(data)))
+                .collect::<BTreeMap<_, _>>(),
+            services: device.services.iter().map(Uuid::to_string).collect(),
+            solicited_services: device
+                .solicited_services
+                .iter()
+                .map(Uuid::to_string)
+                .collect(),
+            category: self.category_for(device),
+            tags: device_tags(device),
+            decoded: self.decoders.decode(device),
+        }
+    }
+}
+
+/// Returns the name of a single-device export, `device-<address>-<timestamp>.json`. Colons
+/// are dropped, since Windows does not allow them in file names, and other characters that
+/// are not letters, digits, `_` or `-` become `_`, so a platform id such as
+/// `hci0/dev_AA_BB_CC_DD_EE_01` does not name a directory.
+fn device_file_name(address: &str, timestamp: &str) -> String {
+    let address: String = address
+        .chars()
+        .filter(|c| *c != ':')
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("device-{}-{}.json", address, timestamp)
+}
+
+#[cfg(test)]
+mod tests {
+    use btleplug::api::AddressType;
+
+    use super::*;
+
+    fn app() -> App {
+        let mut app = App::new(
+            &Args::default(),
+            ScanConfig::default(),
+            DecoderRegistry::with_builtins(HashMap::new()),
+        );
+        // Aliases and notes saved by a previous run would leak into the exports.
+        app.state = PersistedState::default();
+        app
+    }
+
+    /// Compares `actual` with the fixture `name` under `tests/fixtures`, or rewrites the
+    /// fixture when `BTLESCAN_UPDATE_SNAPSHOTS` is set.
+    fn assert_fixture(name: &str, actual: &str) {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(name);
+        if std::env::var_os("BTLESCAN_UPDATE_SNAPSHOTS").is_some() {
+            std::fs::write(&path, actual).unwrap();
+            return;
+        }
+        let expected = std::fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("unable to read {}: {}", path.display(), e));
+        assert_eq!(actual, expected, "{} differs", path.display());
+    }
+
+    /// A local time `secs` seconds after 2024-05-01T12:00:00Z.
+    fn at(secs: i64) -> chrono::DateTime<chrono::Local> {
+        (chrono::DateTime::parse_from_rfc3339("2024-05-01T12:00:00Z").unwrap()
+            + chrono::Duration::seconds(secs))
+        .with_timezone(&chrono::Local)
+    }
+
+    /// Rewrites a local timestamp in UTC, so fixtures do not depend on the time zone.
+    fn in_utc(timestamp: &mut serde_json::Value) {
+        let utc = chrono::DateTime::parse_from_rfc3339(timestamp.as_str().unwrap())
+            .unwrap()
+            .with_timezone(&chrono::Utc)
+            .to_rfc3339();
+        *timestamp = utc.into();
+    }
+
+    /// Writes `devices` as CSV with the app's columns and reads the rows back.
+    fn csv_round_trip(app: &App, devices: &[DeviceInfo]) -> Vec<csv::StringRecord> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("devices.csv");
+        app.write_devices_csv(&path, &devices.iter().collect::<Vec<_>>())
+            .unwrap();
+        let mut reader = csv::Reader::from_path(&path).unwrap();
+        let headers: Vec<String> = reader.headers().unwrap().iter().map(String::from).collect();
+        assert_eq!(
+            headers,
+            app.csv_columns
+                .iter()
+                .map(|column| column.header())
+                .collect::<Vec<_>>()
+        );
+        reader.records().map(Result::unwrap).collect()
+    }
+
+    #[test]
+    fn csv_export_round_trips_quoted_fields_and_rfc_3339_timestamps() {
+        let mut app = app();
+        app.csv_columns = vec![
+            CsvColumn::Address,
+            CsvColumn::Name,
+            CsvColumn::DetectedAt,
+            CsvColumn::FirstSeen,
+            CsvColumn::LastSeen,
+        ];
+        let names = [
+            "Lamp, kitchen",
+            "The \"good\" kettle",
+            "Two\nlines",
+            "Plain",
+        ];
+        let devices: Vec<DeviceInfo> = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| DeviceInfo {
+                address: format!("AA:BB:CC:DD:EE:0{}", i),
+                name: Some(name.to_string()),
+                detected_at: chrono::Local::now()
+                    .to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+                ..DeviceInfo::default()
+            })
+            .collect();
+        let rows = csv_round_trip(&app, &devices);
+        assert_eq!(rows.len(), names.len());
+        for ((row, device), name) in rows.iter().zip(&devices).zip(names) {
+            assert_eq!(&row[0], device.address);
+            assert_eq!(&row[1], name);
+            for timestamp in [&row[2], &row[3], &row[4]] {
+                assert!(
+                    chrono::DateTime::parse_from_rfc3339(timestamp).is_ok(),
+                    "{}",
+                    timestamp
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn json_export_matches_the_golden_file() {
+        let mut app = app();
+        app.adapter = Some("00:1A:7D:DA:71:13".to_string());
+        app.min_rssi = Some(-90);
+        app.excluded_addresses = HashSet::from(["AA:BB:CC:DD:EE:FF".to_string()]);
+        let heart_rate = Uuid::from_u128(0x0000180d_0000_1000_8000_00805f9b34fb);
+        let beacon = DeviceInfo {
+            id: "hci0/dev_AA_BB_CC_DD_EE_01".to_string(),
+            address: "AA:BB:CC:DD:EE:01".to_string(),
+            address_type: Some(AddressType::Public),
+            name: Some("Beacon".to_string()),
+            tx_power: Some(-4),
+            rssi: Some(-58),
+            manufacturer_data: HashMap::from([(
+                0x004c,
+                vec![
+                    0x02, 0x15, 0xf7, 0x82, 0x6d, 0xa6, 0x4f, 0xa2, 0x4e, 0x98, 0x80, 0x24, 0xbc,
+                    0x5b, 0x71, 0xe0, 0x89, 0x3e, 0x00, 0x01, 0x00, 0x02, 0xc5,
+                ],
+            )]),
+            services: vec![heart_rate],
+            service_data: HashMap::from([(heart_rate, vec![0x06, 0x48])]),
+            detected_at: at(0).to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+            first_seen: at(0),
+            last_seen: at(95),
+            ..DeviceInfo::default()
+        };
+        let hidden = DeviceInfo {
+            id: "3F2A-91C0".to_string(),
+            address: "00:00:00:00:00:00".to_string(),
+            detected_at: at(30).to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+            first_seen: at(30),
+            last_seen: at(30),
+            ..DeviceInfo::default()
+        };
+        let mut stats = RssiStats::new(-62);
+        stats.add(-58);
+        app.rssi_stats
+            .insert(beacon.get_id(app.identity_key), stats);
+
+        let mut export = serde_json::to_value(app.devices_export(&[&beacon, &hidden])).unwrap();
+        export["crate_version"] = "<version>".into();
+        export["exported_at"] = "<exported_at>".into();
+        export["session"]["started_at"] = "<started_at>".into();
+        for device in export["devices"].as_array_mut().unwrap() {
+            for field in ["detected_at", "first_seen", "last_seen"] {
+                in_utc(&mut device[field]);
+            }
+        }
+        assert_fixture(
+            "export.json",
+            &(serde_json::to_string_pretty(&export).unwrap() + "\n"),
+        );
+    }
+
+    #[test]
+    fn csv_name_is_the_advertised_name_and_label_the_one_shown() {
+        let mut app = app();
+        app.csv_columns = vec![CsvColumn::Name, CsvColumn::Label];
+        app.name_fallback = NameFallback::Address;
+        let lamp = DeviceInfo {
+            id: "lamp".to_string(),
+            name: Some("Lamp".to_string()),
+            ..DeviceInfo::default()
+        };
+        let unnamed = DeviceInfo {
+            address: "AA:BB:CC:DD:EE:02".to_string(),
+            ..DeviceInfo::default()
+        };
+        app.state
+            .aliases
+            .insert("lamp".to_string(), "Desk lamp".to_string());
+        let rows = csv_round_trip(&app, &[lamp, unnamed]);
+        let rows: Vec<Vec<&str>> = rows.iter().map(|row| row.iter().collect()).collect();
+        assert_eq!(
+            rows,
+            [vec!["Lamp", "Desk lamp"], vec!["", "AA:BB:CC:DD:EE:02"]]
+        );
+    }
+
+    #[test]
+    fn evicting_a_device_forgets_its_names() {
+        let mut app = app();
+        app.max_devices = Some(1);
+        for (address, rssi) in [("AA:BB:CC:DD:EE:01", -80), ("AA:BB:CC:DD:EE:02", -50)] {
+            app.devices.push(DeviceInfo {
+                address: address.to_string(),
+                rssi: Some(rssi),
+                last_seen: chrono::Local::now(),
+                ..DeviceInfo::default()
+            });
+            app.name_history
+                .insert(address.to_string(), vec!["Old".to_string()]);
+            app.renamed_devices.insert(address.to_string());
+        }
+        app.enforce_device_cap();
+        assert_eq!(app.devices.len(), 1);
+        let kept = app.devices[0].get_id(app.identity_key);
+        assert_eq!(app.name_history.keys().collect::<Vec<_>>(), [&kept]);
+        assert_eq!(app.renamed_devices, HashSet::from([kept]));
+
+        app.devices[0].last_seen = chrono::DateTime::default();
+        app.evict_stale();
+        assert!(app.name_history.is_empty());
+        assert!(app.renamed_devices.is_empty());
+    }
+
+    #[test]
+    fn disconnect_results_go_to_the_status_bar() {
+        let mut app = app();
+        app.disconnect_all();
+        assert_eq!(app.status_indicator(), Some("no active connections"));
+        assert!(!app.error_view);
+
+        app.disconnected(
+            vec!["AA:BB:CC:DD:EE:01".to_string()],
+            vec!["AA:BB:CC:DD:EE:02 (timed out)".to_string()],
+        );
+        assert_eq!(
+            app.status_indicator(),
+            Some(
+                "disconnected 1: AA:BB:CC:DD:EE:01; \
+                 failed to disconnect 1: AA:BB:CC:DD:EE:02 (timed out)"
+            )
+        );
+    }
+
+    #[test]
+    fn device_file_name_is_a_plain_file_name() {
+        let timestamp = "2024-05-01_12-00-00";
+        assert_eq!(
+            device_file_name("AA:BB:CC:DD:EE:01", timestamp),
+            "device-AABBCCDDEE01-2024-05-01_12-00-00.json"
+        );
+        assert_eq!(
+            device_file_name("hci0/dev_AA_BB_CC_DD_EE_01", timestamp),
+            "device-hci0_dev_AA_BB_CC_DD_EE_01-2024-05-01_12-00-00.json"
+        );
+        assert_eq!(
+            device_file_name("{6f1c2b3a-0d4e}\\dev 1", timestamp),
+            "device-_6f1c2b3a-0d4e__dev_1-2024-05-01_12-00-00.json"
+        );
     }
 }
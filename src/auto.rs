@@ -0,0 +1,154 @@
+use std::{error::Error, io, path::Path, time::Duration};
+
+use btleplug::{
+    api::{Central, CentralEvent, Peripheral as _, ScanFilter},
+    platform::{Adapter, Peripheral},
+};
+use futures::StreamExt;
+
+use crate::{
+    adapters::default_adapter,
+    cli::{AutoLog, LogRotation},
+    error::BluscanError,
+    event_log::RotatingFile,
+    utils::bytes_to_hex,
+};
+
+/// The delay before the first reconnection attempt. It doubles after every failed attempt.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// The longest delay between two reconnection attempts.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// How long a single connection attempt may take before it is abandoned.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Appends timestamped lines to the log file, echoing connection states to stderr. The
+/// file is rotated by the `rotation` limits before the line that would exceed them.
+struct Logger {
+    file: RotatingFile,
+}
+
+impl Logger {
+    fn open(path: &Path, rotation: LogRotation) -> io::Result<Self> {
+        Ok(Self {
+            file: RotatingFile::open(path, rotation)?,
+        })
+    }
+
+    /// Writes a line and flushes it, so the file is complete whenever it is moved.
+    fn write(&mut self, kind: &str, message: &str) -> Result<(), BluscanError> {
+        let line = format!(
+            "{} {} {}\n",
+            chrono::Local::now().to_rfc3339(),
+            kind,
+            message
+        );
+        self.file
+            .write_line(line.as_bytes())
+            .and_then(|()| self.file.flush())
+            .map_err(|e| BluscanError::Export(e.into()))
+    }
+
+    fn state(&mut self, message: &str) -> Result<(), BluscanError> {
+        eprintln!("{}", message);
+        self.write("state", message)
+    }
+}
+
+/// Connects to the target device, subscribes to the notify characteristic and logs every
+/// notification until the process is interrupted. When the link drops, or a connection
+/// attempt fails, it reconnects with an exponential backoff.
+pub async fn auto_log(options: &AutoLog) -> Result<(), BluscanError> {
+    let mut logger = Logger::open(&options.log, options.rotation.clone()).map_err(|e| {
+        BluscanError::Usage(format!("unable to open {}: {}", options.log.display(), e))
+    })?;
+    let central = default_adapter().await?;
+    central
+        .start_scan(ScanFilter::default())
+        .await
+        .map_err(BluscanError::Discovery)?;
+
+    let mut backoff = INITIAL_BACKOFF;
+    loop {
+        logger.state(&format!("searching for {}", options.target))?;
+        let peripheral = find_target(&central, &options.target).await?;
+
+        logger.state("connecting")?;
+        match session(&central, &peripheral, options, &mut logger).await {
+            Ok(()) => {
+                backoff = INITIAL_BACKOFF;
+                logger.state("disconnected")?;
+            }
+            Err(e) => logger.state(&format!("connection failed: {}", e))?,
+        }
+        let _ = peripheral.disconnect().await;
+
+        logger.state(&format!("reconnecting in {}s", backoff.as_secs()))?;
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+/// Waits until the scan has found a device whose address or platform id matches `target`,
+/// which the command line has already normalized.
+async fn find_target(central: &Adapter, target: &str) -> Result<Peripheral, BluscanError> {
+    loop {
+        for peripheral in central
+            .peripherals()
+            .await
+            .map_err(BluscanError::Discovery)?
+        {
+            if peripheral.address().to_string() == target
+                || peripheral.id().to_string().to_uppercase() == target
+            {
+                return Ok(peripheral);
+            }
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
+
+/// Runs one connection: connects, subscribes and logs notifications until the device
+/// disconnects. Returns an error if the connection could not be set up.
+async fn session(
+    central: &Adapter,
+    peripheral: &Peripheral,
+    options: &AutoLog,
+    logger: &mut Logger,
+) -> Result<(), Box<dyn Error>> {
+    tokio::time::timeout(CONNECT_TIMEOUT, peripheral.connect())
+        .await
+        .map_err(|_| "connection timed out")??;
+    logger.state("connected")?;
+
+    peripheral.discover_services().await?;
+    let characteristic = peripheral
+        .characteristics()
+        .into_iter()
+        .find(|c| c.uuid == options.notify)
+        .ok_or_else(|| format!("characteristic {} not found", options.notify))?;
+    peripheral.subscribe(&characteristic).await?;
+    logger.state(&format!("subscribed to {}", options.notify))?;
+
+    let mut notifications = peripheral.notifications().await?;
+    let mut events = central.events().await?;
+    loop {
+        tokio::select! {
+            notification = notifications.next() => match notification {
+                Some(notification) if notification.uuid == options.notify => {
+                    logger.write("notification", &bytes_to_hex(&notification.value))?;
+                }
+                Some(_) => {}
+                None => return Ok(()),
+            },
+            event = events.next() => match event {
+                Some(CentralEvent::DeviceDisconnected(id)) if id == peripheral.id() => {
+                    return Ok(());
+                }
+                Some(_) => {}
+                None => return Ok(()),
+            },
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{structs::DeviceInfo, utils::bytes_to_hex};
+
+/// The version written in the header of every capture, raised when the line format changes
+/// in a way older versions of `--replay` could not read.
+pub const CAPTURE_VERSION: u32 = 1;
+
+/// How often buffered observations are flushed to the file.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// One device observation in a capture, or a device update in an event log written with
+/// `--log-file`. Event log lines lack the offset and the advertisement payloads, so those
+/// fields default to empty when reading.
+#[derive(Serialize, Deserialize)]
+pub struct CaptureLine {
+    pub event: String,
+    /// Milliseconds since the capture started, on a monotonic clock.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub t_ms: Option<u64>,
+    pub timestamp: String,
+    pub id: String,
+    pub address: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address_type: Option<String>,
+    pub name: Option<String>,
+    pub rssi: Option<i16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tx_power: Option<i16>,
+    /// Manufacturer data by company id, as hex.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub manufacturer_data: BTreeMap<u16, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub services: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub solicited_services: Vec<String>,
+    /// Service data by service UUID, as hex.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub service_data: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub class: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub appearance: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connectable: Option<bool>,
+}
+
+/// Records every device observation the scanner reports, before any view filter, to a
+/// newline-delimited JSON file that `--replay` reads back with the original timing. The
+/// file starts with a header carrying `CAPTURE_VERSION`. Writes are buffered and flushed
+/// at most once per `FLUSH_INTERVAL`, and when the capture is dropped.
+pub struct CaptureWriter {
+    pub path: PathBuf,
+    writer: BufWriter<File>,
+    started: Instant,
+    last_flush: Instant,
+}
+
+impl CaptureWriter {
+    /// Opens the capture file for appending, creating it if needed, and writes the header.
+    /// Appending to an earlier capture starts a new section that replays after it.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = File::options().create(true).append(true).open(path)?;
+        let mut capture = Self {
+            path: path.to_path_buf(),
+            writer: BufWriter::new(file),
+            started: Instant::now(),
+            last_flush: Instant::now(),
+        };
+        let header = serde_json::json!({
+            "event": "capture",
+            "version": CAPTURE_VERSION,
+            "started_at": chrono::Local::now().to_rfc3339(),
+        });
+        capture.write_line(serde_json::to_vec(&header)?)?;
+        Ok(capture)
+    }
+
+    /// Writes an observation of `device`.
+    pub fn record(&mut self, device: &DeviceInfo) -> io::Result<()> {
+        let line = CaptureLine {
+            event: "observed".to_string(),
+            t_ms: Some(self.started.elapsed().as_millis() as u64),
+            timestamp: device.last_seen.to_rfc3339(),
+            id: device.id.clone(),
+            address: device.address.clone(),
+            address_type: device
+                .address_type
+                .map(|_| device.address_type_label().to_string()),
+            name: device.name.clone(),
+            rssi: device.rssi,
+            tx_power: device.tx_power,
+            manufacturer_data: device
+                .manufacturer_data
+                .iter()
+                .map(|(company, data)| (*company, bytes_to_hex(data)))
+                .collect(),
+            services: device.services.iter().map(Uuid::to_string).collect(),
+            solicited_services: device
+                .solicited_services
+                .iter()
+                .map(Uuid::to_string)
+                .collect(),
+            service_data: device
+                .service_data
+                .iter()
+                .map(|(uuid, data)| (uuid.to_string(), bytes_to_hex(data)))
+                .collect(),
+            class: device.class,
+            appearance: device.appearance,
+            connectable: device.is_connectable,
+        };
+        self.write_line(serde_json::to_vec(&line)?)
+    }
+
+    /// Writes the buffered observations to the file.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.last_flush = Instant::now();
+        self.writer.flush()
+    }
+
+    /// Flushes the buffered observations if the flush interval has passed.
+    pub fn flush_if_due(&mut self) -> io::Result<()> {
+        if self.last_flush.elapsed() >= FLUSH_INTERVAL {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn write_line(&mut self, mut line: Vec<u8>) -> io::Result<()> {
+        line.push(b'\n');
+        self.writer.write_all(&line)?;
+        self.flush_if_due()
+    }
+}
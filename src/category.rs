@@ -0,0 +1,246 @@
+use crate::{
+    decoders::{
+        altbeacon::decode_altbeacon,
+        bthome::BTHOME_UUID,
+        eddystone::EDDYSTONE_UUID,
+        exposure::EXPOSURE_NOTIFICATION_UUID,
+        fast_pair::FAST_PAIR_UUID,
+        govee::GOVEE_COMPANY_IDS,
+        ibeacon::decode_ibeacon,
+        mibeacon::MIBEACON_UUID,
+        microsoft::{decode_microsoft, MicrosoftBeacon, MICROSOFT_COMPANY_ID},
+        ruuvi::RUUVI_COMPANY_ID,
+        trackers::detect_tracker,
+        APPLE_COMPANY_ID,
+    },
+    structs::{DeviceCategory, DeviceInfo},
+    utils::short_uuid,
+};
+
+/// Classifies a device into a coarse category from what it advertises. The most specific
+/// evidence wins: tracker detection, then the type the device declares in its classic Class
+/// of Device or GAP Appearance, then beacon and sensor formats, then well-known services.
+pub fn classify(device: &DeviceInfo) -> DeviceCategory {
+    if detect_tracker(device).is_some() {
+        return DeviceCategory::Tracker;
+    }
+    if let Some(class) = device.class {
+        match (class >> 8) & 0x1F {
+            0x01 => return DeviceCategory::Laptop,
+            0x02 => return DeviceCategory::Phone,
+            0x04 => return DeviceCategory::Audio,
+            0x05 => return DeviceCategory::Peripheral,
+            0x07 => return DeviceCategory::Wearable,
+            0x09 => return DeviceCategory::Sensor,
+            _ => {}
+        }
+    }
+    if let Some(category) = device.appearance.and_then(appearance_category) {
+        return category;
+    }
+
+    let is_ibeacon = device
+        .manufacturer_data
+        .get(&APPLE_COMPANY_ID)
+        .and_then(|data| decode_ibeacon(data))
+        .is_some();
+    let is_altbeacon = device
+        .manufacturer_data
+        .values()
+        .any(|data| decode_altbeacon(data).is_some());
+    if is_ibeacon || is_altbeacon || device.service_data.contains_key(&EDDYSTONE_UUID) {
+        return DeviceCategory::Beacon;
+    }
+    if [BTHOME_UUID, MIBEACON_UUID]
+        .iter()
+        .any(|uuid| device.service_data.contains_key(uuid))
+        || GOVEE_COMPANY_IDS
+            .iter()
+            .chain(&[RUUVI_COMPANY_ID])
+            .any(|id| device.manufacturer_data.contains_key(id))
+    {
+        return DeviceCategory::Sensor;
+    }
+    // Exposure Notifications are broadcast by the contact tracing apps on phones.
+    if device
+        .service_data
+        .contains_key(&EXPOSURE_NOTIFICATION_UUID)
+    {
+        return DeviceCategory::Phone;
+    }
+    if device.service_data.contains_key(&FAST_PAIR_UUID) {
+        return DeviceCategory::Audio;
+    }
+    match device
+        .manufacturer_data
+        .get(&MICROSOFT_COMPANY_ID)
+        .and_then(|data| decode_microsoft(data))
+    {
+        Some(Ok(MicrosoftBeacon::SwiftPair { .. })) => return DeviceCategory::Peripheral,
+        Some(Ok(MicrosoftBeacon::Cdp { .. })) => return DeviceCategory::Laptop,
+        _ => {}
+    }
+
+    device
+        .services
+        .iter()
+        .filter_map(short_uuid)
+        .find_map(|service| match service {
+            0x180D | 0x1814 | 0x1816 | 0x1818 | 0x183E => Some(DeviceCategory::Wearable),
+            0x1812 => Some(DeviceCategory::Peripheral),
+            0x1808 | 0x1809 | 0x1810 | 0x181A | 0x181D | 0x181F | 0x1822 => {
+                Some(DeviceCategory::Sensor)
+            }
+            0x1844 | 0x184E | 0x184F | 0x1850 | 0x1853 | 0x1854 | 0xFE03 => {
+                Some(DeviceCategory::Audio)
+            }
+            _ => None,
+        })
+        .unwrap_or(DeviceCategory::Unknown)
+}
+
+/// Returns the category of a GAP Appearance, from its upper ten bits (the Appearance
+/// category of the Bluetooth Assigned Numbers), or `None` for categories that say nothing
+/// about what the device is.
+fn appearance_category(appearance: u16) -> Option<DeviceCategory> {
+    match appearance >> 6 {
+        0x001 => Some(DeviceCategory::Phone),
+        0x002 => Some(DeviceCategory::Laptop),
+        0x003 | 0x007 | 0x00D | 0x011 | 0x012 => Some(DeviceCategory::Wearable),
+        0x008 | 0x009 => Some(DeviceCategory::Tracker),
+        0x00A | 0x021 | 0x022 | 0x025 | 0x029 => Some(DeviceCategory::Audio),
+        0x006 | 0x00B | 0x00F | 0x02A => Some(DeviceCategory::Peripheral),
+        0x00C | 0x00E | 0x010 | 0x015 | 0x031 | 0x032 | 0x034 => Some(DeviceCategory::Sensor),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn uuid16(short: u16) -> Uuid {
+        Uuid::from_u128(0x0000_0000_0000_1000_8000_0080_5f9b_34fb | (u128::from(short) << 96))
+    }
+
+    #[test]
+    fn classify_uses_the_strongest_evidence() {
+        let ibeacon = vec![
+            0x02, 0x15, 0xf7, 0x82, 0x6d, 0xa6, 0x4f, 0xa2, 0x4e, 0x98, 0x80, 0x24, 0xbc, 0x5b,
+            0x71, 0xe0, 0x89, 0x3e, 0x00, 0x01, 0x00, 0x02, 0xc5,
+        ];
+        let cases = [
+            (
+                "nothing advertised",
+                DeviceInfo::default(),
+                DeviceCategory::Unknown,
+            ),
+            (
+                "phone class of device",
+                DeviceInfo {
+                    class: Some(0x5a020c),
+                    ..DeviceInfo::default()
+                },
+                DeviceCategory::Phone,
+            ),
+            (
+                "watch appearance",
+                DeviceInfo {
+                    appearance: Some(0x00C1),
+                    ..DeviceInfo::default()
+                },
+                DeviceCategory::Wearable,
+            ),
+            (
+                "keyboard appearance",
+                DeviceInfo {
+                    appearance: Some(0x03C1),
+                    ..DeviceInfo::default()
+                },
+                DeviceCategory::Peripheral,
+            ),
+            (
+                "earbuds appearance",
+                DeviceInfo {
+                    appearance: Some(0x0941),
+                    ..DeviceInfo::default()
+                },
+                DeviceCategory::Audio,
+            ),
+            (
+                "keyring appearance",
+                DeviceInfo {
+                    appearance: Some(0x0240),
+                    ..DeviceInfo::default()
+                },
+                DeviceCategory::Tracker,
+            ),
+            (
+                "generic appearance falls through to the services",
+                DeviceInfo {
+                    appearance: Some(0x0000),
+                    services: vec![uuid16(0x180D)],
+                    ..DeviceInfo::default()
+                },
+                DeviceCategory::Wearable,
+            ),
+            (
+                "class of device before appearance",
+                DeviceInfo {
+                    class: Some(0x240404),
+                    appearance: Some(0x0040),
+                    ..DeviceInfo::default()
+                },
+                DeviceCategory::Audio,
+            ),
+            (
+                "appearance before beacon formats",
+                DeviceInfo {
+                    appearance: Some(0x0080),
+                    manufacturer_data: HashMap::from([(APPLE_COMPANY_ID, ibeacon.clone())]),
+                    ..DeviceInfo::default()
+                },
+                DeviceCategory::Laptop,
+            ),
+            (
+                "ibeacon",
+                DeviceInfo {
+                    manufacturer_data: HashMap::from([(APPLE_COMPANY_ID, ibeacon)]),
+                    ..DeviceInfo::default()
+                },
+                DeviceCategory::Beacon,
+            ),
+            (
+                "ruuvi sensor",
+                DeviceInfo {
+                    manufacturer_data: HashMap::from([(RUUVI_COMPANY_ID, vec![0x05])]),
+                    ..DeviceInfo::default()
+                },
+                DeviceCategory::Sensor,
+            ),
+            (
+                "exposure notification",
+                DeviceInfo {
+                    service_data: HashMap::from([(EXPOSURE_NOTIFICATION_UUID, vec![0; 20])]),
+                    ..DeviceInfo::default()
+                },
+                DeviceCategory::Phone,
+            ),
+            (
+                "human interface service",
+                DeviceInfo {
+                    services: vec![uuid16(0x1812)],
+                    ..DeviceInfo::default()
+                },
+                DeviceCategory::Peripheral,
+            ),
+        ];
+        for (case, device, expected) in cases {
+            assert_eq!(classify(&device), expected, "{}", case);
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// The span of recent passes the channel health is judged over.
+const HEALTH_WINDOW: Duration = Duration::from_secs(5);
+
+/// How many batches may be waiting when the interface drains the channel before it counts as
+/// lagging. At the default coalescing window a pass finds one batch at most.
+const LAG_THRESHOLD: usize = 8;
+
+/// How many observations the scan may merge into each device of a batch, on average, before
+/// it counts as busy.
+const BUSY_RATIO: f64 = 3.0;
+
+/// How the channel between the scan and the interface is doing.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ChannelState {
+    /// No batches arrived: every recent pass found the channel empty.
+    Quiet,
+    Ok,
+    /// The scan merged many repeated observations of the same devices into each batch.
+    Busy,
+    /// Batches piled up between two passes of the interface.
+    Lagging,
+}
+
+/// What one pass of the interface drained from the channel.
+struct Pass {
+    at: Instant,
+    batches: usize,
+    devices: usize,
+    observations: usize,
+}
+
+/// Tracks what the interface drains from the scan channel on each pass, to tell a quiet
+/// scan from a busy one and to notice batches piling up faster than they are drawn.
+#[derive(Default)]
+pub struct ChannelHealth {
+    passes: VecDeque<Pass>,
+    batches: usize,
+    devices: usize,
+    observations: usize,
+    /// Whether the current lagging episode was reported.
+    warned: bool,
+}
+
+impl ChannelHealth {
+    /// Counts a batch drained in the current pass.
+    pub fn record_batch(&mut self, devices: usize, observations: usize) {
+        self.batches += 1;
+        self.devices += devices;
+        self.observations += observations;
+    }
+
+    /// Ends the current pass. Returns `true` the first time the channel is found lagging,
+    /// and again only after it has recovered.
+    pub fn finish_pass(&mut self) -> bool {
+        let now = Instant::now();
+        self.passes.push_back(Pass {
+            at: now,
+            batches: std::mem::take(&mut self.batches),
+            devices: std::mem::take(&mut self.devices),
+            observations: std::mem::take(&mut self.observations),
+        });
+        while self
+            .passes
+            .front()
+            .is_some_and(|pass| now.duration_since(pass.at) > HEALTH_WINDOW)
+        {
+            self.passes.pop_front();
+        }
+        match self.state() {
+            ChannelState::Lagging => !std::mem::replace(&mut self.warned, true),
+            ChannelState::Quiet | ChannelState::Ok => {
+                self.warned = false;
+                false
+            }
+            ChannelState::Busy => false,
+        }
+    }
+
+    pub fn state(&self) -> ChannelState {
+        let ratio = self.coalescing();
+        if self.max_backlog() >= LAG_THRESHOLD {
+            ChannelState::Lagging
+        } else if self.passes.iter().all(|pass| pass.batches == 0) {
+            ChannelState::Quiet
+        } else if ratio >= BUSY_RATIO {
+            ChannelState::Busy
+        } else {
+            ChannelState::Ok
+        }
+    }
+
+    /// The most batches a single recent pass drained.
+    pub fn max_backlog(&self) -> usize {
+        self.passes
+            .iter()
+            .map(|pass| pass.batches)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// The observations received per second over the recent passes.
+    pub fn rate(&self) -> f64 {
+        let (Some(first), Some(last)) = (self.passes.front(), self.passes.back()) else {
+            return 0.0;
+        };
+        let span = last.at.duration_since(first.at).as_secs_f64().max(1.0);
+        self.passes
+            .iter()
+            .map(|pass| pass.observations)
+            .sum::<usize>() as f64
+            / span
+    }
+
+    /// The average number of observations merged into each device of a batch.
+    pub fn coalescing(&self) -> f64 {
+        let devices: usize = self.passes.iter().map(|pass| pass.devices).sum();
+        let observations: usize = self.passes.iter().map(|pass| pass.observations).sum();
+        if devices == 0 {
+            return 1.0;
+        }
+        observations as f64 / devices as f64
+    }
+
+    /// A short summary for the status bar, such as `busy, 120 obs/s, 3.2× coalesced`.
+    pub fn label(&self) -> String {
+        match self.state() {
+            ChannelState::Quiet => "quiet".to_string(),
+            ChannelState::Ok => format!("ok, {:.0} obs/s", self.rate()),
+            ChannelState::Busy => format!(
+                "busy, {:.0} obs/s, {:.1}× coalesced",
+                self.rate(),
+                self.coalescing()
+            ),
+            ChannelState::Lagging => format!("LAGGING, {} batches queued", self.max_backlog()),
+        }
+    }
+}
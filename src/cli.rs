@@ -0,0 +1,738 @@
+use std::{net::SocketAddr, path::PathBuf, time::Duration};
+
+use clap::{builder::ArgPredicate, ArgGroup, CommandFactory, Parser, Subcommand};
+use ratatui::style::Color;
+use uuid::Uuid;
+
+use crate::{
+    structs::{CsvColumn, EvictionPolicy, IdentityKey, RssiBarStyle, SessionMode, SortKey},
+    utils::normalize_address,
+};
+
+/// The default window over which scan results are coalesced.
+pub const DEFAULT_COALESCE_WINDOW: Duration = Duration::from_millis(200);
+
+/// How long --oneshot scans when no duration is given.
+pub const DEFAULT_ONESHOT_DURATION: Duration = Duration::from_secs(10);
+
+/// The default number of previous names kept per device.
+pub const DEFAULT_NAME_HISTORY: usize = 5;
+
+/// The command line: a subcommand, or the options of a scan.
+#[derive(Parser)]
+#[command(
+    name = "btlescan",
+    version,
+    about = "Scans for Bluetooth Low Energy devices and shows them in an interactive table",
+    args_conflicts_with_subcommands = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    #[command(flatten)]
+    pub args: Args,
+}
+
+/// The subcommands of `btlescan`.
+#[derive(Subcommand)]
+pub enum Command {
+    /// Scan for devices, the default when no subcommand is given
+    Scan(Args),
+    /// Replay a --record capture or --log-file event log instead of scanning
+    Replay {
+        /// The capture or event log to replay
+        #[arg(value_name = "PATH")]
+        path: PathBuf,
+        #[command(flatten)]
+        args: Args,
+    },
+    /// Compare two JSON exports
+    Diff(DiffArgs),
+    /// Scan until a matching device is seen
+    WaitFor(WaitForArgs),
+    /// List the Bluetooth adapters
+    Adapters(AdaptersArgs),
+}
+
+impl Cli {
+    /// Parses the process arguments, printing the usage and exiting on errors and when help
+    /// or the version is requested. Scanning is the default subcommand, and `replay <PATH>`
+    /// is a scan with `--replay <PATH>`.
+    pub fn parse_command() -> Command {
+        let Cli { command, args } = Cli::parse();
+        let mut command = match command {
+            None => Command::Scan(args),
+            Some(Command::Replay { path, mut args }) => {
+                args.replay = Some(path.clone());
+                Command::Replay { path, args }
+            }
+            Some(command) => command,
+        };
+        if let Command::Scan(args) | Command::Replay { args, .. } = &mut command {
+            if let Err(e) = args.resolve() {
+                Cli::command()
+                    .error(clap::error::ErrorKind::ArgumentConflict, e)
+                    .exit();
+            }
+        }
+        command
+    }
+}
+
+/// Options parsed from the command line.
+#[derive(Clone, clap::Args)]
+#[command(group(ArgGroup::new("log_target").args(["log_file", "log"]).multiple(true)))]
+#[command(group(ArgGroup::new("line_output").args(["headless", "oneshot"])))]
+pub struct Args {
+    /// Scan with the adapter at this index in `btlescan adapters`
+    #[arg(long, value_name = "INDEX", default_value_t = 0)]
+    pub adapter: usize,
+    /// How devices that stop advertising are handled: `live` removes stale devices from the
+    /// list, `accumulate` keeps them greyed out
+    #[arg(long, value_name = "MODE", default_value = "live")]
+    pub session_mode: SessionMode,
+    /// What devices are merged and remembered by: `auto` is the address, or the platform id
+    /// where it is hidden; `address` leaves out devices with a hidden address; `id` is the
+    /// platform id, which on Linux differs per adapter
+    #[arg(long = "identity", value_name = "KEY", default_value = "auto")]
+    pub identity_key: IdentityKey,
+    /// Keep at most N devices, evicting by the eviction policy
+    #[arg(long, value_name = "N", value_parser = parse_positive)]
+    pub max_devices: Option<usize>,
+    /// Which devices --max-devices drops first: `oldest` since the last advertisement, or
+    /// `weakest` signal
+    #[arg(long, value_name = "POLICY", default_value = "oldest")]
+    pub eviction_policy: EvictionPolicy,
+    /// Order of the device table, cycled live with S: `discovered` order, `last-seen` (toggle
+    /// live with F) or `rssi`, optionally followed by `:asc` or `:desc`, e.g. rssi:desc
+    #[arg(
+        long,
+        value_name = "KEY[:DIR]",
+        default_value = "discovered",
+        value_parser = parse_sort_order
+    )]
+    pub sort: SortOrder,
+    /// Only show devices whose name, alias or address contains QUERY; edit live with /
+    #[arg(long, value_name = "QUERY")]
+    pub filter: Option<String>,
+    /// Hide devices that advertise no name; toggle live with N
+    #[arg(long)]
+    pub named_only: bool,
+    /// Hide the detail pane; toggle live with C
+    #[arg(long)]
+    pub compact: bool,
+    /// Append the vendor of public addresses to the address column; toggle live with V
+    #[arg(long)]
+    pub show_vendor: bool,
+    /// Show signal strength as a 0-100% quality instead of dBm; toggle live with P
+    #[arg(long)]
+    pub rssi_percent: bool,
+    /// Mark the names more than one listed device advertises with ⧉
+    #[arg(long)]
+    pub flag_duplicate_names: bool,
+    /// Hide devices weaker than this RSSI; adjust live with +/- and [/]
+    #[arg(long, value_name = "DBM", allow_negative_numbers = true)]
+    pub min_rssi: Option<i16>,
+    /// Send scan results to the interface at most once per window
+    #[arg(
+        long = "coalesce-ms",
+        value_name = "MS",
+        default_value = "200",
+        value_parser = parse_millis
+    )]
+    pub coalesce_window: Duration,
+    /// Previous names kept per device when its advertised name changes; 0 turns rename
+    /// detection off
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_NAME_HISTORY)]
+    pub name_history: usize,
+    /// Warn when a scan produces no results for this long; 0 turns the warning off
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value = "15",
+        value_parser = parse_seconds
+    )]
+    pub watchdog: Duration,
+    /// Pause the scan after this long without a key press, resuming on the next one
+    #[arg(long, value_name = "SECONDS", value_parser = parse_positive_seconds)]
+    pub idle_pause: Option<Duration>,
+    /// Only scan for devices advertising this service, filtered by the platform; repeat or
+    /// separate UUIDs with commas, toggle live with v
+    #[arg(
+        long = "service",
+        value_name = "UUID",
+        value_delimiter = ',',
+        value_parser = parse_uuid
+    )]
+    pub scan_services: Vec<Uuid>,
+    /// Skip an advertisement decoder; repeat or separate names with commas
+    #[arg(
+        long = "disable-decoder",
+        value_name = "NAME",
+        value_delimiter = ',',
+        value_parser = trimmed
+    )]
+    pub disabled_decoders: Vec<String>,
+    /// Advertisement key (32 hex digits) to decrypt a Victron device's Instant Readout;
+    /// repeat for several devices
+    #[arg(
+        long = "victron-key",
+        value_name = "ADDRESS=KEY",
+        value_parser = parse_victron_key
+    )]
+    pub victron_keys: Vec<(String, [u8; 16])>,
+    /// How long each attempt to connect to a device may take
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value = "10",
+        value_parser = parse_positive_seconds
+    )]
+    pub connect_timeout: Duration,
+    /// Retry a failed connection N times with exponential backoff
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub connect_retries: u32,
+    /// Leave a device out of the list; repeat or separate addresses with commas
+    #[arg(
+        long = "exclude",
+        value_name = "ADDRESS",
+        value_delimiter = ',',
+        value_parser = trimmed
+    )]
+    pub excluded_addresses: Vec<String>,
+    /// Keep the local adapter's own address in the list (excluded by default)
+    #[arg(long)]
+    pub include_self: bool,
+    /// Alert when this device appears or disappears; repeat or separate addresses with commas
+    #[arg(
+        long,
+        value_name = "ADDRESS",
+        value_delimiter = ',',
+        value_parser = trimmed
+    )]
+    pub watch: Vec<String>,
+    /// Also alert when a watched device's RSSI reaches this level
+    #[arg(
+        long,
+        value_name = "DBM",
+        allow_negative_numbers = true,
+        requires = "watch"
+    )]
+    pub watch_rssi: Option<i16>,
+    /// Show watch-list alerts as desktop notifications
+    #[arg(long, requires = "watch")]
+    pub desktop_notify: bool,
+    /// Log watched devices appearing and disappearing, and detected trackers, to the system
+    /// journal or syslog
+    #[arg(long)]
+    pub syslog: bool,
+    /// Replace the last three octets of addresses with a keyed hash in the display and every
+    /// output; the key changes with every run
+    #[arg(long)]
+    pub redact: bool,
+    /// Also replace device names with a keyed hash (implies --redact)
+    #[arg(long)]
+    pub redact_names: bool,
+    /// Background of every other row when striping is on (toggle with z), a color name or
+    /// #RRGGBB [default: #262626]
+    #[arg(long, value_name = "COLOR")]
+    pub stripe_color: Option<Color>,
+    /// Signal bar drawn in the RSSI column: blocks, ascii, dots or off
+    #[arg(long, value_name = "STYLE", default_value = "blocks")]
+    pub rssi_bar: RssiBarStyle,
+    /// On exit, write every device in the list to a CSV file
+    #[arg(long, value_name = "PATH")]
+    pub csv_out: Option<PathBuf>,
+    /// Comma-separated CSV columns; decoded:<LABEL> picks one decoded field [default: every
+    /// column but company, detected_at, first_seen, last_seen and service_count]
+    #[arg(
+        long,
+        value_name = "LIST",
+        value_delimiter = ',',
+        value_parser = parse_csv_column
+    )]
+    pub csv_columns: Vec<CsvColumn>,
+    /// On exit, write every device in the list to a JSON export
+    #[arg(long, value_name = "PATH")]
+    pub output: Option<PathBuf>,
+    /// Keep up to N timestamped RSSI readings per device for export with H [default when
+    /// --rssi-log is given: 3600]
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = parse_positive,
+        default_value_if("rssi_log", ArgPredicate::IsPresent, Some("3600"))
+    )]
+    pub rssi_history: Option<usize>,
+    /// On exit, write every RSSI reading of the session to a CSV file (identity, timestamp,
+    /// rssi); enables the RSSI history
+    #[arg(long, value_name = "PATH")]
+    pub rssi_log: Option<PathBuf>,
+    /// Append every device update to a newline-delimited JSON file
+    #[arg(long, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+    #[command(flatten)]
+    pub log_rotation: LogRotation,
+    /// On exit, write every device seen during the session to a JSON file
+    #[arg(long, value_name = "PATH")]
+    pub session_out: Option<PathBuf>,
+    /// Replay a --record capture or --log-file event log instead of scanning
+    #[arg(long, value_name = "PATH")]
+    pub replay: Option<PathBuf>,
+    /// How many times faster than recorded to replay
+    #[arg(
+        long,
+        value_name = "N",
+        default_value = "1",
+        value_parser = parse_replay_speed
+    )]
+    pub replay_speed: f64,
+    /// Record every scanner event to a capture file for --replay
+    #[arg(long, value_name = "PATH")]
+    pub record: Option<PathBuf>,
+    /// Where SHIFT+I writes its inventory report, as CSV if the path ends in .csv and JSON
+    /// otherwise [default: btlescan_inventory_<time>.json]
+    #[arg(long, value_name = "PATH")]
+    pub inventory_out: Option<PathBuf>,
+    /// Remember devices across sessions in a SQLite database
+    #[arg(long, value_name = "PATH")]
+    pub db: Option<PathBuf>,
+    /// Read aliases and categories from a CSV file of address,alias[,category] lines;
+    /// aliases set with `a` are written back to it
+    #[arg(long, value_name = "PATH")]
+    pub known_devices: Option<PathBuf>,
+    /// Look vendors up in an OUI database (IEEE oui.txt or oui.csv, or Wireshark manuf)
+    /// before the built-in one
+    #[arg(long, value_name = "PATH")]
+    pub oui_file: Option<PathBuf>,
+    /// Look manufacturers up in a company identifier database (Bluetooth SIG YAML, or CSV of
+    /// identifier,name lines) before the built-in one
+    #[arg(long, value_name = "PATH")]
+    pub company_file: Option<PathBuf>,
+    #[command(flatten)]
+    pub mqtt: Option<MqttConfig>,
+    #[command(flatten)]
+    pub influx: Option<InfluxConfig>,
+    /// Serve the scan state as JSON over HTTP, e.g. 127.0.0.1:8080
+    #[arg(long, value_name = "ADDR", value_parser = parse_listen_address)]
+    pub http_listen: Option<SocketAddr>,
+    /// Require `Authorization: Bearer <TOKEN>` on HTTP API requests; defaults to
+    /// $BTLESCAN_HTTP_TOKEN
+    #[arg(long, value_name = "TOKEN", requires = "http_listen")]
+    pub http_token: Option<String>,
+    /// Stream device updates as JSON to WebSocket clients, e.g. 127.0.0.1:8081
+    #[arg(long, value_name = "ADDR", value_parser = parse_listen_address)]
+    pub ws_listen: Option<SocketAddr>,
+    /// Print what the --db database knows about a device and exit
+    #[arg(long, value_name = "ADDRESS", requires = "db")]
+    pub db_query: Option<String>,
+    /// Scan without the interface, printing a line to stdout for every new device that
+    /// passes the filters
+    #[arg(long, conflicts_with = "oneshot")]
+    pub headless: bool,
+    /// Scan for --duration seconds, print a table of the devices found and exit
+    #[arg(long)]
+    pub oneshot: bool,
+    /// With --oneshot, print the JSON export instead of a table
+    #[arg(long, requires = "oneshot")]
+    pub json: bool,
+    /// With --headless or --oneshot, print one line per device from a template such as
+    /// '{address}\t{name}\t{rssi}'; see the README for the placeholders
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        requires = "line_output",
+        conflicts_with = "json"
+    )]
+    pub format: Option<String>,
+    /// How long --oneshot scans [default: 10] and when a --headless scan stops (runs until
+    /// Ctrl+C by default)
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds)]
+    pub duration: Option<Duration>,
+    #[command(flatten)]
+    pub auto: Option<AutoLog>,
+}
+
+impl Default for Args {
+    /// The options of a scan given no arguments.
+    fn default() -> Self {
+        Cli::parse_from(["btlescan"]).args
+    }
+}
+
+impl Args {
+    /// Fills in what the parser cannot: the tokens given through the environment, the
+    /// default CSV columns and the rotation of the --auto log. Returns the problem if the options do not work together.
+    fn resolve(&mut self) -> Result<(), String> {
+        if cfg!(target_os = "macos") && self.identity_key == IdentityKey::Address {
+            return Err(
+                "--identity address is not available on macOS, which hides device addresses"
+                    .to_string(),
+            );
+        }
+        if let Some(mqtt) = &mut self.mqtt {
+            mqtt.password = mqtt
+                .password
+                .take()
+                .or_else(|| std::env::var("BTLESCAN_MQTT_PASSWORD").ok());
+        }
+        if let Some(influx) = &mut self.influx {
+            influx.token = influx
+                .token
+                .take()
+                .or_else(|| std::env::var("BTLESCAN_INFLUX_TOKEN").ok());
+        }
+        if self.http_listen.is_some() {
+            self.http_token = self
+                .http_token
+                .take()
+                .or_else(|| std::env::var("BTLESCAN_HTTP_TOKEN").ok());
+        }
+        if self.csv_columns.is_empty() {
+            self.csv_columns = CsvColumn::DEFAULT.to_vec();
+        }
+        if let Some(auto) = &mut self.auto {
+            auto.rotation = self.log_rotation.clone();
+        }
+        Ok(())
+    }
+}
+
+/// The order of the device table given with `--sort`.
+#[derive(Clone, Copy)]
+pub struct SortOrder {
+    pub key: SortKey,
+    /// Whether the table is sorted against the sort key's natural order.
+    pub reversed: bool,
+}
+
+/// When the --log-file event log, or the --log of the --auto mode, is rotated, and what
+/// happens to rotated files.
+#[derive(Clone, Default, clap::Args)]
+pub struct LogRotation {
+    /// Rotate the --log-file or --log once it would grow past this many megabytes
+    #[arg(
+        id = "log_rotate_size",
+        long = "log-rotate-size",
+        value_name = "MB",
+        requires = "log_target",
+        value_parser = parse_megabytes
+    )]
+    pub max_bytes: Option<u64>,
+    /// Rotate the --log-file or --log once it has been written to for this long
+    #[arg(
+        id = "log_rotate_interval",
+        long = "log-rotate-interval",
+        value_name = "HOURS",
+        requires = "log_target",
+        value_parser = parse_hours
+    )]
+    pub interval: Option<Duration>,
+    /// Compress rotated log files with gzip
+    #[arg(
+        id = "log_rotate_gzip",
+        long = "log-rotate-gzip",
+        requires = "log_target"
+    )]
+    pub compress: bool,
+    /// Keep only the N most recent rotated log files
+    #[arg(
+        id = "log_rotate_keep",
+        long = "log-rotate-keep",
+        value_name = "N",
+        requires = "log_target"
+    )]
+    pub keep: Option<usize>,
+}
+
+/// The arguments of `btlescan diff`.
+#[derive(clap::Args)]
+#[command(
+    long_about = "Compares two JSON exports and reports the devices only in OLD, only in NEW, and \
+                  those in both whose name, services or manufacturer data changed. Exits with 1 \
+                  if they differ."
+)]
+pub struct DiffArgs {
+    /// The earlier JSON export
+    pub old: PathBuf,
+    /// The later JSON export
+    pub new: PathBuf,
+    /// Print the differences as JSON instead of tables
+    #[arg(long)]
+    pub json: bool,
+    /// Also pair devices by name when their address changed, for devices that rotate random
+    /// addresses
+    #[arg(long)]
+    pub match_names: bool,
+}
+
+/// The arguments of `btlescan wait-for`.
+#[derive(clap::Args)]
+#[command(
+    long_about = "Scans until a matching device is seen, prints it as one line of JSON and exits \
+                  with 0. Exits with 1 on timeout, 130 when interrupted and 2 on error."
+)]
+#[command(group(ArgGroup::new("target").args(["address", "name"]).required(true)))]
+pub struct WaitForArgs {
+    /// Wait for the device with this address, or platform id on macOS
+    #[arg(long, value_name = "ADDRESS", value_parser = parse_address)]
+    pub address: Option<String>,
+    /// Wait for a device advertising exactly this name
+    #[arg(long, value_name = "NAME")]
+    pub name: Option<String>,
+    /// Give up after this long (waits until Ctrl+C by default)
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds)]
+    pub timeout: Option<Duration>,
+    /// Only count the device once its RSSI reaches this level
+    #[arg(long, value_name = "DBM", allow_negative_numbers = true)]
+    pub min_rssi: Option<i16>,
+    /// Read a --record capture or --log-file event log instead of scanning; exits with 1 if
+    /// it ends without a match
+    #[arg(long, value_name = "PATH")]
+    pub replay: Option<PathBuf>,
+    /// How many times faster than recorded to replay
+    #[arg(
+        long,
+        value_name = "N",
+        default_value = "1",
+        value_parser = parse_replay_speed
+    )]
+    pub replay_speed: f64,
+}
+
+/// The arguments of `btlescan adapters`.
+#[derive(clap::Args)]
+#[command(
+    long_about = "Lists the Bluetooth adapters, numbered in the order --adapter picks from. \
+                  Capabilities the platform does not report are shown as unknown."
+)]
+pub struct AdaptersArgs {
+    /// Print the adapters as a JSON array
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// Where and how device updates are published over MQTT.
+#[derive(Clone, clap::Args)]
+#[group(requires = "mqtt_url", multiple = true)]
+pub struct MqttConfig {
+    /// Publish device updates to an MQTT broker, mqtt://HOST[:PORT] or mqtts://HOST[:PORT]
+    /// for TLS
+    #[arg(
+        id = "mqtt_url",
+        long = "mqtt-url",
+        value_name = "URL",
+        required = false
+    )]
+    pub url: String,
+    /// Topic prefix of published messages
+    #[arg(
+        id = "mqtt_topic_prefix",
+        long = "mqtt-topic-prefix",
+        value_name = "PREFIX",
+        default_value = "btlescan"
+    )]
+    pub topic_prefix: String,
+    /// Username to authenticate to the broker with
+    #[arg(id = "mqtt_username", long = "mqtt-username", value_name = "USER")]
+    pub username: Option<String>,
+    /// Password to authenticate with; defaults to $BTLESCAN_MQTT_PASSWORD
+    #[arg(id = "mqtt_password", long = "mqtt-password", value_name = "PASSWORD")]
+    pub password: Option<String>,
+    /// Announce watched devices and decoded sensors to Home Assistant over MQTT
+    #[arg(id = "hass_discovery", long = "hass-discovery")]
+    pub hass_discovery: bool,
+}
+
+/// Where and how device sightings are written to InfluxDB.
+#[derive(Clone, clap::Args)]
+#[group(requires_all = ["influx_url", "influx_bucket"], multiple = true)]
+pub struct InfluxConfig {
+    /// Write device sightings to the InfluxDB v2 server at URL
+    #[arg(
+        id = "influx_url",
+        long = "influx-url",
+        value_name = "URL",
+        required = false,
+        value_parser = parse_base_url
+    )]
+    pub url: String,
+    /// Bucket to write the sightings to
+    #[arg(
+        id = "influx_bucket",
+        long = "influx-bucket",
+        value_name = "NAME",
+        required = false
+    )]
+    pub bucket: String,
+    /// Organization the bucket belongs to
+    #[arg(id = "influx_org", long = "influx-org", value_name = "NAME")]
+    pub org: Option<String>,
+    /// API token to write with; defaults to $BTLESCAN_INFLUX_TOKEN
+    #[arg(id = "influx_token", long = "influx-token", value_name = "TOKEN")]
+    pub token: Option<String>,
+    /// How often sightings are written in batches
+    #[arg(
+        id = "influx_interval",
+        long = "influx-interval",
+        value_name = "SECONDS",
+        default_value = "10",
+        value_parser = parse_positive_seconds
+    )]
+    pub interval: Duration,
+}
+
+/// Options of the headless mode that connects to one device and logs its notifications.
+#[derive(Clone, clap::Args)]
+#[group(requires_all = ["auto_target", "notify", "log"], multiple = true)]
+pub struct AutoLog {
+    /// Run headless: connect to the device with this address (or platform id), log its
+    /// notifications and reconnect when the link drops
+    #[arg(
+        id = "auto_target",
+        long = "auto",
+        value_name = "ADDRESS",
+        required = false,
+        value_parser = parse_address
+    )]
+    pub target: String,
+    /// Characteristic to subscribe to in --auto mode
+    #[arg(long, value_name = "UUID", required = false, value_parser = parse_uuid)]
+    pub notify: Uuid,
+    /// File the --auto mode appends notifications and connection states to; rotated by the
+    /// --log-rotate-* options
+    #[arg(long, value_name = "PATH", required = false)]
+    pub log: PathBuf,
+    #[arg(skip)]
+    pub rotation: LogRotation,
+}
+
+/// Parses a `--sort` value, a sort key optionally followed by `:asc` or `:desc`. Returns
+/// the key and whether that direction reverses the key's natural order.
+pub fn parse_sort(value: &str) -> Result<(SortKey, bool), String> {
+    let (key, direction) = match value.split_once(':') {
+        Some((key, direction)) => (key, Some(direction)),
+        None => (value, None),
+    };
+    let key: SortKey = key.parse()?;
+    let descending = match direction {
+        None => key.descending(),
+        Some("asc") => false,
+        Some("desc") => true,
+        Some(other) => {
+            return Err(format!(
+                "invalid sort direction '{}', expected 'asc' or 'desc'",
+                other
+            ))
+        }
+    };
+    Ok((key, descending != key.descending()))
+}
+
+fn parse_sort_order(value: &str) -> Result<SortOrder, String> {
+    parse_sort(value).map(|(key, reversed)| SortOrder { key, reversed })
+}
+
+fn parse_csv_column(column: &str) -> Result<CsvColumn, String> {
+    column.trim().parse()
+}
+
+fn parse_uuid(uuid: &str) -> Result<Uuid, String> {
+    Uuid::parse_str(uuid.trim()).map_err(|_| "expected a UUID".to_string())
+}
+
+fn parse_address(address: &str) -> Result<String, String> {
+    normalize_address(address).ok_or_else(|| "expected a device address".to_string())
+}
+
+fn trimmed(value: &str) -> Result<String, String> {
+    Ok(value.trim().to_string())
+}
+
+fn parse_positive(count: &str) -> Result<usize, String> {
+    count
+        .parse()
+        .ok()
+        .filter(|count| *count > 0)
+        .ok_or_else(|| "expected a positive number".to_string())
+}
+
+fn parse_millis(millis: &str) -> Result<Duration, String> {
+    millis
+        .parse()
+        .map(Duration::from_millis)
+        .map_err(|_| "expected a number of milliseconds".to_string())
+}
+
+fn parse_seconds(seconds: &str) -> Result<Duration, String> {
+    seconds
+        .parse()
+        .map(Duration::from_secs)
+        .map_err(|_| "expected a number of seconds".to_string())
+}
+
+fn parse_positive_seconds(seconds: &str) -> Result<Duration, String> {
+    match parse_seconds(seconds)? {
+        Duration::ZERO => Err("expected a positive number of seconds".to_string()),
+        duration => Ok(duration),
+    }
+}
+
+fn parse_replay_speed(speed: &str) -> Result<f64, String> {
+    speed
+        .parse()
+        .ok()
+        .filter(|speed: &f64| speed.is_finite() && *speed > 0.0)
+        .ok_or_else(|| "expected a positive speed factor".to_string())
+}
+
+fn parse_listen_address(addr: &str) -> Result<SocketAddr, String> {
+    addr.parse()
+        .map_err(|_| "expected HOST:PORT, e.g. 127.0.0.1:8080".to_string())
+}
+
+fn parse_base_url(url: &str) -> Result<String, String> {
+    Ok(url.trim_end_matches('/').to_string())
+}
+
+/// Parses a positive size in megabytes into bytes.
+fn parse_megabytes(size: &str) -> Result<u64, String> {
+    let megabytes: f64 = size
+        .parse()
+        .ok()
+        .filter(|size| *size > 0.0)
+        .ok_or_else(|| "expected a positive number of megabytes".to_string())?;
+    Ok((megabytes * 1_000_000.0) as u64)
+}
+
+/// Parses a positive number of hours.
+fn parse_hours(interval: &str) -> Result<Duration, String> {
+    let hours: f64 = interval
+        .parse()
+        .ok()
+        .filter(|hours| *hours > 0.0)
+        .ok_or_else(|| "expected a positive number of hours".to_string())?;
+    Ok(Duration::from_secs_f64(hours * 3600.0))
+}
+
+/// Parses a `--victron-key` value, an address or platform id and the device's key joined
+/// by `=`. The address is uppercased, as devices are keyed by.
+fn parse_victron_key(entry: &str) -> Result<(String, [u8; 16]), String> {
+    let (device, key) = entry
+        .split_once('=')
+        .ok_or_else(|| "expected ADDRESS=KEY".to_string())?;
+    Ok((device.trim().to_uppercase(), parse_key(key.trim())?))
+}
+
+/// Parses a 128-bit key written as 32 hex digits.
+fn parse_key(hex: &str) -> Result<[u8; 16], String> {
+    let invalid = || "expected a key of 32 hex digits".to_string();
+    if hex.len() != 32 || !hex.is_ascii() {
+        return Err(invalid());
+    }
+    let mut key = [0; 16];
+    for (i, byte) in key.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(|_| invalid())?;
+    }
+    Ok(key)
+}
@@ -0,0 +1,157 @@
+use crate::{cli::parse_sort, structs::SortKey};
+
+/// The commands the `:` prompt accepts, with their arguments and what they do, in the order
+/// `:help` lists them.
+pub const COMMANDS: [(&str, &str, &str); 10] = [
+    (
+        "sort",
+        "<discovered|last-seen|rssi>[:asc|:desc]",
+        "order the table",
+    ),
+    ("filter", "[text]", "search, or show all without text"),
+    (
+        "export",
+        "<csv|json|device|text|rssi>",
+        "export like e, J, D, Y and H",
+    ),
+    ("connect", "", "connect to the highlighted device"),
+    ("mark", "", "mark or unmark the highlighted device"),
+    ("clear", "", "clear the marks"),
+    ("pause", "", "pause the scan"),
+    ("resume", "", "resume the scan"),
+    ("help", "", "list the commands"),
+    ("quit", "", "exit"),
+];
+
+/// What the export command writes.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ExportTarget {
+    Csv,
+    Json,
+    Device,
+    Text,
+    RssiHistory,
+}
+
+/// A command typed at the `:` prompt.
+#[derive(PartialEq)]
+pub enum Command {
+    Sort(SortKey, bool),
+    Filter(Option<String>),
+    Export(ExportTarget),
+    Connect,
+    Mark,
+    Clear,
+    Pause,
+    Resume,
+    Help,
+    Quit,
+}
+
+/// Parses a command line, without its `:`. A unique prefix of a command name is accepted,
+/// e.g. `:q` for `:quit`.
+pub fn parse(line: &str) -> Result<Command, String> {
+    let line = line.trim();
+    let (name, argument) = match line.split_once(char::is_whitespace) {
+        Some((name, argument)) => (name, argument.trim()),
+        None => (line, ""),
+    };
+    if name.is_empty() {
+        return Err("no command given, :help lists them".to_string());
+    }
+    let matching: Vec<&str> = COMMANDS
+        .iter()
+        .map(|(command, _, _)| *command)
+        .filter(|command| command.starts_with(name))
+        .collect();
+    let name = match matching[..] {
+        [command] => command,
+        [] => return Err(format!("unknown command '{}', :help lists them", name)),
+        _ if matching.contains(&name) => name,
+        _ => {
+            return Err(format!(
+                "ambiguous command '{}': {}",
+                name,
+                matching.join(", ")
+            ))
+        }
+    };
+    let no_argument = |command: Command| {
+        if argument.is_empty() {
+            Ok(command)
+        } else {
+            Err(format!(":{} takes no argument", name))
+        }
+    };
+    match name {
+        "sort" if argument.is_empty() => {
+            Err(":sort needs a key: discovered, last-seen or rssi".to_string())
+        }
+        "sort" => parse_sort(argument).map(|(key, reversed)| Command::Sort(key, reversed)),
+        "filter" => Ok(Command::Filter(
+            (!argument.is_empty()).then(|| argument.to_string()),
+        )),
+        "export" => match argument {
+            "csv" => Ok(Command::Export(ExportTarget::Csv)),
+            "json" => Ok(Command::Export(ExportTarget::Json)),
+            "device" => Ok(Command::Export(ExportTarget::Device)),
+            "text" => Ok(Command::Export(ExportTarget::Text)),
+            "rssi" => Ok(Command::Export(ExportTarget::RssiHistory)),
+            other => Err(format!(
+                "invalid export '{}', expected csv, json, device, text or rssi",
+                other
+            )),
+        },
+        "connect" => no_argument(Command::Connect),
+        "mark" => no_argument(Command::Mark),
+        "clear" => no_argument(Command::Clear),
+        "pause" => no_argument(Command::Pause),
+        "resume" => no_argument(Command::Resume),
+        "help" => no_argument(Command::Help),
+        // Only :quit is left.
+        _ => no_argument(Command::Quit),
+    }
+}
+
+/// Completes the command name being typed: to the command if only one starts with it, and
+/// otherwise to the longest prefix the matching commands share. Returns `None` once an
+/// argument is being typed or nothing matches.
+pub fn complete(input: &str) -> Option<String> {
+    let name = input.trim_start();
+    if name.contains(char::is_whitespace) {
+        return None;
+    }
+    let matching: Vec<&str> = COMMANDS
+        .iter()
+        .map(|(command, _, _)| *command)
+        .filter(|command| command.starts_with(name))
+        .collect();
+    match matching[..] {
+        [] => None,
+        [command] => Some(format!("{} ", command)),
+        _ => {
+            let first = matching[0];
+            let shared = (name.len()..=first.len())
+                .rev()
+                .find(|&len| {
+                    matching
+                        .iter()
+                        .all(|command| command.starts_with(&first[..len]))
+                })
+                .unwrap_or(name.len());
+            Some(first[..shared].to_string())
+        }
+    }
+}
+
+/// Lists the commands for the `:help` notification.
+pub fn help() -> String {
+    COMMANDS
+        .iter()
+        .map(|(command, arguments, description)| match *arguments {
+            "" => format!(":{} — {}", command, description),
+            _ => format!(":{} {} — {}", command, arguments, description),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
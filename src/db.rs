@@ -0,0 +1,262 @@
+use std::path::Path;
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::{
+    structs::{DeviceHistory, DeviceInfo, IdentityKey},
+    utils::normalize_address,
+};
+
+/// The schema migrations, in order. The database's `user_version` records how many have
+/// been applied, so a database created by an older btlescan is brought up to date on open.
+/// New migrations are appended; applied ones are never edited.
+const MIGRATIONS: &[&str] = &[
+    "
+    CREATE TABLE devices (
+        address TEXT PRIMARY KEY,
+        name TEXT,
+        first_seen TEXT NOT NULL,
+        last_seen TEXT NOT NULL,
+        sightings INTEGER NOT NULL,
+        max_rssi INTEGER
+    );
+    CREATE TABLE names (
+        address TEXT NOT NULL,
+        name TEXT NOT NULL,
+        first_seen TEXT NOT NULL,
+        PRIMARY KEY (address, name)
+    );
+    CREATE TABLE sightings (
+        id INTEGER PRIMARY KEY,
+        address TEXT NOT NULL,
+        seen_at TEXT NOT NULL,
+        rssi INTEGER
+    );
+    CREATE INDEX sightings_by_address ON sightings (address, seen_at);
+",
+    "
+    UPDATE OR IGNORE devices SET address = UPPER(address);
+    UPDATE OR IGNORE names SET address = UPPER(address);
+    UPDATE sightings SET address = UPPER(address);
+",
+];
+
+/// Remembers devices across sessions in a SQLite database. A sighting is a presence
+/// episode: a device appearing in a session, or returning after it went stale.
+pub struct DeviceDb {
+    conn: Connection,
+}
+
+impl DeviceDb {
+    /// Opens the database, creating it if needed, and applies pending migrations.
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let conn = Connection::open(path).map_err(|e| e.to_string())?;
+        let db = Self { conn };
+        db.migrate()?;
+        Ok(db)
+    }
+
+    fn migrate(&self) -> Result<(), String> {
+        // Updates are written as they arrive, so trade durability of the last few for speed.
+        self.conn
+            .execute_batch("PRAGMA journal_mode = WAL; PRAGMA synchronous = NORMAL;")
+            .map_err(|e| e.to_string())?;
+        let version: usize = self
+            .conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .map_err(|e| e.to_string())?;
+        if version > MIGRATIONS.len() {
+            return Err(format!(
+                "schema version {} is newer than this btlescan supports ({})",
+                version,
+                MIGRATIONS.len()
+            ));
+        }
+        for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            self.conn
+                .execute_batch(&format!(
+                    "BEGIN; {} PRAGMA user_version = {}; COMMIT;",
+                    migration,
+                    i + 1
+                ))
+                .map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    /// Records a batch of device updates in one transaction. Each update carries whether
+    /// it starts a new presence episode.
+    pub fn record(
+        &mut self,
+        updates: &[(DeviceInfo, bool)],
+        key: IdentityKey,
+    ) -> rusqlite::Result<()> {
+        let tx = self.conn.transaction()?;
+        for (device, new_sighting) in updates {
+            let address = storage_key(&device.get_id(key));
+            let seen_at = device.last_seen.to_rfc3339();
+            let rssi = device.rssi;
+            tx.prepare_cached(
+                "INSERT INTO devices (address, name, first_seen, last_seen, sightings, max_rssi)
+                 VALUES (?1, ?2, ?3, ?3, 0, ?4)
+                 ON CONFLICT (address) DO UPDATE SET
+                     name = COALESCE(excluded.name, name),
+                     last_seen = excluded.last_seen,
+                     max_rssi = MAX(COALESCE(max_rssi, excluded.max_rssi), excluded.max_rssi)",
+            )?
+            .execute(params![address, device.name, seen_at, rssi])?;
+            if let Some(name) = &device.name {
+                tx.prepare_cached(
+                    "INSERT OR IGNORE INTO names (address, name, first_seen) VALUES (?1, ?2, ?3)",
+                )?
+                .execute(params![address, name, seen_at])?;
+            }
+            if *new_sighting {
+                tx.prepare_cached(
+                    "INSERT INTO sightings (address, seen_at, rssi) VALUES (?1, ?2, ?3)",
+                )?
+                .execute(params![address, seen_at, rssi])?;
+                tx.prepare_cached(
+                    "UPDATE devices SET sightings = sightings + 1 WHERE address = ?1",
+                )?
+                .execute(params![address])?;
+            }
+        }
+        tx.commit()
+    }
+
+    /// Looks up a device by address (or platform id on platforms that hide addresses).
+    pub fn lookup(&self, address: &str) -> rusqlite::Result<Option<DeviceHistory>> {
+        let address = storage_key(address);
+        let Some(mut history) = self
+            .conn
+            .query_row(
+                "SELECT address, name, first_seen, last_seen, sightings, max_rssi
+                 FROM devices WHERE address = ?1",
+                params![address],
+                |row| {
+                    Ok(DeviceHistory {
+                        address: row.get(0)?,
+                        name: row.get(1)?,
+                        names: Vec::new(),
+                        first_seen: row.get(2)?,
+                        last_seen: row.get(3)?,
+                        sightings: row.get(4)?,
+                        days_seen: 0,
+                        max_rssi: row.get(5)?,
+                    })
+                },
+            )
+            .optional()?
+        else {
+            return Ok(None);
+        };
+        let mut names = self
+            .conn
+            .prepare("SELECT name FROM names WHERE address = ?1 ORDER BY first_seen")?;
+        history.names = names
+            .query_map(params![history.address], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        // Sightings are stored in local time, so their first ten characters are the date.
+        history.days_seen = self.conn.query_row(
+            "SELECT COUNT(DISTINCT substr(seen_at, 1, 10)) FROM sightings WHERE address = ?1",
+            params![history.address],
+            |row| row.get(0),
+        )?;
+        Ok(Some(history))
+    }
+
+    /// Returns the start time and RSSI of a device's most recent sightings, newest first.
+    pub fn recent_sightings(
+        &self,
+        address: &str,
+        limit: usize,
+    ) -> rusqlite::Result<Vec<(String, Option<i16>)>> {
+        let mut sightings = self.conn.prepare(
+            "SELECT seen_at, rssi FROM sightings WHERE address = ?1
+             ORDER BY seen_at DESC LIMIT ?2",
+        )?;
+        let rows = sightings.query_map(params![address, limit], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?;
+        rows.collect()
+    }
+}
+
+/// The key a device is stored under: its identity, with addresses in the uppercase form
+/// lookups query by, so they can use the primary key.
+fn storage_key(identity: &str) -> String {
+    normalize_address(identity).unwrap_or_else(|| identity.trim().to_string())
+}
+
+/// Prints what the database at `path` knows about a device, for `--db-query`. Returns
+/// `false` if the device is unknown.
+pub fn print_history(path: &Path, address: &str) -> Result<bool, String> {
+    let db = DeviceDb::open(path)?;
+    let Some(history) = db.lookup(address).map_err(|e| e.to_string())? else {
+        println!("{} has not been seen", address);
+        return Ok(false);
+    };
+    println!("Address:     {}", history.address);
+    println!("Name:        {}", history.name.as_deref().unwrap_or("-"));
+    if history.names.len() > 1 {
+        println!("Names:       {}", history.names.join(", "));
+    }
+    println!("First seen:  {}", history.first_seen);
+    println!("Last seen:   {}", history.last_seen);
+    println!("Sightings:   {}", history.sightings);
+    println!("Days seen:   {}", history.days_seen);
+    println!(
+        "Max RSSI:    {}",
+        history
+            .max_rssi
+            .map_or_else(|| "n/a".to_string(), |rssi| format!("{} dBm", rssi))
+    );
+    let sightings = db
+        .recent_sightings(&history.address, 10)
+        .map_err(|e| e.to_string())?;
+    if !sightings.is_empty() {
+        println!("Recent sightings:");
+        for (seen_at, rssi) in sightings {
+            match rssi {
+                Some(rssi) => println!("  {}  {} dBm", seen_at, rssi),
+                None => println!("  {}", seen_at),
+            }
+        }
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn updates_are_stored_under_the_normalized_address() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = DeviceDb::open(&dir.path().join("devices.db")).unwrap();
+        let lamp = DeviceInfo {
+            address: "aa:bb:cc:dd:ee:01".to_string(),
+            name: Some("Lamp".to_string()),
+            rssi: Some(-60),
+            last_seen: chrono::Local::now() - chrono::Duration::minutes(1),
+            ..DeviceInfo::default()
+        };
+        let renamed = DeviceInfo {
+            name: Some("Desk lamp".to_string()),
+            rssi: Some(-50),
+            last_seen: chrono::Local::now(),
+            ..lamp.clone()
+        };
+        db.record(&[(lamp.clone(), true), (renamed, false)], IdentityKey::Auto)
+            .unwrap();
+        db.record(&[(lamp, true)], IdentityKey::Auto).unwrap();
+
+        let history = db.lookup(" Aa:Bb:Cc:Dd:Ee:01 ").unwrap().unwrap();
+        assert_eq!(history.address, "AA:BB:CC:DD:EE:01");
+        assert_eq!(history.names, ["Lamp", "Desk lamp"]);
+        assert_eq!(history.sightings, 2);
+        assert_eq!(history.max_rssi, Some(-50));
+        assert!(db.lookup("AA:BB:CC:DD:EE:02").unwrap().is_none());
+    }
+}
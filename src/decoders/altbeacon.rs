@@ -0,0 +1,148 @@
+use crate::structs::{DecodedField, DeviceInfo};
+
+use super::Decoder;
+
+/// A struct to hold the information of an AltBeacon frame.
+#[derive(Debug, PartialEq)]
+pub struct AltBeacon {
+    pub beacon_id: [u8; 20],
+    pub reference_rssi: i8,
+    pub reserved: u8,
+}
+
+impl AltBeacon {
+    /// Returns the beacon ID in the conventional 16/2/2 grouping: a 16-byte identifier
+    /// followed by two 2-byte values.
+    pub fn grouped_id(&self) -> (String, u16, u16) {
+        let id = self.beacon_id[..16]
+            .iter()
+            .map(|byte| format!("{:02X}", byte))
+            .collect::<String>();
+        (
+            id,
+            u16::from_be_bytes([self.beacon_id[16], self.beacon_id[17]]),
+            u16::from_be_bytes([self.beacon_id[18], self.beacon_id[19]]),
+        )
+    }
+}
+
+/// Decodes an AltBeacon frame from the payload of manufacturer data of any company.
+/// Returns `None` if the payload does not start with the 0xBE 0xAC beacon code.
+/// Returns `Some(Err(_))` if the frame is recognized but truncated.
+pub fn decode_altbeacon(data: &[u8]) -> Option<Result<AltBeacon, String>> {
+    if data.len() < 2 || data[0] != 0xBE || data[1] != 0xAC {
+        return None;
+    }
+    if data.len() < 24 {
+        return Some(Err(format!("truncated frame ({} of 24 bytes)", data.len())));
+    }
+    let mut beacon_id = [0; 20];
+    beacon_id.copy_from_slice(&data[2..22]);
+    Some(Ok(AltBeacon {
+        beacon_id,
+        reference_rssi: data[22] as i8,
+        reserved: data[23],
+    }))
+}
+
+/// Decodes AltBeacon frames from the manufacturer data of any company.
+pub struct AltBeaconDecoder;
+
+impl Decoder for AltBeaconDecoder {
+    fn name(&self) -> &'static str {
+        "altbeacon"
+    }
+
+    fn matches(&self, device: &DeviceInfo) -> bool {
+        !device.manufacturer_data.is_empty()
+    }
+
+    fn decode(&self, device: &DeviceInfo) -> Vec<DecodedField> {
+        let mut companies: Vec<_> = device.manufacturer_data.iter().collect();
+        companies.sort_by_key(|(code, _)| **code);
+        let mut fields = Vec::new();
+        for (_, data) in companies {
+            match decode_altbeacon(data) {
+                Some(Ok(beacon)) => {
+                    let (id, id2, id3) = beacon.grouped_id();
+                    fields.push(DecodedField::new("AltBeacon ID", id));
+                    fields.push(DecodedField::new("AltBeacon ID 2", id2.to_string()));
+                    fields.push(DecodedField::new("AltBeacon ID 3", id3.to_string()));
+                    fields.push(DecodedField::new(
+                        "AltBeacon Ref RSSI",
+                        format!("{} dBm", beacon.reference_rssi),
+                    ));
+                    fields.push(DecodedField::new(
+                        "AltBeacon Reserved",
+                        format!("0x{:02X}", beacon.reserved),
+                    ));
+                }
+                Some(Err(e)) => fields.push(DecodedField::new(
+                    "AltBeacon",
+                    format!("<malformed: {}>", e),
+                )),
+                None => {}
+            }
+        }
+        fields
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::decoders::reference_rssi;
+
+    /// An AltBeacon frame with ID 00010203-…-0F, 2 and 3, a reference RSSI of -65 dBm and
+    /// reserved byte 0x7F.
+    fn frame() -> Vec<u8> {
+        let mut frame = vec![0xBE, 0xAC];
+        frame.extend(0x00..0x10);
+        frame.extend([0x00, 0x02, 0x00, 0x03, 0xBF, 0x7F]);
+        frame
+    }
+
+    #[test]
+    fn valid_frame_decodes_with_the_grouped_id() {
+        let beacon = decode_altbeacon(&frame()).unwrap().unwrap();
+        assert_eq!(beacon.reference_rssi, -65);
+        assert_eq!(beacon.reserved, 0x7F);
+        assert_eq!(
+            beacon.grouped_id(),
+            ("000102030405060708090A0B0C0D0E0F".to_string(), 2, 3)
+        );
+        assert_eq!(
+            decode_altbeacon(&frame()[..12]),
+            Some(Err("truncated frame (12 of 24 bytes)".to_string()))
+        );
+        assert_eq!(decode_altbeacon(&[0x02, 0x15, 0x00]), None);
+    }
+
+    #[test]
+    fn frames_are_recognized_under_any_company() {
+        // Radius Networks, then two other companies.
+        for company in [0x0118, 0x004C, 0xFFFF] {
+            let device = DeviceInfo {
+                manufacturer_data: HashMap::from([(company, frame())]),
+                ..DeviceInfo::default()
+            };
+            let fields = AltBeaconDecoder.decode(&device);
+            assert_eq!(fields.len(), 5, "0x{:04X}", company);
+            assert_eq!(reference_rssi(&device), Some(-65), "0x{:04X}", company);
+        }
+    }
+
+    #[test]
+    fn reference_rssi_is_preferred_over_ibeacon_power() {
+        let mut ibeacon = vec![0x02, 0x15];
+        ibeacon.extend([0; 20]);
+        ibeacon.push(0xC5);
+        let device = DeviceInfo {
+            manufacturer_data: HashMap::from([(0x004C, ibeacon), (0x0118, frame())]),
+            ..DeviceInfo::default()
+        };
+        assert_eq!(reference_rssi(&device), Some(-65));
+    }
+}
@@ -0,0 +1,413 @@
+use btleplug::api::bleuuid::uuid_from_u16;
+use uuid::Uuid;
+
+use crate::{
+    structs::{DecodedField, DeviceInfo},
+    utils::bytes_to_hex,
+};
+
+use super::Decoder;
+
+/// The 16-bit service UUID under which BTHome sensor data is advertised.
+pub const BTHOME_UUID: Uuid = uuid_from_u16(0xFCD2);
+
+/// The layout of a BTHome object's value, as fixed by its object ID.
+enum ObjectFormat {
+    /// A little-endian integer of `size` bytes, multiplied by `factor` and shown with
+    /// `decimals` digits after the point.
+    Numeric {
+        name: &'static str,
+        size: usize,
+        signed: bool,
+        factor: f64,
+        decimals: usize,
+        unit: &'static str,
+    },
+    /// A single byte that is either off (0) or on (1).
+    Binary(&'static str),
+    /// A single byte holding a button event.
+    Button,
+    /// A byte holding a dimmer event followed by a byte with the number of steps.
+    Dimmer,
+    /// A length byte followed by that many bytes of UTF-8 text.
+    Text,
+    /// A length byte followed by that many bytes of opaque data.
+    Raw,
+}
+
+/// Returns the format of a BTHome v2 object, or `None` for an unknown object ID.
+fn object_format(id: u8) -> Option<ObjectFormat> {
+    use ObjectFormat::*;
+    let numeric = |name, size, signed, factor, decimals, unit| Numeric {
+        name,
+        size,
+        signed,
+        factor,
+        decimals,
+        unit,
+    };
+    let format = match id {
+        0x00 => numeric("Packet ID", 1, false, 1.0, 0, ""),
+        0x01 => numeric("Battery", 1, false, 1.0, 0, "%"),
+        0x02 => numeric("Temperature", 2, true, 0.01, 2, "°C"),
+        0x03 => numeric("Humidity", 2, false, 0.01, 2, "%"),
+        0x04 => numeric("Pressure", 3, false, 0.01, 2, "hPa"),
+        0x05 => numeric("Illuminance", 3, false, 0.01, 2, "lux"),
+        0x06 => numeric("Mass", 2, false, 0.01, 2, "kg"),
+        0x07 => numeric("Mass", 2, false, 0.01, 2, "lb"),
+        0x08 => numeric("Dew Point", 2, true, 0.01, 2, "°C"),
+        0x09 => numeric("Count", 1, false, 1.0, 0, ""),
+        0x0A => numeric("Energy", 3, false, 0.001, 3, "kWh"),
+        0x0B => numeric("Power", 3, false, 0.01, 2, "W"),
+        0x0C => numeric("Voltage", 2, false, 0.001, 3, "V"),
+        0x0D => numeric("PM2.5", 2, false, 1.0, 0, "µg/m³"),
+        0x0E => numeric("PM10", 2, false, 1.0, 0, "µg/m³"),
+        0x0F => Binary("Generic Boolean"),
+        0x10 => Binary("Power"),
+        0x11 => Binary("Opening"),
+        0x12 => numeric("CO2", 2, false, 1.0, 0, "ppm"),
+        0x13 => numeric("TVOC", 2, false, 1.0, 0, "µg/m³"),
+        0x14 => numeric("Moisture", 2, false, 0.01, 2, "%"),
+        0x15 => Binary("Battery Low"),
+        0x16 => Binary("Battery Charging"),
+        0x17 => Binary("Carbon Monoxide"),
+        0x18 => Binary("Cold"),
+        0x19 => Binary("Connectivity"),
+        0x1A => Binary("Door"),
+        0x1B => Binary("Garage Door"),
+        0x1C => Binary("Gas"),
+        0x1D => Binary("Heat"),
+        0x1E => Binary("Light"),
+        0x1F => Binary("Lock"),
+        0x20 => Binary("Moisture"),
+        0x21 => Binary("Motion"),
+        0x22 => Binary("Moving"),
+        0x23 => Binary("Occupancy"),
+        0x24 => Binary("Plug"),
+        0x25 => Binary("Presence"),
+        0x26 => Binary("Problem"),
+        0x27 => Binary("Running"),
+        0x28 => Binary("Safety"),
+        0x29 => Binary("Smoke"),
+        0x2A => Binary("Sound"),
+        0x2B => Binary("Tamper"),
+        0x2C => Binary("Vibration"),
+        0x2D => Binary("Window"),
+        0x2E => numeric("Humidity", 1, false, 1.0, 0, "%"),
+        0x2F => numeric("Moisture", 1, false, 1.0, 0, "%"),
+        0x3A => Button,
+        0x3C => Dimmer,
+        0x3D => numeric("Count", 2, false, 1.0, 0, ""),
+        0x3E => numeric("Count", 4, false, 1.0, 0, ""),
+        0x3F => numeric("Rotation", 2, true, 0.1, 1, "°"),
+        0x40 => numeric("Distance", 2, false, 1.0, 0, "mm"),
+        0x41 => numeric("Distance", 2, false, 0.1, 1, "m"),
+        0x42 => numeric("Duration", 3, false, 0.001, 3, "s"),
+        0x43 => numeric("Current", 2, false, 0.001, 3, "A"),
+        0x44 => numeric("Speed", 2, false, 0.01, 2, "m/s"),
+        0x45 => numeric("Temperature", 2, true, 0.1, 1, "°C"),
+        0x46 => numeric("UV Index", 1, false, 0.1, 1, ""),
+        0x47 => numeric("Volume", 2, false, 0.1, 1, "L"),
+        0x48 => numeric("Volume", 2, false, 1.0, 0, "mL"),
+        0x49 => numeric("Volume Flow Rate", 2, false, 0.001, 3, "m³/h"),
+        0x4A => numeric("Voltage", 2, false, 0.1, 1, "V"),
+        0x4B => numeric("Gas", 3, false, 0.001, 3, "m³"),
+        0x4C => numeric("Gas", 4, false, 0.001, 3, "m³"),
+        0x4D => numeric("Energy", 4, false, 0.001, 3, "kWh"),
+        0x4E => numeric("Volume", 4, false, 0.001, 3, "L"),
+        0x4F => numeric("Water", 4, false, 0.001, 3, "L"),
+        0x50 => numeric("Timestamp", 4, false, 1.0, 0, ""),
+        0x51 => numeric("Acceleration", 2, false, 0.001, 3, "m/s²"),
+        0x52 => numeric("Gyroscope", 2, false, 0.001, 3, "°/s"),
+        0x53 => Text,
+        0x54 => Raw,
+        0x55 => numeric("Volume Storage", 4, false, 0.001, 3, "L"),
+        0x56 => numeric("Conductivity", 2, false, 1.0, 0, "µS/cm"),
+        0x57 => numeric("Temperature", 1, true, 1.0, 0, "°C"),
+        0x58 => numeric("Temperature", 1, true, 0.35, 2, "°C"),
+        0x59 => numeric("Count", 1, true, 1.0, 0, ""),
+        0x5A => numeric("Count", 2, true, 1.0, 0, ""),
+        0x5B => numeric("Count", 4, true, 1.0, 0, ""),
+        0x5C => numeric("Power", 4, true, 0.01, 2, "W"),
+        0x5D => numeric("Current", 2, true, 0.001, 3, "A"),
+        0x5E => numeric("Direction", 2, false, 0.01, 2, "°"),
+        0x5F => numeric("Precipitation", 2, false, 0.1, 1, "mm"),
+        0x60 => numeric("Channel", 1, false, 1.0, 0, ""),
+        0xF0 => numeric("Device Type ID", 2, false, 1.0, 0, ""),
+        0xF1 => numeric("Firmware", 4, false, 1.0, 0, ""),
+        0xF2 => numeric("Firmware", 3, false, 1.0, 0, ""),
+        _ => return None,
+    };
+    Some(format)
+}
+
+/// A single decoded BTHome measurement or event.
+#[derive(Debug, PartialEq)]
+pub struct Measurement {
+    pub name: String,
+    pub value: String,
+}
+
+/// A decoded BTHome v2 advertisement.
+#[derive(Debug, PartialEq)]
+pub struct BtHome {
+    pub trigger_based: bool,
+    /// The encrypted object data, or `None` when the payload is sent in the clear.
+    pub encrypted: Option<Vec<u8>>,
+    pub measurements: Vec<Measurement>,
+}
+
+impl BtHome {
+    /// Returns the decoded values of the advertisement as label/value pairs.
+    pub fn fields(&self) -> Vec<(String, String)> {
+        let mut fields = vec![(
+            "BTHome".to_string(),
+            format!(
+                "v2{}",
+                if self.trigger_based {
+                    " (trigger based)"
+                } else {
+                    ""
+                }
+            ),
+        )];
+        if let Some(payload) = &self.encrypted {
+            fields.push((
+                "BTHome Payload".to_string(),
+                format!("<encrypted> {}", bytes_to_hex(payload)),
+            ));
+        }
+        for measurement in &self.measurements {
+            fields.push((
+                format!("BTHome {}", measurement.name),
+                measurement.value.clone(),
+            ));
+        }
+        fields
+    }
+}
+
+/// Reads a little-endian integer of up to four bytes.
+fn read_int(bytes: &[u8], signed: bool) -> i64 {
+    let mut value = bytes
+        .iter()
+        .rev()
+        .fold(0i64, |acc, byte| (acc << 8) | i64::from(*byte));
+    let bits = bytes.len() * 8;
+    if signed && value >> (bits - 1) & 1 == 1 {
+        value -= 1 << bits;
+    }
+    value
+}
+
+/// Decodes the 0xFCD2 service data of a BTHome v2 sensor. Objects with an unknown ID
+/// end decoding, since their length is unknown; the remaining bytes are shown raw.
+pub fn decode_bthome(data: &[u8]) -> Result<BtHome, String> {
+    let (&device_info, mut objects) = data
+        .split_first()
+        .ok_or_else(|| "empty payload".to_string())?;
+    let version = device_info >> 5;
+    if version != 2 {
+        return Err(format!("unsupported version {}", version));
+    }
+    let mut bthome = BtHome {
+        trigger_based: device_info & 0x04 != 0,
+        encrypted: None,
+        measurements: Vec::new(),
+    };
+    if device_info & 0x01 != 0 {
+        bthome.encrypted = Some(objects.to_vec());
+        return Ok(bthome);
+    }
+
+    while let Some((&id, rest)) = objects.split_first() {
+        let Some(format) = object_format(id) else {
+            bthome.measurements.push(Measurement {
+                name: format!("Object 0x{:02X}", id),
+                value: bytes_to_hex(rest),
+            });
+            break;
+        };
+        let size = match format {
+            ObjectFormat::Numeric { size, .. } => size,
+            ObjectFormat::Binary(_) | ObjectFormat::Button => 1,
+            ObjectFormat::Dimmer => 2,
+            ObjectFormat::Text | ObjectFormat::Raw => {
+                1 + *rest
+                    .first()
+                    .ok_or_else(|| format!("truncated object 0x{:02X}", id))?
+                    as usize
+            }
+        };
+        let value = rest
+            .get(..size)
+            .ok_or_else(|| format!("truncated object 0x{:02X}", id))?;
+        objects = &rest[size..];
+
+        let (name, value) = match format {
+            ObjectFormat::Numeric {
+                name,
+                signed,
+                factor,
+                decimals,
+                unit,
+                ..
+            } => {
+                let number = read_int(value, signed) as f64 * factor;
+                let mut text = format!("{:.*}", decimals, number);
+                if !unit.is_empty() {
+                    text = format!("{} {}", text, unit);
+                }
+                (name.to_string(), text)
+            }
+            ObjectFormat::Binary(name) => (
+                name.to_string(),
+                match value[0] {
+                    0 => "off".to_string(),
+                    1 => "on".to_string(),
+                    other => format!("0x{:02X}", other),
+                },
+            ),
+            ObjectFormat::Button => (
+                "Button".to_string(),
+                match value[0] {
+                    0x00 => "none",
+                    0x01 => "press",
+                    0x02 => "double press",
+                    0x03 => "triple press",
+                    0x04 => "long press",
+                    0x05 => "long double press",
+                    0x06 => "long triple press",
+                    0x80 => "hold press",
+                    _ => "unknown",
+                }
+                .to_string(),
+            ),
+            ObjectFormat::Dimmer => (
+                "Dimmer".to_string(),
+                match value[0] {
+                    0x00 => "none".to_string(),
+                    0x01 => format!("rotate left {} steps", value[1]),
+                    0x02 => format!("rotate right {} steps", value[1]),
+                    other => format!("unknown event 0x{:02X}", other),
+                },
+            ),
+            ObjectFormat::Text => (
+                "Text".to_string(),
+                String::from_utf8_lossy(&value[1..]).into_owned(),
+            ),
+            ObjectFormat::Raw => ("Raw".to_string(), bytes_to_hex(&value[1..])),
+        };
+        bthome.measurements.push(Measurement { name, value });
+    }
+    Ok(bthome)
+}
+
+/// Decodes BTHome v2 service data.
+pub struct BtHomeDecoder;
+
+impl Decoder for BtHomeDecoder {
+    fn name(&self) -> &'static str {
+        "bthome"
+    }
+
+    fn matches(&self, device: &DeviceInfo) -> bool {
+        device.service_data.contains_key(&BTHOME_UUID)
+    }
+
+    fn decode(&self, device: &DeviceInfo) -> Vec<DecodedField> {
+        match device
+            .service_data
+            .get(&BTHOME_UUID)
+            .map(|data| decode_bthome(data))
+        {
+            Some(Ok(decoded)) => decoded
+                .fields()
+                .into_iter()
+                .map(|(label, value)| DecodedField::new(label, value))
+                .collect(),
+            Some(Err(e)) => vec![DecodedField::new("BTHome", format!("<malformed: {}>", e))],
+            None => Vec::new(),
+        }
+    }
+
+    fn reports_measurements(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the measurements of a decoded payload as `name: value` lines.
+    fn measurements(data: &[u8]) -> Vec<String> {
+        decode_bthome(data)
+            .unwrap()
+            .measurements
+            .into_iter()
+            .map(|measurement| format!("{}: {}", measurement.name, measurement.value))
+            .collect()
+    }
+
+    #[test]
+    fn spec_examples_decode_with_their_units() {
+        // The object examples of the BTHome v2 format description, in one payload.
+        let payload = [
+            0x40, 0x00, 0x01, 0x01, 0x61, 0x02, 0xCA, 0x09, 0x03, 0xBF, 0x13, 0x04, 0x13, 0x8A,
+            0x01, 0x0C, 0x02, 0x0C, 0x21, 0x01, 0x3A, 0x04,
+        ];
+        assert_eq!(
+            measurements(&payload),
+            [
+                "Packet ID: 1",
+                "Battery: 97 %",
+                "Temperature: 25.06 °C",
+                "Humidity: 50.55 %",
+                "Pressure: 1008.83 hPa",
+                "Voltage: 3.074 V",
+                "Motion: on",
+                "Button: long press",
+            ]
+        );
+        assert_eq!(
+            measurements(&[0x40, 0x02, 0x2C, 0xF7]),
+            ["Temperature: -22.60 °C"]
+        );
+    }
+
+    #[test]
+    fn encrypted_payloads_are_labeled_and_not_decoded() {
+        let decoded = decode_bthome(&[0x41, 0xA4, 0x72, 0x66, 0xC9]).unwrap();
+        assert!(decoded.measurements.is_empty());
+        assert_eq!(
+            decoded.fields()[1],
+            (
+                "BTHome Payload".to_string(),
+                "<encrypted> A4 72 66 C9".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn unknown_object_shows_the_rest_raw() {
+        assert_eq!(
+            measurements(&[0x44, 0x01, 0x64, 0xEE, 0x01, 0x02]),
+            ["Battery: 100 %", "Object 0xEE: 01 02"]
+        );
+        assert!(decode_bthome(&[0x44, 0x01, 0x64]).unwrap().trigger_based);
+    }
+
+    #[test]
+    fn bad_payloads_are_errors() {
+        assert_eq!(decode_bthome(&[]), Err("empty payload".to_string()));
+        assert_eq!(
+            decode_bthome(&[0x20, 0x01, 0x64]),
+            Err("unsupported version 1".to_string())
+        );
+        assert_eq!(
+            decode_bthome(&[0x40, 0x02, 0xCA]),
+            Err("truncated object 0x02".to_string())
+        );
+    }
+}
@@ -0,0 +1,213 @@
+use crate::{
+    structs::{DecodedField, DeviceInfo},
+    utils::bytes_to_hex,
+};
+
+use super::{Decoder, APPLE_COMPANY_ID};
+
+/// A single type/length/value message from Apple Continuity manufacturer data.
+#[derive(Debug, PartialEq)]
+pub struct ContinuityMessage {
+    pub msg_type: u8,
+    pub payload: Vec<u8>,
+}
+
+impl ContinuityMessage {
+    /// Returns the name of the message type, if it is a known one.
+    pub fn type_name(&self) -> Option<&'static str> {
+        match self.msg_type {
+            0x02 => Some("iBeacon"),
+            0x03 => Some("AirPrint"),
+            0x05 => Some("AirDrop"),
+            0x06 => Some("HomeKit"),
+            0x07 => Some("AirPods Status"),
+            0x08 => Some("Hey Siri"),
+            0x09 => Some("AirPlay Target"),
+            0x0A => Some("AirPlay Source"),
+            0x0B => Some("Magic Switch"),
+            0x0C => Some("Handoff"),
+            0x0D => Some("Tethering Target"),
+            0x0E => Some("Tethering Source"),
+            0x0F => Some("Nearby Action"),
+            0x10 => Some("Nearby Info"),
+            0x12 => Some("Find My"),
+            _ => None,
+        }
+    }
+
+    /// Returns a label for the message, e.g. `Handoff` or `Continuity (type 0x1F)`.
+    pub fn label(&self) -> String {
+        match self.type_name() {
+            Some(name) => name.to_string(),
+            None => format!("Continuity (type 0x{:02X})", self.msg_type),
+        }
+    }
+
+    /// Returns the fields that can be extracted without decrypting the message.
+    /// Unknown message types keep their payload as hex.
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        let p = &self.payload;
+        match self.msg_type {
+            0x07 if p.len() >= 6 => vec![
+                (
+                    "AirPods Model",
+                    airpods_model(u16::from_be_bytes([p[1], p[2]])),
+                ),
+                (
+                    "AirPods Battery",
+                    format!(
+                        "L {} / R {} / Case {}",
+                        battery_level(p[4] >> 4),
+                        battery_level(p[4] & 0x0F),
+                        battery_level(p[5] & 0x0F)
+                    ),
+                ),
+            ],
+            0x0C if p.len() >= 3 => vec![(
+                "Handoff Sequence",
+                u16::from_le_bytes([p[1], p[2]]).to_string(),
+            )],
+            0x0F if p.len() >= 2 => vec![("Nearby Action", nearby_action(p[1]))],
+            0x10 if !p.is_empty() => vec![("Nearby Activity", nearby_activity(p[0] & 0x0F))],
+            0x02 | 0x03 | 0x05 | 0x06 | 0x08 | 0x09 | 0x0A | 0x0B | 0x0C | 0x0D | 0x0E | 0x0F
+            | 0x10 | 0x12 => Vec::new(),
+            _ => vec![("Payload", bytes_to_hex(p))],
+        }
+    }
+}
+
+/// Splits the payload of Apple (0x004C) manufacturer data into Continuity messages.
+/// Returns an error if a message claims more bytes than remain.
+pub fn parse_continuity(data: &[u8]) -> Result<Vec<ContinuityMessage>, String> {
+    let mut messages = Vec::new();
+    let mut i = 0;
+    while i < data.len() {
+        if i + 1 >= data.len() {
+            return Err(format!("dangling byte at offset {}", i));
+        }
+        let length = data[i + 1] as usize;
+        let end = i + 2 + length;
+        if end > data.len() {
+            return Err(format!(
+                "type 0x{:02X} truncated ({} of {} bytes)",
+                data[i],
+                data.len() - i - 2,
+                length
+            ));
+        }
+        messages.push(ContinuityMessage {
+            msg_type: data[i],
+            payload: data[i + 2..end].to_vec(),
+        });
+        i = end;
+    }
+    Ok(messages)
+}
+
+fn airpods_model(model: u16) -> String {
+    let name = match model {
+        0x0220 => "AirPods",
+        0x0F20 => "AirPods (2nd gen)",
+        0x1320 => "AirPods (3rd gen)",
+        0x0E20 => "AirPods Pro",
+        0x1420 => "AirPods Pro (2nd gen)",
+        0x0A20 => "AirPods Max",
+        0x0320 => "Powerbeats3",
+        0x0B20 => "Powerbeats Pro",
+        0x0520 => "BeatsX",
+        0x0620 => "Beats Solo3",
+        0x0920 => "Beats Studio3",
+        0x1020 => "Beats Flex",
+        0x1120 => "Beats Studio Buds",
+        _ => return format!("unknown (0x{:04X})", model),
+    };
+    name.to_string()
+}
+
+fn battery_level(nibble: u8) -> String {
+    match nibble {
+        0..=10 => format!("{}%", nibble as u16 * 10),
+        _ => "n/a".to_string(),
+    }
+}
+
+fn nearby_action(action: u8) -> String {
+    let name = match action {
+        0x01 => "Apple TV setup",
+        0x04 => "Mobile backup",
+        0x05 => "Watch setup",
+        0x06 => "Apple TV pair",
+        0x07 => "Internet relay",
+        0x08 => "Wi-Fi password",
+        0x09 => "iOS setup",
+        0x0A => "Repair",
+        0x0B => "Speaker setup",
+        0x0C => "Apple Pay",
+        0x0D => "Whole home audio setup",
+        0x0E => "Developer tools pairing",
+        0x0F => "Answered call",
+        0x10 => "Ended call",
+        0x11 => "DD ping",
+        0x12 => "DD pong",
+        0x13 => "Remote auto fill",
+        0x14 => "Companion link proximity",
+        0x15 => "Remote management",
+        0x16 => "Remote auto fill pong",
+        0x17 => "Remote display",
+        _ => return format!("unknown (0x{:02X})", action),
+    };
+    name.to_string()
+}
+
+fn nearby_activity(code: u8) -> String {
+    let name = match code {
+        0x00 => "Activity level unknown",
+        0x01 => "Activity reporting disabled",
+        0x03 => "Idle user",
+        0x05 => "Audio playing, screen locked",
+        0x07 => "Active user (screen on)",
+        0x09 => "Screen on with video",
+        0x0A => "Watch on wrist and unlocked",
+        0x0B => "Recent user interaction",
+        0x0D => "User is driving",
+        0x0E => "Phone or FaceTime call",
+        _ => return format!("unknown (0x{:X})", code),
+    };
+    name.to_string()
+}
+
+/// Decodes the Continuity messages in Apple manufacturer data.
+pub struct ContinuityDecoder;
+
+impl Decoder for ContinuityDecoder {
+    fn name(&self) -> &'static str {
+        "continuity"
+    }
+
+    fn matches(&self, device: &DeviceInfo) -> bool {
+        device.manufacturer_data.contains_key(&APPLE_COMPANY_ID)
+    }
+
+    fn decode(&self, device: &DeviceInfo) -> Vec<DecodedField> {
+        let Some(data) = device.manufacturer_data.get(&APPLE_COMPANY_ID) else {
+            return Vec::new();
+        };
+        match parse_continuity(data) {
+            Ok(messages) => messages
+                .iter()
+                .flat_map(|message| {
+                    std::iter::once(DecodedField::new("Continuity", message.label())).chain(
+                        message
+                            .fields()
+                            .into_iter()
+                            .map(|(label, value)| DecodedField::new(label, value)),
+                    )
+                })
+                .collect(),
+            Err(e) => vec![DecodedField::new(
+                "Continuity",
+                format!("<malformed: {}> {}", e, bytes_to_hex(data)),
+            )],
+        }
+    }
+}
@@ -0,0 +1,304 @@
+use btleplug::api::bleuuid::uuid_from_u16;
+use uuid::Uuid;
+
+use crate::{
+    structs::{DecodedField, DeviceInfo},
+    utils::bytes_to_hex,
+};
+
+use super::Decoder;
+
+/// The 16-bit service UUID under which Eddystone frames are advertised.
+pub const EDDYSTONE_UUID: Uuid = uuid_from_u16(0xFEAA);
+
+/// A decoded Eddystone frame.
+#[derive(Debug, PartialEq)]
+pub enum Eddystone {
+    Uid {
+        tx_power: i8,
+        namespace: [u8; 10],
+        instance: [u8; 6],
+    },
+    Url {
+        tx_power: i8,
+        url: String,
+    },
+    Tlm {
+        version: u8,
+        battery_mv: Option<u16>,
+        temperature: Option<f32>,
+        adv_count: u32,
+        uptime_secs: f64,
+    },
+    Eid {
+        tx_power: i8,
+        eid: [u8; 8],
+    },
+}
+
+impl Eddystone {
+    /// Returns the name of the frame type.
+    pub fn frame_name(&self) -> &'static str {
+        match self {
+            Eddystone::Uid { .. } => "UID",
+            Eddystone::Url { .. } => "URL",
+            Eddystone::Tlm { .. } => "TLM",
+            Eddystone::Eid { .. } => "EID",
+        }
+    }
+
+    /// Returns the decoded values of the frame as label/value pairs.
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        match self {
+            Eddystone::Uid {
+                tx_power,
+                namespace,
+                instance,
+            } => vec![
+                ("Namespace", bytes_to_hex(namespace).replace(' ', "")),
+                ("Instance", bytes_to_hex(instance).replace(' ', "")),
+                ("TX Power @ 0m", format!("{} dBm", tx_power)),
+            ],
+            Eddystone::Url { tx_power, url } => vec![
+                ("URL", url.clone()),
+                ("TX Power @ 0m", format!("{} dBm", tx_power)),
+            ],
+            Eddystone::Tlm {
+                version,
+                battery_mv,
+                temperature,
+                adv_count,
+                uptime_secs,
+            } => {
+                if *version != 0x00 {
+                    return vec![("TLM", format!("encrypted (version 0x{:02X})", version))];
+                }
+                vec![
+                    (
+                        "Battery",
+                        battery_mv.map_or_else(|| "n/a".to_string(), |mv| format!("{} mV", mv)),
+                    ),
+                    (
+                        "Temperature",
+                        temperature.map_or_else(|| "n/a".to_string(), |t| format!("{:.2} °C", t)),
+                    ),
+                    ("Adv Count", adv_count.to_string()),
+                    ("Uptime", format!("{:.1} s", uptime_secs)),
+                ]
+            }
+            Eddystone::Eid { tx_power, eid } => vec![
+                ("EID", bytes_to_hex(eid).replace(' ', "")),
+                ("TX Power @ 0m", format!("{} dBm", tx_power)),
+            ],
+        }
+    }
+}
+
+const URL_SCHEMES: [&str; 4] = ["http://www.", "https://www.", "http://", "https://"];
+
+const URL_EXPANSIONS: [&str; 14] = [
+    ".com/", ".org/", ".edu/", ".net/", ".info/", ".biz/", ".gov/", ".com", ".org", ".edu", ".net",
+    ".info", ".biz", ".gov",
+];
+
+/// Decodes an Eddystone frame from the payload of 0xFEAA service data.
+pub fn decode_eddystone(data: &[u8]) -> Result<Eddystone, String> {
+    let frame_type = *data.first().ok_or("empty frame")?;
+    let require = |len: usize| {
+        if data.len() < len {
+            Err(format!("truncated frame ({} of {} bytes)", data.len(), len))
+        } else {
+            Ok(())
+        }
+    };
+    match frame_type {
+        0x00 => {
+            require(18)?;
+            let mut namespace = [0; 10];
+            let mut instance = [0; 6];
+            namespace.copy_from_slice(&data[2..12]);
+            instance.copy_from_slice(&data[12..18]);
+            Ok(Eddystone::Uid {
+                tx_power: data[1] as i8,
+                namespace,
+                instance,
+            })
+        }
+        0x10 => {
+            require(3)?;
+            let scheme = URL_SCHEMES
+                .get(data[2] as usize)
+                .ok_or_else(|| format!("unknown URL scheme 0x{:02X}", data[2]))?;
+            let mut url = scheme.to_string();
+            for &byte in &data[3..] {
+                match byte {
+                    0x00..=0x0D => url.push_str(URL_EXPANSIONS[byte as usize]),
+                    0x21..=0x7E => url.push(byte as char),
+                    _ => return Err(format!("invalid URL byte 0x{:02X}", byte)),
+                }
+            }
+            Ok(Eddystone::Url {
+                tx_power: data[1] as i8,
+                url,
+            })
+        }
+        0x20 => {
+            require(2)?;
+            if data[1] != 0x00 {
+                return Ok(Eddystone::Tlm {
+                    version: data[1],
+                    battery_mv: None,
+                    temperature: None,
+                    adv_count: 0,
+                    uptime_secs: 0.0,
+                });
+            }
+            require(14)?;
+            let battery = u16::from_be_bytes([data[2], data[3]]);
+            let temperature = i16::from_be_bytes([data[4], data[5]]);
+            Ok(Eddystone::Tlm {
+                version: data[1],
+                battery_mv: (battery != 0).then_some(battery),
+                // 0x8000 marks the temperature as unsupported, otherwise it is 8.8 fixed point.
+                temperature: (temperature != i16::MIN).then(|| temperature as f32 / 256.0),
+                adv_count: u32::from_be_bytes([data[6], data[7], data[8], data[9]]),
+                uptime_secs: u32::from_be_bytes([data[10], data[11], data[12], data[13]]) as f64
+                    / 10.0,
+            })
+        }
+        0x30 => {
+            require(10)?;
+            let mut eid = [0; 8];
+            eid.copy_from_slice(&data[2..10]);
+            Ok(Eddystone::Eid {
+                tx_power: data[1] as i8,
+                eid,
+            })
+        }
+        other => Err(format!("unknown frame type 0x{:02X}", other)),
+    }
+}
+
+/// Decodes Eddystone frames from their service data.
+pub struct EddystoneDecoder;
+
+impl Decoder for EddystoneDecoder {
+    fn name(&self) -> &'static str {
+        "eddystone"
+    }
+
+    fn matches(&self, device: &DeviceInfo) -> bool {
+        device.service_data.contains_key(&EDDYSTONE_UUID)
+    }
+
+    fn decode(&self, device: &DeviceInfo) -> Vec<DecodedField> {
+        match device
+            .service_data
+            .get(&EDDYSTONE_UUID)
+            .map(|data| decode_eddystone(data))
+        {
+            Some(Ok(frame)) => {
+                std::iter::once(DecodedField::new("Eddystone Frame", frame.frame_name()))
+                    .chain(frame.fields().into_iter().map(|(label, value)| {
+                        DecodedField::new(format!("Eddystone {}", label), value)
+                    }))
+                    .collect()
+            }
+            Some(Err(e)) => vec![DecodedField::new(
+                "Eddystone",
+                format!("<malformed: {}>", e),
+            )],
+            None => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uid_frame_splits_namespace_and_instance() {
+        let frame = [
+            0x00, 0xEC, 0xED, 0xD1, 0xEB, 0xEA, 0xC0, 0x4E, 0x5D, 0xEF, 0xA0, 0x17, 0x0B, 0xDB,
+            0x87, 0x53, 0x9B, 0x67, 0x00, 0x00,
+        ];
+        let decoded = decode_eddystone(&frame).unwrap();
+        assert_eq!(decoded.frame_name(), "UID");
+        assert_eq!(
+            decoded.fields(),
+            [
+                ("Namespace", "EDD1EBEAC04E5DEFA017".to_string()),
+                ("Instance", "0BDB87539B67".to_string()),
+                ("TX Power @ 0m", "-20 dBm".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn url_frame_expands_the_scheme_and_suffixes() {
+        let cases: [(&[u8], &str); 2] = [
+            (b"\x10\xEB\x03goo.gl/S6zT6P", "https://goo.gl/S6zT6P"),
+            (b"\x10\xEB\x00example\x07", "http://www.example.com"),
+        ];
+        for (frame, url) in cases {
+            assert_eq!(
+                decode_eddystone(frame),
+                Ok(Eddystone::Url {
+                    tx_power: -21,
+                    url: url.to_string()
+                })
+            );
+        }
+        assert_eq!(
+            decode_eddystone(b"\x10\xEB\x04x"),
+            Err("unknown URL scheme 0x04".to_string())
+        );
+    }
+
+    #[test]
+    fn tlm_frame_scales_its_readings() {
+        let frame = [
+            0x20, 0x00, 0x0B, 0xB8, 0x18, 0x80, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x64,
+        ];
+        assert_eq!(
+            decode_eddystone(&frame).unwrap().fields(),
+            [
+                ("Battery", "3000 mV".to_string()),
+                ("Temperature", "24.50 °C".to_string()),
+                ("Adv Count", "256".to_string()),
+                ("Uptime", "10.0 s".to_string()),
+            ]
+        );
+
+        let unsupported = [
+            0x20, 0x00, 0x00, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x0A,
+        ];
+        let fields = decode_eddystone(&unsupported).unwrap().fields();
+        assert_eq!(fields[0], ("Battery", "n/a".to_string()));
+        assert_eq!(fields[1], ("Temperature", "n/a".to_string()));
+
+        assert_eq!(
+            decode_eddystone(&[0x20, 0x01]).unwrap().fields(),
+            [("TLM", "encrypted (version 0x01)".to_string())]
+        );
+    }
+
+    #[test]
+    fn bad_frames_are_errors() {
+        let cases: [(&[u8], &str); 4] = [
+            (&[], "empty frame"),
+            (&[0x00, 0xEC, 0xED], "truncated frame (3 of 18 bytes)"),
+            (&[0x20, 0x00, 0x0B], "truncated frame (3 of 14 bytes)"),
+            (&[0x40, 0x00], "unknown frame type 0x40"),
+        ];
+        for (frame, error) in cases {
+            assert_eq!(
+                decode_eddystone(frame),
+                Err(error.to_string()),
+                "{:02X?}",
+                frame
+            );
+        }
+    }
+}
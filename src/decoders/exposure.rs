@@ -0,0 +1,65 @@
+use btleplug::api::bleuuid::uuid_from_u16;
+use uuid::Uuid;
+
+use crate::{
+    structs::{DecodedField, DeviceInfo},
+    utils::bytes_to_hex,
+};
+
+use super::Decoder;
+
+/// The 16-bit service UUID of the Google/Apple Exposure Notification service.
+pub const EXPOSURE_NOTIFICATION_UUID: Uuid = uuid_from_u16(0xFD6F);
+
+/// A struct to hold an Exposure Notification advertisement.
+#[derive(Debug, PartialEq)]
+pub struct ExposureNotification {
+    pub rpi: [u8; 16],
+    pub metadata: [u8; 4],
+}
+
+/// Decodes the 0xFD6F service data into the Rolling Proximity Identifier and the
+/// Associated Encrypted Metadata. Both are encrypted, so they are kept as raw bytes.
+pub fn decode_exposure_notification(data: &[u8]) -> Result<ExposureNotification, String> {
+    if data.len() != 20 {
+        return Err(format!("expected 20 bytes, got {}", data.len()));
+    }
+    let mut rpi = [0; 16];
+    let mut metadata = [0; 4];
+    rpi.copy_from_slice(&data[..16]);
+    metadata.copy_from_slice(&data[16..]);
+    Ok(ExposureNotification { rpi, metadata })
+}
+
+/// Decodes Exposure Notification service data.
+pub struct ExposureNotificationDecoder;
+
+impl Decoder for ExposureNotificationDecoder {
+    fn name(&self) -> &'static str {
+        "exposure"
+    }
+
+    fn matches(&self, device: &DeviceInfo) -> bool {
+        device
+            .service_data
+            .contains_key(&EXPOSURE_NOTIFICATION_UUID)
+    }
+
+    fn decode(&self, device: &DeviceInfo) -> Vec<DecodedField> {
+        let data = device.service_data.get(&EXPOSURE_NOTIFICATION_UUID);
+        match data.map(|data| decode_exposure_notification(data)) {
+            Some(Ok(exposure)) => vec![
+                DecodedField::new("EN RPI", bytes_to_hex(&exposure.rpi).replace(' ', "")),
+                DecodedField::new(
+                    "EN Metadata",
+                    bytes_to_hex(&exposure.metadata).replace(' ', ""),
+                ),
+            ],
+            Some(Err(e)) => vec![DecodedField::new(
+                "Exposure Notification",
+                format!("<malformed: {}>", e),
+            )],
+            None => Vec::new(),
+        }
+    }
+}
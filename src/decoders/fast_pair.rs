@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+
+use btleplug::api::bleuuid::uuid_from_u16;
+use uuid::Uuid;
+
+use crate::{
+    structs::{DecodedField, DeviceInfo},
+    utils::bytes_to_hex,
+};
+
+use super::Decoder;
+
+/// The 16-bit service UUID under which Google Fast Pair data is advertised.
+pub const FAST_PAIR_UUID: Uuid = uuid_from_u16(0xFE2C);
+
+lazy_static! {
+    /// A handful of well-known Fast Pair model IDs.
+    static ref FAST_PAIR_MODELS: HashMap<u32, &'static str> = HashMap::from([
+        (0x00000A, "Anti-Spoof Test"),
+        (0x0000F0, "Bose QuietComfort 35 II"),
+        (0x0001F0, "Bisto CSR8670 Dev Board"),
+        (0x0E30C3, "Razer Hammerhead TWS"),
+        (0x2D7A23, "Sony WF-1000XM4"),
+        (0x718FA4, "JBL Live 300TWS"),
+        (0x72EF8D, "Razer Hammerhead TWS X"),
+        (0x821F66, "JBL Flip 6"),
+        (0x92BBBD, "Pixel Buds"),
+        (0xCD8256, "Bose NC 700"),
+        (0xD446A7, "Sony WH-1000XM5"),
+        (0xF52494, "JBL Buds Pro"),
+    ]);
+}
+
+/// A decoded Fast Pair advertisement.
+#[derive(Debug, PartialEq)]
+pub enum FastPair {
+    /// The provider is in pairing mode and advertises its model ID.
+    Discoverable { model_id: u32 },
+    /// The provider is paired and advertises a filter of the account keys it knows.
+    NotDiscoverable { account_key_filter: Vec<u8> },
+}
+
+impl FastPair {
+    /// Returns the decoded values of the advertisement as label/value pairs.
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        match self {
+            FastPair::Discoverable { model_id } => {
+                let mut fields = vec![
+                    ("Fast Pair Mode", "discoverable".to_string()),
+                    ("Fast Pair Model ID", format!("0x{:06X}", model_id)),
+                ];
+                if let Some(model) = FAST_PAIR_MODELS.get(model_id) {
+                    fields.push(("Fast Pair Model", model.to_string()));
+                }
+                fields
+            }
+            FastPair::NotDiscoverable { account_key_filter } => vec![
+                ("Fast Pair Mode", "not discoverable".to_string()),
+                (
+                    "Fast Pair Key Filter",
+                    bytes_to_hex(account_key_filter).replace(' ', ""),
+                ),
+            ],
+        }
+    }
+}
+
+/// Decodes the 0xFE2C service data of a Fast Pair provider.
+pub fn decode_fast_pair(data: &[u8]) -> Result<FastPair, String> {
+    match data.len() {
+        0 => Err("empty payload".to_string()),
+        3 => Ok(FastPair::Discoverable {
+            model_id: u32::from_be_bytes([0, data[0], data[1], data[2]]),
+        }),
+        _ if data[0] >> 4 != 0 => Err(format!("unsupported version {}", data[0] >> 4)),
+        1 => Ok(FastPair::NotDiscoverable {
+            account_key_filter: Vec::new(),
+        }),
+        _ => {
+            // The account key filter is a length/type field followed by the filter bytes.
+            let length = (data[1] >> 4) as usize;
+            let filter = data
+                .get(2..2 + length)
+                .ok_or_else(|| format!("truncated account key filter ({} bytes)", length))?;
+            Ok(FastPair::NotDiscoverable {
+                account_key_filter: filter.to_vec(),
+            })
+        }
+    }
+}
+
+/// Decodes Google Fast Pair service data.
+pub struct FastPairDecoder;
+
+impl Decoder for FastPairDecoder {
+    fn name(&self) -> &'static str {
+        "fast-pair"
+    }
+
+    fn matches(&self, device: &DeviceInfo) -> bool {
+        device.service_data.contains_key(&FAST_PAIR_UUID)
+    }
+
+    fn decode(&self, device: &DeviceInfo) -> Vec<DecodedField> {
+        match device
+            .service_data
+            .get(&FAST_PAIR_UUID)
+            .map(|data| decode_fast_pair(data))
+        {
+            Some(Ok(decoded)) => decoded
+                .fields()
+                .into_iter()
+                .map(|(label, value)| DecodedField::new(label, value))
+                .collect(),
+            Some(Err(e)) => vec![DecodedField::new(
+                "Fast Pair",
+                format!("<malformed: {}>", e),
+            )],
+            None => Vec::new(),
+        }
+    }
+}
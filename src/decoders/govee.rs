@@ -0,0 +1,161 @@
+use crate::structs::{DecodedField, DeviceInfo};
+
+use super::Decoder;
+
+/// The company identifiers Govee sensors advertise their readings under. Both byte orders
+/// are seen in the wild.
+pub const GOVEE_COMPANY_IDS: [u16; 2] = [0xEC88, 0x88EC];
+
+/// A decoded Govee thermometer/hygrometer reading.
+#[derive(Debug, PartialEq)]
+pub struct GoveeReading {
+    /// The payload layout the reading was decoded from.
+    pub model: &'static str,
+    /// Temperature in degrees Celsius.
+    pub temperature: f64,
+    /// Relative humidity in percent.
+    pub humidity: f64,
+    /// Battery level in percent.
+    pub battery: u8,
+}
+
+impl GoveeReading {
+    /// Returns the decoded values of the reading as label/value pairs.
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("Govee Format", self.model.to_string()),
+            ("Govee Temperature", format!("{:.2} °C", self.temperature)),
+            ("Govee Humidity", format!("{:.1} %", self.humidity)),
+            ("Govee Battery", format!("{} %", self.battery)),
+        ]
+    }
+}
+
+/// Decodes the manufacturer data of a Govee sensor. Returns `None` if the payload does
+/// not match a known layout, since other Govee products use the same company identifier.
+pub fn decode_govee(data: &[u8]) -> Option<GoveeReading> {
+    match data {
+        // H5075/H5072/H5101 style: temperature and humidity packed into one 24-bit big-endian
+        // integer as `temperature * 1000 + humidity`, both in tenths, with the top bit set
+        // for sub-zero temperatures.
+        [0x00, b1, b2, b3, battery, _] if *battery <= 100 => {
+            let packed = u32::from_be_bytes([0, *b1, *b2, *b3]);
+            let value = packed & 0x7F_FFFF;
+            let sign = if packed & 0x80_0000 != 0 { -1.0 } else { 1.0 };
+            Some(GoveeReading {
+                model: "H5075",
+                temperature: sign * f64::from(value / 1000) / 10.0,
+                humidity: f64::from(value % 1000) / 10.0,
+                battery: *battery,
+            })
+        }
+        // H5074 style: little-endian signed temperature and unsigned humidity in hundredths.
+        [0x00, t1, t2, h1, h2, battery, _] if *battery <= 100 => Some(GoveeReading {
+            model: "H5074",
+            temperature: f64::from(i16::from_le_bytes([*t1, *t2])) / 100.0,
+            humidity: f64::from(u16::from_le_bytes([*h1, *h2])) / 100.0,
+            battery: *battery,
+        }),
+        _ => None,
+    }
+}
+
+/// Decodes Govee thermometer/hygrometer manufacturer data.
+pub struct GoveeDecoder;
+
+impl Decoder for GoveeDecoder {
+    fn name(&self) -> &'static str {
+        "govee"
+    }
+
+    fn matches(&self, device: &DeviceInfo) -> bool {
+        GOVEE_COMPANY_IDS
+            .iter()
+            .any(|id| device.manufacturer_data.contains_key(id))
+    }
+
+    fn decode(&self, device: &DeviceInfo) -> Vec<DecodedField> {
+        GOVEE_COMPANY_IDS
+            .iter()
+            .filter_map(|id| device.manufacturer_data.get(id))
+            .find_map(|data| decode_govee(data))
+            .map(|reading| {
+                reading
+                    .fields()
+                    .into_iter()
+                    .map(|(label, value)| DecodedField::new(label, value))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn reports_measurements(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn packed_readings_decode_above_and_below_zero() {
+        // 23.4 °C and 45.6 %: 234 * 1000 + 456 = 0x0393D8.
+        assert_eq!(
+            decode_govee(&[0x00, 0x03, 0x93, 0xD8, 0x64, 0x00]),
+            Some(GoveeReading {
+                model: "H5075",
+                temperature: 23.4,
+                humidity: 45.6,
+                battery: 100,
+            })
+        );
+        // -5.2 °C and 61.0 %: 52 * 1000 + 610 = 0x00CD82, with the sign bit set.
+        assert_eq!(
+            decode_govee(&[0x00, 0x80, 0xCD, 0x82, 0x37, 0x00]),
+            Some(GoveeReading {
+                model: "H5075",
+                temperature: -5.2,
+                humidity: 61.0,
+                battery: 55,
+            })
+        );
+    }
+
+    #[test]
+    fn little_endian_readings_decode_below_zero() {
+        // -3.25 °C and 55.00 % in hundredths.
+        let reading = decode_govee(&[0x00, 0xBB, 0xFE, 0x7C, 0x15, 0x50, 0x02]).unwrap();
+        assert_eq!(
+            reading.fields(),
+            [
+                ("Govee Format", "H5074".to_string()),
+                ("Govee Temperature", "-3.25 °C".to_string()),
+                ("Govee Humidity", "55.0 %".to_string()),
+                ("Govee Battery", "80 %".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn other_payloads_under_the_company_are_ignored() {
+        for data in [
+            &[0x01, 0x03, 0x93, 0xD8, 0x64, 0x00][..],
+            &[0x00, 0x03, 0x93, 0xD8, 0xC8, 0x00][..],
+            &[0x09, 0x48, 0x36, 0x31, 0x33, 0x35, 0x5F, 0x30][..],
+            &[][..],
+        ] {
+            assert_eq!(decode_govee(data), None, "{:02X?}", data);
+        }
+
+        // A Govee light strip under the same company produces no fields.
+        let device = DeviceInfo {
+            manufacturer_data: HashMap::from([(0x88EC, vec![0x09, 0x48, 0x36, 0x31, 0x33])]),
+            ..DeviceInfo::default()
+        };
+        assert!(GoveeDecoder.matches(&device));
+        assert!(GoveeDecoder.decode(&device).is_empty());
+    }
+}
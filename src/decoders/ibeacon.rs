@@ -0,0 +1,105 @@
+use uuid::Uuid;
+
+use crate::structs::{DecodedField, DeviceInfo};
+
+use super::{Decoder, APPLE_COMPANY_ID};
+
+/// A struct to hold the information of an iBeacon frame.
+#[derive(Debug, PartialEq)]
+pub struct IBeacon {
+    pub uuid: Uuid,
+    pub major: u16,
+    pub minor: u16,
+    pub measured_power: i8,
+}
+
+/// Decodes an iBeacon frame from the payload of Apple (0x004C) manufacturer data.
+/// Returns `None` if the payload is not an iBeacon frame (type 0x02, length 0x15).
+/// Returns `Some(Err(_))` if the frame is recognized but truncated.
+pub fn decode_ibeacon(data: &[u8]) -> Option<Result<IBeacon, String>> {
+    if data.len() < 2 || data[0] != 0x02 || data[1] != 0x15 {
+        return None;
+    }
+    if data.len() < 23 {
+        return Some(Err(format!("truncated frame ({} of 23 bytes)", data.len())));
+    }
+    let uuid = Uuid::from_slice(&data[2..18]).ok()?;
+    Some(Ok(IBeacon {
+        uuid,
+        major: u16::from_be_bytes([data[18], data[19]]),
+        minor: u16::from_be_bytes([data[20], data[21]]),
+        measured_power: data[22] as i8,
+    }))
+}
+
+/// Decodes iBeacon frames from Apple manufacturer data.
+pub struct IBeaconDecoder;
+
+impl Decoder for IBeaconDecoder {
+    fn name(&self) -> &'static str {
+        "ibeacon"
+    }
+
+    fn matches(&self, device: &DeviceInfo) -> bool {
+        device.manufacturer_data.contains_key(&APPLE_COMPANY_ID)
+    }
+
+    fn decode(&self, device: &DeviceInfo) -> Vec<DecodedField> {
+        let data = device.manufacturer_data.get(&APPLE_COMPANY_ID);
+        match data.and_then(|data| decode_ibeacon(data)) {
+            Some(Ok(beacon)) => vec![
+                DecodedField::new("iBeacon UUID", beacon.uuid.to_string()),
+                DecodedField::new("iBeacon Major", beacon.major.to_string()),
+                DecodedField::new("iBeacon Minor", beacon.minor.to_string()),
+                DecodedField::new("iBeacon Power", format!("{} dBm", beacon.measured_power)),
+            ],
+            Some(Err(e)) => vec![DecodedField::new("iBeacon", format!("<malformed: {}>", e))],
+            None => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An iBeacon frame for E2C56DB5-DFFB-48D2-B060-D0F5A71096E0, major 1, minor 2, at
+    /// -59 dBm.
+    const FRAME: [u8; 23] = [
+        0x02, 0x15, 0xE2, 0xC5, 0x6D, 0xB5, 0xDF, 0xFB, 0x48, 0xD2, 0xB0, 0x60, 0xD0, 0xF5, 0xA7,
+        0x10, 0x96, 0xE0, 0x00, 0x01, 0x00, 0x02, 0xC5,
+    ];
+
+    #[test]
+    fn valid_frame_decodes() {
+        assert_eq!(
+            decode_ibeacon(&FRAME),
+            Some(Ok(IBeacon {
+                uuid: Uuid::parse_str("e2c56db5-dffb-48d2-b060-d0f5a71096e0").unwrap(),
+                major: 1,
+                minor: 2,
+                measured_power: -59,
+            }))
+        );
+    }
+
+    #[test]
+    fn truncated_frame_is_an_error() {
+        assert_eq!(
+            decode_ibeacon(&FRAME[..10]),
+            Some(Err("truncated frame (10 of 23 bytes)".to_string()))
+        );
+    }
+
+    #[test]
+    fn other_apple_payloads_are_not_ibeacons() {
+        // A Nearby Info message, then an empty and a one-byte payload.
+        for payload in [
+            &[0x10, 0x05, 0x01, 0x18, 0x44, 0x2F, 0x0A][..],
+            &[],
+            &[0x02],
+        ] {
+            assert_eq!(decode_ibeacon(payload), None, "{:02X?}", payload);
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use btleplug::api::bleuuid::uuid_from_u16;
+use uuid::Uuid;
+
+use crate::{
+    structs::{DecodedField, DeviceInfo},
+    utils::bytes_to_hex,
+};
+
+use super::Decoder;
+
+/// The 16-bit service UUID advertised by unprovisioned Mesh devices.
+pub const MESH_PROVISIONING_UUID: Uuid = uuid_from_u16(0x1827);
+
+/// The 16-bit service UUID advertised by Mesh Proxy nodes.
+pub const MESH_PROXY_UUID: Uuid = uuid_from_u16(0x1828);
+
+/// The OOB information flags, by bit position.
+const OOB_FLAGS: [(u16, &str); 13] = [
+    (0, "other"),
+    (1, "URI"),
+    (2, "2D code"),
+    (3, "bar code"),
+    (4, "NFC"),
+    (5, "number"),
+    (6, "string"),
+    (7, "certificate"),
+    (8, "records"),
+    (11, "on box"),
+    (12, "inside box"),
+    (13, "on paper"),
+    (14, "inside manual"),
+];
+
+/// A decoded Bluetooth Mesh advertisement.
+#[derive(Debug, PartialEq)]
+pub enum MeshAdvertisement {
+    /// An unprovisioned device waiting to join a network.
+    Unprovisioned { uuid: Uuid, oob_info: u16 },
+    /// A proxy node, identified by the network it belongs to or by its node identity.
+    Proxy { identification: u8, data: Vec<u8> },
+}
+
+impl MeshAdvertisement {
+    /// Returns the decoded values of the advertisement as label/value pairs.
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        match self {
+            MeshAdvertisement::Unprovisioned { uuid, oob_info } => vec![
+                ("Mesh", "unprovisioned device".to_string()),
+                ("Mesh Device UUID", uuid.to_string()),
+                ("Mesh OOB Info", oob_label(*oob_info)),
+            ],
+            MeshAdvertisement::Proxy {
+                identification,
+                data,
+            } => vec![
+                ("Mesh", "proxy node".to_string()),
+                (
+                    match identification {
+                        0x00 => "Mesh Network ID",
+                        0x01 => "Mesh Node Identity",
+                        0x02 => "Mesh Private Network ID",
+                        0x03 => "Mesh Private Node ID",
+                        _ => "Mesh Proxy Data",
+                    },
+                    bytes_to_hex(data).replace(' ', ""),
+                ),
+            ],
+        }
+    }
+}
+
+/// Lists the OOB information flags that are set, e.g. `0x0810 (NFC, on box)`.
+fn oob_label(oob_info: u16) -> String {
+    let flags: Vec<&str> = OOB_FLAGS
+        .iter()
+        .filter(|(bit, _)| oob_info & (1 << bit) != 0)
+        .map(|(_, name)| *name)
+        .collect();
+    if flags.is_empty() {
+        format!("0x{:04X} (none)", oob_info)
+    } else {
+        format!("0x{:04X} ({})", oob_info, flags.join(", "))
+    }
+}
+
+/// Decodes the 0x1827 service data of an unprovisioned Mesh device.
+pub fn decode_mesh_provisioning(data: &[u8]) -> Result<MeshAdvertisement, String> {
+    if data.len() != 18 {
+        return Err(format!("expected 18 bytes, got {}", data.len()));
+    }
+    Ok(MeshAdvertisement::Unprovisioned {
+        uuid: Uuid::from_slice(&data[..16]).map_err(|e| e.to_string())?,
+        oob_info: u16::from_be_bytes([data[16], data[17]]),
+    })
+}
+
+/// Decodes the 0x1828 service data of a Mesh Proxy node.
+pub fn decode_mesh_proxy(data: &[u8]) -> Result<MeshAdvertisement, String> {
+    let (&identification, rest) = data
+        .split_first()
+        .ok_or_else(|| "empty payload".to_string())?;
+    Ok(MeshAdvertisement::Proxy {
+        identification,
+        data: rest.to_vec(),
+    })
+}
+
+/// Returns `true` if the device advertises one of the Mesh services.
+pub fn is_mesh(device: &DeviceInfo) -> bool {
+    [MESH_PROVISIONING_UUID, MESH_PROXY_UUID]
+        .iter()
+        .any(|uuid| device.service_data.contains_key(uuid) || device.services.contains(uuid))
+}
+
+/// Decodes Bluetooth Mesh provisioning and proxy service data.
+pub struct MeshDecoder;
+
+impl Decoder for MeshDecoder {
+    fn name(&self) -> &'static str {
+        "mesh"
+    }
+
+    fn matches(&self, device: &DeviceInfo) -> bool {
+        is_mesh(device)
+    }
+
+    fn decode(&self, device: &DeviceInfo) -> Vec<DecodedField> {
+        let decoded = match (
+            device.service_data.get(&MESH_PROVISIONING_UUID),
+            device.service_data.get(&MESH_PROXY_UUID),
+        ) {
+            (Some(data), _) => decode_mesh_provisioning(data),
+            (None, Some(data)) => decode_mesh_proxy(data),
+            (None, None) => return vec![DecodedField::new("Mesh", "service advertised")],
+        };
+        match decoded {
+            Ok(mesh) => mesh
+                .fields()
+                .into_iter()
+                .map(|(label, value)| DecodedField::new(label, value))
+                .collect(),
+            Err(e) => vec![DecodedField::new("Mesh", format!("<malformed: {}>", e))],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::decoders::device_tags;
+
+    #[test]
+    fn unprovisioned_beacon_decodes_uuid_and_oob_flags() {
+        let mut data: Vec<u8> = (0x00..0x10).collect();
+        data.extend([0x08, 0x10]);
+        let decoded = decode_mesh_provisioning(&data).unwrap();
+        assert_eq!(
+            decoded.fields(),
+            [
+                ("Mesh", "unprovisioned device".to_string()),
+                (
+                    "Mesh Device UUID",
+                    "00010203-0405-0607-0809-0a0b0c0d0e0f".to_string()
+                ),
+                ("Mesh OOB Info", "0x0810 (NFC, on box)".to_string()),
+            ]
+        );
+        data[16..].copy_from_slice(&[0x00, 0x00]);
+        assert_eq!(
+            decode_mesh_provisioning(&data).unwrap().fields()[2].1,
+            "0x0000 (none)"
+        );
+        assert_eq!(
+            decode_mesh_provisioning(&data[..10]),
+            Err("expected 18 bytes, got 10".to_string())
+        );
+    }
+
+    #[test]
+    fn proxy_advertisements_name_their_identification() {
+        for (identification, label) in [
+            (0x00, "Mesh Network ID"),
+            (0x01, "Mesh Node Identity"),
+            (0x02, "Mesh Private Network ID"),
+            (0x03, "Mesh Private Node ID"),
+            (0x7F, "Mesh Proxy Data"),
+        ] {
+            let decoded = decode_mesh_proxy(&[identification, 0xAB, 0xCD]).unwrap();
+            assert_eq!(
+                decoded.fields()[1],
+                (label, "ABCD".to_string()),
+                "identification 0x{:02X}",
+                identification
+            );
+        }
+        assert_eq!(decode_mesh_proxy(&[]), Err("empty payload".to_string()));
+    }
+
+    #[test]
+    fn mesh_devices_are_tagged() {
+        let proxy = DeviceInfo {
+            service_data: HashMap::from([(MESH_PROXY_UUID, vec![0x00, 0x11, 0x22])]),
+            ..DeviceInfo::default()
+        };
+        assert!(device_tags(&proxy).contains(&"BT Mesh"));
+        assert_eq!(MeshDecoder.decode(&proxy)[0].value, "proxy node");
+
+        // Advertising the service without data is enough for the tag.
+        let listed = DeviceInfo {
+            services: vec![MESH_PROVISIONING_UUID],
+            ..DeviceInfo::default()
+        };
+        assert!(device_tags(&listed).contains(&"BT Mesh"));
+        assert_eq!(MeshDecoder.decode(&listed)[0].value, "service advertised");
+
+        assert!(!device_tags(&DeviceInfo::default()).contains(&"BT Mesh"));
+    }
+}
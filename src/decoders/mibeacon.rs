@@ -0,0 +1,314 @@
+use std::collections::HashMap;
+
+use btleplug::api::bleuuid::uuid_from_u16;
+use uuid::Uuid;
+
+use crate::{
+    structs::{DecodedField, DeviceInfo},
+    utils::bytes_to_hex,
+};
+
+use super::Decoder;
+
+/// The 16-bit service UUID under which Xiaomi MiBeacon data is advertised.
+pub const MIBEACON_UUID: Uuid = uuid_from_u16(0xFE95);
+
+/// Frame control bits of a MiBeacon frame.
+const ENCRYPTED: u16 = 0x0008;
+const MAC_INCLUDED: u16 = 0x0010;
+const CAPABILITY_INCLUDED: u16 = 0x0020;
+const OBJECT_INCLUDED: u16 = 0x0040;
+
+lazy_static! {
+    /// A handful of common Xiaomi product IDs.
+    static ref MIBEACON_PRODUCTS: HashMap<u16, &'static str> = HashMap::from([
+        (0x0098, "HHCCJCY01 Flower Care"),
+        (0x0153, "YLYK01YL Remote"),
+        (0x01AA, "LYWSDCGQ Thermometer"),
+        (0x0347, "CGG1 Thermometer"),
+        (0x03BC, "GCLS002 Flower Pot"),
+        (0x045B, "LYWSD02 Clock"),
+        (0x055B, "LYWSD03MMC Thermometer"),
+        (0x0576, "CGD1 Alarm Clock"),
+        (0x066F, "CGDK2 Thermometer"),
+        (0x0A83, "CGPR1 Motion Sensor"),
+    ]);
+}
+
+/// The payload carried by a MiBeacon frame.
+#[derive(Debug, PartialEq)]
+pub enum MiBeaconPayload {
+    /// The frame does not carry an object, e.g. a pairing advertisement.
+    None,
+    /// The object is encrypted with the device's bind key and cannot be decoded.
+    Encrypted(Vec<u8>),
+    /// A plain object, identified by its object ID.
+    Object { id: u16, data: Vec<u8> },
+}
+
+/// A decoded MiBeacon frame.
+#[derive(Debug, PartialEq)]
+pub struct MiBeacon {
+    pub frame_control: u16,
+    pub product_id: u16,
+    pub frame_counter: u8,
+    pub mac: Option<[u8; 6]>,
+    pub payload: MiBeaconPayload,
+}
+
+impl MiBeacon {
+    /// Returns the version of the MiBeacon format used by the frame.
+    pub fn version(&self) -> u16 {
+        self.frame_control >> 12
+    }
+
+    /// Returns the decoded values of the frame as label/value pairs.
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        let mut fields = vec![
+            (
+                "MiBeacon",
+                format!(
+                    "v{} (frame control 0x{:04X})",
+                    self.version(),
+                    self.frame_control
+                ),
+            ),
+            (
+                "MiBeacon Product",
+                match MIBEACON_PRODUCTS.get(&self.product_id) {
+                    Some(model) => format!("{} (0x{:04X})", model, self.product_id),
+                    None => format!("0x{:04X}", self.product_id),
+                },
+            ),
+            ("MiBeacon Counter", self.frame_counter.to_string()),
+        ];
+        if let Some(mac) = self.mac {
+            // The MAC address is transmitted least significant byte first.
+            let mut mac = mac;
+            mac.reverse();
+            fields.push(("MiBeacon MAC", bytes_to_hex(&mac).replace(' ', ":")));
+        }
+        match &self.payload {
+            MiBeaconPayload::None => {}
+            MiBeaconPayload::Encrypted(data) => fields.push((
+                "MiBeacon Payload",
+                format!("<encrypted> {}", bytes_to_hex(data)),
+            )),
+            MiBeaconPayload::Object { id, data } => fields.extend(object_fields(*id, data)),
+        }
+        fields
+    }
+}
+
+/// Decodes a plain MiBeacon object into labeled measurements. Objects that are unknown or
+/// have an unexpected length are shown as raw bytes.
+fn object_fields(id: u16, data: &[u8]) -> Vec<(&'static str, String)> {
+    let u16_at = |i: usize| u16::from_le_bytes([data[i], data[i + 1]]);
+    let tenths = |value: f64| format!("{:.1}", value / 10.0);
+    match (id, data.len()) {
+        (0x1004, 2) => vec![(
+            "MiBeacon Temperature",
+            format!("{} °C", tenths(f64::from(u16_at(0) as i16))),
+        )],
+        (0x1006, 2) => vec![(
+            "MiBeacon Humidity",
+            format!("{} %", tenths(f64::from(u16_at(0)))),
+        )],
+        (0x100D, 4) => vec![
+            (
+                "MiBeacon Temperature",
+                format!("{} °C", tenths(f64::from(u16_at(0) as i16))),
+            ),
+            (
+                "MiBeacon Humidity",
+                format!("{} %", tenths(f64::from(u16_at(2)))),
+            ),
+        ],
+        (0x100A, 1) => vec![("MiBeacon Battery", format!("{} %", data[0]))],
+        (0x1007, 3) => vec![(
+            "MiBeacon Illuminance",
+            format!("{} lux", u32::from_le_bytes([data[0], data[1], data[2], 0])),
+        )],
+        (0x1008, 1) => vec![("MiBeacon Moisture", format!("{} %", data[0]))],
+        (0x1009, 2) => vec![("MiBeacon Conductivity", format!("{} µS/cm", u16_at(0)))],
+        _ => vec![(
+            "MiBeacon Object",
+            format!("0x{:04X}: {}", id, bytes_to_hex(data)),
+        )],
+    }
+}
+
+/// Decodes the 0xFE95 service data of a Xiaomi device.
+pub fn decode_mibeacon(data: &[u8]) -> Result<MiBeacon, String> {
+    if data.len() < 5 {
+        return Err(format!("expected at least 5 bytes, got {}", data.len()));
+    }
+    let frame_control = u16::from_le_bytes([data[0], data[1]]);
+    let truncated = || "truncated frame".to_string();
+    let mut rest = &data[5..];
+
+    let mac = if frame_control & MAC_INCLUDED != 0 {
+        let mac: [u8; 6] = rest
+            .get(..6)
+            .ok_or_else(truncated)?
+            .try_into()
+            .map_err(|_| truncated())?;
+        rest = &rest[6..];
+        Some(mac)
+    } else {
+        None
+    };
+    if frame_control & CAPABILITY_INCLUDED != 0 {
+        let capability = *rest.first().ok_or_else(truncated)?;
+        // Capabilities with the I/O flag set are followed by two bytes of I/O capability.
+        let size = if capability & 0x20 != 0 { 3 } else { 1 };
+        rest = rest.get(size..).ok_or_else(truncated)?;
+    }
+
+    let payload = if frame_control & OBJECT_INCLUDED == 0 {
+        MiBeaconPayload::None
+    } else if frame_control & ENCRYPTED != 0 {
+        MiBeaconPayload::Encrypted(rest.to_vec())
+    } else {
+        let header = rest.get(..3).ok_or_else(truncated)?;
+        let length = header[2] as usize;
+        let object = rest.get(3..3 + length).ok_or_else(truncated)?;
+        MiBeaconPayload::Object {
+            id: u16::from_le_bytes([header[0], header[1]]),
+            data: object.to_vec(),
+        }
+    };
+
+    Ok(MiBeacon {
+        frame_control,
+        product_id: u16::from_le_bytes([data[2], data[3]]),
+        frame_counter: data[4],
+        mac,
+        payload,
+    })
+}
+
+/// Decodes Xiaomi MiBeacon service data.
+pub struct MiBeaconDecoder;
+
+impl Decoder for MiBeaconDecoder {
+    fn name(&self) -> &'static str {
+        "mibeacon"
+    }
+
+    fn matches(&self, device: &DeviceInfo) -> bool {
+        device.service_data.contains_key(&MIBEACON_UUID)
+    }
+
+    fn decode(&self, device: &DeviceInfo) -> Vec<DecodedField> {
+        match device
+            .service_data
+            .get(&MIBEACON_UUID)
+            .map(|data| decode_mibeacon(data))
+        {
+            Some(Ok(decoded)) => decoded
+                .fields()
+                .into_iter()
+                .map(|(label, value)| DecodedField::new(label, value))
+                .collect(),
+            Some(Err(e)) => vec![DecodedField::new("MiBeacon", format!("<malformed: {}>", e))],
+            None => Vec::new(),
+        }
+    }
+
+    fn reports_measurements(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lywsd03mmc_plain_frame_decodes() {
+        // A v5 frame with the MAC included and a temperature and humidity object.
+        let frame = [
+            0x50, 0x50, 0x5B, 0x05, 0x2A, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11, 0x0D, 0x10, 0x04,
+            0xD2, 0x00, 0xF3, 0x01,
+        ];
+        let decoded = decode_mibeacon(&frame).unwrap();
+        assert_eq!(decoded.version(), 5);
+        assert_eq!(decoded.mac, Some([0x66, 0x55, 0x44, 0x33, 0x22, 0x11]));
+        assert_eq!(
+            decoded.fields(),
+            [
+                ("MiBeacon", "v5 (frame control 0x5050)".to_string()),
+                (
+                    "MiBeacon Product",
+                    "LYWSD03MMC Thermometer (0x055B)".to_string()
+                ),
+                ("MiBeacon Counter", "42".to_string()),
+                ("MiBeacon MAC", "11:22:33:44:55:66".to_string()),
+                ("MiBeacon Temperature", "21.0 °C".to_string()),
+                ("MiBeacon Humidity", "49.9 %".to_string()),
+            ]
+        );
+
+        // A negative temperature and a battery object.
+        let frame = [0x40, 0x50, 0x5B, 0x05, 0x01, 0x04, 0x10, 0x02, 0x9C, 0xFF];
+        assert_eq!(
+            decode_mibeacon(&frame).unwrap().fields()[3],
+            ("MiBeacon Temperature", "-10.0 °C".to_string())
+        );
+        let frame = [0x40, 0x50, 0x5B, 0x05, 0x01, 0x0A, 0x10, 0x01, 0x5D];
+        assert_eq!(
+            decode_mibeacon(&frame).unwrap().fields()[3],
+            ("MiBeacon Battery", "93 %".to_string())
+        );
+    }
+
+    #[test]
+    fn encrypted_frame_is_labeled() {
+        let frame = [
+            0x58, 0x58, 0x5B, 0x05, 0x07, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11, 0xAB, 0xCD, 0xEF,
+        ];
+        let decoded = decode_mibeacon(&frame).unwrap();
+        assert_eq!(
+            decoded.payload,
+            MiBeaconPayload::Encrypted(vec![0xAB, 0xCD, 0xEF])
+        );
+        assert_eq!(
+            decoded.fields().last().unwrap(),
+            &("MiBeacon Payload", "<encrypted> AB CD EF".to_string())
+        );
+    }
+
+    #[test]
+    fn frames_without_objects_and_unknown_objects() {
+        let decoded = decode_mibeacon(&[0x20, 0x20, 0x34, 0x12, 0x00, 0x09]).unwrap();
+        assert_eq!(decoded.payload, MiBeaconPayload::None);
+        assert_eq!(decoded.fields()[1].1, "0x1234");
+
+        let frame = [0x40, 0x50, 0x5B, 0x05, 0x01, 0x99, 0x99, 0x02, 0x01, 0x02];
+        assert_eq!(
+            decode_mibeacon(&frame).unwrap().fields()[3],
+            ("MiBeacon Object", "0x9999: 01 02".to_string())
+        );
+    }
+
+    #[test]
+    fn truncated_frames_are_errors() {
+        assert_eq!(
+            decode_mibeacon(&[0x50, 0x50, 0x5B]),
+            Err("expected at least 5 bytes, got 3".to_string())
+        );
+        for frame in [
+            &[0x50, 0x50, 0x5B, 0x05, 0x01, 0x66, 0x55][..],
+            &[0x40, 0x50, 0x5B, 0x05, 0x01, 0x0D, 0x10][..],
+            &[0x40, 0x50, 0x5B, 0x05, 0x01, 0x0D, 0x10, 0x04, 0xD2][..],
+        ] {
+            assert_eq!(
+                decode_mibeacon(frame),
+                Err("truncated frame".to_string()),
+                "{:02X?}",
+                frame
+            );
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use crate::{
+    structs::{DecodedField, DeviceInfo},
+    utils::bytes_to_hex,
+};
+
+use super::Decoder;
+
+/// The Bluetooth SIG company identifier assigned to Microsoft.
+pub const MICROSOFT_COMPANY_ID: u16 = 0x0006;
+
+/// A decoded Microsoft beacon.
+#[derive(Debug, PartialEq)]
+pub enum MicrosoftBeacon {
+    /// A Connected Devices Platform "Nearby" beacon.
+    Cdp {
+        device_type: u8,
+        salt: [u8; 4],
+        device_hash: Vec<u8>,
+    },
+    /// A Swift Pair beacon from an accessory in pairing mode.
+    SwiftPair {
+        sub_scenario: u8,
+        display_name: Option<String>,
+    },
+}
+
+impl MicrosoftBeacon {
+    /// Returns a label for the beacon type.
+    pub fn label(&self) -> &'static str {
+        match self {
+            MicrosoftBeacon::Cdp { .. } => "CDP",
+            MicrosoftBeacon::SwiftPair { .. } => "Swift Pair",
+        }
+    }
+
+    /// Returns the name of the device type advertised by a CDP beacon.
+    pub fn device_type_name(&self) -> Option<&'static str> {
+        let MicrosoftBeacon::Cdp { device_type, .. } = self else {
+            return None;
+        };
+        Some(match device_type {
+            1 => "Xbox One",
+            6 => "Apple iPhone",
+            7 => "Apple iPad",
+            8 => "Android device",
+            9 => "Windows 10 Desktop",
+            11 => "Windows 10 Phone",
+            12 => "Linux device",
+            13 => "Windows IoT",
+            14 => "Surface Hub",
+            15 => "Windows laptop",
+            16 => "Windows tablet",
+            _ => "Unknown",
+        })
+    }
+
+    /// Returns the coarse device category implied by the beacon, e.g. `phone` or `laptop`.
+    pub fn category(&self) -> Option<&'static str> {
+        match self {
+            MicrosoftBeacon::Cdp { device_type, .. } => match device_type {
+                1 => Some("console"),
+                6 | 8 | 11 => Some("phone"),
+                7 | 16 => Some("tablet"),
+                9 | 12 | 14 => Some("desktop"),
+                15 => Some("laptop"),
+                _ => None,
+            },
+            MicrosoftBeacon::SwiftPair { .. } => Some("peripheral"),
+        }
+    }
+
+    /// Returns the decoded values of the beacon as label/value pairs.
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        match self {
+            MicrosoftBeacon::Cdp {
+                device_type,
+                salt,
+                device_hash,
+            } => vec![
+                (
+                    "CDP Device Type",
+                    format!(
+                        "{} ({})",
+                        self.device_type_name().unwrap_or("Unknown"),
+                        device_type
+                    ),
+                ),
+                ("CDP Salt", bytes_to_hex(salt).replace(' ', "")),
+                (
+                    "CDP Device Hash",
+                    bytes_to_hex(device_hash).replace(' ', ""),
+                ),
+            ],
+            MicrosoftBeacon::SwiftPair {
+                sub_scenario,
+                display_name,
+            } => vec![
+                (
+                    "Swift Pair Mode",
+                    match sub_scenario {
+                        0x00 => "LE only".to_string(),
+                        0x01 => "LE and BR/EDR".to_string(),
+                        0x02 => "BR/EDR only".to_string(),
+                        other => format!("unknown (0x{:02X})", other),
+                    },
+                ),
+                (
+                    "Swift Pair Name",
+                    display_name.clone().unwrap_or_else(|| "n/a".to_string()),
+                ),
+            ],
+        }
+    }
+}
+
+/// Decodes a Microsoft beacon from the payload of 0x0006 manufacturer data.
+/// Returns `None` if the scenario type is not one of the known beacons.
+pub fn decode_microsoft(data: &[u8]) -> Option<Result<MicrosoftBeacon, String>> {
+    match data.first()? {
+        0x01 => {
+            if data.len() < 8 {
+                return Some(Err(format!(
+                    "truncated CDP beacon ({} of 8 bytes)",
+                    data.len()
+                )));
+            }
+            let mut salt = [0; 4];
+            salt.copy_from_slice(&data[4..8]);
+            Some(Ok(MicrosoftBeacon::Cdp {
+                device_type: data[1] & 0x1F,
+                salt,
+                device_hash: data[8..].to_vec(),
+            }))
+        }
+        0x03 => {
+            if data.len() < 3 {
+                return Some(Err(format!(
+                    "truncated Swift Pair beacon ({} of 3 bytes)",
+                    data.len()
+                )));
+            }
+            // The sub scenario decides what precedes the display name: nothing for LE only,
+            // a BR/EDR address and class of device for dual mode, only the class for BR/EDR.
+            let name_offset = match data[1] {
+                0x01 => 12,
+                0x02 => 6,
+                _ => 3,
+            };
+            let display_name = data
+                .get(name_offset..)
+                .filter(|name| !name.is_empty())
+                .map(|name| String::from_utf8_lossy(name).to_string());
+            Some(Ok(MicrosoftBeacon::SwiftPair {
+                sub_scenario: data[1],
+                display_name,
+            }))
+        }
+        _ => None,
+    }
+}
+
+/// Decodes Microsoft CDP and Swift Pair beacons.
+pub struct MicrosoftDecoder;
+
+impl Decoder for MicrosoftDecoder {
+    fn name(&self) -> &'static str {
+        "microsoft"
+    }
+
+    fn matches(&self, device: &DeviceInfo) -> bool {
+        device.manufacturer_data.contains_key(&MICROSOFT_COMPANY_ID)
+    }
+
+    fn decode(&self, device: &DeviceInfo) -> Vec<DecodedField> {
+        let data = device.manufacturer_data.get(&MICROSOFT_COMPANY_ID);
+        match data.and_then(|data| decode_microsoft(data)) {
+            Some(Ok(beacon)) => {
+                let mut fields = vec![DecodedField::new("Microsoft Beacon", beacon.label())];
+                if let Some(category) = beacon.category() {
+                    fields.push(DecodedField::new("Category", category));
+                }
+                for (label, value) in beacon.fields() {
+                    fields.push(DecodedField::new(label, value));
+                }
+                fields
+            }
+            Some(Err(e)) => vec![DecodedField::new(
+                "Microsoft Beacon",
+                format!("<malformed: {}>", e),
+            )],
+            None => Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+
+use crate::structs::{DecodedField, DeviceInfo};
+
+use self::{
+    altbeacon::{decode_altbeacon, AltBeaconDecoder},
+    bthome::BtHomeDecoder,
+    continuity::ContinuityDecoder,
+    eddystone::EddystoneDecoder,
+    exposure::{ExposureNotificationDecoder, EXPOSURE_NOTIFICATION_UUID},
+    fast_pair::{FastPairDecoder, FAST_PAIR_UUID},
+    govee::GoveeDecoder,
+    ibeacon::{decode_ibeacon, IBeaconDecoder},
+    mesh::{is_mesh, MeshDecoder},
+    mibeacon::MiBeaconDecoder,
+    microsoft::{decode_microsoft, MicrosoftDecoder, MICROSOFT_COMPANY_ID},
+    ruuvi::RuuviDecoder,
+    trackers::{detect_tracker, TrackerDecoder},
+    victron::VictronDecoder,
+};
+
+pub mod altbeacon;
+pub mod bthome;
+pub mod continuity;
+pub mod eddystone;
+pub mod exposure;
+pub mod fast_pair;
+pub mod govee;
+pub mod ibeacon;
+pub mod mesh;
+pub mod mibeacon;
+pub mod microsoft;
+pub mod ruuvi;
+pub mod trackers;
+pub mod victron;
+
+/// The Bluetooth SIG company identifier assigned to Apple, Inc.
+pub const APPLE_COMPANY_ID: u16 = 0x004C;
+
+/// The tags that decoders can attach to a device, in the order the tag filter cycles them.
+pub const TAGS: [&str; 6] = ["Tracker", "EN", "CDP", "Swift Pair", "Fast Pair", "BT Mesh"];
+
+/// An advertisement decoder. Decoders are run by a [`DecoderRegistry`], and their labeled
+/// output is shown in the detail pane and included in exports.
+pub trait Decoder: Send + Sync {
+    /// Returns the name used to enable or disable the decoder, e.g. `ibeacon`.
+    fn name(&self) -> &'static str;
+
+    /// Returns `true` if the device carries data this decoder understands.
+    fn matches(&self, device: &DeviceInfo) -> bool;
+
+    /// Decodes the device's advertisement into labeled fields.
+    fn decode(&self, device: &DeviceInfo) -> Vec<DecodedField>;
+
+    /// Returns `true` if the decoded fields are sensor readings, such as a temperature,
+    /// rather than a description of the device.
+    fn reports_measurements(&self) -> bool {
+        false
+    }
+}
+
+/// An ordered set of decoders, each of which can be enabled or disabled.
+#[derive(Default)]
+pub struct DecoderRegistry {
+    decoders: Vec<(Box<dyn Decoder>, bool)>,
+}
+
+impl DecoderRegistry {
+    /// Creates a registry with every built-in decoder enabled. `victron_keys` holds the
+    /// advertisement keys the Victron decoder decrypts readouts with.
+    pub fn with_builtins(victron_keys: HashMap<String, [u8; 16]>) -> Self {
+        let mut registry = Self::default();
+        registry.register(Box::new(IBeaconDecoder));
+        registry.register(Box::new(ContinuityDecoder));
+        registry.register(Box::new(AltBeaconDecoder));
+        registry.register(Box::new(EddystoneDecoder));
+        registry.register(Box::new(ExposureNotificationDecoder));
+        registry.register(Box::new(MicrosoftDecoder));
+        registry.register(Box::new(RuuviDecoder));
+        registry.register(Box::new(GoveeDecoder));
+        registry.register(Box::new(FastPairDecoder));
+        registry.register(Box::new(BtHomeDecoder));
+        registry.register(Box::new(MiBeaconDecoder));
+        registry.register(Box::new(MeshDecoder));
+        registry.register(Box::new(TrackerDecoder));
+        registry.register(Box::new(VictronDecoder::new(victron_keys)));
+        registry
+    }
+
+    /// Adds an enabled decoder after the ones already registered.
+    pub fn register(&mut self, decoder: Box<dyn Decoder>) {
+        self.decoders.push((decoder, true));
+    }
+
+    /// Enables or disables the decoder with the given name. Returns an error if no
+    /// decoder has that name.
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> Result<(), String> {
+        let names = self.names();
+        let (_, state) = self
+            .decoders
+            .iter_mut()
+            .find(|(decoder, _)| decoder.name() == name)
+            .ok_or_else(|| {
+                format!(
+                    "unknown decoder '{}', expected one of: {}",
+                    name,
+                    names.join(", ")
+                )
+            })?;
+        *state = enabled;
+        Ok(())
+    }
+
+    /// Returns the names of the registered decoders.
+    pub fn names(&self) -> Vec<&'static str> {
+        self.decoders
+            .iter()
+            .map(|(decoder, _)| decoder.name())
+            .collect()
+    }
+
+    /// Runs the enabled decoders that match the device and returns their labeled results.
+    pub fn decode(&self, device: &DeviceInfo) -> Vec<DecodedField> {
+        self.decoders
+            .iter()
+            .filter(|(decoder, enabled)| *enabled && decoder.matches(device))
+            .flat_map(|(decoder, _)| decoder.decode(device))
+            .collect()
+    }
+
+    /// Runs the decoder with the given name, if it is enabled and matches the device.
+    pub fn decode_with(&self, name: &str, device: &DeviceInfo) -> Vec<DecodedField> {
+        self.decoders
+            .iter()
+            .filter(|(decoder, enabled)| {
+                *enabled && decoder.name() == name && decoder.matches(device)
+            })
+            .flat_map(|(decoder, _)| decoder.decode(device))
+            .collect()
+    }
+
+    /// Like [`decode`](Self::decode), but only runs the decoders that report sensor
+    /// readings.
+    pub fn decode_measurements(&self, device: &DeviceInfo) -> Vec<DecodedField> {
+        self.decoders
+            .iter()
+            .filter(|(decoder, enabled)| {
+                *enabled && decoder.reports_measurements() && decoder.matches(device)
+            })
+            .flat_map(|(decoder, _)| decoder.decode(device))
+            .collect()
+    }
+}
+
+/// Returns the calibrated RSSI at one meter advertised by a beacon frame, if any.
+/// AltBeacon's reference RSSI is preferred, followed by the iBeacon measured power.
+pub fn reference_rssi(device: &DeviceInfo) -> Option<i8> {
+    let mut companies: Vec<_> = device.manufacturer_data.iter().collect();
+    companies.sort_by_key(|(code, _)| **code);
+    let altbeacon = companies
+        .iter()
+        .find_map(|(_, data)| decode_altbeacon(data).and_then(Result::ok))
+        .map(|beacon| beacon.reference_rssi);
+    altbeacon.or_else(|| {
+        device
+            .manufacturer_data
+            .get(&APPLE_COMPANY_ID)
+            .and_then(|data| decode_ibeacon(data))
+            .and_then(Result::ok)
+            .map(|beacon| beacon.measured_power)
+    })
+}
+
+/// Returns the tags that decoders attach to a device, e.g. `Tracker` or `EN`.
+pub fn device_tags(device: &DeviceInfo) -> Vec<&'static str> {
+    let mut tags = Vec::new();
+    if detect_tracker(device).is_some() {
+        tags.push("Tracker");
+    }
+    if device
+        .service_data
+        .contains_key(&EXPOSURE_NOTIFICATION_UUID)
+    {
+        tags.push("EN");
+    }
+    if let Some(Ok(beacon)) = device
+        .manufacturer_data
+        .get(&MICROSOFT_COMPANY_ID)
+        .and_then(|data| decode_microsoft(data))
+    {
+        tags.push(beacon.label());
+    }
+    if device.service_data.contains_key(&FAST_PAIR_UUID) {
+        tags.push("Fast Pair");
+    }
+    if is_mesh(device) {
+        tags.push("BT Mesh");
+    }
+    tags
+}
@@ -0,0 +1,217 @@
+use crate::{
+    structs::{DecodedField, DeviceInfo},
+    utils::bytes_to_hex,
+};
+
+use super::Decoder;
+
+/// The Bluetooth SIG company identifier assigned to Ruuvi Innovations Ltd.
+pub const RUUVI_COMPANY_ID: u16 = 0x0499;
+
+/// The data format byte of the RAWv2 format.
+const RAWV2_FORMAT: u8 = 0x05;
+
+/// A decoded RuuviTag RAWv2 (data format 5) payload. Values the tag reports as
+/// invalid or unavailable are `None`.
+#[derive(Debug, PartialEq)]
+pub struct RuuviRawV2 {
+    /// Temperature in degrees Celsius.
+    pub temperature: Option<f64>,
+    /// Relative humidity in percent.
+    pub humidity: Option<f64>,
+    /// Atmospheric pressure in pascals.
+    pub pressure: Option<u32>,
+    /// Acceleration along the X, Y and Z axes in milli-g.
+    pub acceleration: [Option<i16>; 3],
+    /// Battery voltage in millivolts.
+    pub battery_voltage: Option<u16>,
+    /// TX power in dBm.
+    pub tx_power: Option<i8>,
+    pub movement_counter: Option<u8>,
+    pub sequence_number: Option<u16>,
+    pub mac: [u8; 6],
+}
+
+impl RuuviRawV2 {
+    /// Returns the decoded values of the payload as label/value pairs.
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        fn show<T>(value: Option<T>, format: impl Fn(T) -> String) -> String {
+            value.map_or_else(|| "n/a".to_string(), format)
+        }
+        let [x, y, z] = self
+            .acceleration
+            .map(|axis| show(axis, |g| format!("{:.3}", f64::from(g) / 1000.0)));
+        vec![
+            (
+                "Ruuvi Temperature",
+                show(self.temperature, |t| format!("{:.3} °C", t)),
+            ),
+            (
+                "Ruuvi Humidity",
+                show(self.humidity, |h| format!("{:.4} %", h)),
+            ),
+            (
+                "Ruuvi Pressure",
+                show(self.pressure, |p| {
+                    format!("{:.2} hPa", f64::from(p) / 100.0)
+                }),
+            ),
+            ("Ruuvi Acceleration", format!("X {} Y {} Z {} g", x, y, z)),
+            (
+                "Ruuvi Battery",
+                show(self.battery_voltage, |mv| {
+                    format!("{:.3} V", f64::from(mv) / 1000.0)
+                }),
+            ),
+            (
+                "Ruuvi TX Power",
+                show(self.tx_power, |tx| format!("{} dBm", tx)),
+            ),
+            (
+                "Ruuvi Movements",
+                show(self.movement_counter, |count| count.to_string()),
+            ),
+            (
+                "Ruuvi Sequence",
+                show(self.sequence_number, |seq| seq.to_string()),
+            ),
+            ("Ruuvi MAC", bytes_to_hex(&self.mac).replace(' ', ":")),
+        ]
+    }
+}
+
+/// Decodes the manufacturer data of a RuuviTag. Returns `None` if the payload is not in
+/// the RAWv2 format, and an error if it is truncated.
+pub fn decode_ruuvi(data: &[u8]) -> Option<Result<RuuviRawV2, String>> {
+    if data.first() != Some(&RAWV2_FORMAT) {
+        return None;
+    }
+    if data.len() != 24 {
+        return Some(Err(format!("expected 24 bytes, got {}", data.len())));
+    }
+    let u16_at = |i: usize| u16::from_be_bytes([data[i], data[i + 1]]);
+    let i16_at = |i: usize| i16::from_be_bytes([data[i], data[i + 1]]);
+    let signed = |value: i16| (value != i16::MIN).then_some(value);
+    let unsigned = |value: u16| (value != u16::MAX).then_some(value);
+
+    // The power info packs the battery voltage into the top 11 bits and the TX power into
+    // the bottom 5 bits; each uses its all-ones value to mark an invalid reading.
+    let power_info = u16_at(13);
+    let battery = power_info >> 5;
+    let tx_power = power_info & 0x1F;
+    let mut mac = [0; 6];
+    mac.copy_from_slice(&data[18..24]);
+
+    Some(Ok(RuuviRawV2 {
+        temperature: signed(i16_at(1)).map(|t| f64::from(t) * 0.005),
+        humidity: unsigned(u16_at(3)).map(|h| f64::from(h) * 0.0025),
+        pressure: unsigned(u16_at(5)).map(|p| u32::from(p) + 50_000),
+        acceleration: [signed(i16_at(7)), signed(i16_at(9)), signed(i16_at(11))],
+        battery_voltage: (battery != 0x7FF).then_some(battery + 1600),
+        tx_power: (tx_power != 0x1F).then_some(tx_power as i8 * 2 - 40),
+        movement_counter: (data[15] != u8::MAX).then_some(data[15]),
+        sequence_number: unsigned(u16_at(16)),
+        mac,
+    }))
+}
+
+/// Decodes RuuviTag RAWv2 manufacturer data.
+pub struct RuuviDecoder;
+
+impl Decoder for RuuviDecoder {
+    fn name(&self) -> &'static str {
+        "ruuvi"
+    }
+
+    fn matches(&self, device: &DeviceInfo) -> bool {
+        device.manufacturer_data.contains_key(&RUUVI_COMPANY_ID)
+    }
+
+    fn decode(&self, device: &DeviceInfo) -> Vec<DecodedField> {
+        match device
+            .manufacturer_data
+            .get(&RUUVI_COMPANY_ID)
+            .and_then(|data| decode_ruuvi(data))
+        {
+            Some(Ok(decoded)) => decoded
+                .fields()
+                .into_iter()
+                .map(|(label, value)| DecodedField::new(label, value))
+                .collect(),
+            Some(Err(e)) => vec![DecodedField::new("Ruuvi", format!("<malformed: {}>", e))],
+            None => Vec::new(),
+        }
+    }
+
+    fn reports_measurements(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::hex_to_bytes;
+
+    #[test]
+    fn reference_vectors_decode() {
+        // The "valid data" test vector of the RAWv2 format specification.
+        let payload = hex_to_bytes("0512FC5394C37C0004FFFC040CAC364200CDCBB8334C884F").unwrap();
+        let decoded = decode_ruuvi(&payload).unwrap().unwrap();
+        assert!((decoded.temperature.unwrap() - 24.3).abs() < 1e-9);
+        assert!((decoded.humidity.unwrap() - 53.49).abs() < 1e-9);
+        assert_eq!(decoded.pressure, Some(100_044));
+        assert_eq!(decoded.acceleration, [Some(4), Some(-4), Some(1036)]);
+        assert_eq!(decoded.battery_voltage, Some(2977));
+        assert_eq!(decoded.tx_power, Some(4));
+        assert_eq!(decoded.movement_counter, Some(66));
+        assert_eq!(decoded.sequence_number, Some(205));
+        assert_eq!(
+            decoded.fields().last().unwrap().1,
+            "CB:B8:33:4C:88:4F".to_string()
+        );
+
+        // The "maximum values" test vector.
+        let payload = hex_to_bytes("057FFFFFFEFFFE7FFF7FFF7FFFFFDEFEFFFECBB8334C884F").unwrap();
+        let decoded = decode_ruuvi(&payload).unwrap().unwrap();
+        assert!((decoded.temperature.unwrap() - 163.835).abs() < 1e-9);
+        assert_eq!(decoded.pressure, Some(115_534));
+        assert_eq!(decoded.acceleration, [Some(32767); 3]);
+        assert_eq!(decoded.battery_voltage, Some(3646));
+        assert_eq!(decoded.tx_power, Some(20));
+        assert_eq!(decoded.movement_counter, Some(254));
+        assert_eq!(decoded.sequence_number, Some(65534));
+    }
+
+    #[test]
+    fn invalid_values_are_none() {
+        // The "invalid values" test vector: every reading is marked unavailable.
+        let payload = hex_to_bytes("058000FFFFFFFF800080008000FFFFFFFFFFFFFFFFFFFFFF").unwrap();
+        let decoded = decode_ruuvi(&payload).unwrap().unwrap();
+        assert_eq!(
+            decoded,
+            RuuviRawV2 {
+                temperature: None,
+                humidity: None,
+                pressure: None,
+                acceleration: [None; 3],
+                battery_voltage: None,
+                tx_power: None,
+                movement_counter: None,
+                sequence_number: None,
+                mac: [0xFF; 6],
+            }
+        );
+        assert_eq!(decoded.fields()[0].1, "n/a");
+    }
+
+    #[test]
+    fn other_formats_and_truncated_payloads() {
+        assert!(decode_ruuvi(&[0x03, 0x29, 0x1A]).is_none());
+        assert!(decode_ruuvi(&[]).is_none());
+        assert_eq!(
+            decode_ruuvi(&[0x05, 0x12, 0xFC]).unwrap(),
+            Err("expected 24 bytes, got 3".to_string())
+        );
+    }
+}
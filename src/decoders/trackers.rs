@@ -0,0 +1,176 @@
+use btleplug::api::bleuuid::uuid_from_u16;
+use uuid::Uuid;
+
+use crate::{
+    structs::{DecodedField, DeviceInfo},
+    utils::bytes_to_hex,
+};
+
+use super::{continuity::parse_continuity, Decoder, APPLE_COMPANY_ID};
+
+/// The 16-bit service UUIDs Tile trackers advertise under. 0xFEED is used by older and
+/// unactivated Tiles, 0xFEEC by activated ones.
+pub const TILE_UUIDS: [Uuid; 2] = [uuid_from_u16(0xFEED), uuid_from_u16(0xFEEC)];
+
+/// The 16-bit service UUID of Samsung's offline finding network, used by SmartTags.
+pub const SMARTTAG_UUID: Uuid = uuid_from_u16(0xFD5A);
+
+/// The kind of item tracker a device was recognized as.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TrackerKind {
+    FindMy,
+    Tile,
+    SmartTag,
+}
+
+/// A struct to hold the result of recognizing a device as an item tracker.
+#[derive(Debug, PartialEq)]
+pub struct Tracker {
+    pub kind: TrackerKind,
+    pub status: String,
+    /// A label and value identifying the tracker, when its format exposes one.
+    pub identifier: Option<(&'static str, String)>,
+}
+
+impl Tracker {
+    /// Returns a label for the kind of tracker.
+    pub fn label(&self) -> &'static str {
+        match self.kind {
+            TrackerKind::FindMy => "Find My tracker",
+            TrackerKind::Tile => "Tile tracker",
+            TrackerKind::SmartTag => "Samsung SmartTag",
+        }
+    }
+
+    /// Returns a note about how far the tracker can be followed across sightings.
+    pub fn note(&self) -> &'static str {
+        match self.kind {
+            TrackerKind::FindMy => {
+                "Address rotates; repeated sightings may be the same physical tag"
+            }
+            TrackerKind::Tile => "The Tile ID stays the same across sightings",
+            TrackerKind::SmartTag => "Privacy ID rotates every 15 minutes along with the address",
+        }
+    }
+}
+
+/// Recognizes item trackers from their advertisements.
+pub fn detect_tracker(device: &DeviceInfo) -> Option<Tracker> {
+    detect_find_my(device)
+        .or_else(|| detect_tile(device))
+        .or_else(|| detect_smarttag(device))
+}
+
+/// Recognizes Tile trackers from their service UUID or service data.
+fn detect_tile(device: &DeviceInfo) -> Option<Tracker> {
+    let [legacy, activated] = TILE_UUIDS;
+    let data = device
+        .service_data
+        .get(&legacy)
+        .or_else(|| device.service_data.get(&activated));
+    if data.is_none() && !TILE_UUIDS.iter().any(|uuid| device.services.contains(uuid)) {
+        return None;
+    }
+    // Only the legacy service data carries the Tile ID; activated Tiles rotate their payload.
+    let identifier = device
+        .service_data
+        .get(&legacy)
+        .filter(|data| data.len() >= 8)
+        .map(|data| {
+            (
+                "Tile ID",
+                bytes_to_hex(&data[data.len() - 8..]).replace(' ', ""),
+            )
+        });
+    Some(Tracker {
+        kind: TrackerKind::Tile,
+        status: if device.service_data.contains_key(&activated) {
+            "activated".to_string()
+        } else {
+            "unactivated or legacy".to_string()
+        },
+        identifier,
+    })
+}
+
+/// Recognizes Samsung SmartTags from their offline finding service data.
+fn detect_smarttag(device: &DeviceInfo) -> Option<Tracker> {
+    let data = device.service_data.get(&SMARTTAG_UUID)?;
+    Some(Tracker {
+        kind: TrackerKind::SmartTag,
+        status: format!("offline finding v{}", data.first()? >> 4),
+        identifier: data
+            .get(4..12)
+            .map(|id| ("SmartTag Privacy ID", bytes_to_hex(id).replace(' ', ""))),
+    })
+}
+
+/// Recognizes AirTags and other Find My accessories from their Continuity messages.
+fn detect_find_my(device: &DeviceInfo) -> Option<Tracker> {
+    let data = device.manufacturer_data.get(&APPLE_COMPANY_ID)?;
+    let messages = parse_continuity(data).ok()?;
+    messages.iter().find_map(|message| {
+        let p = &message.payload;
+        match (message.msg_type, p.len()) {
+            // Offline finding: the owner's device is out of range and the full key is advertised.
+            (0x12, 25) => Some(Tracker {
+                kind: TrackerKind::FindMy,
+                status: format!(
+                    "registered, separated from owner (battery {})",
+                    find_my_battery(p[0])
+                ),
+                identifier: None,
+            }),
+            // The short variant is sent while the owner's device is nearby.
+            (0x12, 2) => Some(Tracker {
+                kind: TrackerKind::FindMy,
+                status: format!("registered, near owner (battery {})", find_my_battery(p[0])),
+                identifier: None,
+            }),
+            // An AirTag that has not been set up announces itself with a pairing message.
+            (0x07, 25) if p[1..3] == [0x00, 0x55] => Some(Tracker {
+                kind: TrackerKind::FindMy,
+                status: "unregistered".to_string(),
+                identifier: None,
+            }),
+            _ => None,
+        }
+    })
+}
+
+fn find_my_battery(status: u8) -> &'static str {
+    match status >> 6 {
+        0 => "full",
+        1 => "medium",
+        2 => "low",
+        _ => "critical",
+    }
+}
+
+/// Labels devices recognized as item trackers.
+pub struct TrackerDecoder;
+
+impl Decoder for TrackerDecoder {
+    fn name(&self) -> &'static str {
+        "trackers"
+    }
+
+    fn matches(&self, device: &DeviceInfo) -> bool {
+        detect_tracker(device).is_some()
+    }
+
+    fn decode(&self, device: &DeviceInfo) -> Vec<DecodedField> {
+        let Some(tracker) = detect_tracker(device) else {
+            return Vec::new();
+        };
+        let mut fields = vec![
+            DecodedField::new("Tracker", tracker.label()),
+            DecodedField::new("Tracker Status", tracker.status.clone()),
+        ];
+        if let Some((label, value)) = &tracker.identifier {
+            fields.push(DecodedField::new(*label, value.clone()));
+        }
+        fields.push(DecodedField::new("Tracker Note", tracker.note()));
+        fields
+    }
+}
@@ -0,0 +1,306 @@
+use std::collections::HashMap;
+
+#[cfg(feature = "victron")]
+use aes::{
+    cipher::{KeyIvInit, StreamCipher},
+    Aes128,
+};
+
+use crate::{
+    structs::{DecodedField, DeviceInfo},
+    utils::bytes_to_hex,
+};
+
+use super::Decoder;
+
+/// The Bluetooth SIG company identifier assigned to Victron Energy BV.
+pub const VICTRON_COMPANY_ID: u16 = 0x02E1;
+
+/// The prefix of an Instant Readout record, which distinguishes it from Victron's other
+/// manufacturer data.
+const INSTANT_READOUT_PREFIX: u8 = 0x10;
+
+/// The length of the unencrypted header: prefix, model id, record type, nonce, and the
+/// first byte of the key.
+const HEADER_LEN: usize = 8;
+
+#[cfg(feature = "victron")]
+type Aes128Ctr = ctr::Ctr128LE<Aes128>;
+
+/// The unencrypted header of a Victron Instant Readout record.
+#[derive(Debug, PartialEq)]
+pub struct VictronHeader {
+    pub model_id: u16,
+    pub record_type: u8,
+    /// The counter the payload is encrypted with.
+    pub nonce: u16,
+    /// The first byte of the advertisement key, to check a configured key against.
+    pub key_check: u8,
+}
+
+impl VictronHeader {
+    /// Returns the name of the kind of product the record type describes.
+    pub fn record_type_name(&self) -> &'static str {
+        match self.record_type {
+            0x01 => "Solar Charger",
+            0x02 => "Battery Monitor",
+            0x03 => "Inverter",
+            0x04 => "DC/DC Converter",
+            0x05 => "Smart Lithium",
+            0x06 => "Inverter RS",
+            0x07 => "GX Device",
+            0x08 => "AC Charger",
+            0x09 => "Smart Battery Protect",
+            0x0A => "Lynx Smart BMS",
+            0x0B => "Multi RS",
+            0x0C => "VE.Bus",
+            0x0D => "DC Energy Meter",
+            0x0F => "Orion XS",
+            _ => "Unknown",
+        }
+    }
+}
+
+/// Reads little-endian bit fields from the start of a decrypted payload, least significant
+/// bit first, the way Victron packs its readouts.
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl BitReader<'_> {
+    fn unsigned(&mut self, bits: usize) -> u32 {
+        let mut value = 0;
+        for i in 0..bits {
+            let bit = self
+                .data
+                .get(self.position / 8)
+                .map_or(0, |byte| (byte >> (self.position % 8)) & 1);
+            value |= u32::from(bit) << i;
+            self.position += 1;
+        }
+        value
+    }
+
+    fn signed(&mut self, bits: usize) -> i32 {
+        let value = self.unsigned(bits);
+        let shift = 32 - bits;
+        ((value << shift) as i32) >> shift
+    }
+}
+
+/// Decodes the header of a Victron Instant Readout record. Returns `None` if the
+/// manufacturer data is not an Instant Readout record, and an error if it is truncated.
+pub fn decode_victron_header(data: &[u8]) -> Option<Result<VictronHeader, String>> {
+    if data.first() != Some(&INSTANT_READOUT_PREFIX) {
+        return None;
+    }
+    if data.len() < HEADER_LEN {
+        return Some(Err(format!(
+            "expected at least {} bytes, got {}",
+            HEADER_LEN,
+            data.len()
+        )));
+    }
+    Some(Ok(VictronHeader {
+        model_id: u16::from_le_bytes([data[2], data[3]]),
+        record_type: data[4],
+        nonce: u16::from_le_bytes([data[5], data[6]]),
+        key_check: data[7],
+    }))
+}
+
+/// Decrypts the payload of an Instant Readout record with the device's advertisement key.
+/// Returns an error if the key does not match the one the record was encrypted with.
+#[cfg(feature = "victron")]
+pub fn decrypt_victron(data: &[u8], key: &[u8; 16]) -> Result<Vec<u8>, String> {
+    let header = decode_victron_header(data).ok_or("not an Instant Readout record")??;
+    if header.key_check != key[0] {
+        return Err(format!(
+            "key does not match (record expects a key starting with {:02X})",
+            header.key_check
+        ));
+    }
+    let mut iv = [0; 16];
+    iv[..2].copy_from_slice(&header.nonce.to_le_bytes());
+    let mut payload = data[HEADER_LEN..].to_vec();
+    Aes128Ctr::new(key.into(), &iv.into()).apply_keystream(&mut payload);
+    Ok(payload)
+}
+
+/// Decrypting needs the `victron` feature; without it, every record is reported as
+/// undecryptable.
+#[cfg(not(feature = "victron"))]
+pub fn decrypt_victron(_data: &[u8], _key: &[u8; 16]) -> Result<Vec<u8>, String> {
+    Err("decryption needs btlescan built with the `victron` feature".to_string())
+}
+
+/// Decodes a decrypted readout into labeled values. Battery monitors and solar chargers are
+/// decoded; other record types are shown as hex.
+pub fn victron_readout_fields(record_type: u8, payload: &[u8]) -> Vec<(&'static str, String)> {
+    let mut bits = BitReader {
+        data: payload,
+        position: 0,
+    };
+    match record_type {
+        0x01 if payload.len() >= 12 => {
+            let state = bits.unsigned(8);
+            let error = bits.unsigned(8);
+            let voltage = bits.signed(16);
+            let current = bits.signed(16);
+            let yield_today = bits.unsigned(16);
+            let pv_power = bits.unsigned(16);
+            vec![
+                ("Victron State", charger_state_name(state).to_string()),
+                ("Victron Charger Error", error.to_string()),
+                (
+                    "Victron Battery Voltage",
+                    format!("{:.2} V", f64::from(voltage) * 0.01),
+                ),
+                (
+                    "Victron Battery Current",
+                    format!("{:.1} A", f64::from(current) * 0.1),
+                ),
+                (
+                    "Victron Yield Today",
+                    format!("{:.2} kWh", f64::from(yield_today) * 0.01),
+                ),
+                ("Victron PV Power", format!("{} W", pv_power)),
+            ]
+        }
+        0x02 if payload.len() >= 15 => {
+            let remaining = bits.unsigned(16);
+            let voltage = bits.signed(16);
+            let alarm = bits.unsigned(16);
+            let _aux = bits.unsigned(16);
+            let _aux_input = bits.unsigned(2);
+            let current = bits.signed(22);
+            let consumed = bits.unsigned(20);
+            let soc = bits.unsigned(10);
+            vec![
+                (
+                    "Victron Battery Voltage",
+                    format!("{:.2} V", f64::from(voltage) * 0.01),
+                ),
+                (
+                    "Victron Battery Current",
+                    format!("{:.3} A", f64::from(current) * 0.001),
+                ),
+                (
+                    "Victron State of Charge",
+                    format!("{:.1} %", f64::from(soc) * 0.1),
+                ),
+                (
+                    "Victron Consumed",
+                    format!("{:.1} Ah", -f64::from(consumed) * 0.1),
+                ),
+                (
+                    "Victron Time Remaining",
+                    if remaining == 0xFFFF {
+                        "n/a".to_string()
+                    } else {
+                        format!("{}h {:02}m", remaining / 60, remaining % 60)
+                    },
+                ),
+                ("Victron Alarm", format!("0x{:04X}", alarm)),
+            ]
+        }
+        _ => vec![("Victron Readout", bytes_to_hex(payload))],
+    }
+}
+
+/// Returns the name of a solar charger's operation state.
+fn charger_state_name(state: u32) -> &'static str {
+    match state {
+        0 => "Off",
+        1 => "Low Power",
+        2 => "Fault",
+        3 => "Bulk",
+        4 => "Absorption",
+        5 => "Float",
+        6 => "Storage",
+        7 => "Equalize",
+        9 => "Inverting",
+        11 => "Power Supply",
+        245 => "Starting Up",
+        247 => "Auto Equalize",
+        252 => "External Control",
+        _ => "Unknown",
+    }
+}
+
+/// Decodes Victron Instant Readout records. The payload is decrypted when an advertisement
+/// key is configured for the device, keyed by uppercase address or platform id.
+#[derive(Default)]
+pub struct VictronDecoder {
+    keys: HashMap<String, [u8; 16]>,
+}
+
+impl VictronDecoder {
+    /// Creates a decoder with the given advertisement keys.
+    pub fn new(keys: HashMap<String, [u8; 16]>) -> Self {
+        Self { keys }
+    }
+
+    fn key_for(&self, device: &DeviceInfo) -> Option<&[u8; 16]> {
+        self.keys
+            .get(&device.address.to_uppercase())
+            .or_else(|| self.keys.get(&device.id.to_uppercase()))
+    }
+}
+
+impl Decoder for VictronDecoder {
+    fn name(&self) -> &'static str {
+        "victron"
+    }
+
+    fn matches(&self, device: &DeviceInfo) -> bool {
+        device.manufacturer_data.contains_key(&VICTRON_COMPANY_ID)
+    }
+
+    fn decode(&self, device: &DeviceInfo) -> Vec<DecodedField> {
+        let Some(data) = device.manufacturer_data.get(&VICTRON_COMPANY_ID) else {
+            return Vec::new();
+        };
+        let header = match decode_victron_header(data) {
+            Some(Ok(header)) => header,
+            Some(Err(e)) => {
+                return vec![DecodedField::new("Victron", format!("<malformed: {}>", e))]
+            }
+            None => return Vec::new(),
+        };
+        let mut fields = vec![
+            DecodedField::new(
+                "Victron Record",
+                format!(
+                    "{} (0x{:02X})",
+                    header.record_type_name(),
+                    header.record_type
+                ),
+            ),
+            DecodedField::new("Victron Model", format!("0x{:04X}", header.model_id)),
+        ];
+        match self.key_for(device) {
+            Some(key) => match decrypt_victron(data, key) {
+                Ok(payload) => fields.extend(
+                    victron_readout_fields(header.record_type, &payload)
+                        .into_iter()
+                        .map(|(label, value)| DecodedField::new(label, value)),
+                ),
+                Err(e) => fields.push(DecodedField::new(
+                    "Victron Readout",
+                    format!("<malformed: {}>", e),
+                )),
+            },
+            None => fields.push(DecodedField::new(
+                "Victron Readout",
+                "encrypted (key not configured)",
+            )),
+        }
+        fields
+    }
+
+    fn reports_measurements(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,288 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    error::Error,
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cli::DiffArgs, oneshot::format_table, structs::EXPORT_SCHEMA_VERSION, utils::normalize_address,
+};
+
+/// The parts of a JSON export a diff compares.
+#[derive(Deserialize)]
+struct Export {
+    schema_version: Option<u32>,
+    devices: Vec<ExportedDevice>,
+}
+
+#[derive(Deserialize)]
+struct ExportedDevice {
+    id: String,
+    address: String,
+    name: Option<String>,
+    #[serde(default)]
+    services: Vec<String>,
+    #[serde(default)]
+    manufacturer_data: Vec<ExportedManufacturerData>,
+}
+
+#[derive(Deserialize)]
+struct ExportedManufacturerData {
+    company_id: u16,
+    data: String,
+}
+
+impl ExportedDevice {
+    /// The address, or the platform id on platforms that hide addresses.
+    fn identity(&self) -> String {
+        normalize_address(&self.address).unwrap_or_else(|| self.id.clone())
+    }
+
+    fn name(&self) -> String {
+        self.name.clone().unwrap_or_default()
+    }
+}
+
+/// The differences between two exports.
+#[derive(Serialize)]
+struct SessionDiff {
+    only_in_old: Vec<DeviceSummary>,
+    only_in_new: Vec<DeviceSummary>,
+    changed: Vec<ChangedDevice>,
+}
+
+#[derive(Serialize)]
+struct DeviceSummary {
+    address: String,
+    name: Option<String>,
+}
+
+/// A device in both exports whose advertisement changed.
+#[derive(Serialize)]
+struct ChangedDevice {
+    address: String,
+    /// The address in the old export, when the device was paired by name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    old_address: Option<String>,
+    changes: Vec<Change>,
+}
+
+#[derive(Serialize)]
+struct Change {
+    field: String,
+    old: String,
+    new: String,
+}
+
+/// Runs `btlescan diff`: prints the differences between two JSON exports and returns
+/// whether there were any.
+pub fn run(args: &DiffArgs) -> Result<bool, Box<dyn Error>> {
+    let old = read_export(&args.old)?;
+    let new = read_export(&args.new)?;
+    let diff = diff(old.devices, new.devices, args.match_names);
+    let differ =
+        !diff.only_in_old.is_empty() || !diff.only_in_new.is_empty() || !diff.changed.is_empty();
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&diff)?);
+    } else {
+        print_tables(&diff, &args.old, &args.new);
+    }
+    Ok(differ)
+}
+
+/// Reads a JSON export, refusing files that are not one or come from a newer btlescan.
+fn read_export(path: &Path) -> Result<Export, Box<dyn Error>> {
+    let contents =
+        std::fs::read(path).map_err(|e| format!("unable to read {}: {}", path.display(), e))?;
+    let export: Export = serde_json::from_slice(&contents)
+        .map_err(|e| format!("{} is not a btlescan JSON export: {}", path.display(), e))?;
+    match export.schema_version {
+        Some(version) if (1..=EXPORT_SCHEMA_VERSION).contains(&version) => Ok(export),
+        Some(version) => Err(format!(
+            "{} has schema version {}, but this btlescan supports up to {}",
+            path.display(),
+            version,
+            EXPORT_SCHEMA_VERSION
+        )
+        .into()),
+        None => Err(format!("{} is not a btlescan JSON export", path.display()).into()),
+    }
+}
+
+/// Pairs the devices of two exports by address and, with `match_names`, pairs the rest by
+/// name where the name is unique among the unpaired devices of each export.
+fn diff(old: Vec<ExportedDevice>, new: Vec<ExportedDevice>, match_names: bool) -> SessionDiff {
+    let mut old: BTreeMap<String, ExportedDevice> = old
+        .into_iter()
+        .map(|device| (device.identity(), device))
+        .collect();
+    let mut new: BTreeMap<String, ExportedDevice> = new
+        .into_iter()
+        .map(|device| (device.identity(), device))
+        .collect();
+    let mut pairs = Vec::new();
+    let common: Vec<String> = old
+        .keys()
+        .filter(|identity| new.contains_key(*identity))
+        .cloned()
+        .collect();
+    for identity in common {
+        pairs.push((
+            old.remove(&identity).unwrap(),
+            new.remove(&identity).unwrap(),
+        ));
+    }
+    if match_names {
+        let old_names = unique_names(&old);
+        let new_names = unique_names(&new);
+        for (name, old_identity) in old_names {
+            if let Some(new_identity) = new_names.get(&name) {
+                pairs.push((
+                    old.remove(&old_identity).unwrap(),
+                    new.remove(new_identity).unwrap(),
+                ));
+            }
+        }
+    }
+    let summary = |device: ExportedDevice| DeviceSummary {
+        address: device.identity(),
+        name: device.name,
+    };
+    let mut changed: Vec<ChangedDevice> = pairs
+        .into_iter()
+        .filter_map(|(old, new)| {
+            let changes = changes(&old, &new);
+            let (old_identity, identity) = (old.identity(), new.identity());
+            (!changes.is_empty() || old_identity != identity).then(|| ChangedDevice {
+                old_address: (old_identity != identity).then_some(old_identity),
+                address: identity,
+                changes,
+            })
+        })
+        .collect();
+    changed.sort_by(|a, b| a.address.cmp(&b.address));
+    SessionDiff {
+        only_in_old: old.into_values().map(summary).collect(),
+        only_in_new: new.into_values().map(summary).collect(),
+        changed,
+    }
+}
+
+/// Maps each name carried by exactly one device to that device's identity.
+fn unique_names(devices: &BTreeMap<String, ExportedDevice>) -> HashMap<String, String> {
+    let mut names: HashMap<String, Option<String>> = HashMap::new();
+    for (identity, device) in devices {
+        if let Some(name) = device.name.clone().filter(|name| !name.is_empty()) {
+            names
+                .entry(name)
+                .and_modify(|paired| *paired = None)
+                .or_insert_with(|| Some(identity.clone()));
+        }
+    }
+    names
+        .into_iter()
+        .filter_map(|(name, identity)| Some((name, identity?)))
+        .collect()
+}
+
+/// Lists how the name, services and manufacturer data of a device differ.
+fn changes(old: &ExportedDevice, new: &ExportedDevice) -> Vec<Change> {
+    let mut changes = Vec::new();
+    if old.name != new.name {
+        changes.push(Change {
+            field: "name".to_string(),
+            old: old.name(),
+            new: new.name(),
+        });
+    }
+    let old_services: BTreeSet<&String> = old.services.iter().collect();
+    let new_services: BTreeSet<&String> = new.services.iter().collect();
+    if old_services != new_services {
+        let join = |services: BTreeSet<&String>| {
+            services
+                .into_iter()
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        changes.push(Change {
+            field: "services".to_string(),
+            old: join(old_services),
+            new: join(new_services),
+        });
+    }
+    let manufacturer_data = |device: &ExportedDevice| -> BTreeMap<u16, String> {
+        device
+            .manufacturer_data
+            .iter()
+            .map(|entry| (entry.company_id, entry.data.clone()))
+            .collect()
+    };
+    let (old_data, new_data) = (manufacturer_data(old), manufacturer_data(new));
+    for company_id in old_data
+        .keys()
+        .chain(new_data.keys())
+        .collect::<BTreeSet<_>>()
+    {
+        let (old, new) = (old_data.get(company_id), new_data.get(company_id));
+        if old != new {
+            changes.push(Change {
+                field: format!("manufacturer data 0x{:04X}", company_id),
+                old: old.cloned().unwrap_or_default(),
+                new: new.cloned().unwrap_or_default(),
+            });
+        }
+    }
+    changes
+}
+
+/// Prints the differences as plain-text tables, one per kind.
+fn print_tables(diff: &SessionDiff, old: &Path, new: &Path) {
+    for (title, devices) in [
+        (format!("Only in {}", old.display()), &diff.only_in_old),
+        (format!("Only in {}", new.display()), &diff.only_in_new),
+    ] {
+        println!("{} ({}):", title, devices.len());
+        if !devices.is_empty() {
+            let rows: Vec<Vec<String>> = devices
+                .iter()
+                .map(|device| {
+                    vec![
+                        device.address.clone(),
+                        device.name.clone().unwrap_or_default(),
+                    ]
+                })
+                .collect();
+            print!("{}", format_table(&["Address", "Name"], &rows));
+        }
+        println!();
+    }
+    println!("Changed ({}):", diff.changed.len());
+    if !diff.changed.is_empty() {
+        let mut rows = Vec::new();
+        for device in &diff.changed {
+            if let Some(old_address) = &device.old_address {
+                rows.push(vec![
+                    device.address.clone(),
+                    "address".to_string(),
+                    old_address.clone(),
+                    device.address.clone(),
+                ]);
+            }
+            for change in &device.changes {
+                rows.push(vec![
+                    device.address.clone(),
+                    change.field.clone(),
+                    change.old.clone(),
+                    change.new.clone(),
+                ]);
+            }
+        }
+        print!(
+            "{}",
+            format_table(&["Address", "Field", "Old", "New"], &rows)
+        );
+    }
+}
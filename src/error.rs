@@ -0,0 +1,121 @@
+use std::{error::Error, io};
+
+/// The errors that end a scan or the interface. Each maps to its own exit status, so
+/// scripts can tell a missing adapter from a broken terminal.
+#[derive(Debug, thiserror::Error)]
+pub enum BluscanError {
+    /// An option, or a file or address given on the command line, could not be used.
+    #[error("{0}")]
+    Usage(String),
+    /// A subcommand failed. The subcommands document a single error status for every
+    /// failure, so this covers a missing adapter too.
+    #[error("{0}")]
+    Subcommand(String),
+    /// A subcommand's negative outcome: `diff` found differences, or `wait-for` and
+    /// `--db-query` found no device. The outcome has already been printed.
+    #[error("no match")]
+    NoMatch,
+    /// `wait-for` was interrupted with Ctrl+C.
+    #[error("interrupted")]
+    Interrupted,
+    /// The platform reports no Bluetooth adapter.
+    #[error("no Bluetooth adapter found")]
+    NoAdapter,
+    /// The platform's Bluetooth stack could not be reached or listed no adapters.
+    #[error("unable to access the Bluetooth adapter: {0}")]
+    Adapter(#[source] btleplug::Error),
+    /// The adapter refused to start the scan or stopped reporting results.
+    #[error("unable to scan: {0}")]
+    Discovery(#[source] btleplug::Error),
+    /// Drawing to or reading from the terminal failed.
+    #[error("terminal error: {0}")]
+    Terminal(#[from] io::Error),
+    /// The channel the scan sends its results over closed while they were being read.
+    #[error("the scan stopped sending results")]
+    ChannelClosed,
+    /// Writing the results to stdout or a file failed.
+    #[error("unable to write the results: {0}")]
+    Export(#[source] Box<dyn Error + Send + Sync>),
+}
+
+impl BluscanError {
+    /// The exit status `main` ends with for this error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            BluscanError::NoMatch => 1,
+            BluscanError::Usage(_) | BluscanError::Subcommand(_) => 2,
+            BluscanError::Interrupted => 130,
+            BluscanError::NoAdapter | BluscanError::Adapter(_) => 3,
+            BluscanError::Discovery(_) => 4,
+            BluscanError::Terminal(_) => 5,
+            BluscanError::ChannelClosed => 6,
+            BluscanError::Export(_) => 7,
+        }
+    }
+
+    /// Whether the error is an outcome the user has already been shown, which `main` exits
+    /// with without printing a message.
+    pub fn is_outcome(&self) -> bool {
+        matches!(self, BluscanError::NoMatch | BluscanError::Interrupted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exit_codes_follow_the_documented_statuses() {
+        let cases = [
+            (BluscanError::NoMatch, 1),
+            (BluscanError::Usage("bad".to_string()), 2),
+            (BluscanError::Subcommand("bad".to_string()), 2),
+            (BluscanError::NoAdapter, 3),
+            (BluscanError::Adapter(btleplug::Error::DeviceNotFound), 3),
+            (BluscanError::Discovery(btleplug::Error::NotConnected), 4),
+            (BluscanError::Terminal(io::Error::other("tty")), 5),
+            (BluscanError::ChannelClosed, 6),
+            (BluscanError::Export("disk full".into()), 7),
+            (BluscanError::Interrupted, 130),
+        ];
+        for (error, code) in cases {
+            assert_eq!(error.exit_code(), code, "{:?}", error);
+        }
+    }
+
+    #[test]
+    fn display_gives_the_message_main_prints() {
+        assert_eq!(
+            BluscanError::Usage("unable to open a.csv: denied".to_string()).to_string(),
+            "unable to open a.csv: denied"
+        );
+        assert_eq!(
+            BluscanError::NoAdapter.to_string(),
+            "no Bluetooth adapter found"
+        );
+        assert_eq!(
+            BluscanError::Discovery(btleplug::Error::NotConnected).to_string(),
+            "unable to scan: Not connected"
+        );
+        assert_eq!(
+            BluscanError::Terminal(io::Error::other("not a tty")).to_string(),
+            "terminal error: not a tty"
+        );
+        assert_eq!(
+            BluscanError::Export("disk full".into()).to_string(),
+            "unable to write the results: disk full"
+        );
+        assert_eq!(
+            BluscanError::ChannelClosed.to_string(),
+            "the scan stopped sending results"
+        );
+    }
+
+    #[test]
+    fn only_outcomes_are_exited_with_silently() {
+        assert!(BluscanError::NoMatch.is_outcome());
+        assert!(BluscanError::Interrupted.is_outcome());
+        assert!(!BluscanError::Usage("bad".to_string()).is_outcome());
+        assert!(!BluscanError::NoAdapter.is_outcome());
+    }
+}
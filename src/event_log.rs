@@ -0,0 +1,378 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use flate2::{write::GzEncoder, Compression};
+
+use crate::{
+    app::STALE_TIMEOUT,
+    cli::LogRotation,
+    decoders::DecoderRegistry,
+    structs::{DeviceInfo, IdentityKey, LogEvent},
+};
+
+/// How often buffered events are flushed to the file.
+pub const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Appends one JSON object per device update to a newline-delimited JSON file. Writes are
+/// buffered and flushed at most once per `FLUSH_INTERVAL`, and when the log is dropped.
+/// The file is rotated by the `rotation` limits.
+pub struct EventLog {
+    file: RotatingFile,
+    last_flush: Instant,
+    decoders: DecoderRegistry,
+    /// The last update written for each identity, which the next one is compared with.
+    /// Devices that have gone stale are dropped when the log is flushed.
+    previous: HashMap<String, DeviceInfo>,
+}
+
+impl EventLog {
+    /// Opens the log file for appending, creating it if needed. Updates carry the fields
+    /// `decoders` read from the advertisement.
+    pub fn open(path: &Path, rotation: LogRotation, decoders: DecoderRegistry) -> io::Result<Self> {
+        Ok(Self {
+            file: RotatingFile::open(path, rotation)?,
+            last_flush: Instant::now(),
+            decoders,
+            previous: HashMap::new(),
+        })
+    }
+
+    /// Writes an update of `device`, keyed by `key`. A device not seen before, or not since
+    /// it went stale, is logged as discovered; otherwise the fields that differ from the
+    /// previous update are listed in the event. Devices `key` leaves out are skipped.
+    pub fn record(&mut self, device: &DeviceInfo, key: IdentityKey) -> io::Result<()> {
+        if key.excludes(device) {
+            return Ok(());
+        }
+        let identity = device.get_id(key);
+        let previous = self
+            .previous
+            .get(&identity)
+            .filter(|previous| !previous.is_stale(STALE_TIMEOUT));
+        let event = LogEvent {
+            timestamp: device.last_seen.to_rfc3339(),
+            event: if previous.is_some() {
+                "updated"
+            } else {
+                "discovered"
+            },
+            id: device.id.clone(),
+            address: device.address.clone(),
+            name: device.name.clone(),
+            rssi: device.rssi,
+            changed: previous.map_or_else(Vec::new, |previous| changed_fields(previous, device)),
+            decoded: self.decoders.decode(device),
+        };
+        self.previous.insert(identity, device.clone());
+        self.write_line(serde_json::to_vec(&event)?)
+    }
+
+    /// Writes a WebSocket client connecting to or disconnecting from `--ws-listen`.
+    pub fn record_client(&mut self, peer: SocketAddr, connected: bool) -> io::Result<()> {
+        let event = serde_json::json!({
+            "timestamp": chrono::Local::now().to_rfc3339(),
+            "event": if connected { "client_connected" } else { "client_disconnected" },
+            "peer": peer.to_string(),
+        });
+        self.write_line(serde_json::to_vec(&event)?)
+    }
+
+    /// Writes the end of a `--replay`, with the number of lines that could not be parsed.
+    pub fn record_replay_finished(&mut self, replayed: usize, skipped: usize) -> io::Result<()> {
+        let event = serde_json::json!({
+            "timestamp": chrono::Local::now().to_rfc3339(),
+            "event": "replay_finished",
+            "replayed": replayed,
+            "skipped": skipped,
+        });
+        self.write_line(serde_json::to_vec(&event)?)
+    }
+
+    fn write_line(&mut self, mut line: Vec<u8>) -> io::Result<()> {
+        line.push(b'\n');
+        self.file.write_line(&line)?;
+        self.flush_if_due()
+    }
+
+    /// Writes the buffered events to the file if the flush interval has passed.
+    pub fn flush_if_due(&mut self) -> io::Result<()> {
+        if self.last_flush.elapsed() >= FLUSH_INTERVAL {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the buffered events to the file.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.last_flush = Instant::now();
+        self.previous
+            .retain(|_, previous| !previous.is_stale(STALE_TIMEOUT));
+        self.file.flush()
+    }
+}
+
+/// A buffered log file that is rotated by the `rotation` limits: before a line that would
+/// take it past the size limit, or once it has been open for the rotation interval. Lines
+/// are written from a single task, so none can arrive while the file is moved aside, and
+/// a line is never split across two files.
+pub struct RotatingFile {
+    path: PathBuf,
+    rotation: LogRotation,
+    archiver: Archiver,
+    writer: BufWriter<File>,
+    /// The size of the current file, including buffered writes.
+    written: u64,
+    opened_at: Instant,
+}
+
+impl RotatingFile {
+    /// Opens the file for appending, creating it if needed.
+    pub fn open(path: &Path, rotation: LogRotation) -> io::Result<Self> {
+        let file = File::options().create(true).append(true).open(path)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            archiver: Archiver::new(path, &rotation),
+            rotation,
+            written: file.metadata()?.len(),
+            writer: BufWriter::new(file),
+            opened_at: Instant::now(),
+        })
+    }
+
+    /// Appends a whole line, rotating the file first if the line would take it past the
+    /// limits.
+    pub fn write_line(&mut self, line: &[u8]) -> io::Result<()> {
+        if self.rotation_due(line.len() as u64) {
+            self.rotate()?;
+        }
+        self.writer.write_all(line)?;
+        self.written += line.len() as u64;
+        Ok(())
+    }
+
+    /// Returns `true` if writing `next` more bytes would exceed the size limit, or the
+    /// current file is older than the rotation interval. An empty file is never rotated.
+    fn rotation_due(&self, next: u64) -> bool {
+        self.written > 0
+            && (self
+                .rotation
+                .max_bytes
+                .is_some_and(|max| self.written + next > max)
+                || self
+                    .rotation
+                    .interval
+                    .is_some_and(|interval| self.opened_at.elapsed() >= interval))
+    }
+
+    /// Moves the current file aside under a timestamped name and starts a fresh one.
+    /// Compressing and pruning the rotated files happens in the background.
+    fn rotate(&mut self) -> io::Result<()> {
+        self.writer.flush()?;
+        self.archiver.rotate_aside()?;
+        let file = File::options().create(true).append(true).open(&self.path)?;
+        self.writer = BufWriter::new(file);
+        self.written = 0;
+        self.opened_at = Instant::now();
+        Ok(())
+    }
+
+    /// Writes the buffered lines to the file.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Moves rotated log files aside, then compresses and prunes them on a background thread,
+/// one rotation after the other so that pruning never races a compression. Dropping it
+/// waits for the rotations still pending.
+pub struct Archiver {
+    path: PathBuf,
+    rotated: Option<mpsc::Sender<PathBuf>>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl Archiver {
+    /// Starts the worker archiving the rotated files of the log at `path`.
+    pub fn new(path: &Path, rotation: &LogRotation) -> Self {
+        let (rotated, files) = mpsc::channel::<PathBuf>();
+        let (log, rotation) = (path.to_path_buf(), rotation.clone());
+        let worker = std::thread::spawn(move || {
+            for file in files {
+                // A file that fails to compress is kept as it is.
+                if rotation.compress {
+                    let _ = compress(&file);
+                }
+                if let Some(keep) = rotation.keep {
+                    let _ = prune_rotated(&log, keep);
+                }
+            }
+        });
+        Self {
+            path: path.to_path_buf(),
+            rotated: Some(rotated),
+            worker: Some(worker),
+        }
+    }
+
+    /// Renames the log with a timestamp suffix, so a fresh file can be opened in its place,
+    /// and queues the rotated file for compression and pruning.
+    pub fn rotate_aside(&self) -> io::Result<()> {
+        let rotated = PathBuf::from(format!(
+            "{}.{}",
+            self.path.display(),
+            chrono::Local::now().format("%Y%m%dT%H%M%S%3f")
+        ));
+        fs::rename(&self.path, &rotated)?;
+        if let Some(sender) = &self.rotated {
+            let _ = sender.send(rotated);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Archiver {
+    fn drop(&mut self) {
+        // Closing the channel ends the worker once the queued files are done.
+        self.rotated.take();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// Replaces a rotated file with a gzip-compressed copy ending in `.gz`.
+fn compress(path: &Path) -> io::Result<()> {
+    let compressed = PathBuf::from(format!("{}.gz", path.display()));
+    let mut encoder = GzEncoder::new(File::create(&compressed)?, Compression::default());
+    io::copy(&mut File::open(path)?, &mut encoder)?;
+    encoder.finish()?;
+    fs::remove_file(path)
+}
+
+/// Deletes the oldest rotated files of the log at `path` until `keep` remain. Rotated
+/// files are named after the log with a timestamp suffix, so they sort by age.
+fn prune_rotated(path: &Path, keep: usize) -> io::Result<()> {
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+        return Ok(());
+    };
+    let prefix = format!("{}.", name);
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let mut rotated: Vec<PathBuf> = fs::read_dir(directory)?
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .is_some_and(|file| file.starts_with(&prefix))
+        })
+        .map(|entry| entry.path())
+        .collect();
+    rotated.sort();
+    for old in rotated.iter().take(rotated.len().saturating_sub(keep)) {
+        fs::remove_file(old)?;
+    }
+    Ok(())
+}
+
+/// Returns the names of the advertised fields that differ between two observations.
+fn changed_fields(previous: &DeviceInfo, device: &DeviceInfo) -> Vec<&'static str> {
+    [
+        ("name", previous.name != device.name),
+        ("rssi", previous.rssi != device.rssi),
+        ("tx_power", previous.tx_power != device.tx_power),
+        (
+            "manufacturer_data",
+            previous.manufacturer_data != device.manufacturer_data,
+        ),
+        ("services", previous.services != device.services),
+        ("service_data", previous.service_data != device.service_data),
+    ]
+    .into_iter()
+    .filter_map(|(field, changed)| changed.then_some(field))
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn archiver_compresses_and_prunes_every_rotation_before_dropping() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("events.log");
+        let rotation = LogRotation {
+            compress: true,
+            keep: Some(2),
+            ..LogRotation::default()
+        };
+        let archiver = Archiver::new(&log, &rotation);
+        for _ in 0..4 {
+            fs::write(&log, "line\n").unwrap();
+            archiver.rotate_aside().unwrap();
+            // Rotated files are named to the millisecond.
+            std::thread::sleep(Duration::from_millis(2));
+        }
+        drop(archiver);
+
+        let mut rotated: Vec<String> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        rotated.sort();
+        assert_eq!(rotated.len(), 2);
+        assert!(rotated
+            .iter()
+            .all(|name| name.starts_with("events.log.") && name.ends_with(".gz")));
+    }
+
+    #[test]
+    fn rotating_file_moves_whole_lines_aside_at_the_size_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("auto.log");
+        let rotation = LogRotation {
+            max_bytes: Some(10),
+            ..LogRotation::default()
+        };
+        let mut file = RotatingFile::open(&log, rotation).unwrap();
+        file.write_line(b"first\n").unwrap();
+        file.write_line(b"second\n").unwrap();
+        file.flush().unwrap();
+        drop(file);
+
+        assert_eq!(fs::read_to_string(&log).unwrap(), "second\n");
+        let rotated: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| *path != log)
+            .collect();
+        assert_eq!(rotated.len(), 1);
+        assert_eq!(fs::read_to_string(&rotated[0]).unwrap(), "first\n");
+    }
+
+    #[test]
+    fn changed_fields_lists_the_differing_advertised_fields() {
+        let previous = DeviceInfo {
+            name: Some("Old".to_string()),
+            rssi: Some(-60),
+            ..DeviceInfo::default()
+        };
+        let device = DeviceInfo {
+            name: Some("New".to_string()),
+            rssi: Some(-60),
+            tx_power: Some(4),
+            ..DeviceInfo::default()
+        };
+        assert_eq!(changed_fields(&previous, &device), ["name", "tx_power"]);
+    }
+}
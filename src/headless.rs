@@ -0,0 +1,92 @@
+use std::{
+    collections::HashSet,
+    io::{self, Write},
+    time::Duration,
+};
+
+use futures::future;
+
+use crate::{
+    app::{App, DeviceData},
+    error::BluscanError,
+    utils::{company_name, format_reading},
+};
+
+/// Scans without the interface, printing a line to stdout the first time a device passes
+/// the filters. Stops once `duration` has elapsed, on Ctrl+C, or when stdout is closed, and
+/// fails if the scan does.
+pub async fn headless(app: &mut App, duration: Option<Duration>) -> Result<(), BluscanError> {
+    let deadline = async {
+        match duration {
+            Some(duration) => tokio::time::sleep(duration).await,
+            None => future::pending().await,
+        }
+    };
+    let interrupted = tokio::signal::ctrl_c();
+    tokio::pin!(deadline, interrupted);
+    let mut printed = HashSet::new();
+    let mut stdout = io::stdout().lock();
+    loop {
+        let api_query = async {
+            match app.api_queries.as_mut() {
+                Some(queries) => queries.recv().await,
+                None => future::pending().await,
+            }
+        };
+        let data = tokio::select! {
+            data = app.rx.recv() => data,
+            Some(query) = api_query => {
+                app.answer_api_query(query);
+                continue;
+            }
+            _ = &mut interrupted => break,
+            _ = &mut deadline => break,
+        };
+        match data {
+            Some(DeviceData::DeviceBatch {
+                devices,
+                observations,
+            }) => app.merge_batch(devices, observations),
+            Some(DeviceData::Error(error)) => eprintln!("error: {}", error),
+            Some(DeviceData::ScanFailed(e)) => return Err(e),
+            Some(DeviceData::ReplayFinished { replayed, skipped }) => {
+                app.finish_replay(replayed, skipped)
+            }
+            Some(DeviceData::WatchAlert(alert)) => eprintln!("{}", alert),
+            Some(DeviceData::WsClient { peer, connected }) => app.ws_client(peer, connected),
+            Some(_) => {}
+            None => return Err(BluscanError::ChannelClosed),
+        }
+        for device in app.visible_devices() {
+            if !printed.insert(device.get_id(app.identity_key)) {
+                continue;
+            }
+            // Each line is flushed so the output can be piped into other tools as it comes.
+            let line = match &app.line_format {
+                Some(format) => writeln!(stdout, "{}", format.render(device, &app.decoders)),
+                None => writeln!(
+                    stdout,
+                    "{}\t{}\t{}\t{}\t{}",
+                    device.last_seen.format("%H:%M:%S"),
+                    device.get_id(app.identity_key),
+                    format_reading(device.rssi),
+                    device.name.as_deref().unwrap_or("-"),
+                    company_name(&device.manufacturer_data).unwrap_or_else(|| "-".to_string()),
+                ),
+            }
+            .and_then(|_| stdout.flush());
+            match line {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return Ok(()),
+                Err(e) => return Err(BluscanError::Export(e.into())),
+            }
+        }
+        app.check_watch_list();
+        app.flush_capture(false);
+        if app.error_view {
+            eprintln!("error: {}", app.error_message);
+            app.error_view = false;
+        }
+    }
+    Ok(())
+}
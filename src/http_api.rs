@@ -0,0 +1,204 @@
+use std::convert::Infallible;
+
+use http_body_util::Full;
+use hyper::{
+    body::{Bytes, Incoming},
+    header::{AUTHORIZATION, CONTENT_TYPE},
+    server::conn::http1,
+    service::service_fn,
+    Method, Request, Response, StatusCode,
+};
+use hyper_util::rt::TokioIo;
+use tokio::{
+    net::TcpListener,
+    sync::{mpsc::UnboundedSender, oneshot},
+};
+
+use crate::app::{ApiQuery, ApiRequest};
+
+/// Serves the HTTP API on `listener`, forwarding requests to the application over `queries`.
+/// When `token` is set, requests must carry it as `Authorization: Bearer <token>`.
+pub async fn serve(
+    listener: TcpListener,
+    token: Option<String>,
+    queries: UnboundedSender<ApiQuery>,
+) {
+    loop {
+        let Ok((stream, _)) = listener.accept().await else {
+            continue;
+        };
+        let (token, queries) = (token.clone(), queries.clone());
+        tokio::spawn(async move {
+            let service = service_fn(move |request| {
+                let (token, queries) = (token.clone(), queries.clone());
+                async move { Ok::<_, Infallible>(handle(request, token.as_deref(), &queries).await) }
+            });
+            let _ = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await;
+        });
+    }
+}
+
+/// Routes a request to the application and turns its answer into a response.
+async fn handle(
+    request: Request<Incoming>,
+    token: Option<&str>,
+    queries: &UnboundedSender<ApiQuery>,
+) -> Response<Full<Bytes>> {
+    if let Some(token) = token {
+        let authorized = request
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|given| given == token);
+        if !authorized {
+            return error(StatusCode::UNAUTHORIZED, "missing or invalid token");
+        }
+    }
+    let path = request.uri().path().trim_end_matches('/');
+    let api_request = match (request.method(), path) {
+        (&Method::GET, "/devices") => ApiRequest::Devices,
+        (&Method::GET, path) if path.starts_with("/devices/") => {
+            ApiRequest::Device(path["/devices/".len()..].to_string())
+        }
+        (&Method::POST, "/scan/pause") => ApiRequest::SetPaused(true),
+        (&Method::POST, "/scan/resume") => ApiRequest::SetPaused(false),
+        (_, "/devices" | "/scan/pause" | "/scan/resume") => {
+            return error(StatusCode::METHOD_NOT_ALLOWED, "method not allowed")
+        }
+        _ => return error(StatusCode::NOT_FOUND, "not found"),
+    };
+    let (reply, answer) = oneshot::channel();
+    if queries
+        .send(ApiQuery {
+            request: api_request,
+            reply,
+        })
+        .is_err()
+    {
+        return error(StatusCode::SERVICE_UNAVAILABLE, "btlescan is shutting down");
+    }
+    match answer.await {
+        Ok(Some(body)) => Response::builder()
+            .header(CONTENT_TYPE, "application/json")
+            .body(Full::new(Bytes::from(body)))
+            .unwrap(),
+        Ok(None) => error(StatusCode::NOT_FOUND, "device not found"),
+        Err(_) => error(StatusCode::SERVICE_UNAVAILABLE, "btlescan is shutting down"),
+    }
+}
+
+/// Builds a JSON error response.
+fn error(status: StatusCode, message: &str) -> Response<Full<Bytes>> {
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Full::new(Bytes::from(
+            serde_json::json!({ "error": message }).to_string(),
+        )))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+        sync::mpsc,
+    };
+
+    use super::*;
+
+    /// Serves the API with `token` in front of a mock scanner that knows one device, and
+    /// returns the address it listens on.
+    async fn start(token: Option<&str>) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (queries, mut received) = mpsc::unbounded_channel::<ApiQuery>();
+        tokio::spawn(async move {
+            while let Some(query) = received.recv().await {
+                let answer = match query.request {
+                    ApiRequest::Devices => Some(br#"[{"address":"AA:BB:CC:DD:EE:01"}]"#.to_vec()),
+                    ApiRequest::Device(id) if id == "AA:BB:CC:DD:EE:01" => {
+                        Some(br#"{"address":"AA:BB:CC:DD:EE:01"}"#.to_vec())
+                    }
+                    ApiRequest::Device(_) => None,
+                    ApiRequest::SetPaused(paused) => Some(
+                        serde_json::json!({ "paused": paused })
+                            .to_string()
+                            .into_bytes(),
+                    ),
+                };
+                let _ = query.reply.send(answer);
+            }
+        });
+        tokio::spawn(serve(listener, token.map(str::to_string), queries));
+        addr
+    }
+
+    /// Sends one request and returns the status code and body of the response.
+    async fn request(
+        addr: SocketAddr,
+        method: &str,
+        path: &str,
+        authorization: Option<&str>,
+    ) -> (u16, String) {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let mut head = format!(
+            "{} {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n",
+            method, path
+        );
+        if let Some(authorization) = authorization {
+            head.push_str(&format!("Authorization: {}\r\n", authorization));
+        }
+        head.push_str("\r\n");
+        stream.write_all(head.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        let status = response[9..12].parse().unwrap();
+        let body = response.split("\r\n\r\n").nth(1).unwrap_or_default();
+        (status, body.to_string())
+    }
+
+    #[tokio::test]
+    async fn requests_need_the_bearer_token() {
+        let addr = start(Some("secret")).await;
+        for authorization in [
+            None,
+            Some("Bearer wrong"),
+            Some("secret"),
+            Some("Basic secret"),
+        ] {
+            let (status, body) = request(addr, "GET", "/devices", authorization).await;
+            assert_eq!(status, 401, "{:?}", authorization);
+            assert_eq!(body, r#"{"error":"missing or invalid token"}"#);
+        }
+        let (status, body) = request(addr, "GET", "/devices", Some("Bearer secret")).await;
+        assert_eq!(status, 200);
+        assert_eq!(body, r#"[{"address":"AA:BB:CC:DD:EE:01"}]"#);
+    }
+
+    #[tokio::test]
+    async fn routes_reach_the_scanner() {
+        let addr = start(None).await;
+        for (method, path, expected) in [
+            ("GET", "/devices/", 200),
+            ("GET", "/devices/AA:BB:CC:DD:EE:01", 200),
+            ("GET", "/devices/AA:BB:CC:DD:EE:02", 404),
+            ("POST", "/scan/pause", 200),
+            ("POST", "/scan/resume", 200),
+            ("DELETE", "/devices", 405),
+            ("GET", "/scan/pause", 405),
+            ("GET", "/nowhere", 404),
+        ] {
+            let (status, _) = request(addr, method, path, None).await;
+            assert_eq!(status, expected, "{} {}", method, path);
+        }
+        let (_, body) = request(addr, "POST", "/scan/pause", None).await;
+        assert_eq!(body, r#"{"paused":true}"#);
+    }
+}
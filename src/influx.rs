@@ -0,0 +1,274 @@
+use std::{collections::VecDeque, time::Duration};
+
+use tokio::{
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    task::JoinHandle,
+    time::Instant,
+};
+
+use crate::{app::DeviceData, cli::InfluxConfig, structs::DeviceInfo, utils::company_name};
+
+/// The measurement device sightings are written to.
+const MEASUREMENT: &str = "ble_sighting";
+
+/// The most points sent in one write request.
+const BATCH_SIZE: usize = 5000;
+
+/// How many points may wait for the server before the oldest are dropped.
+const MAX_PENDING: usize = 100_000;
+
+/// The longest wait between two attempts after failed writes.
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+/// How long the last points may take to be written when btlescan exits.
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Writes device sightings to the InfluxDB v2 write API as line-protocol points. Recording
+/// never blocks: points are buffered by a background task that writes them every interval,
+/// backing off when the server fails and reporting failures as `DeviceData::Error`.
+pub struct InfluxWriter {
+    points: UnboundedSender<String>,
+    task: JoinHandle<()>,
+}
+
+impl InfluxWriter {
+    /// Starts writing to the server in the background.
+    pub fn start(config: InfluxConfig, tx: UnboundedSender<DeviceData>) -> Self {
+        let (points, receiver) = mpsc::unbounded_channel();
+        let task = tokio::spawn(write_points(config, receiver, tx));
+        Self { points, task }
+    }
+
+    /// Buffers a sighting of the device. Devices without an RSSI or TX power reading have
+    /// nothing to plot and are skipped.
+    pub fn record(&self, device: &DeviceInfo) {
+        let company = company_name(&device.manufacturer_data);
+        let tags = [
+            ("address", device.address_or_id()),
+            ("name", device.name.clone().unwrap_or_default()),
+            ("company", company.unwrap_or_default()),
+        ];
+        let fields: Vec<(&str, i64)> = [
+            ("rssi", device.rssi.map(i64::from)),
+            ("tx_power", device.tx_power.map(i64::from)),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| Some((key, value?)))
+        .collect();
+        let Some(timestamp) = device.last_seen.timestamp_nanos_opt() else {
+            return;
+        };
+        if let Some(point) = encode_point(MEASUREMENT, &tags, &fields, timestamp) {
+            let _ = self.points.send(point);
+        }
+    }
+
+    /// Writes the points still buffered, giving up after `CLOSE_TIMEOUT`.
+    pub async fn close(self) {
+        drop(self.points);
+        let _ = tokio::time::timeout(CLOSE_TIMEOUT, self.task).await;
+    }
+}
+
+/// Encodes a point in InfluxDB line protocol with integer fields and a nanosecond
+/// timestamp. Tags with an empty value are left out, since the protocol does not allow
+/// them. Returns `None` if there are no fields, which the protocol requires.
+pub fn encode_point(
+    measurement: &str,
+    tags: &[(&str, String)],
+    fields: &[(&str, i64)],
+    timestamp_ns: i64,
+) -> Option<String> {
+    if fields.is_empty() {
+        return None;
+    }
+    let mut line = escape(measurement, &[',', ' ']);
+    for (key, value) in tags.iter().filter(|(_, value)| !value.is_empty()) {
+        line.push(',');
+        line.push_str(&escape(key, &[',', '=', ' ']));
+        line.push('=');
+        line.push_str(&escape(value, &[',', '=', ' ']));
+    }
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|(key, value)| format!("{}={}i", escape(key, &[',', '=', ' ']), value))
+        .collect();
+    line.push(' ');
+    line.push_str(&fields.join(","));
+    line.push(' ');
+    line.push_str(&timestamp_ns.to_string());
+    Some(line)
+}
+
+/// Escapes the characters the line protocol gives a meaning to, and backslashes. Line
+/// breaks cannot be escaped and are replaced by spaces, which are.
+fn escape(value: &str, special: &[char]) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        let c = if c == '\n' || c == '\r' { ' ' } else { c };
+        if c == '\\' || special.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Why a write failed, and whether it is worth retrying.
+struct WriteError {
+    message: String,
+    retry: bool,
+}
+
+/// Buffers points as they arrive and writes them every interval until the channel closes,
+/// then makes a last attempt at writing what is left.
+async fn write_points(
+    config: InfluxConfig,
+    mut points: UnboundedReceiver<String>,
+    tx: UnboundedSender<DeviceData>,
+) {
+    let client = reqwest::Client::new();
+    let mut pending = VecDeque::new();
+    let mut interval = tokio::time::interval(config.interval);
+    let mut backoff = config.interval;
+    let mut retry_at = Instant::now();
+    let mut failing = false;
+    loop {
+        tokio::select! {
+            point = points.recv() => match point {
+                Some(point) => {
+                    if pending.len() == MAX_PENDING {
+                        pending.pop_front();
+                    }
+                    pending.push_back(point);
+                }
+                None => break,
+            },
+            _ = interval.tick() => {
+                if Instant::now() < retry_at {
+                    continue;
+                }
+                match write_pending(&client, &config, &mut pending).await {
+                    Ok(()) => {
+                        failing = false;
+                        backoff = config.interval;
+                    }
+                    Err(e) => {
+                        if !failing {
+                            let _ = tx.send(DeviceData::Error(format!(
+                                "Unable to write to InfluxDB: {}",
+                                e.message
+                            )));
+                        }
+                        failing = true;
+                        if e.retry {
+                            retry_at = Instant::now() + backoff;
+                            backoff = (backoff * 2).min(MAX_BACKOFF);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    let _ = write_pending(&client, &config, &mut pending).await;
+}
+
+/// Writes the pending points in batches, removing each batch once written. A batch the
+/// server rejects as malformed or unauthorized is dropped, since retrying cannot help.
+async fn write_pending(
+    client: &reqwest::Client,
+    config: &InfluxConfig,
+    pending: &mut VecDeque<String>,
+) -> Result<(), WriteError> {
+    while !pending.is_empty() {
+        let count = pending.len().min(BATCH_SIZE);
+        let body: Vec<&str> = pending.range(..count).map(String::as_str).collect();
+        let result = write_batch(client, config, body.join("\n")).await;
+        if result.is_ok() || result.as_ref().is_err_and(|e| !e.retry) {
+            pending.drain(..count);
+        }
+        result?;
+    }
+    Ok(())
+}
+
+/// Posts one batch of points to the write API.
+async fn write_batch(
+    client: &reqwest::Client,
+    config: &InfluxConfig,
+    body: String,
+) -> Result<(), WriteError> {
+    let mut query = vec![("bucket", config.bucket.as_str()), ("precision", "ns")];
+    if let Some(org) = &config.org {
+        query.push(("org", org));
+    }
+    let mut request = client
+        .post(format!("{}/api/v2/write", config.url))
+        .query(&query)
+        .header("Content-Type", "text/plain; charset=utf-8")
+        .body(body);
+    if let Some(token) = &config.token {
+        request = request.header("Authorization", format!("Token {}", token));
+    }
+    let response = request.send().await.map_err(|e| WriteError {
+        message: e.to_string(),
+        retry: true,
+    })?;
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+    let detail = response.text().await.unwrap_or_default();
+    Err(WriteError {
+        message: format!("{} {}", status, detail.trim()),
+        // Rate limiting and server errors pass; other client errors do not.
+        retry: status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn special_characters_are_escaped() {
+        let key = [',', '=', ' '];
+        for (value, expected) in [
+            ("Kitchen Sensor", r"Kitchen\ Sensor"),
+            ("a=b,c", r"a\=b\,c"),
+            (r"back\slash", r"back\\slash"),
+            ("two\r\nlines", r"two\ \ lines"),
+            ("plain", "plain"),
+        ] {
+            assert_eq!(escape(value, &key), expected, "{:?}", value);
+        }
+        // Measurements do not give `=` a meaning.
+        assert_eq!(escape("a=b c", &[',', ' ']), r"a=b\ c");
+    }
+
+    #[test]
+    fn points_are_encoded_in_line_protocol() {
+        let tags = [
+            ("address", "AA:BB:CC:DD:EE:01".to_string()),
+            ("name", "Desk Lamp".to_string()),
+            ("company", String::new()),
+        ];
+        assert_eq!(
+            encode_point(
+                MEASUREMENT,
+                &tags,
+                &[("rssi", -60), ("tx_power", 4)],
+                1_700_000_000_000_000_000
+            )
+            .as_deref(),
+            Some(
+                r"ble_sighting,address=AA:BB:CC:DD:EE:01,name=Desk\ Lamp rssi=-60i,tx_power=4i 1700000000000000000"
+            )
+        );
+        assert_eq!(
+            encode_point("m", &[], &[("rssi", -90)], 0).as_deref(),
+            Some("m rssi=-90i 0")
+        );
+        assert_eq!(encode_point(MEASUREMENT, &tags, &[], 0), None);
+    }
+}
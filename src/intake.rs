@@ -0,0 +1,209 @@
+use std::{
+    collections::HashSet,
+    io,
+    sync::{atomic::AtomicBool, Arc, Mutex},
+};
+
+use futures::future::BoxFuture;
+use tokio::sync::{
+    mpsc::{self, UnboundedReceiver, UnboundedSender},
+    Notify,
+};
+
+use crate::{
+    app::DeviceData,
+    event_log::{EventLog, FLUSH_INTERVAL},
+    redact::Redactor,
+    source::{DeviceSource, ScanCommand},
+    structs::IdentityKey,
+    utils::normalize_address,
+};
+
+/// Prepares the results of another source where they leave it, so every mode that scans
+/// sees and logs the same devices: excluded addresses are dropped, the rest are redacted,
+/// and each one is written to the event log before it is passed on. The log is flushed
+/// every `FLUSH_INTERVAL` and when the source ends.
+pub struct Intake {
+    pub source: Box<dyn DeviceSource>,
+    /// Normalized addresses that are never reported.
+    pub excluded_addresses: HashSet<String>,
+    pub redactor: Option<Redactor>,
+    pub event_log: Option<Arc<Mutex<EventLog>>>,
+    pub identity: IdentityKey,
+}
+
+impl DeviceSource for Intake {
+    fn run(
+        self: Box<Self>,
+        tx: UnboundedSender<DeviceData>,
+        pause_signal: Arc<AtomicBool>,
+        shutdown: Arc<Notify>,
+        commands: UnboundedReceiver<ScanCommand>,
+    ) -> BoxFuture<'static, ()> {
+        let Intake {
+            source,
+            excluded_addresses,
+            redactor,
+            event_log,
+            identity,
+        } = *self;
+        Box::pin(async move {
+            let (source_tx, mut rx) = mpsc::unbounded_channel();
+            let source = tokio::spawn(source.run(source_tx, pause_signal, shutdown, commands));
+            let mut flush = tokio::time::interval(FLUSH_INTERVAL);
+            let mut log_failing = false;
+            // Reports a failed write once, until a write succeeds in between.
+            let mut report = |result: io::Result<()>| match result {
+                Ok(()) => log_failing = false,
+                Err(e) if !log_failing => {
+                    log_failing = true;
+                    let _ = tx.send(DeviceData::Error(format!(
+                        "Unable to write the event log: {}",
+                        e
+                    )));
+                }
+                Err(_) => {}
+            };
+            loop {
+                let data = tokio::select! {
+                    data = rx.recv() => match data {
+                        Some(data) => data,
+                        None => break,
+                    },
+                    _ = flush.tick() => {
+                        if let Some(event_log) = &event_log {
+                            report(event_log.lock().unwrap().flush_if_due());
+                        }
+                        continue;
+                    }
+                };
+                let data = match data {
+                    DeviceData::DeviceBatch {
+                        devices,
+                        observations,
+                    } => {
+                        let mut admitted = Vec::with_capacity(devices.len());
+                        for mut device in devices {
+                            if normalize_address(&device.address)
+                                .is_some_and(|address| excluded_addresses.contains(&address))
+                            {
+                                continue;
+                            }
+                            // Redacting first keeps real addresses out of everything
+                            // recorded from here on.
+                            if let Some(redactor) = &redactor {
+                                redactor.redact(&mut device);
+                            }
+                            if let Some(event_log) = &event_log {
+                                report(event_log.lock().unwrap().record(&device, identity));
+                            }
+                            admitted.push(device);
+                        }
+                        DeviceData::DeviceBatch {
+                            devices: admitted,
+                            observations,
+                        }
+                    }
+                    DeviceData::ReplayFinished { replayed, skipped } => {
+                        if let Some(event_log) = &event_log {
+                            report(
+                                event_log
+                                    .lock()
+                                    .unwrap()
+                                    .record_replay_finished(replayed, skipped),
+                            );
+                        }
+                        DeviceData::ReplayFinished { replayed, skipped }
+                    }
+                    data => data,
+                };
+                let _ = tx.send(data);
+            }
+            let _ = source.await;
+            if let Some(event_log) = &event_log {
+                report(event_log.lock().unwrap().flush());
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, fs, time::Duration};
+
+    use super::*;
+    use crate::{
+        cli::LogRotation, decoders::DecoderRegistry, source::MockSource, structs::DeviceInfo,
+    };
+
+    fn device(address: &str, rssi: i16) -> DeviceInfo {
+        DeviceInfo {
+            id: format!("hci0/dev_{}", address.replace(':', "_")),
+            address: address.to_string(),
+            rssi: Some(rssi),
+            last_seen: chrono::Local::now(),
+            ..DeviceInfo::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn results_are_logged_as_they_leave_the_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.log");
+        let event_log = EventLog::open(
+            &path,
+            LogRotation::default(),
+            DecoderRegistry::with_builtins(HashMap::new()),
+        )
+        .unwrap();
+        let lamp = device("AA:BB:CC:DD:EE:01", -50);
+        let closer = DeviceInfo {
+            rssi: Some(-45),
+            ..lamp.clone()
+        };
+        let source = MockSource::new()
+            .batch(Duration::ZERO, vec![lamp, device("AA:BB:CC:DD:EE:02", -60)])
+            .batch(Duration::ZERO, vec![closer]);
+        let intake = Box::new(Intake {
+            source: Box::new(source),
+            excluded_addresses: HashSet::from(["AA:BB:CC:DD:EE:02".to_string()]),
+            redactor: None,
+            event_log: Some(Arc::new(Mutex::new(event_log))),
+            identity: IdentityKey::Auto,
+        });
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let (_, commands) = mpsc::unbounded_channel();
+        intake
+            .run(tx, Arc::default(), Arc::new(Notify::new()), commands)
+            .await;
+
+        let mut forwarded = Vec::new();
+        while let Ok(DeviceData::DeviceBatch { devices, .. }) = rx.try_recv() {
+            forwarded.extend(devices.into_iter().map(|device| device.address));
+        }
+        assert_eq!(forwarded, ["AA:BB:CC:DD:EE:01", "AA:BB:CC:DD:EE:01"]);
+
+        let events: Vec<serde_json::Value> = fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let logged: Vec<_> = events
+            .iter()
+            .map(|event| {
+                (
+                    event["event"].as_str().unwrap(),
+                    event["address"].as_str().unwrap(),
+                    event["changed"].to_string(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            logged,
+            [
+                ("discovered", "AA:BB:CC:DD:EE:01", "[]".to_string()),
+                ("updated", "AA:BB:CC:DD:EE:01", r#"["rssi"]"#.to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,165 @@
+use std::{
+    error::Error,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use btleplug::api::{bleuuid::uuid_from_u16, Peripheral};
+use tokio::{sync::mpsc::UnboundedSender, time::timeout};
+use uuid::Uuid;
+
+use crate::{
+    app::DeviceData,
+    structs::{DeviceInfo, IdentityKey},
+};
+
+/// The Device Information Service characteristics read from each device.
+const MANUFACTURER_NAME: Uuid = uuid_from_u16(0x2A29);
+const MODEL_NUMBER: Uuid = uuid_from_u16(0x2A24);
+const SERIAL_NUMBER: Uuid = uuid_from_u16(0x2A25);
+const HARDWARE_REVISION: Uuid = uuid_from_u16(0x2A27);
+const FIRMWARE_REVISION: Uuid = uuid_from_u16(0x2A26);
+const SOFTWARE_REVISION: Uuid = uuid_from_u16(0x2A28);
+
+/// What an inventory run read from one device. Characteristics the device does not
+/// expose are left empty; `error` is set when the device could not be read at all.
+#[derive(Clone, Default, serde::Serialize)]
+pub struct InventoryEntry {
+    pub address: String,
+    pub name: Option<String>,
+    pub read_at: String,
+    pub manufacturer: Option<String>,
+    pub model: Option<String>,
+    pub serial: Option<String>,
+    pub hardware_revision: Option<String>,
+    pub firmware_revision: Option<String>,
+    pub software_revision: Option<String>,
+    pub error: Option<String>,
+}
+
+/// An inventory run in progress: the devices are connected to one after the other, and the
+/// report at `path` is rewritten after each one, so it is complete up to the last device
+/// even if the run is cancelled.
+pub struct Inventory {
+    pub path: PathBuf,
+    pub entries: Vec<InventoryEntry>,
+    pub total: usize,
+    /// The identity of the device being read.
+    pub current: Option<String>,
+    pub cancel: Arc<AtomicBool>,
+    /// Whether the scan was paused before the run paused it.
+    pub paused_before: bool,
+}
+
+impl Inventory {
+    /// Returns how many devices could not be read.
+    pub fn failed(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|entry| entry.error.is_some())
+            .count()
+    }
+
+    /// Writes the entries read so far to the report, as CSV when the path ends in `.csv`
+    /// and as a JSON array otherwise.
+    pub fn write_report(&self) -> Result<(), Box<dyn Error>> {
+        let csv = self
+            .path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("csv"));
+        if csv {
+            let mut wtr = csv::Writer::from_path(&self.path)?;
+            for entry in &self.entries {
+                wtr.serialize(entry)?;
+            }
+            wtr.flush()?;
+        } else {
+            let file = std::fs::File::create(&self.path)?;
+            serde_json::to_writer_pretty(file, &self.entries)?;
+        }
+        Ok(())
+    }
+}
+
+/// Connects to each device in turn, reads its Device Information Service and disconnects.
+/// Devices are named by their identity under `identity_key`.
+/// Connecting and reading may each take up to `connect_timeout`; a device that fails is
+/// recorded with the error and skipped. Each device is announced with
+/// `DeviceData::InventoryReading` and its result sent as `DeviceData::InventoryEntry`.
+/// Stops early when `cancel` is set, and sends `DeviceData::InventoryDone` at the end.
+pub async fn run_inventory(
+    tx: UnboundedSender<DeviceData>,
+    devices: Vec<DeviceInfo>,
+    identity_key: IdentityKey,
+    connect_timeout: Duration,
+    cancel: Arc<AtomicBool>,
+) {
+    for device in devices {
+        if cancel.load(Ordering::SeqCst) {
+            break;
+        }
+        let _ = tx.send(DeviceData::InventoryReading(device.get_id(identity_key)));
+        let mut entry = InventoryEntry {
+            address: device.get_id(identity_key),
+            name: device.name.clone(),
+            read_at: chrono::Local::now().to_rfc3339(),
+            ..InventoryEntry::default()
+        };
+        if let Err(e) = read_device_information(&device, connect_timeout, &mut entry).await {
+            entry.error = Some(e);
+        }
+        if let Some(peripheral) = &device.device {
+            let _ = peripheral.disconnect().await;
+        }
+        let _ = tx.send(DeviceData::InventoryEntry(Box::new(entry)));
+    }
+    let _ = tx.send(DeviceData::InventoryDone);
+}
+
+/// Connects to a device and fills `entry` with its Device Information Service.
+async fn read_device_information(
+    device: &DeviceInfo,
+    connect_timeout: Duration,
+    entry: &mut InventoryEntry,
+) -> Result<(), String> {
+    let Some(peripheral) = &device.device else {
+        return Err("device not found".to_string());
+    };
+    match timeout(connect_timeout, peripheral.connect()).await {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => return Err(format!("connection error: {}", e)),
+        Err(_) => return Err("connection timed out".to_string()),
+    }
+    let read = async {
+        peripheral.discover_services().await?;
+        for characteristic in peripheral.characteristics() {
+            let field = match characteristic.uuid {
+                MANUFACTURER_NAME => &mut entry.manufacturer,
+                MODEL_NUMBER => &mut entry.model,
+                SERIAL_NUMBER => &mut entry.serial,
+                HARDWARE_REVISION => &mut entry.hardware_revision,
+                FIRMWARE_REVISION => &mut entry.firmware_revision,
+                SOFTWARE_REVISION => &mut entry.software_revision,
+                _ => continue,
+            };
+            let value = peripheral.read(&characteristic).await?;
+            // Some devices pad their strings with NULs.
+            *field = Some(
+                String::from_utf8_lossy(&value)
+                    .trim_end_matches('\0')
+                    .trim()
+                    .to_string(),
+            );
+        }
+        Ok::<_, btleplug::Error>(())
+    };
+    match timeout(connect_timeout, read).await {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(e)) => Err(format!("read error: {}", e)),
+        Err(_) => Err("reading timed out".to_string()),
+    }
+}
@@ -0,0 +1,165 @@
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    structs::{DeviceCategory, DeviceInfo},
+    utils::normalize_address,
+};
+
+/// What the known-devices file says about a device.
+pub struct KnownDevice {
+    pub alias: Option<String>,
+    pub category: Option<DeviceCategory>,
+}
+
+/// Friendly names and categories for devices, read from the `--known-devices` file. Each
+/// line is `address,alias[,category]`, where the address may also be a platform id and the
+/// alias may be left empty to set only the category; blank lines and lines starting with
+/// `#` are skipped. Aliases edited in the interface are written back to the file.
+pub struct KnownDevices {
+    path: PathBuf,
+    /// The entries keyed by the normalized address or platform id.
+    entries: BTreeMap<String, KnownDevice>,
+}
+
+impl KnownDevices {
+    /// Reads the file, which is created on the first edit if it does not exist yet. Returns
+    /// a warning for each line that could not be used.
+    pub fn load(path: &Path) -> Result<(Self, Vec<String>), Box<dyn Error>> {
+        let mut known = Self {
+            path: path.to_path_buf(),
+            entries: BTreeMap::new(),
+        };
+        let mut warnings = Vec::new();
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok((known, warnings)),
+            Err(e) => return Err(e.into()),
+        };
+        // Each line is parsed on its own, so a stray quote spoils only its own line.
+        for (index, line) in contents.lines().enumerate() {
+            let warn = |message: String| format!("{}:{}: {}", path.display(), index + 1, message);
+            if line.trim().is_empty() || line.trim_start().starts_with('#') {
+                continue;
+            }
+            let record = csv::ReaderBuilder::new()
+                .has_headers(false)
+                .trim(csv::Trim::All)
+                .from_reader(line.as_bytes())
+                .records()
+                .next();
+            let record = match record {
+                Some(Ok(record)) => record,
+                Some(Err(e)) => {
+                    warnings.push(warn(e.to_string()));
+                    continue;
+                }
+                None => continue,
+            };
+            if record.len() > 3 {
+                warnings.push(warn(format!(
+                    "expected address,alias[,category], found {} fields",
+                    record.len()
+                )));
+                continue;
+            }
+            let Some(address) = normalize_address(&record[0]) else {
+                warnings.push(warn("missing address".to_string()));
+                continue;
+            };
+            let alias = record
+                .get(1)
+                .filter(|alias| !alias.is_empty())
+                .map(str::to_string);
+            let category = match record.get(2).filter(|category| !category.is_empty()) {
+                Some(label) => match DeviceCategory::ALL
+                    .into_iter()
+                    .find(|category| category.label() == label)
+                {
+                    Some(category) => Some(category),
+                    None => {
+                        warnings.push(warn(format!(
+                            "unknown category '{}', expected one of: {}",
+                            label,
+                            DeviceCategory::ALL.map(DeviceCategory::label).join(", ")
+                        )));
+                        continue;
+                    }
+                },
+                None => None,
+            };
+            if alias.is_none() && category.is_none() {
+                warnings.push(warn(format!("no alias or category for {}", address)));
+                continue;
+            }
+            if known.entries.contains_key(&address) {
+                warnings.push(warn(format!(
+                    "{} is listed again, replacing the earlier entry",
+                    address
+                )));
+            }
+            known
+                .entries
+                .insert(address, KnownDevice { alias, category });
+        }
+        Ok((known, warnings))
+    }
+
+    /// Returns the entry for the device, looked up by its address and platform id.
+    pub fn get(&self, device: &DeviceInfo) -> Option<&KnownDevice> {
+        self.entries
+            .get(&device.address_or_id())
+            .or_else(|| self.entries.get(&device.id.to_uppercase()))
+    }
+
+    /// Sets or, when `None`, removes the alias of the device with this identity and
+    /// rewrites the file. An entry left with neither an alias nor a category is removed.
+    pub fn set_alias(
+        &mut self,
+        identity: &str,
+        alias: Option<String>,
+    ) -> Result<(), Box<dyn Error>> {
+        let identity = normalize_address(identity).unwrap_or_else(|| identity.to_uppercase());
+        match self.entries.get_mut(&identity) {
+            Some(entry) => {
+                entry.alias = alias;
+                if entry.alias.is_none() && entry.category.is_none() {
+                    self.entries.remove(&identity);
+                }
+            }
+            None => {
+                if alias.is_some() {
+                    self.entries.insert(
+                        identity,
+                        KnownDevice {
+                            alias,
+                            category: None,
+                        },
+                    );
+                }
+            }
+        }
+        self.save()
+    }
+
+    /// Writes the entries back to the file, sorted by address. Comments in the original
+    /// file are not kept.
+    fn save(&self) -> Result<(), Box<dyn Error>> {
+        let mut writer = csv::WriterBuilder::new()
+            .flexible(true)
+            .from_path(&self.path)?;
+        for (address, entry) in &self.entries {
+            let mut record = vec![address.as_str(), entry.alias.as_deref().unwrap_or_default()];
+            if let Some(category) = entry.category {
+                record.push(category.label());
+            }
+            writer.write_record(record)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,61 @@
+#[macro_use]
+extern crate lazy_static;
+
+mod adapters;
+mod app;
+mod auto;
+mod capture;
+mod category;
+mod channel_health;
+mod cli;
+mod commands;
+mod company_codes;
+#[cfg(feature = "db")]
+mod db;
+pub mod decoders;
+mod diff;
+pub mod error;
+mod event_log;
+mod headless;
+#[cfg(feature = "http-api")]
+mod http_api;
+#[cfg(feature = "influx")]
+mod influx;
+mod intake;
+mod inventory;
+mod known_devices;
+mod line_format;
+#[cfg(feature = "mqtt")]
+mod mqtt;
+mod oneshot;
+mod oui_codes;
+mod pause_changes;
+mod redact;
+mod rssi_history;
+mod run;
+mod scan;
+pub mod scanner;
+#[cfg(feature = "device-serde")]
+pub mod serialization;
+mod service_names;
+mod session;
+pub mod source;
+mod state;
+pub mod structs;
+mod syslog;
+pub mod utils;
+mod vendor_db;
+mod viewer;
+mod wait_for;
+mod watch;
+mod widgets;
+#[cfg(feature = "websocket")]
+mod ws;
+
+pub use app::DeviceData;
+pub use decoders::{Decoder, DecoderRegistry};
+pub use error::BluscanError;
+pub use run::run;
+pub use scanner::{DeviceUpdate, ScanConfig, ScanConfigBuilder, ScanConfigError, Scanner};
+pub use source::{DeviceSource, MockSource, ScanCommand};
+pub use structs::{DecodedField, DeviceInfo};
@@ -0,0 +1,325 @@
+use uuid::Uuid;
+
+use crate::{
+    decoders::{device_tags, DecoderRegistry},
+    structs::DeviceInfo,
+    utils::{bytes_to_hex, company_name, format_duration, slug},
+};
+
+/// A device property a placeholder stands for.
+#[derive(Clone, Copy)]
+enum Field {
+    Id,
+    Address,
+    AddressType,
+    Name,
+    Rssi,
+    TxPower,
+    Company,
+    Vendor,
+    ManufacturerData,
+    Services,
+    SolicitedServices,
+    ServiceData,
+    Class,
+    Type,
+    Tags,
+    Decoded,
+    DetectedAt,
+    FirstSeen,
+    LastSeen,
+    Time,
+    Dwell,
+    Raw,
+}
+
+/// The placeholders `--format` accepts, besides the decoded `<decoder>.<field>` values.
+const FIELDS: [(&str, Field); 22] = [
+    ("id", Field::Id),
+    ("address", Field::Address),
+    ("address_type", Field::AddressType),
+    ("name", Field::Name),
+    ("rssi", Field::Rssi),
+    ("tx_power", Field::TxPower),
+    ("company", Field::Company),
+    ("vendor", Field::Vendor),
+    ("manufacturer_data", Field::ManufacturerData),
+    ("services", Field::Services),
+    ("solicited_services", Field::SolicitedServices),
+    ("service_data", Field::ServiceData),
+    ("class", Field::Class),
+    ("type", Field::Type),
+    ("tags", Field::Tags),
+    ("decoded", Field::Decoded),
+    ("detected_at", Field::DetectedAt),
+    ("first_seen", Field::FirstSeen),
+    ("last_seen", Field::LastSeen),
+    ("time", Field::Time),
+    ("dwell", Field::Dwell),
+    ("raw", Field::Raw),
+];
+
+enum Part {
+    Literal(String),
+    Field(Field),
+    /// A field of a decoder's output, by the decoder name and the field's key.
+    Decoded {
+        decoder: String,
+        key: String,
+    },
+}
+
+/// A line template given with `--format`, such as `{address}\t{name}\t{rssi}`. Placeholders
+/// in braces are replaced by the device's values, `{{` and `}}` stand for literal braces,
+/// and `\t`, `\n` and `\\` for a tab, a line break and a backslash. Absent values, such as
+/// the RSSI of a device that reported none, are left empty.
+pub struct LineFormat {
+    parts: Vec<Part>,
+}
+
+impl LineFormat {
+    /// Parses a template. `decoders` holds the decoder names `<decoder>.<field>`
+    /// placeholders may refer to. Returns an error listing the valid placeholders if one is
+    /// unknown.
+    pub fn parse(template: &str, decoders: &[&str]) -> Result<Self, String> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => match chars.peek() {
+                    Some('t') => literal.push('\t'),
+                    Some('n') => literal.push('\n'),
+                    Some('\\') => literal.push('\\'),
+                    _ => {
+                        literal.push('\\');
+                        continue;
+                    }
+                },
+                '{' if chars.peek() == Some(&'{') => literal.push('{'),
+                '}' if chars.peek() == Some(&'}') => literal.push('}'),
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err(format!("unclosed placeholder '{{{}'", name)),
+                        }
+                    }
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(placeholder(&name, decoders)?);
+                    continue;
+                }
+                c => {
+                    literal.push(c);
+                    continue;
+                }
+            }
+            // The second character of an escape sequence.
+            chars.next();
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        Ok(Self { parts })
+    }
+
+    /// Formats a line for the device, without a trailing line break.
+    pub fn render(&self, device: &DeviceInfo, decoders: &DecoderRegistry) -> String {
+        let mut line = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => line.push_str(text),
+                Part::Field(field) => line.push_str(&value(*field, device, decoders)),
+                Part::Decoded { decoder, key } => {
+                    if let Some(field) = decoders
+                        .decode_with(decoder, device)
+                        .into_iter()
+                        .find(|field| field_key(decoder, &field.label) == *key)
+                    {
+                        line.push_str(&field.value);
+                    }
+                }
+            }
+        }
+        line
+    }
+}
+
+/// Resolves the name between the braces of a placeholder.
+fn placeholder(name: &str, decoders: &[&str]) -> Result<Part, String> {
+    if let Some((_, field)) = FIELDS.iter().find(|(field, _)| *field == name) {
+        return Ok(Part::Field(*field));
+    }
+    if let Some((decoder, key)) = name.split_once('.') {
+        if decoders.contains(&decoder) && !key.is_empty() {
+            return Ok(Part::Decoded {
+                decoder: decoder.to_string(),
+                key: key.to_string(),
+            });
+        }
+    }
+    let names: Vec<&str> = FIELDS.iter().map(|(name, _)| *name).collect();
+    Err(format!(
+        "unknown placeholder '{{{}}}', expected one of: {}, or <decoder>.<field> with a decoder of: {}",
+        name,
+        names.join(", "),
+        decoders.join(", ")
+    ))
+}
+
+/// Returns the key a decoded field is referred to by: its label in lowercase with words
+/// joined by underscores, without the decoder name it may start with. The iBeacon
+/// decoder's `iBeacon UUID` is `uuid`, so `{ibeacon.uuid}`.
+fn field_key(decoder: &str, label: &str) -> String {
+    let key = slug(label);
+    let prefix = format!("{}_", slug(decoder));
+    match key.strip_prefix(&prefix) {
+        Some(rest) => rest.to_string(),
+        None => key,
+    }
+}
+
+fn value(field: Field, device: &DeviceInfo, decoders: &DecoderRegistry) -> String {
+    let uuids = |uuids: &[Uuid]| {
+        uuids
+            .iter()
+            .map(Uuid::to_string)
+            .collect::<Vec<String>>()
+            .join(",")
+    };
+    let hex = |bytes: &[u8]| bytes_to_hex(bytes).replace(' ', "");
+    match field {
+        Field::Id => device.id.clone(),
+        Field::Address => device.address_or_id(),
+        Field::AddressType => device.address_type_label().to_string(),
+        Field::Name => device.name.clone().unwrap_or_default(),
+        Field::Rssi => reading(device.rssi),
+        Field::TxPower => reading(device.tx_power),
+        Field::Company => company_name(&device.manufacturer_data).unwrap_or_default(),
+        Field::Vendor => device.vendor().unwrap_or_default().to_string(),
+        Field::ManufacturerData => {
+            let mut entries: Vec<_> = device.manufacturer_data.iter().collect();
+            entries.sort_by_key(|(company, _)| **company);
+            entries
+                .into_iter()
+                .map(|(company, data)| format!("0x{:04X}:{}", company, hex(data)))
+                .collect::<Vec<String>>()
+                .join(",")
+        }
+        Field::Services => uuids(&device.services),
+        Field::SolicitedServices => uuids(&device.solicited_services),
+        Field::ServiceData => {
+            let mut entries: Vec<_> = device.service_data.iter().collect();
+            entries.sort_by_key(|(uuid, _)| **uuid);
+            entries
+                .into_iter()
+                .map(|(uuid, data)| format!("{}:{}", uuid, hex(data)))
+                .collect::<Vec<String>>()
+                .join(",")
+        }
+        Field::Class => device
+            .class
+            .map(|class| format!("0x{:06X}", class))
+            .unwrap_or_default(),
+        Field::Type => device.device_type_label().to_string(),
+        Field::Tags => device_tags(device).join(","),
+        Field::Decoded => decoders
+            .decode(device)
+            .into_iter()
+            .map(|field| format!("{}: {}", field.label, field.value))
+            .collect::<Vec<String>>()
+            .join("; "),
+        Field::DetectedAt => device.detected_at.clone(),
+        Field::FirstSeen => device.first_seen.to_rfc3339(),
+        Field::LastSeen => device.last_seen.to_rfc3339(),
+        Field::Time => device.last_seen.format("%H:%M:%S").to_string(),
+        Field::Dwell => format_duration(device.dwell()),
+        Field::Raw => hex(&device.raw_advertisement),
+    }
+}
+
+/// Returns a dBm reading, or an empty string where the device reported none.
+fn reading(value: Option<i16>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn device() -> DeviceInfo {
+        let mut ibeacon = vec![0x02, 0x15];
+        ibeacon.extend(0x00..0x10);
+        ibeacon.extend([0x00, 0x01, 0x00, 0x02, 0xC5]);
+        DeviceInfo {
+            address: "AA:BB:CC:DD:EE:01".to_string(),
+            name: Some("Lamp".to_string()),
+            rssi: Some(-60),
+            manufacturer_data: HashMap::from([(0x004C, ibeacon)]),
+            ..DeviceInfo::default()
+        }
+    }
+
+    fn render(template: &str) -> String {
+        let decoders = DecoderRegistry::with_builtins(HashMap::new());
+        LineFormat::parse(template, &decoders.names())
+            .unwrap()
+            .render(&device(), &decoders)
+    }
+
+    #[test]
+    fn placeholders_are_replaced_by_the_device_values() {
+        assert_eq!(
+            render("{address} {name} {rssi} {tx_power}|{company}"),
+            "AA:BB:CC:DD:EE:01 Lamp -60 |Apple, Inc."
+        );
+        assert_eq!(
+            render("{ibeacon.uuid} {ibeacon.major}/{ibeacon.minor} {ibeacon.unknown}."),
+            "00010203-0405-0607-0809-0a0b0c0d0e0f 1/2 ."
+        );
+    }
+
+    #[test]
+    fn escapes_and_literal_braces() {
+        assert_eq!(render(r"{address}\t{name}\n"), "AA:BB:CC:DD:EE:01\tLamp\n");
+        assert_eq!(render(r"a\\b \q"), r"a\b \q");
+        assert_eq!(render("{{{name}}}"), "{Lamp}");
+        assert_eq!(render(r"trailing\"), r"trailing\");
+    }
+
+    #[test]
+    fn unknown_placeholders_list_the_valid_names() {
+        let error = LineFormat::parse("{address} {bogus}", &["ibeacon", "ruuvi"])
+            .err()
+            .unwrap();
+        assert!(
+            error.starts_with(
+                "unknown placeholder '{bogus}', expected one of: id, address, address_type,"
+            ),
+            "{}",
+            error
+        );
+        assert!(
+            error.ends_with("raw, or <decoder>.<field> with a decoder of: ibeacon, ruuvi"),
+            "{}",
+            error
+        );
+        for template in ["{govee.temperature}", "{ibeacon.}"] {
+            assert!(
+                LineFormat::parse(template, &["ibeacon"]).is_err(),
+                "{}",
+                template
+            );
+        }
+        assert_eq!(
+            LineFormat::parse("{name", &[]).err().unwrap(),
+            "unclosed placeholder '{name'"
+        );
+    }
+}
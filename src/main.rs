@@ -1,39 +1,9 @@
-#[macro_use]
-extern crate lazy_static;
-use crate::viewer::viewer;
-use crossterm::{
-    event::{DisableMouseCapture, EnableMouseCapture},
-    execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
-};
-use ratatui::{backend::CrosstermBackend, Terminal};
-use std::{error::Error, io};
-
-mod app;
-mod company_codes;
-mod scan;
-mod structs;
-mod utils;
-mod viewer;
-mod widgets;
-
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn Error>> {
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
-
-    let mut app = app::App::new();
-    app.scan().await;
-    viewer(&mut terminal, &mut app).await?;
-
-    disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
-    Ok(())
+async fn main() {
+    if let Err(e) = btlescan::run().await {
+        if !e.is_outcome() {
+            eprintln!("error: {}", e);
+        }
+        std::process::exit(e.exit_code());
+    }
 }
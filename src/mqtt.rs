@@ -0,0 +1,262 @@
+use std::{collections::HashSet, sync::Mutex, time::Duration};
+
+use rumqttc::{AsyncClient, ConnectionError, Event, LastWill, MqttOptions, Packet, QoS, Transport};
+use serde_json::{json, Map, Value};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{
+    app::DeviceData,
+    cli::MqttConfig,
+    structs::{DecodedField, MqttStatus},
+    utils::slug,
+};
+
+/// The topic prefix Home Assistant listens on for MQTT discovery messages.
+const HASS_DISCOVERY_PREFIX: &str = "homeassistant";
+
+/// How many messages may wait for the broker before new ones are dropped.
+const QUEUE_CAPACITY: usize = 1000;
+
+/// How long to wait before reconnecting after the connection to the broker fails.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Publishes device updates and availability to an MQTT broker. Publishing never blocks:
+/// messages are queued for a background task that keeps the connection up, reconnecting
+/// when it drops, and reports the connection status as `DeviceData::MqttStatus`.
+pub struct MqttPublisher {
+    client: AsyncClient,
+    prefix: String,
+    /// Whether Home Assistant discovery messages are published, with `--hass-discovery`.
+    hass: bool,
+    /// The Home Assistant entities announced so far, by unique id.
+    announced: Mutex<HashSet<String>>,
+    /// The devices announced as Home Assistant device trackers.
+    trackers: Mutex<HashSet<String>>,
+}
+
+impl MqttPublisher {
+    /// Connects to the broker in the background.
+    pub fn start(config: &MqttConfig, tx: UnboundedSender<DeviceData>) -> Result<Self, String> {
+        let (host, port, tls) = parse_url(&config.url)?;
+        let prefix = config.topic_prefix.trim_end_matches('/').to_string();
+        let status_topic = format!("{}/status", prefix);
+        let mut options = MqttOptions::new(format!("btlescan-{}", std::process::id()), host, port);
+        options.set_keep_alive(Duration::from_secs(30));
+        options.set_last_will(LastWill::new(
+            &status_topic,
+            "offline",
+            QoS::AtLeastOnce,
+            true,
+        ));
+        if let Some(username) = &config.username {
+            options.set_credentials(username, config.password.clone().unwrap_or_default());
+        }
+        if tls {
+            options.set_transport(Transport::tls_with_default_config());
+        }
+        let (client, mut eventloop) = AsyncClient::new(options, QUEUE_CAPACITY);
+        let announcer = client.clone();
+        tokio::spawn(async move {
+            let _ = tx.send(DeviceData::MqttStatus(MqttStatus::Connecting));
+            loop {
+                match eventloop.poll().await {
+                    Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                        let _ =
+                            announcer.try_publish(&status_topic, QoS::AtLeastOnce, true, "online");
+                        let _ = tx.send(DeviceData::MqttStatus(MqttStatus::Connected));
+                    }
+                    Ok(_) => {}
+                    // The client was dropped, so btlescan is exiting.
+                    Err(ConnectionError::RequestsDone) => break,
+                    Err(e) => {
+                        let _ = tx.send(DeviceData::MqttStatus(MqttStatus::Disconnected(
+                            e.to_string(),
+                        )));
+                        // Polling again reconnects.
+                        tokio::time::sleep(RECONNECT_DELAY).await;
+                    }
+                }
+            }
+        });
+        Ok(Self {
+            client,
+            prefix,
+            hass: config.hass_discovery,
+            announced: Mutex::default(),
+            trackers: Mutex::default(),
+        })
+    }
+
+    /// Publishes the current state of a device to `<prefix>/<identity>`, retained so
+    /// subscribers get the latest state on subscribing. Dropped if the queue is full.
+    pub fn publish_device(&self, identity: &str, payload: Vec<u8>) {
+        let _ = self.client.try_publish(
+            format!("{}/{}", self.prefix, identity),
+            QoS::AtMostOnce,
+            true,
+            payload,
+        );
+    }
+
+    /// Publishes whether a device is present to `<prefix>/<identity>/availability`, and
+    /// `home` or `not_home` to `<prefix>/<identity>/presence` for device trackers.
+    pub fn publish_availability(&self, identity: &str, online: bool) {
+        let _ = self.client.try_publish(
+            format!("{}/{}/availability", self.prefix, identity),
+            QoS::AtLeastOnce,
+            true,
+            if online { "online" } else { "offline" },
+        );
+        if self.trackers.lock().unwrap().contains(identity) {
+            let _ = self.client.try_publish(
+                format!("{}/{}/presence", self.prefix, identity),
+                QoS::AtLeastOnce,
+                true,
+                if online { "home" } else { "not_home" },
+            );
+        }
+    }
+
+    /// With `--hass-discovery`, announces a watched device to Home Assistant as a device
+    /// tracker, once per session. Its presence follows `publish_availability`.
+    pub fn announce_tracker(&self, identity: &str, name: &str, address: Option<&str>) {
+        if !self.hass {
+            return;
+        }
+        let unique_id = format!("btlescan_{}_tracker", slug(identity));
+        if !self.announced.lock().unwrap().insert(unique_id.clone()) {
+            return;
+        }
+        self.trackers.lock().unwrap().insert(identity.to_string());
+        let config = json!({
+            // A null name gives the entity the name of its device.
+            "name": null,
+            "unique_id": unique_id,
+            "state_topic": format!("{}/{}/presence", self.prefix, identity),
+            "payload_home": "home",
+            "payload_not_home": "not_home",
+            "source_type": "bluetooth_le",
+            "availability_topic": format!("{}/status", self.prefix),
+            "device": self.hass_device(identity, name, address),
+        });
+        self.publish_discovery(
+            &format!("device_tracker/btlescan_{}", slug(identity)),
+            config,
+        );
+    }
+
+    /// With `--hass-discovery`, publishes decoded sensor readings to
+    /// `<prefix>/<identity>/sensors` as a JSON object, announcing each reading to Home
+    /// Assistant as a sensor the first time it is seen. Sensors are unavailable while the
+    /// device is offline. Numeric readings carry their unit; other readings are text.
+    pub fn publish_sensors(
+        &self,
+        identity: &str,
+        name: &str,
+        address: Option<&str>,
+        fields: &[DecodedField],
+    ) {
+        if !self.hass {
+            return;
+        }
+        let state_topic = format!("{}/{}/sensors", self.prefix, identity);
+        let mut state = Map::new();
+        for field in fields {
+            // Readings a decoder could not make sense of are left out.
+            if field.value.starts_with('<') || field.value == "n/a" {
+                continue;
+            }
+            let key = slug(&field.label);
+            let (value, unit) = match field.value.split_once(' ') {
+                Some((number, unit)) => match number.parse::<f64>() {
+                    Ok(number) => (json!(number), Some(unit)),
+                    Err(_) => (json!(field.value), None),
+                },
+                None => match field.value.parse::<f64>() {
+                    Ok(number) => (json!(number), None),
+                    Err(_) => (json!(field.value), None),
+                },
+            };
+            let unique_id = format!("btlescan_{}_{}", slug(identity), key);
+            if self.announced.lock().unwrap().insert(unique_id.clone()) {
+                let mut config = json!({
+                    "name": field.label,
+                    "unique_id": unique_id,
+                    "state_topic": state_topic,
+                    "value_template": format!("{{{{ value_json.{} }}}}", key),
+                    "availability": [
+                        { "topic": format!("{}/status", self.prefix) },
+                        { "topic": format!("{}/{}/availability", self.prefix, identity) },
+                    ],
+                    "availability_mode": "all",
+                    "device": self.hass_device(identity, name, address),
+                });
+                if value.is_number() {
+                    config["state_class"] = json!("measurement");
+                }
+                if let Some(unit) = unit {
+                    config["unit_of_measurement"] = json!(unit);
+                }
+                self.publish_discovery(
+                    &format!("sensor/btlescan_{}/{}", slug(identity), key),
+                    config,
+                );
+            }
+            state.insert(key, value);
+        }
+        if !state.is_empty() {
+            let _ = self.client.try_publish(
+                state_topic,
+                QoS::AtMostOnce,
+                true,
+                Value::Object(state).to_string(),
+            );
+        }
+    }
+
+    /// Describes a device for Home Assistant's device registry, which groups its entities.
+    /// The MAC address, where known, lets Home Assistant merge it with other integrations
+    /// that see the same device.
+    fn hass_device(&self, identity: &str, name: &str, address: Option<&str>) -> Value {
+        let mut device = json!({
+            "identifiers": [format!("btlescan_{}", slug(identity))],
+            "name": name,
+        });
+        if let Some(address) = address {
+            device["connections"] = json!([["bluetooth", address]]);
+        }
+        device
+    }
+
+    /// Publishes a retained discovery config to `homeassistant/<path>/config`.
+    fn publish_discovery(&self, path: &str, config: Value) {
+        let _ = self.client.try_publish(
+            format!("{}/{}/config", HASS_DISCOVERY_PREFIX, path),
+            QoS::AtLeastOnce,
+            true,
+            config.to_string(),
+        );
+    }
+}
+
+/// Splits a broker URL such as `mqtts://broker.local:8883` into its host, port, and whether
+/// TLS is used. The port defaults to 1883, or 8883 with TLS.
+fn parse_url(url: &str) -> Result<(String, u16, bool), String> {
+    let invalid = || format!("invalid MQTT URL '{}', expected mqtt://HOST[:PORT]", url);
+    let (tls, rest) = if let Some(rest) = url.strip_prefix("mqtts://") {
+        (true, rest)
+    } else if let Some(rest) = url.strip_prefix("mqtt://") {
+        (false, rest)
+    } else {
+        return Err(invalid());
+    };
+    let rest = rest.trim_end_matches('/');
+    let (host, port) = match rest.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse().map_err(|_| invalid())?),
+        None => (rest, if tls { 8883 } else { 1883 }),
+    };
+    if host.is_empty() {
+        return Err(invalid());
+    }
+    Ok((host.to_string(), port, tls))
+}
@@ -0,0 +1,209 @@
+use std::{
+    error::Error,
+    io::{self, Write},
+    time::Duration,
+};
+
+use futures::future;
+
+use crate::{
+    app::{App, DeviceData},
+    decoders::device_tags,
+    error::BluscanError,
+    structs::ProximityZone,
+    utils::{device_zone, format_reading, rssi_percent},
+};
+
+/// The widest a column of the plain-text table may grow; longer values are truncated.
+const MAX_COLUMN_WIDTH: usize = 40;
+
+/// Merges scan results into the device list until `duration` has elapsed or Ctrl+C is
+/// pressed, then stops the scan. The application may be fed by any source.
+pub async fn collect(app: &mut App, duration: Duration) -> Result<(), BluscanError> {
+    let deadline = tokio::time::sleep(duration);
+    let interrupted = tokio::signal::ctrl_c();
+    tokio::pin!(deadline, interrupted);
+    loop {
+        let api_query = async {
+            match app.api_queries.as_mut() {
+                Some(queries) => queries.recv().await,
+                None => future::pending().await,
+            }
+        };
+        tokio::select! {
+            Some(query) = api_query => app.answer_api_query(query),
+            data = app.rx.recv() => match data {
+                Some(DeviceData::DeviceBatch {
+                    devices,
+                    observations,
+                }) => app.merge_batch(devices, observations),
+                Some(DeviceData::Error(error)) => eprintln!("error: {}", error),
+                Some(DeviceData::ScanFailed(e)) => return Err(e),
+                Some(_) => {}
+                None => return Err(BluscanError::ChannelClosed),
+            },
+            _ = &mut deadline => break,
+            _ = &mut interrupted => break,
+        }
+    }
+    app.stop_scan().await;
+    Ok(())
+}
+
+/// Writes the devices that pass the filters to `out`, as the JSON export when `json` is
+/// set, one line per device when a `--format` template is set, and as a plain-text table
+/// with the interface's columns otherwise.
+pub fn print_devices(
+    app: &App,
+    json: bool,
+    out: &mut impl Write,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if json {
+        serde_json::to_writer_pretty(&mut *out, &app.devices_export(&app.visible_devices()))?;
+        writeln!(out)?;
+        return Ok(());
+    }
+    if let Some(format) = &app.line_format {
+        for device in app.visible_devices() {
+            writeln!(out, "{}", format.render(device, &app.decoders))?;
+        }
+        return Ok(());
+    }
+    write!(out, "{}", devices_text(app))?;
+    Ok(())
+}
+
+/// Formats the devices that pass the filters as a plain-text table with the interface's
+/// columns, in the order they are displayed, for pasting where monospace text is shown.
+pub fn devices_text(app: &App) -> String {
+    let devices = app.visible_devices();
+    let mut header = vec![
+        app.identity_display.header(),
+        "Name",
+        "Type",
+        "Category",
+        "TX Power",
+        if app.rssi_percent { "Signal" } else { "RSSI" },
+        "Tags",
+    ];
+    if app.show_vendor {
+        header.insert(1, "Vendor");
+    }
+    if app.group_by_zone {
+        header.insert(0, "Zone");
+    }
+    let rows: Vec<Vec<String>> = devices
+        .iter()
+        .map(|device| {
+            let mut row = vec![
+                device.display_identity(app.identity_display),
+                match (app.alias_for(device), &device.name) {
+                    (Some(alias), Some(name)) => format!("{} ({})", alias, name),
+                    (Some(alias), None) => alias.to_string(),
+                    (None, _) => device.display_name(app.name_fallback),
+                },
+                device.device_type_label().to_string(),
+                app.category_for(device).label().to_string(),
+                format_reading(device.tx_power),
+                match device.rssi.filter(|_| app.rssi_percent) {
+                    Some(rssi) => format!("{}%", rssi_percent(rssi)),
+                    None => format_reading(device.rssi),
+                },
+                device_tags(device).join(" "),
+            ];
+            if app.show_vendor {
+                row.insert(1, device.vendor().unwrap_or_default().to_string());
+            }
+            if app.group_by_zone {
+                row.insert(
+                    0,
+                    device_zone(device)
+                        .map_or("n/a", ProximityZone::label)
+                        .to_string(),
+                );
+            }
+            row
+        })
+        .collect();
+    format_table(&header, &rows)
+}
+
+/// Lays out rows under a header with every column padded to its widest value, capped at
+/// `MAX_COLUMN_WIDTH`. Widths are counted in characters.
+pub fn format_table(header: &[&str], rows: &[Vec<String>]) -> String {
+    let widths: Vec<usize> = header
+        .iter()
+        .enumerate()
+        .map(|(i, title)| {
+            rows.iter()
+                .map(|row| row[i].chars().count())
+                .chain([title.chars().count()])
+                .max()
+                .unwrap_or(0)
+                .min(MAX_COLUMN_WIDTH)
+        })
+        .collect();
+    let line = |cells: Vec<&str>| {
+        let padded: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", truncate(cell, *width), width = width))
+            .collect();
+        format!("{}\n", padded.join("  ").trim_end())
+    };
+    let mut table = line(header.to_vec());
+    let rule: Vec<String> = widths.iter().map(|width| "-".repeat(*width)).collect();
+    table.push_str(&line(rule.iter().map(String::as_str).collect()));
+    for row in rows {
+        table.push_str(&line(row.iter().map(String::as_str).collect()));
+    }
+    table
+}
+
+/// Shortens `value` to `width` characters, ending it with an ellipsis when cut.
+fn truncate(value: &str, width: usize) -> String {
+    if value.chars().count() <= width {
+        value.to_string()
+    } else {
+        let mut truncated: String = value.chars().take(width.saturating_sub(1)).collect();
+        truncated.push('…');
+        truncated
+    }
+}
+
+/// Collects scan results for `duration`, then prints them to stdout.
+pub async fn oneshot(app: &mut App, duration: Duration, json: bool) -> Result<(), BluscanError> {
+    collect(app, duration).await?;
+    print_devices(app, json, &mut io::stdout().lock()).map_err(BluscanError::Export)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn columns_are_padded_to_their_widest_value() {
+        let rows = vec![
+            vec!["AA:BB:CC:DD:EE:01".to_string(), "Lamp".to_string()],
+            vec!["1".to_string(), "Ünïcode".to_string()],
+        ];
+        assert_eq!(
+            format_table(&["Address", "Name"], &rows),
+            "Address            Name\n\
+             -----------------  -------\n\
+             AA:BB:CC:DD:EE:01  Lamp\n\
+             1                  Ünïcode\n"
+        );
+        assert_eq!(format_table(&["Address"], &[]), "Address\n-------\n");
+    }
+
+    #[test]
+    fn long_values_are_truncated_to_the_column_cap() {
+        let long = "x".repeat(MAX_COLUMN_WIDTH + 5);
+        let table = format_table(&["Name", "RSSI"], &[vec![long, "-60".to_string()]]);
+        let row = table.lines().nth(2).unwrap();
+        assert_eq!(row, format!("{}…  -60", "x".repeat(MAX_COLUMN_WIDTH - 1)));
+        assert_eq!(truncate("short", 5), "short");
+        assert_eq!(truncate("longer", 5), "long…");
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+
+lazy_static! {
+    /// Short vendor names for a selection of IEEE OUI assignments, keyed by the first three
+    /// octets of an address. Only vendors common among Bluetooth devices are included.
+    pub static ref OUI_VENDOR: HashMap<u32, &'static str> = {
+        HashMap::from([
+            (0x0000F0, "Samsung"),
+            (0x000393, "Apple"),
+            (0x0007AB, "Samsung"),
+            (0x0009BF, "Nintendo"),
+            (0x000A95, "Apple"),
+            (0x000AF7, "Broadcom"),
+            (0x000B57, "Silicon Labs"),
+            (0x000D3A, "Microsoft"),
+            (0x000D93, "Apple"),
+            (0x001018, "Broadcom"),
+            (0x0010FA, "Apple"),
+            (0x001124, "Apple"),
+            (0x00124B, "TI"),
+            (0x0012FB, "Samsung"),
+            (0x0013A9, "Sony"),
+            (0x001599, "Samsung"),
+            (0x001632, "Samsung"),
+            (0x0016CB, "Apple"),
+            (0x0017AB, "Nintendo"),
+            (0x0017D5, "Samsung"),
+            (0x0017F2, "Apple"),
+            (0x0019E3, "Apple"),
+            (0x0019FD, "Nintendo"),
+            (0x001B21, "Intel"),
+            (0x001B63, "Apple"),
+            (0x001CB3, "Apple"),
+            (0x001D4F, "Apple"),
+            (0x001E52, "Apple"),
+            (0x001E7D, "Samsung"),
+            (0x001EC2, "Apple"),
+            (0x001F20, "Logitech"),
+            (0x001F32, "Nintendo"),
+            (0x001FF3, "Apple"),
+            (0x002119, "Samsung"),
+            (0x0021E9, "Apple"),
+            (0x002241, "Apple"),
+            (0x0022AA, "Nintendo"),
+            (0x002312, "Apple"),
+            (0x002332, "Apple"),
+            (0x002339, "Samsung"),
+            (0x00236C, "Apple"),
+            (0x0023DF, "Apple"),
+            (0x002436, "Apple"),
+            (0x002454, "Samsung"),
+            (0x0024BE, "Sony"),
+            (0x002500, "Apple"),
+            (0x00254B, "Apple"),
+            (0x002566, "Samsung"),
+            (0x0025BC, "Apple"),
+            (0x002608, "Apple"),
+            (0x00264A, "Apple"),
+            (0x0026B0, "Apple"),
+            (0x0026BB, "Apple"),
+            (0x0050F2, "Microsoft"),
+            (0x00A050, "Cypress"),
+            (0x00E04C, "Realtek"),
+            (0x0452C7, "Bose"),
+            (0x08DF1F, "Bose"),
+            (0x18FE34, "Espressif"),
+            (0x1CBA8C, "TI"),
+            (0x240AC4, "Espressif"),
+            (0x246F28, "Espressif"),
+            (0x281878, "Microsoft"),
+            (0x286C07, "Xiaomi"),
+            (0x28CDC1, "Raspberry Pi"),
+            (0x2C41A1, "Bose"),
+            (0x2CCF67, "Raspberry Pi"),
+            (0x30AEA4, "Espressif"),
+            (0x34885D, "Logitech"),
+            (0x34B1F7, "TI"),
+            (0x3C5AB4, "Google"),
+            (0x3C71BF, "Espressif"),
+            (0x4C875D, "Bose"),
+            (0x546009, "Google"),
+            (0x588E81, "Silicon Labs"),
+            (0x5CCF7F, "Espressif"),
+            (0x600194, "Espressif"),
+            (0x60ABD2, "Bose"),
+            (0x640980, "Xiaomi"),
+            (0x680AE2, "Silicon Labs"),
+            (0x78A504, "TI"),
+            (0x7C1E52, "Microsoft"),
+            (0x807D3A, "Espressif"),
+            (0x8086F2, "Intel"),
+            (0x840D8E, "Espressif"),
+            (0x842E14, "Silicon Labs"),
+            (0x84F3EB, "Espressif"),
+            (0x90FD9F, "Silicon Labs"),
+            (0x98B6E9, "Nintendo"),
+            (0xA4C138, "Telink"),
+            (0xA4CF12, "Espressif"),
+            (0xB0B448, "TI"),
+            (0xB827EB, "Raspberry Pi"),
+            (0xBCDDC2, "Espressif"),
+            (0xCC50E3, "Espressif"),
+            (0xD83ADD, "Raspberry Pi"),
+            (0xDCA632, "Raspberry Pi"),
+            (0xE45F01, "Raspberry Pi"),
+            (0xECFABC, "Espressif"),
+            (0xF4F5D8, "Google"),
+            (0xF88FCA, "Google"),
+        ])
+    };
+}
@@ -0,0 +1,164 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+use crate::structs::{DeviceInfo, IdentityKey};
+
+/// How long after resuming devices are compared with the list as it was when paused. A
+/// device not heard from by then counts as gone.
+const CHANGE_WINDOW: Duration = Duration::from_secs(5);
+
+/// How long a device stays highlighted after it was found to have changed.
+const HIGHLIGHT_DURATION: Duration = Duration::from_secs(5);
+
+/// How far the RSSI has to move, in dB, to count as a change rather than noise.
+const RSSI_CHANGE_DB: i16 = 6;
+
+/// How many gone devices the summary names before it only counts them.
+const MAX_GONE_NAMES: usize = 3;
+
+/// How a device differs from the list as it was when the scan was paused.
+#[derive(Clone, Copy, PartialEq)]
+pub enum PauseChange {
+    /// The device was not in the list.
+    Appeared,
+    /// The device's name changed, or its RSSI moved by `RSSI_CHANGE_DB` or more.
+    Changed,
+}
+
+/// A device as the list showed it when the scan was paused.
+struct SnapshotEntry {
+    name: Option<String>,
+    rssi: Option<i16>,
+    /// The alias or name to list the device by if it is gone.
+    label: String,
+}
+
+/// The device list as it was when the scan was paused, keyed by identity.
+pub struct PauseSnapshot {
+    devices: HashMap<String, SnapshotEntry>,
+}
+
+impl PauseSnapshot {
+    /// Takes a snapshot of the devices by their identity under `key`, each listed by the
+    /// label `label` gives it.
+    pub fn take(
+        devices: &[DeviceInfo],
+        key: IdentityKey,
+        label: impl Fn(&DeviceInfo) -> String,
+    ) -> Self {
+        Self {
+            devices: devices
+                .iter()
+                .map(|device| {
+                    let entry = SnapshotEntry {
+                        name: device.name.clone(),
+                        rssi: device.rssi,
+                        label: label(device),
+                    };
+                    (device.get_id(key), entry)
+                })
+                .collect(),
+        }
+    }
+}
+
+/// What changed while the scan was paused: the devices updated soon after resuming are
+/// compared with the snapshot taken when it was paused, and those that differ are
+/// highlighted for a few seconds. Devices of the snapshot not heard from within the
+/// change window are listed as gone.
+pub struct PauseChanges {
+    snapshot: PauseSnapshot,
+    resumed_at: Instant,
+    /// The changed devices by identity, with when the change was found.
+    changes: HashMap<String, (PauseChange, Instant)>,
+    /// The identities of the snapshot's devices heard from since resuming.
+    seen: HashSet<String>,
+}
+
+impl PauseChanges {
+    pub fn new(snapshot: PauseSnapshot) -> Self {
+        Self {
+            snapshot,
+            resumed_at: Instant::now(),
+            changes: HashMap::new(),
+            seen: HashSet::new(),
+        }
+    }
+
+    /// Compares an update of the device with the snapshot, while the change window is open.
+    pub fn observe(&mut self, identity: &str, device: &DeviceInfo) {
+        if self.resumed_at.elapsed() >= CHANGE_WINDOW || self.changes.contains_key(identity) {
+            return;
+        }
+        let change = match self.snapshot.devices.get(identity) {
+            None => Some(PauseChange::Appeared),
+            Some(entry) => {
+                self.seen.insert(identity.to_string());
+                let renamed = device.name.is_some() && device.name != entry.name;
+                let moved = match (device.rssi, entry.rssi) {
+                    (Some(rssi), Some(before)) => (rssi - before).abs() >= RSSI_CHANGE_DB,
+                    _ => false,
+                };
+                (renamed || moved).then_some(PauseChange::Changed)
+            }
+        };
+        if let Some(change) = change {
+            self.changes
+                .insert(identity.to_string(), (change, Instant::now()));
+        }
+    }
+
+    /// Returns the devices to highlight, by identity.
+    pub fn highlights(&self) -> HashMap<String, PauseChange> {
+        self.changes
+            .iter()
+            .filter(|(_, (_, at))| at.elapsed() < HIGHLIGHT_DURATION)
+            .map(|(identity, (change, _))| (identity.clone(), *change))
+            .collect()
+    }
+
+    /// Returns `true` once the change window has closed and every highlight has faded.
+    pub fn is_expired(&self) -> bool {
+        self.resumed_at.elapsed() >= CHANGE_WINDOW + HIGHLIGHT_DURATION
+    }
+
+    /// A summary for the status bar, such as `2 new, 1 changed, 2 gone: Lamp, Tag`. Gone
+    /// devices are only known once the change window has closed.
+    pub fn summary(&self) -> String {
+        let count = |kind: PauseChange| {
+            self.changes
+                .values()
+                .filter(|(change, _)| *change == kind)
+                .count()
+        };
+        let mut summary = format!(
+            "{} new, {} changed",
+            count(PauseChange::Appeared),
+            count(PauseChange::Changed)
+        );
+        if self.resumed_at.elapsed() < CHANGE_WINDOW {
+            return summary;
+        }
+        let mut gone: Vec<&str> = self
+            .snapshot
+            .devices
+            .iter()
+            .filter(|(identity, _)| !self.seen.contains(*identity))
+            .map(|(_, entry)| entry.label.as_str())
+            .collect();
+        gone.sort_unstable();
+        summary.push_str(&format!(", {} gone", gone.len()));
+        if !gone.is_empty() {
+            summary.push_str(&format!(
+                ": {}",
+                gone[..gone.len().min(MAX_GONE_NAMES)].join(", ")
+            ));
+            if gone.len() > MAX_GONE_NAMES {
+                summary.push_str(&format!(" (+{})", gone.len() - MAX_GONE_NAMES));
+            }
+        }
+        summary
+    }
+}
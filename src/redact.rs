@@ -0,0 +1,65 @@
+use std::hash::{BuildHasher, RandomState};
+
+use crate::{structs::DeviceInfo, utils::normalize_address};
+
+/// Replaces device addresses, and optionally names, with keyed hashes before they are shown
+/// or exported. The key is drawn at random when btlescan starts, so a device keeps the same
+/// redacted value for the whole session but the real one cannot be recovered from it.
+#[derive(Clone)]
+pub struct Redactor {
+    key: RandomState,
+    names: bool,
+}
+
+impl Redactor {
+    /// Creates a redactor with a fresh key. Names are redacted too when `names` is set.
+    pub fn new(names: bool) -> Self {
+        Self {
+            key: RandomState::new(),
+            names,
+        }
+    }
+
+    /// Redacts an address by replacing its last three octets with a keyed hash of the whole
+    /// address. The first three octets are kept so the vendor prefix stays recognizable.
+    /// Addresses hidden by the platform are returned unchanged.
+    pub fn address(&self, address: &str) -> String {
+        let Some(normalized) = normalize_address(address) else {
+            return address.to_string();
+        };
+        let octets: Vec<&str> = normalized.split(':').collect();
+        if octets.len() != 6 {
+            return format!("{:012X}", self.key.hash_one(&normalized) & 0xFFFF_FFFF_FFFF);
+        }
+        let hash = self.key.hash_one(&normalized).to_be_bytes();
+        format!(
+            "{}:{:02X}:{:02X}:{:02X}",
+            octets[..3].join(":"),
+            hash[5],
+            hash[6],
+            hash[7]
+        )
+    }
+
+    /// Redacts the address and platform id of a device, and its name when names are
+    /// redacted.
+    pub fn redact(&self, device: &mut DeviceInfo) {
+        let redacted = self.address(&device.address);
+        device.id = match normalize_address(&device.address) {
+            // BlueZ ids embed the address, e.g. hci0/dev_AA_BB_CC_DD_EE_FF.
+            Some(address) => device
+                .id
+                .replace(&address, &redacted)
+                .replace(&address.replace(':', "_"), &redacted.replace(':', "_")),
+            // Other platforms use an opaque per-host id.
+            None => format!("{:016x}", self.key.hash_one(&device.id)),
+        };
+        device.address = redacted;
+        if self.names {
+            device.name = device
+                .name
+                .as_ref()
+                .map(|name| format!("device-{:06x}", self.key.hash_one(name) & 0xFF_FFFF));
+        }
+    }
+}
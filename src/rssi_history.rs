@@ -0,0 +1,66 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    error::Error,
+    path::Path,
+};
+
+/// Keeps every timestamped RSSI reading of each device, up to `depth` readings per device,
+/// so the session can be exported as a time series. The oldest readings are dropped first.
+pub struct RssiHistory {
+    depth: usize,
+    samples: HashMap<String, VecDeque<(chrono::DateTime<chrono::Local>, i16)>>,
+}
+
+impl RssiHistory {
+    /// Creates an empty history keeping at most `depth` readings per device.
+    pub fn new(depth: usize) -> Self {
+        Self {
+            depth,
+            samples: HashMap::new(),
+        }
+    }
+
+    /// Records a reading of the device with the given identity.
+    pub fn record(&mut self, identity: &str, at: chrono::DateTime<chrono::Local>, rssi: i16) {
+        let samples = self.samples.entry(identity.to_string()).or_default();
+        if samples.len() == self.depth {
+            samples.pop_front();
+        }
+        samples.push_back((at, rssi));
+    }
+
+    /// Returns the readings of the device with the given identity, oldest first.
+    pub fn samples(
+        &self,
+        identity: &str,
+    ) -> impl Iterator<Item = &(chrono::DateTime<chrono::Local>, i16)> {
+        self.samples.get(identity).into_iter().flatten()
+    }
+
+    /// Writes the readings to a long-format CSV file with `identity`, `timestamp` and `rssi`
+    /// columns, grouped by device in time order. When `only` is given, just those devices
+    /// are written. Returns the number of readings written.
+    pub fn write_csv(
+        &self,
+        path: &Path,
+        only: Option<&HashSet<String>>,
+    ) -> Result<usize, Box<dyn Error>> {
+        let mut identities: Vec<&String> = self
+            .samples
+            .keys()
+            .filter(|identity| only.is_none_or(|only| only.contains(*identity)))
+            .collect();
+        identities.sort();
+        let mut wtr = csv::Writer::from_path(path)?;
+        wtr.write_record(["identity", "timestamp", "rssi"])?;
+        let mut written = 0;
+        for identity in identities {
+            for (at, rssi) in &self.samples[identity] {
+                wtr.write_record([identity, &at.to_rfc3339(), &rssi.to_string()])?;
+                written += 1;
+            }
+        }
+        wtr.flush()?;
+        Ok(written)
+    }
+}
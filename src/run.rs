@@ -0,0 +1,489 @@
+#[cfg(feature = "db")]
+use crate::db;
+use crate::error::BluscanError;
+#[cfg(feature = "http-api")]
+use crate::http_api;
+#[cfg(feature = "influx")]
+use crate::influx;
+#[cfg(feature = "mqtt")]
+use crate::mqtt;
+use crate::scanner::ScanConfig;
+use crate::viewer::{viewer, TerminalEvents};
+#[cfg(feature = "websocket")]
+use crate::ws;
+use crate::{
+    adapters, app, auto, capture, cli, decoders, diff, event_log, headless, intake, known_devices,
+    line_format, oneshot, redact, session, source, syslog, utils::normalize_address, vendor_db,
+    wait_for,
+};
+use btleplug::api::ScanFilter;
+use crossterm::{
+    event::{DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{backend::CrosstermBackend, Terminal};
+use std::{
+    fmt::Display,
+    io,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+/// Runs the btlescan command line: a subcommand, one of the headless modes, or the
+/// interface. The error says what `main` prints and the status it exits with; the terminal
+/// has already been restored when it is returned.
+pub async fn run() -> Result<(), BluscanError> {
+    let args = match cli::Cli::parse_command() {
+        cli::Command::Diff(args) => {
+            return match diff::run(&args) {
+                Ok(false) => Ok(()),
+                Ok(true) => Err(BluscanError::NoMatch),
+                Err(e) => Err(BluscanError::Subcommand(e.to_string())),
+            }
+        }
+        cli::Command::WaitFor(args) => {
+            return match wait_for::run(&args).await {
+                Ok(wait_for::WaitOutcome::Found) => Ok(()),
+                Ok(wait_for::WaitOutcome::TimedOut) => Err(BluscanError::NoMatch),
+                Ok(wait_for::WaitOutcome::Interrupted) => Err(BluscanError::Interrupted),
+                Err(e) => Err(BluscanError::Subcommand(e.to_string())),
+            }
+        }
+        cli::Command::Adapters(args) => {
+            return adapters::run(&args)
+                .await
+                .map_err(|e| BluscanError::Subcommand(e.to_string()));
+        }
+        cli::Command::Scan(args) | cli::Command::Replay { args, .. } => args,
+    };
+    check_features(&args)?;
+    // The scan options are checked here, before anything is started or the terminal is
+    // taken over.
+    let mut scan_config = ScanConfig::builder()
+        .adapter(args.adapter)
+        .window(args.coalesce_window)
+        .identity(args.identity_key)
+        .services(args.scan_services.iter().copied());
+    if let Some(min_rssi) = args.min_rssi {
+        scan_config = scan_config.min_rssi(min_rssi);
+    }
+    let scan_config = match scan_config.build() {
+        Ok(scan_config) => scan_config,
+        Err(e) => return Err(BluscanError::Usage(e.to_string())),
+    };
+
+    let decoders = decoder_registry(&args)?;
+
+    let line_format = match &args.format {
+        Some(template) => match line_format::LineFormat::parse(template, &decoders.names()) {
+            Ok(format) => Some(format),
+            Err(e) => return Err(BluscanError::Usage(e.to_string())),
+        },
+        None => None,
+    };
+
+    #[cfg(feature = "db")]
+    if let Some(address) = &args.db_query {
+        // --db is required with --db-query, which the parser checks.
+        let path = args.db.as_ref().unwrap();
+        match db::print_history(path, address) {
+            Ok(true) => return Ok(()),
+            Ok(false) => return Err(BluscanError::NoMatch),
+            Err(e) => return Err(file_error("read", path, e)),
+        }
+    }
+
+    #[cfg(feature = "db")]
+    let db = match &args.db {
+        Some(path) => match db::DeviceDb::open(path) {
+            Ok(db) => Some(db),
+            Err(e) => return Err(file_error("open", path, e)),
+        },
+        None => None,
+    };
+
+    let event_log = match &args.log_file {
+        Some(path) => match event_log::EventLog::open(
+            path,
+            args.log_rotation.clone(),
+            decoder_registry(&args)?,
+        ) {
+            Ok(event_log) => Some(Arc::new(Mutex::new(event_log))),
+            Err(e) => return Err(file_error("open", path, e)),
+        },
+        None => None,
+    };
+
+    let capture = match &args.record {
+        Some(path) => match capture::CaptureWriter::open(path) {
+            Ok(capture) => Some(capture),
+            Err(e) => return Err(file_error("open", path, e)),
+        },
+        None => None,
+    };
+
+    let syslog = if args.syslog {
+        match syslog::SyslogWriter::open() {
+            Ok(syslog) => Some(syslog),
+            Err(e) => {
+                return Err(BluscanError::Usage(format!(
+                    "unable to connect to syslog: {}",
+                    e
+                )))
+            }
+        }
+    } else {
+        None
+    };
+
+    let known_devices = match &args.known_devices {
+        Some(path) => match known_devices::KnownDevices::load(path) {
+            Ok(loaded) => Some(loaded),
+            Err(e) => return Err(file_error("read", path, e)),
+        },
+        None => None,
+    };
+
+    let mut startup_warnings = Vec::new();
+    if let Some(path) = &args.oui_file {
+        match vendor_db::load_vendors(path) {
+            Ok((vendors, warnings)) => {
+                vendor_db::install_vendors(vendors);
+                startup_warnings.extend(warnings);
+            }
+            Err(e) => return Err(file_error("read", path, e)),
+        }
+    }
+    if let Some(path) = &args.company_file {
+        match vendor_db::load_companies(path) {
+            Ok((companies, warnings)) => {
+                vendor_db::install_companies(companies);
+                startup_warnings.extend(warnings);
+            }
+            Err(e) => return Err(file_error("read", path, e)),
+        }
+    }
+    if args.auto.is_some() {
+        for warning in &startup_warnings {
+            eprintln!("warning: {}", warning);
+        }
+    }
+
+    let recorder = args
+        .session_out
+        .as_ref()
+        .map(|_| Arc::new(Mutex::new(session::SessionRecorder::new())));
+
+    if let Some(auto) = &args.auto {
+        if recorder.is_none() && event_log.is_none() {
+            return auto::auto_log(auto).await;
+        }
+        // The session and the event log come from a scan in the background, through the
+        // same intake as the other modes.
+        let source = Box::new(intake::Intake {
+            source: Box::new(source::BtleplugSource {
+                filter: ScanFilter::default(),
+                ..scan_config.source()
+            }),
+            excluded_addresses: args
+                .excluded_addresses
+                .iter()
+                .filter_map(|address| normalize_address(address))
+                .collect(),
+            redactor: (args.redact || args.redact_names)
+                .then(|| redact::Redactor::new(args.redact_names)),
+            event_log: event_log.clone(),
+            identity: scan_config.identity(),
+        });
+        tokio::spawn(session::record_scan(
+            source,
+            recorder.clone(),
+            decoders,
+            scan_config.identity(),
+        ));
+        // The headless mode runs until interrupted, so the session is written on Ctrl+C.
+        let result = tokio::select! {
+            result = auto::auto_log(auto) => result,
+            _ = tokio::signal::ctrl_c() => Ok(()),
+        };
+        if let Some((recorder, path)) = recorder.zip(args.session_out.as_ref()) {
+            session::write_session(&recorder.lock().unwrap(), path);
+        }
+        if let Some(event_log) = &event_log {
+            if let Err(e) = event_log.lock().unwrap().flush() {
+                eprintln!("error: unable to write the event log: {}", e);
+            }
+        }
+        return result;
+    }
+
+    let mut app = app::App::new(&args, scan_config, decoders);
+    app.session_recorder = recorder.clone();
+    app.event_log = event_log;
+    app.capture = capture;
+    #[cfg(feature = "db")]
+    {
+        app.db = db;
+    }
+    app.line_format = line_format;
+    app.syslog = syslog;
+    if let Some((known, warnings)) = known_devices {
+        app.known_devices = Some(known);
+        startup_warnings.extend(warnings);
+    }
+    if !startup_warnings.is_empty() {
+        // The interface replaces the terminal, so it shows the warnings itself.
+        if args.headless || args.oneshot {
+            for warning in &startup_warnings {
+                eprintln!("warning: {}", warning);
+            }
+        } else {
+            app.error_message = format!("Skipped lines:\n{}", startup_warnings.join("\n"));
+            app.error_view = true;
+        }
+    }
+    #[cfg(feature = "influx")]
+    if let Some(config) = &args.influx {
+        app.influx = Some(influx::InfluxWriter::start(config.clone(), app.tx.clone()));
+    }
+    #[cfg(feature = "mqtt")]
+    if let Some(config) = &args.mqtt {
+        match mqtt::MqttPublisher::start(config, app.tx.clone()) {
+            Ok(publisher) => app.mqtt = Some(publisher),
+            Err(e) => return Err(BluscanError::Usage(e.to_string())),
+        }
+    }
+
+    #[cfg(feature = "http-api")]
+    if let Some(addr) = args.http_listen {
+        let listener = match tokio::net::TcpListener::bind(addr).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                return Err(BluscanError::Usage(format!(
+                    "unable to listen on {}: {}",
+                    addr, e
+                )))
+            }
+        };
+        let (queries, api_queries) = tokio::sync::mpsc::unbounded_channel();
+        app.api_queries = Some(api_queries);
+        tokio::spawn(http_api::serve(listener, args.http_token.clone(), queries));
+    }
+
+    #[cfg(feature = "websocket")]
+    if let Some(addr) = args.ws_listen {
+        match tokio::net::TcpListener::bind(addr).await {
+            Ok(listener) => {
+                app.ws = Some(ws::WsHub::start(listener, app.tx.clone()));
+                app.ws_clients = Some(0);
+            }
+            Err(e) => {
+                return Err(BluscanError::Usage(format!(
+                    "unable to listen on {}: {}",
+                    addr, e
+                )))
+            }
+        }
+    }
+
+    let replay = match &args.replay {
+        Some(path) => match source::ReplaySource::open(path, args.replay_speed) {
+            Ok(source) => Some(source),
+            Err(e) => return Err(file_error("read", path, e)),
+        },
+        None => None,
+    };
+
+    if args.oneshot {
+        start_scan(&mut app, replay, args.replay_speed).await;
+        let duration = args.duration.unwrap_or(cli::DEFAULT_ONESHOT_DURATION);
+        let result = oneshot::oneshot(&mut app, duration, args.json).await;
+        write_exports(&app, &args, recorder);
+        return result;
+    }
+
+    // The headless mode never touches the terminal, so there is nothing to restore.
+    if args.headless {
+        start_scan(&mut app, replay, args.replay_speed).await;
+        let result = headless::headless(&mut app, args.duration).await;
+        app.stop_scan().await;
+        write_exports(&app, &args, recorder);
+        return result;
+    }
+
+    // Restore the terminal before a panic message is printed, and save the session
+    // recorded so far.
+    let default_hook = std::panic::take_hook();
+    let panic_recorder = recorder.clone().zip(args.session_out.clone());
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = disable_raw_mode();
+        let _ = execute!(
+            io::stdout(),
+            LeaveAlternateScreen,
+            DisableMouseCapture,
+            DisableFocusChange
+        );
+        // The panicking thread may hold the lock, so the session is skipped then.
+        if let Some((recorder, path)) = &panic_recorder {
+            if let Ok(recorder) = recorder.try_lock() {
+                session::write_session(&recorder, path);
+            }
+        }
+        default_hook(info);
+    }));
+
+    let mut terminal = match setup_terminal() {
+        Ok(terminal) => terminal,
+        Err(e) => {
+            let _ = restore_terminal();
+            return Err(e);
+        }
+    };
+
+    start_scan(&mut app, replay, args.replay_speed).await;
+    let result = viewer(&mut terminal, &mut app, &mut TerminalEvents).await;
+
+    let restored = restore_terminal();
+    write_exports(&app, &args, recorder);
+    result.and(restored)
+}
+
+/// Switches the terminal to raw mode on the alternate screen, with mouse and focus
+/// reporting. Terminals that do not report focus changes ignore the request, and are
+/// redrawn at the normal rate.
+fn setup_terminal() -> Result<Terminal<CrosstermBackend<io::Stdout>>, BluscanError> {
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(
+        stdout,
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableFocusChange
+    )?;
+    Ok(Terminal::new(CrosstermBackend::new(stdout))?)
+}
+
+/// Leaves raw mode and the alternate screen.
+fn restore_terminal() -> Result<(), BluscanError> {
+    disable_raw_mode()?;
+    execute!(
+        io::stdout(),
+        LeaveAlternateScreen,
+        DisableMouseCapture,
+        DisableFocusChange
+    )?;
+    Ok(())
+}
+
+/// Rejects the options of integrations this build leaves out, naming the Cargo feature
+/// that provides them.
+fn check_features(args: &cli::Args) -> Result<(), BluscanError> {
+    let options = [
+        ("--db", "db", args.db.is_some(), cfg!(feature = "db")),
+        (
+            "--mqtt-url",
+            "mqtt",
+            args.mqtt.is_some(),
+            cfg!(feature = "mqtt"),
+        ),
+        (
+            "--http-listen",
+            "http-api",
+            args.http_listen.is_some(),
+            cfg!(feature = "http-api"),
+        ),
+        (
+            "--ws-listen",
+            "websocket",
+            args.ws_listen.is_some(),
+            cfg!(feature = "websocket"),
+        ),
+        (
+            "--desktop-notify",
+            "notifications",
+            args.desktop_notify,
+            cfg!(feature = "notifications"),
+        ),
+        (
+            "--influx-url",
+            "influx",
+            args.influx.is_some(),
+            cfg!(feature = "influx"),
+        ),
+        (
+            "--victron-key",
+            "victron",
+            !args.victron_keys.is_empty(),
+            cfg!(feature = "victron"),
+        ),
+    ];
+    match options
+        .into_iter()
+        .find(|(_, _, given, enabled)| *given && !enabled)
+    {
+        Some((option, feature, _, _)) => Err(BluscanError::Usage(format!(
+            "{} needs btlescan built with the `{}` feature",
+            option, feature
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Builds the decoders, with those disabled on the command line turned off.
+fn decoder_registry(args: &cli::Args) -> Result<decoders::DecoderRegistry, BluscanError> {
+    let mut decoders =
+        decoders::DecoderRegistry::with_builtins(args.victron_keys.iter().cloned().collect());
+    for name in &args.disabled_decoders {
+        decoders
+            .set_enabled(name, false)
+            .map_err(|e| BluscanError::Usage(e.to_string()))?;
+    }
+    Ok(decoders)
+}
+
+/// The usage error for a file given on the command line that could not be used, e.g.
+/// `unable to read known.csv: ...`.
+fn file_error(action: &str, path: &Path, e: impl Display) -> BluscanError {
+    BluscanError::Usage(format!("unable to {} {}: {}", action, path.display(), e))
+}
+
+/// Starts feeding the application, from the replay if one was given and from the adapter
+/// otherwise.
+async fn start_scan(app: &mut app::App, replay: Option<source::ReplaySource>, speed: f64) {
+    match replay {
+        Some(replay) => app.replay(replay, speed),
+        None => app.scan().await,
+    }
+}
+
+/// Writes the exports requested on the command line once scanning has ended.
+fn write_exports(
+    app: &app::App,
+    args: &cli::Args,
+    recorder: Option<Arc<Mutex<session::SessionRecorder>>>,
+) {
+    if let Some(path) = &args.csv_out {
+        let devices: Vec<_> = app.devices.iter().collect();
+        match app.write_devices_csv(path, &devices) {
+            Ok(()) => eprintln!("{} devices written to {}", devices.len(), path.display()),
+            Err(e) => eprintln!("error: unable to write {}: {}", path.display(), e),
+        }
+    }
+    if let Some(path) = &args.output {
+        let devices: Vec<_> = app.devices.iter().collect();
+        match app.write_devices_json(path, &devices) {
+            Ok(()) => eprintln!("{} devices written to {}", devices.len(), path.display()),
+            Err(e) => eprintln!("error: unable to write {}: {}", path.display(), e),
+        }
+    }
+    if let Some((history, path)) = app.rssi_history.as_ref().zip(args.rssi_log.as_ref()) {
+        match history.write_csv(path, None) {
+            Ok(count) => eprintln!("{} RSSI readings written to {}", count, path.display()),
+            Err(e) => eprintln!("error: unable to write {}: {}", path.display(), e),
+        }
+    }
+    if let Some((recorder, path)) = recorder.zip(args.session_out.as_ref()) {
+        session::write_session(&recorder.lock().unwrap(), path);
+    }
+}
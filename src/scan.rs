@@ -1,103 +1,341 @@
+use crate::adapters;
 use crate::app::DeviceData;
-use crate::structs::{Characteristic, DeviceInfo};
+use crate::error::BluscanError;
+use crate::source::ScanCommand;
+use crate::structs::{Characteristic, ConnectProgress, DeviceInfo};
 use btleplug::api::{
-    Central, CentralEvent, Manager as _, Peripheral, PeripheralProperties, ScanFilter,
+    Central, CentralEvent, CharPropFlags, Peripheral, PeripheralProperties, ScanFilter,
 };
-use btleplug::platform::Manager;
+use btleplug::platform::Adapter;
 use futures::StreamExt;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
-use tokio::sync::mpsc;
-use tokio::time::timeout;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, Notify};
+use tokio::time::{timeout, MissedTickBehavior};
 
-/// Scans for Bluetooth devices and sends the information to the provided `mpsc::Sender`.
-/// The scan can be paused by setting the `pause_signal` to `true`.
-pub async fn bluetooth_scan(tx: mpsc::UnboundedSender<DeviceData>, pause_signal: Arc<AtomicBool>) {
-    let manager = Manager::new().await.unwrap();
-    let adapters = manager.adapters().await.unwrap();
-    let central = adapters.into_iter().next().expect("No adapters found");
+/// Collects scan results between two sends. Repeated observations of the same device are
+/// merged into the latest one, while every observation is still counted.
+#[derive(Default)]
+struct Coalescer {
+    devices: Vec<DeviceInfo>,
+    observations: usize,
+}
+
+impl Coalescer {
+    fn push(&mut self, device: DeviceInfo) {
+        self.observations += 1;
+        // A scan reads a single adapter, where the platform id names one device.
+        match self.devices.iter_mut().find(|d| d.id == device.id) {
+            Some(existing) => *existing = device,
+            None => self.devices.push(device),
+        }
+    }
+
+    /// Sends the pending batch, if any, and starts a new one.
+    fn flush(&mut self, tx: &mpsc::UnboundedSender<DeviceData>) {
+        if self.observations > 0 {
+            let batch = std::mem::take(self);
+            let _ = tx.send(DeviceData::DeviceBatch {
+                devices: batch.devices,
+                observations: batch.observations,
+            });
+        }
+    }
+}
+
+/// How long the appearances read from BlueZ are trusted before a device missing from them
+/// makes them be read again.
+#[cfg(target_os = "linux")]
+const APPEARANCE_REFRESH: Duration = Duration::from_secs(5);
+
+/// The GAP Appearance of the devices BlueZ knows about, which btleplug does not report.
+#[cfg(target_os = "linux")]
+struct Appearances {
+    session: Option<bluez_async::BluetoothSession>,
+    known: std::collections::HashMap<String, u16>,
+    read_at: Option<Instant>,
+}
+
+#[cfg(target_os = "linux")]
+impl Appearances {
+    async fn new() -> Self {
+        Self {
+            session: bluez_async::BluetoothSession::new()
+                .await
+                .ok()
+                .map(|(_, session)| session),
+            known: std::collections::HashMap::new(),
+            read_at: None,
+        }
+    }
+
+    /// Returns the appearance of the device with the platform id `id`, reading the
+    /// appearances again if it is missing and they are older than `APPEARANCE_REFRESH`.
+    async fn get(&mut self, id: &str) -> Option<u16> {
+        let stale = self
+            .read_at
+            .is_none_or(|read_at| read_at.elapsed() >= APPEARANCE_REFRESH);
+        if !self.known.contains_key(id) && stale {
+            self.read_at = Some(Instant::now());
+            if let Some(session) = &self.session {
+                if let Ok(devices) = session.get_devices().await {
+                    self.known = devices
+                        .into_iter()
+                        .filter_map(|device| Some((device.id.to_string(), device.appearance?)))
+                        .collect();
+                }
+            }
+        }
+        self.known.get(id).copied()
+    }
+}
+
+/// The GAP Appearance of devices, which only BlueZ exposes; other platforms report none.
+#[cfg(not(target_os = "linux"))]
+struct Appearances;
+
+#[cfg(not(target_os = "linux"))]
+impl Appearances {
+    async fn new() -> Self {
+        Self
+    }
+
+    async fn get(&mut self, _id: &str) -> Option<u16> {
+        None
+    }
+}
+
+/// Scans for Bluetooth devices on the adapter at index `adapter` and sends the information
+/// to the provided `mpsc::Sender`.
+/// Results are coalesced and sent at most once per `window`; a zero window sends every
+/// result as it arrives. The scan can be paused by setting the `pause_signal` to `true`,
+/// and stops after flushing the pending results when `shutdown` is notified. A
+/// `ScanCommand::Restart` restarts the platform scan with a new filter, and
+/// `ScanCommand::DisconnectAll` disconnects what the adapter is connected to. Fails if there is
+/// no adapter or the scan cannot be started.
+pub async fn bluetooth_scan(
+    tx: mpsc::UnboundedSender<DeviceData>,
+    adapter: usize,
+    pause_signal: Arc<AtomicBool>,
+    window: Duration,
+    filter: ScanFilter,
+    shutdown: Arc<Notify>,
+    mut commands: mpsc::UnboundedReceiver<ScanCommand>,
+) -> Result<(), BluscanError> {
+    let central = adapters::adapter(adapter).await?;
 
     central
-        .start_scan(ScanFilter::default())
+        .start_scan(filter)
         .await
-        .expect("Scanning failure");
-    let mut events = central.events().await.unwrap();
+        .map_err(BluscanError::Discovery)?;
+    let mut events = central.events().await.map_err(BluscanError::Discovery)?;
+    let mut coalescer = Coalescer::default();
+    let mut ticker = tokio::time::interval(window.max(Duration::from_millis(1)));
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut commands_open = true;
+    let mut appearances = Appearances::new().await;
+
+    loop {
+        let event = tokio::select! {
+            event = events.next() => match event {
+                Some(event) => event,
+                None => break,
+            },
+            _ = ticker.tick() => {
+                coalescer.flush(&tx);
+                continue;
+            }
+            command = commands.recv(), if commands_open => {
+                match command {
+                    Some(ScanCommand::Restart(filter)) => {
+                        coalescer.flush(&tx);
+                        let result = match central.stop_scan().await {
+                            Ok(()) => central.start_scan(filter).await,
+                            Err(e) => Err(e),
+                        };
+                        let _ = tx.send(DeviceData::ScanReconfigured(
+                            result.map_err(|e| e.to_string()),
+                        ));
+                    }
+                    Some(ScanCommand::DisconnectAll) => {
+                        tokio::spawn(disconnect_all(tx.clone(), central.clone()));
+                    }
+                    None => commands_open = false,
+                }
+                continue;
+            }
+            _ = shutdown.notified() => break,
+        };
 
-    while let Some(event) = events.next().await {
         // Check the pause signal before processing the event
+        if pause_signal.load(Ordering::SeqCst) {
+            coalescer.flush(&tx);
+        }
         while pause_signal.load(Ordering::SeqCst) {
             tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
         }
 
-        if let CentralEvent::DeviceDiscovered(id) = event {
+        if let CentralEvent::DeviceDiscovered(id) | CentralEvent::DeviceUpdated(id) = event {
             if let Ok(device) = central.peripheral(&id).await {
-                let properties = device
-                    .properties()
-                    .await
-                    .unwrap()
-                    .unwrap_or(PeripheralProperties::default());
-
-                // Add the new device's information to the accumulated list
-                let device = DeviceInfo::new(
+                // A device whose properties cannot be read is skipped rather than ending the scan.
+                let Ok(properties) = device.properties().await else {
+                    continue;
+                };
+                let properties = properties.unwrap_or(PeripheralProperties::default());
+                let appearance = appearances.get(&device.id().to_string()).await;
+
+                // Add the new device's information to the pending batch
+                coalescer.push(DeviceInfo::new(
                     device.id().to_string(),
                     properties.local_name,
                     properties.tx_power_level,
                     properties.address.to_string(),
+                    properties.address_type,
                     properties.rssi,
                     properties.manufacturer_data,
                     properties.services,
+                    // btleplug does not expose Service Solicitation UUIDs on any platform.
+                    Vec::new(),
                     properties.service_data,
+                    properties.class,
+                    appearance,
                     device.clone(),
-                );
-
-                // Send a clone of the accumulated device information so far
-                let _ = tx.send(DeviceData::DeviceInfo(device));
+                ));
+                if window.is_zero() {
+                    coalescer.flush(&tx);
+                }
             }
         }
     }
+    coalescer.flush(&tx);
+    Ok(())
 }
 
+/// Returns the addresses of the local Bluetooth adapters, so the host's own reports can be
+/// left out of the device list. Only BlueZ exposes them; other platforms return none.
+#[cfg(target_os = "linux")]
+pub async fn adapter_addresses() -> Vec<String> {
+    let Ok((_, session)) = bluez_async::BluetoothSession::new().await else {
+        return Vec::new();
+    };
+    session
+        .get_adapters()
+        .await
+        .map(|adapters| {
+            adapters
+                .iter()
+                .map(|adapter| adapter.mac_address.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Returns the addresses of the local Bluetooth adapters, so the host's own reports can be
+/// left out of the device list. Only BlueZ exposes them; other platforms return none.
+#[cfg(not(target_os = "linux"))]
+pub async fn adapter_addresses() -> Vec<String> {
+    Vec::new()
+}
+
+/// The delay before the first connection retry. It doubles after every failed attempt.
+const INITIAL_CONNECT_BACKOFF: Duration = Duration::from_secs(1);
+
+/// The longest delay between two connection attempts.
+const MAX_CONNECT_BACKOFF: Duration = Duration::from_secs(30);
+
 /// Gets the characteristics of a Bluetooth device and returns them as a `Vec<Characteristic>`.
-/// The device is identified by its address or UUID.
+/// The device is identified by its address or UUID. Each connection attempt may take up to
+/// `connect_timeout`, and a failed one is retried up to `retries` times with exponential
+/// backoff. Progress is reported as `DeviceData::Connecting`, and exhausting the attempts
+/// as `DeviceData::ConnectFailed`.
 pub async fn get_characteristics(
     tx: mpsc::UnboundedSender<DeviceData>,
     peripheral: Arc<DeviceInfo>,
+    connect_timeout: Duration,
+    retries: u32,
 ) {
-    let duration = Duration::from_secs(10);
-    match &peripheral.device {
-        Some(device) => match timeout(duration, device.connect()).await {
+    let Some(device) = &peripheral.device else {
+        let _ = tx.send(DeviceData::ConnectFailed("Device not found".to_string()));
+        return;
+    };
+    let attempts = retries + 1;
+    let mut backoff = INITIAL_CONNECT_BACKOFF;
+    let mut error = String::new();
+    for attempt in 1..=attempts {
+        let _ = tx.send(DeviceData::Connecting(ConnectProgress {
+            attempt,
+            attempts,
+            deadline: Instant::now() + connect_timeout,
+            retrying: false,
+        }));
+        match timeout(connect_timeout, device.connect()).await {
             Ok(Ok(_)) => {
-                if let Some(device) = &peripheral.device {
-                    let characteristics = device.characteristics();
-                    let mut result = Vec::new();
-                    for characteristic in characteristics {
-                        result.push(Characteristic {
-                            uuid: characteristic.uuid,
-                            properties: characteristic.properties,
-                            descriptors: characteristic
-                                .descriptors
-                                .into_iter()
-                                .map(|d| d.uuid)
-                                .collect(),
-                            service: characteristic.service_uuid,
-                        });
-                    }
-                    let _ = tx.send(DeviceData::Characteristics(result));
-                }
-            }
-            Ok(Err(e)) => {
-                tx.send(DeviceData::Error(format!("Connection error: {}", e)))
-                    .unwrap();
+                let result = device
+                    .characteristics()
+                    .into_iter()
+                    .map(|characteristic| Characteristic {
+                        uuid: characteristic.uuid,
+                        properties: characteristic.properties,
+                        descriptors: characteristic
+                            .descriptors
+                            .into_iter()
+                            .map(|d| d.uuid)
+                            .collect(),
+                        service: characteristic.service_uuid,
+                    })
+                    .collect();
+                let _ = tx.send(DeviceData::Characteristics(result));
+                return;
             }
-            Err(_) => {
-                tx.send(DeviceData::Error("Connection timed out".to_string()))
-                    .unwrap();
+            Ok(Err(e)) => error = format!("connection error: {}", e),
+            Err(_) => error = "connection timed out".to_string(),
+        }
+        if attempt < attempts {
+            let _ = tx.send(DeviceData::Connecting(ConnectProgress {
+                attempt,
+                attempts,
+                deadline: Instant::now() + backoff,
+                retrying: true,
+            }));
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_CONNECT_BACKOFF);
+        }
+    }
+    let _ = tx.send(DeviceData::ConnectFailed(format!(
+        "Unable to connect after {} attempt(s): {}",
+        attempts, error
+    )));
+}
+
+/// Tears down every connection `central` holds: unsubscribes from the notifying
+/// characteristics and disconnects. Reports the devices that were disconnected, which is
+/// none when nothing was connected, and those that could not be.
+async fn disconnect_all(tx: mpsc::UnboundedSender<DeviceData>, central: Adapter) {
+    let (mut disconnected, mut failed) = (Vec::new(), Vec::new());
+    for peripheral in central.peripherals().await.unwrap_or_default() {
+        if !peripheral.is_connected().await.unwrap_or(false) {
+            continue;
+        }
+        for characteristic in peripheral.characteristics() {
+            if characteristic
+                .properties
+                .intersects(CharPropFlags::NOTIFY | CharPropFlags::INDICATE)
+            {
+                let _ = peripheral.unsubscribe(&characteristic).await;
             }
-        },
-        None => {
-            tx.send(DeviceData::Error("Device not found".to_string()))
-                .unwrap();
+        }
+        let identity = match peripheral.properties().await {
+            Ok(Some(properties)) => properties.address.to_string(),
+            _ => peripheral.id().to_string(),
+        };
+        match peripheral.disconnect().await {
+            Ok(()) => disconnected.push(identity),
+            Err(e) => failed.push(format!("{} ({})", identity, e)),
         }
     }
+    let _ = tx.send(DeviceData::Disconnected {
+        disconnected,
+        failed,
+    });
 }
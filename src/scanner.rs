@@ -0,0 +1,443 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    ops::RangeInclusive,
+    sync::{atomic::AtomicBool, Arc},
+    time::{Duration, Instant},
+};
+
+use btleplug::api::ScanFilter;
+use futures::{stream, Stream};
+use tokio::{
+    sync::{
+        mpsc::{self, UnboundedReceiver},
+        Notify,
+    },
+    time::{Interval, MissedTickBehavior},
+};
+use uuid::Uuid;
+
+use crate::{
+    app::{DeviceData, STALE_TIMEOUT},
+    cli::DEFAULT_COALESCE_WINDOW,
+    error::BluscanError,
+    source::{BtleplugSource, DeviceSource},
+    structs::{DeviceInfo, IdentityKey},
+};
+
+/// How often `Scanner::events` looks for devices that have expired.
+const EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The shortest and longest expiry a `ScanConfig` accepts.
+const EXPIRY_RANGE: RangeInclusive<Duration> =
+    Duration::from_secs(1)..=Duration::from_secs(24 * 60 * 60);
+
+/// The RSSI levels, in dBm, a minimum RSSI can be set to.
+const RSSI_RANGE: RangeInclusive<i16> = -127..=20;
+
+/// A change in the devices around, as `Scanner::events` reports it.
+pub enum DeviceUpdate {
+    /// A device heard from for the first time, or again after it was lost.
+    Discovered(Box<DeviceInfo>),
+    /// A new advertisement of a device already known.
+    Updated(Box<DeviceInfo>),
+    /// The identity of a device not heard from within the expiry.
+    Lost(String),
+    /// The scan could not start or ended with an error. Nothing follows it.
+    Failed(BluscanError),
+}
+
+/// Why `ScanConfigBuilder::build` rejected a configuration.
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum ScanConfigError {
+    /// Results would be held back for longer than a device takes to expire, so devices
+    /// would be lost between two batches.
+    #[error("the coalescing window ({window:?}) must not be longer than the expiry ({expiry:?})")]
+    WindowExceedsExpiry { window: Duration, expiry: Duration },
+    /// The nil UUID was given as a service to filter by; no device advertises it.
+    #[error("the nil UUID is not a service UUID")]
+    NilService,
+    /// The expiry is shorter than a second or longer than a day.
+    #[error("invalid expiry {0:?}: expected between 1 second and 1 day")]
+    Expiry(Duration),
+    /// The minimum RSSI is not a level a receiver reports.
+    #[error("invalid minimum RSSI {0} dBm: expected between -127 and 20")]
+    MinRssi(i16),
+}
+
+/// What a `Scanner` scans and reports. Build one with `ScanConfig::builder`; the default
+/// scans the first adapter for every device, drops repeated advertisements and loses
+/// devices after 30 seconds of silence.
+#[derive(Clone, Debug)]
+pub struct ScanConfig {
+    adapter: usize,
+    services: Vec<Uuid>,
+    min_rssi: Option<i16>,
+    expiry: Duration,
+    dedupe: bool,
+    window: Duration,
+    identity: IdentityKey,
+}
+
+impl Default for ScanConfig {
+    fn default() -> Self {
+        Self {
+            adapter: 0,
+            services: Vec::new(),
+            min_rssi: None,
+            expiry: STALE_TIMEOUT,
+            dedupe: true,
+            window: DEFAULT_COALESCE_WINDOW,
+            identity: IdentityKey::default(),
+        }
+    }
+}
+
+impl ScanConfig {
+    /// Starts from the default configuration.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use btlescan::ScanConfig;
+    ///
+    /// let config = ScanConfig::builder()
+    ///     .adapter(1)
+    ///     .min_rssi(-70)
+    ///     .expiry(Duration::from_secs(10))
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(config.min_rssi(), Some(-70));
+    /// ```
+    pub fn builder() -> ScanConfigBuilder {
+        ScanConfigBuilder::default()
+    }
+
+    /// The adapter's position in the order the platform lists them.
+    pub fn adapter(&self) -> usize {
+        self.adapter
+    }
+
+    /// The services devices must advertise one of to be reported; empty for every device.
+    pub fn services(&self) -> &[Uuid] {
+        &self.services
+    }
+
+    pub fn min_rssi(&self) -> Option<i16> {
+        self.min_rssi
+    }
+
+    pub fn expiry(&self) -> Duration {
+        self.expiry
+    }
+
+    pub fn dedupe(&self) -> bool {
+        self.dedupe
+    }
+
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// The key updates of the same device are merged by.
+    pub fn identity(&self) -> IdentityKey {
+        self.identity
+    }
+
+    /// Returns a source scanning the configured adapter for the configured services.
+    pub fn source(&self) -> BtleplugSource {
+        BtleplugSource {
+            adapter: self.adapter,
+            window: self.window,
+            filter: ScanFilter {
+                services: self.services.clone(),
+            },
+        }
+    }
+}
+
+/// Builds a `ScanConfig`.
+#[derive(Default)]
+pub struct ScanConfigBuilder {
+    config: ScanConfig,
+}
+
+impl ScanConfigBuilder {
+    /// Scans the adapter at `index` in the order the platform lists them, as
+    /// `btlescan adapters` shows it.
+    pub fn adapter(mut self, index: usize) -> Self {
+        self.config.adapter = index;
+        self
+    }
+
+    /// Reports only the devices advertising one of `services`. The adapter is asked to
+    /// filter as well where the platform supports it.
+    pub fn services(mut self, services: impl IntoIterator<Item = Uuid>) -> Self {
+        self.config.services = services.into_iter().collect();
+        self
+    }
+
+    /// Reports only the advertisements received at `rssi` dBm or stronger.
+    pub fn min_rssi(mut self, rssi: i16) -> Self {
+        self.config.min_rssi = Some(rssi);
+        self
+    }
+
+    /// Reports a device as lost once it has not been heard from for `expiry`.
+    pub fn expiry(mut self, expiry: Duration) -> Self {
+        self.config.expiry = expiry;
+        self
+    }
+
+    /// Whether advertisements that repeat a device's name, RSSI, TX power, services and
+    /// payloads are dropped. On by default.
+    pub fn dedupe(mut self, dedupe: bool) -> Self {
+        self.config.dedupe = dedupe;
+        self
+    }
+
+    /// Coalesces the adapter's results over `window`, keeping each device's latest.
+    pub fn window(mut self, window: Duration) -> Self {
+        self.config.window = window;
+        self
+    }
+
+    /// Merges the updates of a device by `key`, and leaves out the devices it cannot
+    /// identify. `IdentityKey::Auto` by default.
+    pub fn identity(mut self, key: IdentityKey) -> Self {
+        self.config.identity = key;
+        self
+    }
+
+    /// Checks the configuration: the expiry is between a second and a day and no shorter
+    /// than the coalescing window, no service is the nil UUID and the minimum RSSI is
+    /// between -127 and 20 dBm.
+    pub fn build(self) -> Result<ScanConfig, ScanConfigError> {
+        let config = self.config;
+        if !EXPIRY_RANGE.contains(&config.expiry) {
+            return Err(ScanConfigError::Expiry(config.expiry));
+        }
+        if config.window > config.expiry {
+            return Err(ScanConfigError::WindowExceedsExpiry {
+                window: config.window,
+                expiry: config.expiry,
+            });
+        }
+        if config.services.iter().any(Uuid::is_nil) {
+            return Err(ScanConfigError::NilService);
+        }
+        if let Some(rssi) = config.min_rssi.filter(|rssi| !RSSI_RANGE.contains(rssi)) {
+            return Err(ScanConfigError::MinRssi(rssi));
+        }
+        Ok(config)
+    }
+}
+
+/// Scans for devices and reports them as a stream of `DeviceUpdate`s.
+///
+/// ```no_run
+/// use btlescan::{DeviceUpdate, ScanConfig, Scanner};
+/// use futures::StreamExt;
+///
+/// # async fn example() -> Result<(), btlescan::ScanConfigError> {
+/// let scanner = Scanner::new(ScanConfig::builder().min_rssi(-70).build()?);
+/// let events = scanner.events().await;
+/// futures::pin_mut!(events);
+/// while let Some(update) = events.next().await {
+///     match update {
+///         DeviceUpdate::Discovered(device) => println!("found {}", device.address),
+///         DeviceUpdate::Lost(identity) => println!("lost {}", identity),
+///         DeviceUpdate::Failed(e) => eprintln!("error: {}", e),
+///         DeviceUpdate::Updated(_) => {}
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct Scanner {
+    config: ScanConfig,
+    source: Box<dyn Fn() -> Box<dyn DeviceSource> + Send + Sync>,
+}
+
+impl Scanner {
+    /// Scans the configured adapter through `btleplug`.
+    pub fn new(config: ScanConfig) -> Self {
+        let source = config.source();
+        Self::with_source(config, move || source.clone())
+    }
+
+    /// Reads the results of the sources `source` makes, one for each call to `events`,
+    /// instead of an adapter.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use btlescan::{DeviceInfo, DeviceUpdate, MockSource, ScanConfig, Scanner};
+    /// use futures::StreamExt;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let lamp = DeviceInfo {
+    ///     address: "AA:BB:CC:DD:EE:01".to_string(),
+    ///     rssi: Some(-50),
+    ///     ..Default::default()
+    /// };
+    /// let source = MockSource::new().batch(Duration::ZERO, vec![lamp.clone(), lamp]);
+    /// let scanner = Scanner::with_source(ScanConfig::default(), move || source.clone());
+    /// let updates: Vec<DeviceUpdate> = scanner.events().await.collect().await;
+    /// // The repeated advertisement is dropped.
+    /// assert_eq!(updates.len(), 1);
+    /// # }
+    /// ```
+    pub fn with_source<S: DeviceSource>(
+        config: ScanConfig,
+        source: impl Fn() -> S + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            config,
+            source: Box::new(move || Box::new(source())),
+        }
+    }
+
+    pub fn config(&self) -> &ScanConfig {
+        &self.config
+    }
+
+    /// Starts a scan and returns its updates. The scan runs on the Tokio runtime until the
+    /// stream is dropped or its source runs out of results.
+    pub async fn events(&self) -> impl Stream<Item = DeviceUpdate> + Send {
+        let (tx, rx) = mpsc::unbounded_channel();
+        // No commands are sent, so the command channel is closed right away.
+        let (_, commands) = mpsc::unbounded_channel();
+        let shutdown = Arc::new(Notify::new());
+        tokio::spawn((self.source)().run(
+            tx,
+            Arc::new(AtomicBool::default()),
+            shutdown.clone(),
+            commands,
+        ));
+        let mut ticker = tokio::time::interval(EXPIRY_CHECK_INTERVAL);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let events = Events {
+            config: self.config.clone(),
+            rx,
+            ticker,
+            devices: HashMap::new(),
+            pending: VecDeque::new(),
+            finished: false,
+            _stop: StopOnDrop(shutdown),
+        };
+        stream::unfold(events, |mut events| async move {
+            events.next().await.map(|update| (update, events))
+        })
+    }
+}
+
+/// Stops the scan when the stream reading it is dropped.
+struct StopOnDrop(Arc<Notify>);
+
+impl Drop for StopOnDrop {
+    fn drop(&mut self) {
+        self.0.notify_one();
+    }
+}
+
+/// The state of the stream `Scanner::events` returns.
+struct Events {
+    config: ScanConfig,
+    rx: UnboundedReceiver<DeviceData>,
+    ticker: Interval,
+    /// The devices not lost yet by identity, with when each was last heard from.
+    devices: HashMap<String, (DeviceInfo, Instant)>,
+    /// The updates worked out but not returned yet.
+    pending: VecDeque<DeviceUpdate>,
+    /// Whether the source has stopped sending results.
+    finished: bool,
+    _stop: StopOnDrop,
+}
+
+impl Events {
+    /// Returns the next update, or `None` once the source has stopped and every update has
+    /// been returned.
+    async fn next(&mut self) -> Option<DeviceUpdate> {
+        loop {
+            if let Some(update) = self.pending.pop_front() {
+                return Some(update);
+            }
+            if self.finished {
+                return None;
+            }
+            tokio::select! {
+                data = self.rx.recv() => match data {
+                    Some(DeviceData::DeviceBatch { devices, .. }) => {
+                        for device in devices {
+                            self.observe(device);
+                        }
+                    }
+                    Some(DeviceData::ScanFailed(e)) => {
+                        self.pending.push_back(DeviceUpdate::Failed(e));
+                        self.finished = true;
+                    }
+                    Some(_) => {}
+                    None => self.finished = true,
+                },
+                _ = self.ticker.tick() => self.expire(),
+            }
+        }
+    }
+
+    /// Works out the update an advertisement makes, if it passes the filters.
+    fn observe(&mut self, device: DeviceInfo) {
+        let config = &self.config;
+        if config
+            .min_rssi
+            .is_some_and(|min| device.rssi.is_none_or(|rssi| rssi < min))
+        {
+            return;
+        }
+        if !config.services.is_empty()
+            && !device
+                .services
+                .iter()
+                .any(|service| config.services.contains(service))
+        {
+            return;
+        }
+        if config.identity.excludes(&device) {
+            return;
+        }
+        let identity = device.get_id(config.identity);
+        let update = match self.devices.get(&identity) {
+            None => Some(DeviceUpdate::Discovered(Box::new(device.clone()))),
+            Some((previous, _)) if config.dedupe && repeats(previous, &device) => None,
+            Some(_) => Some(DeviceUpdate::Updated(Box::new(device.clone()))),
+        };
+        self.devices.insert(identity, (device, Instant::now()));
+        self.pending.extend(update);
+    }
+
+    /// Loses the devices not heard from within the expiry, in identity order.
+    fn expire(&mut self) {
+        let expiry = self.config.expiry;
+        let mut lost: Vec<String> = self
+            .devices
+            .iter()
+            .filter(|(_, (_, heard))| heard.elapsed() > expiry)
+            .map(|(identity, _)| identity.clone())
+            .collect();
+        lost.sort_unstable();
+        for identity in lost {
+            self.devices.remove(&identity);
+            self.pending.push_back(DeviceUpdate::Lost(identity));
+        }
+    }
+}
+
+/// Returns `true` if `device` advertises nothing `previous` did not.
+fn repeats(previous: &DeviceInfo, device: &DeviceInfo) -> bool {
+    previous.name == device.name
+        && previous.rssi == device.rssi
+        && previous.tx_power == device.tx_power
+        && previous.services == device.services
+        && previous.manufacturer_data == device.manufacturer_data
+        && previous.service_data == device.service_data
+}
@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::utils::{bytes_to_hex, hex_to_bytes};
+
+/// The version of the canonical serialized form of `DeviceInfo`, written in every
+/// `Envelope`. Byte payloads are hex strings as `bytes_to_hex` writes them, UUIDs are
+/// hyphenated strings and timestamps are RFC 3339, so the form does not depend on the serde
+/// features of btleplug, uuid or chrono. Fields added to `DeviceInfo` must default when
+/// missing so older files still read; the version is raised only for changes they cannot
+/// absorb.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// A payload tagged with what it is and the version of its form, for files and streams
+/// that outlive a single run.
+#[derive(Serialize, Deserialize)]
+pub struct Envelope<T> {
+    /// What the payload is, such as `device`.
+    pub kind: String,
+    pub version: u32,
+    pub data: T,
+}
+
+impl<T> Envelope<T> {
+    /// Wraps a payload in an envelope of the current version.
+    pub fn new(kind: impl Into<String>, data: T) -> Self {
+        Self {
+            kind: kind.into(),
+            version: FORMAT_VERSION,
+            data,
+        }
+    }
+}
+
+/// Reads a hex string into bytes, failing with a serde error.
+fn decode_hex<'de, D: Deserializer<'de>>(hex: &str) -> Result<Vec<u8>, D::Error> {
+    hex_to_bytes(hex).ok_or_else(|| D::Error::custom(format!("invalid hex '{}'", hex)))
+}
+
+/// A byte payload as a hex string.
+pub mod hex_bytes {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&bytes_to_hex(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        decode_hex::<D>(&String::deserialize(deserializer)?)
+    }
+}
+
+/// Manufacturer data as an object of hex payloads keyed by company id, sorted by id.
+pub mod manufacturer_data {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        data: &HashMap<u16, Vec<u8>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        data.iter()
+            .map(|(company, payload)| (*company, bytes_to_hex(payload)))
+            .collect::<BTreeMap<_, _>>()
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<u16, Vec<u8>>, D::Error> {
+        BTreeMap::<u16, String>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(company, payload)| Ok((company, decode_hex::<D>(&payload)?)))
+            .collect()
+    }
+}
+
+/// Service data as an object of hex payloads keyed by service UUID, sorted by UUID.
+pub mod service_data {
+    use std::collections::BTreeMap;
+
+    use uuid::Uuid;
+
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        data: &HashMap<Uuid, Vec<u8>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        data.iter()
+            .map(|(uuid, payload)| (uuid.to_string(), bytes_to_hex(payload)))
+            .collect::<BTreeMap<_, _>>()
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<Uuid, Vec<u8>>, D::Error> {
+        BTreeMap::<String, String>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(uuid, payload)| {
+                let uuid = Uuid::parse_str(&uuid).map_err(D::Error::custom)?;
+                Ok((uuid, decode_hex::<D>(&payload)?))
+            })
+            .collect()
+    }
+}
+
+/// A list of UUIDs as hyphenated strings.
+pub mod uuid_list {
+    use uuid::Uuid;
+
+    use super::*;
+
+    pub fn serialize<S: Serializer>(uuids: &[Uuid], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(uuids.iter().map(Uuid::to_string))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Uuid>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|uuid| Uuid::parse_str(uuid).map_err(D::Error::custom))
+            .collect()
+    }
+}
+
+/// A local timestamp as RFC 3339, read back into the local time zone.
+pub mod timestamp {
+    use chrono::{DateTime, Local};
+
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        timestamp: &DateTime<Local>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&timestamp.to_rfc3339())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<DateTime<Local>, D::Error> {
+        let timestamp = String::deserialize(deserializer)?;
+        DateTime::parse_from_rfc3339(&timestamp)
+            .map(|timestamp| timestamp.with_timezone(&Local))
+            .map_err(D::Error::custom)
+    }
+}
+
+/// An address type as `public` or `random`, or `null` when the platform does not report it.
+pub mod address_type {
+    use btleplug::api::AddressType;
+
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        address_type: &Option<AddressType>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match address_type {
+            Some(AddressType::Public) => serializer.serialize_some("public"),
+            Some(AddressType::Random) => serializer.serialize_some("random"),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<AddressType>, D::Error> {
+        match Option::<String>::deserialize(deserializer)?.as_deref() {
+            Some("public") => Ok(Some(AddressType::Public)),
+            Some("random") => Ok(Some(AddressType::Random)),
+            Some(other) => Err(D::Error::custom(format!(
+                "unknown address type '{}', expected public or random",
+                other
+            ))),
+            None => Ok(None),
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    path::Path,
+    sync::{atomic::AtomicBool, Arc, Mutex},
+};
+
+use tokio::sync::{mpsc, Notify};
+use uuid::Uuid;
+
+use crate::{
+    app::DeviceData,
+    decoders::{device_tags, DecoderRegistry},
+    source::DeviceSource,
+    structs::{
+        DecodedField, DeviceInfo, IdentityKey, RssiStats, SessionDeviceJson, SessionExport,
+        EXPORT_SCHEMA_VERSION,
+    },
+    utils::extract_manufacturer_data,
+};
+
+/// Everything recorded about one device over the session.
+struct SessionDevice {
+    /// The latest observation of the device.
+    device: DeviceInfo,
+    first_seen: chrono::DateTime<chrono::Local>,
+    seen_count: usize,
+    rssi: Option<RssiStats>,
+    /// The decoder output of the latest observation that produced any.
+    decoded: Vec<DecodedField>,
+}
+
+/// Accumulates every device seen during the session, including those the live list has
+/// since dropped, so the whole session can be written out on exit.
+pub struct SessionRecorder {
+    started_at: chrono::DateTime<chrono::Local>,
+    devices: HashMap<String, SessionDevice>,
+}
+
+impl SessionRecorder {
+    /// Creates a recorder for a session starting now.
+    pub fn new() -> Self {
+        Self {
+            started_at: chrono::Local::now(),
+            devices: HashMap::new(),
+        }
+    }
+
+    /// Records an observation of a device, merged with the others of the same identity under
+    /// `key`. Decoding happens here rather than on write, so
+    /// the session can also be written from the panic hook.
+    pub fn record(&mut self, device: &DeviceInfo, key: IdentityKey, decoders: &DecoderRegistry) {
+        if key.excludes(device) {
+            return;
+        }
+        let entry = self
+            .devices
+            .entry(device.get_id(key))
+            .or_insert_with(|| SessionDevice {
+                device: device.clone(),
+                first_seen: device.last_seen,
+                seen_count: 0,
+                rssi: None,
+                decoded: Vec::new(),
+            });
+        entry.seen_count += 1;
+        if let Some(rssi) = device.rssi {
+            match entry.rssi.as_mut() {
+                Some(stats) => stats.add(rssi),
+                None => entry.rssi = Some(RssiStats::new(rssi)),
+            }
+        }
+        let decoded = decoders.decode(device);
+        if !decoded.is_empty() {
+            entry.decoded = decoded;
+        }
+        entry.device = device.clone();
+    }
+
+    /// Writes every recorded device, in the order they were first seen, to a JSON file.
+    /// Returns the number of devices written.
+    pub fn write(&self, path: &Path) -> Result<usize, Box<dyn Error>> {
+        let mut devices: Vec<_> = self.devices.values().collect();
+        devices.sort_by_key(|entry| entry.first_seen);
+        let export = SessionExport {
+            schema_version: EXPORT_SCHEMA_VERSION,
+            crate_version: env!("CARGO_PKG_VERSION"),
+            started_at: self.started_at.to_rfc3339(),
+            ended_at: chrono::Local::now().to_rfc3339(),
+            devices: devices
+                .iter()
+                .map(|entry| {
+                    let device = &entry.device;
+                    SessionDeviceJson {
+                        id: device.id.clone(),
+                        address: device.address.clone(),
+                        address_type: device.address_type_label(),
+                        device_type: device.device_type_label(),
+                        class: device.class,
+                        name: device.name.clone(),
+                        tx_power: device.tx_power,
+                        first_seen: entry.first_seen.to_rfc3339(),
+                        last_seen: device.last_seen.to_rfc3339(),
+                        seen_count: entry.seen_count,
+                        rssi_min: entry.rssi.map(|stats| stats.min),
+                        rssi_max: entry.rssi.map(|stats| stats.max),
+                        rssi_mean: entry.rssi.map(|stats| stats.mean()),
+                        manufacturer_data: extract_manufacturer_data(&device.manufacturer_data),
+                        services: device.services.iter().map(Uuid::to_string).collect(),
+                        solicited_services: device
+                            .solicited_services
+                            .iter()
+                            .map(Uuid::to_string)
+                            .collect(),
+                        tags: device_tags(device),
+                        decoded: entry.decoded.clone(),
+                    }
+                })
+                .collect(),
+        };
+        let file = std::fs::File::create(path)?;
+        serde_json::to_writer_pretty(file, &export)?;
+        Ok(export.devices.len())
+    }
+}
+
+/// Writes the session to `path` and reports the result on stderr.
+pub fn write_session(recorder: &SessionRecorder, path: &Path) {
+    match recorder.write(path) {
+        Ok(count) => eprintln!("{} devices written to {}", count, path.display()),
+        Err(e) => eprintln!("error: unable to write the session: {}", e),
+    }
+}
+
+/// Runs `source` in the background of the headless `--auto` mode, which does not otherwise
+/// keep a device list, recording every device into `recorder` when a session is written.
+pub async fn record_scan(
+    source: Box<dyn DeviceSource>,
+    recorder: Option<Arc<Mutex<SessionRecorder>>>,
+    decoders: DecoderRegistry,
+    identity: IdentityKey,
+) {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    // No commands are sent, so the command channel is closed right away.
+    let (_, commands) = mpsc::unbounded_channel();
+    tokio::spawn(source.run(
+        tx,
+        Arc::new(AtomicBool::default()),
+        Arc::new(Notify::new()),
+        commands,
+    ));
+    while let Some(data) = rx.recv().await {
+        if let (DeviceData::DeviceBatch { devices, .. }, Some(recorder)) = (data, &recorder) {
+            let mut recorder = recorder.lock().unwrap();
+            for device in &devices {
+                recorder.record(device, identity, &decoders);
+            }
+        }
+    }
+}
@@ -0,0 +1,374 @@
+use std::{
+    collections::HashMap,
+    io,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use btleplug::api::{AddressType, ScanFilter};
+use futures::future::BoxFuture;
+use tokio::sync::{
+    mpsc::{UnboundedReceiver, UnboundedSender},
+    Notify,
+};
+
+use uuid::Uuid;
+
+use crate::{
+    app::DeviceData,
+    capture::{CaptureLine, CAPTURE_VERSION},
+    scan::bluetooth_scan,
+    structs::DeviceInfo,
+    utils::{hex_to_bytes, normalize_address, reconstruct_advertisement},
+};
+
+/// A command sent to a running source.
+pub enum ScanCommand {
+    /// Restarts the scan with a new platform-level filter, keeping the channel and the
+    /// results sent so far. The source answers with `DeviceData::ScanReconfigured`.
+    Restart(ScanFilter),
+    /// Unsubscribes from and disconnects every device the source's adapter is connected
+    /// to. The source answers with `DeviceData::Disconnected`.
+    DisconnectAll,
+}
+
+/// A source of scan results. It sends `DeviceData::DeviceBatch` batches until it runs out
+/// of results or `shutdown` is notified, waiting while `pause_signal` is `true`, and acts on
+/// the commands received over `commands`.
+pub trait DeviceSource: Send + 'static {
+    fn run(
+        self: Box<Self>,
+        tx: UnboundedSender<DeviceData>,
+        pause_signal: Arc<AtomicBool>,
+        shutdown: Arc<Notify>,
+        commands: UnboundedReceiver<ScanCommand>,
+    ) -> BoxFuture<'static, ()>;
+}
+
+/// Scans a Bluetooth adapter through `btleplug` with `filter`, coalescing results over
+/// `window`. A scan that fails is reported as `DeviceData::ScanFailed`.
+#[derive(Clone)]
+pub struct BtleplugSource {
+    /// The adapter's position in `adapters::platform_adapters`; the first is 0.
+    pub adapter: usize,
+    pub window: Duration,
+    pub filter: ScanFilter,
+}
+
+impl DeviceSource for BtleplugSource {
+    fn run(
+        self: Box<Self>,
+        tx: UnboundedSender<DeviceData>,
+        pause_signal: Arc<AtomicBool>,
+        shutdown: Arc<Notify>,
+        commands: UnboundedReceiver<ScanCommand>,
+    ) -> BoxFuture<'static, ()> {
+        Box::pin(async move {
+            let result = bluetooth_scan(
+                tx.clone(),
+                self.adapter,
+                pause_signal,
+                self.window,
+                self.filter,
+                shutdown,
+                commands,
+            )
+            .await;
+            if let Err(e) = result {
+                let _ = tx.send(DeviceData::ScanFailed(e));
+            }
+        })
+    }
+}
+
+/// Replays scripted batches, each sent after its delay, so the application can be driven
+/// without a Bluetooth adapter. Commands are ignored.
+#[derive(Clone, Default)]
+pub struct MockSource {
+    batches: Vec<(Duration, Vec<DeviceInfo>)>,
+}
+
+impl MockSource {
+    /// Creates a source with no batches.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a batch sent `delay` after the previous one.
+    pub fn batch(mut self, delay: Duration, devices: Vec<DeviceInfo>) -> Self {
+        self.batches.push((delay, devices));
+        self
+    }
+}
+
+impl DeviceSource for MockSource {
+    fn run(
+        self: Box<Self>,
+        tx: UnboundedSender<DeviceData>,
+        pause_signal: Arc<AtomicBool>,
+        shutdown: Arc<Notify>,
+        _commands: UnboundedReceiver<ScanCommand>,
+    ) -> BoxFuture<'static, ()> {
+        Box::pin(async move {
+            for (delay, devices) in self.batches {
+                tokio::select! {
+                    _ = tokio::time::sleep(delay) => {}
+                    _ = shutdown.notified() => return,
+                }
+                while pause_signal.load(Ordering::SeqCst) {
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                }
+                let observations = devices.len();
+                let _ = tx.send(DeviceData::DeviceBatch {
+                    devices,
+                    observations,
+                });
+            }
+        })
+    }
+}
+
+/// Replays a capture written with `--record`, or the device updates of an event log written
+/// with `--log-file`, keeping the time between them divided by `speed`. Captures keep the
+/// advertisement payloads and their monotonic offsets; event logs only record each device's
+/// identity, name and RSSI, and are timed by their timestamps. Other events are skipped, and
+/// lines that cannot be parsed are skipped and counted. Service filter restarts are
+/// acknowledged without effect.
+pub struct ReplaySource {
+    /// Each device with the delay since the previous one.
+    events: Vec<(Duration, DeviceInfo)>,
+    skipped: usize,
+    speed: f64,
+}
+
+impl ReplaySource {
+    /// Reads and parses the capture or event log at `path`.
+    pub fn open(path: &Path, speed: f64) -> io::Result<Self> {
+        let mut events = Vec::new();
+        let mut skipped = 0;
+        let mut previous_offset: Option<u64> = None;
+        let mut previous_timestamp: Option<chrono::DateTime<chrono::FixedOffset>> = None;
+        for line in std::fs::read_to_string(path)?.lines() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let value: Option<serde_json::Value> = serde_json::from_str(line).ok();
+            match value.as_ref().map(|value| value["event"].as_str()) {
+                Some(Some("discovered" | "updated" | "observed")) => {}
+                // A capture header starts a new recording, whose offsets restart at zero.
+                Some(Some("capture")) => {
+                    if value.as_ref().and_then(|value| value["version"].as_u64())
+                        > Some(CAPTURE_VERSION.into())
+                    {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            "the capture was written by a newer version of btlescan",
+                        ));
+                    }
+                    previous_offset = None;
+                    continue;
+                }
+                // Other events, such as WebSocket clients connecting, are not replayed.
+                Some(_) => continue,
+                None => {}
+            }
+            let Some((timestamp, line)) = value
+                .and_then(|value| serde_json::from_value::<CaptureLine>(value).ok())
+                .and_then(|line| {
+                    let timestamp = chrono::DateTime::parse_from_rfc3339(&line.timestamp).ok()?;
+                    Some((timestamp, line))
+                })
+            else {
+                skipped += 1;
+                continue;
+            };
+            let delay = match (line.t_ms, previous_offset) {
+                (Some(offset), Some(previous)) => {
+                    Duration::from_millis(offset.saturating_sub(previous))
+                }
+                (Some(_), None) => Duration::ZERO,
+                (None, _) => previous_timestamp.map_or(Duration::ZERO, |previous| {
+                    (timestamp - previous).to_std().unwrap_or_default()
+                }),
+            };
+            previous_offset = line.t_ms;
+            previous_timestamp = Some(timestamp);
+            let Some(device) = replayed_device(line) else {
+                skipped += 1;
+                continue;
+            };
+            events.push((delay, device));
+        }
+        Ok(Self {
+            events,
+            skipped,
+            speed,
+        })
+    }
+}
+
+/// Rebuilds a device from a replayed line. Returns `None` if its payloads are not valid hex
+/// or its UUIDs cannot be parsed.
+fn replayed_device(line: CaptureLine) -> Option<DeviceInfo> {
+    let services = parse_uuids(&line.services)?;
+    let solicited_services = parse_uuids(&line.solicited_services)?;
+    let manufacturer_data = line
+        .manufacturer_data
+        .iter()
+        .map(|(company, data)| Some((*company, hex_to_bytes(data)?)))
+        .collect::<Option<HashMap<_, _>>>()?;
+    let service_data = line
+        .service_data
+        .iter()
+        .map(|(uuid, data)| Some((uuid.parse().ok()?, hex_to_bytes(data)?)))
+        .collect::<Option<HashMap<_, _>>>()?;
+    let raw_advertisement = reconstruct_advertisement(
+        line.name.as_deref(),
+        line.tx_power,
+        &services,
+        &solicited_services,
+        &service_data,
+        &manufacturer_data,
+    );
+    let now = chrono::Local::now();
+    Some(DeviceInfo {
+        id: line.id,
+        name: line.name,
+        tx_power: line.tx_power,
+        address: normalize_address(&line.address).unwrap_or(line.address),
+        address_type: match line.address_type.as_deref() {
+            Some("public") => Some(AddressType::Public),
+            Some("random") => Some(AddressType::Random),
+            _ => None,
+        },
+        rssi: line.rssi,
+        manufacturer_data,
+        services,
+        solicited_services,
+        detected_at: now.to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+        first_seen: now,
+        last_seen: now,
+        service_data,
+        class: line.class,
+        appearance: line.appearance,
+        raw_advertisement,
+        raw_reconstructed: true,
+        is_connectable: line.connectable,
+        ..DeviceInfo::default()
+    })
+}
+
+fn parse_uuids(uuids: &[String]) -> Option<Vec<Uuid>> {
+    uuids.iter().map(|uuid| uuid.parse().ok()).collect()
+}
+
+impl DeviceSource for ReplaySource {
+    fn run(
+        self: Box<Self>,
+        tx: UnboundedSender<DeviceData>,
+        pause_signal: Arc<AtomicBool>,
+        shutdown: Arc<Notify>,
+        mut commands: UnboundedReceiver<ScanCommand>,
+    ) -> BoxFuture<'static, ()> {
+        Box::pin(async move {
+            let mut replayed = 0;
+            let mut commands_open = true;
+            for (delay, mut device) in self.events {
+                let sleep = tokio::time::sleep(delay.div_f64(self.speed));
+                tokio::pin!(sleep);
+                loop {
+                    tokio::select! {
+                        _ = &mut sleep => break,
+                        _ = shutdown.notified() => return,
+                        command = commands.recv(), if commands_open => match command {
+                            Some(ScanCommand::Restart(_)) => {
+                                let _ = tx.send(DeviceData::ScanReconfigured(Ok(())));
+                            }
+                            // A replay holds no connections.
+                            Some(ScanCommand::DisconnectAll) => {
+                                let _ = tx.send(DeviceData::Disconnected {
+                                    disconnected: Vec::new(),
+                                    failed: Vec::new(),
+                                });
+                            }
+                            None => commands_open = false,
+                        },
+                    }
+                }
+                while pause_signal.load(Ordering::SeqCst) {
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                }
+                // The device is seen now, so it goes stale on the replay's clock.
+                device.last_seen = chrono::Local::now();
+                device.first_seen = device.last_seen;
+                let _ = tx.send(DeviceData::DeviceBatch {
+                    devices: vec![device],
+                    observations: 1,
+                });
+                replayed += 1;
+            }
+            let _ = tx.send(DeviceData::ReplayFinished {
+                replayed,
+                skipped: self.skipped,
+            });
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture::CaptureWriter;
+
+    #[test]
+    fn replay_reads_back_what_the_capture_recorded() {
+        let heart_rate = Uuid::from_u128(0x0000180d_0000_1000_8000_00805f9b34fb);
+        let band = DeviceInfo {
+            id: "hci0/dev_AA_BB_CC_DD_EE_01".to_string(),
+            address: "AA:BB:CC:DD:EE:01".to_string(),
+            address_type: Some(AddressType::Random),
+            name: Some("Band".to_string()),
+            rssi: Some(-58),
+            tx_power: Some(-4),
+            manufacturer_data: HashMap::from([(0x004c, vec![0x12, 0x19, 0x00])]),
+            services: vec![heart_rate],
+            service_data: HashMap::from([(heart_rate, vec![0x06, 0x48])]),
+            is_connectable: Some(true),
+            ..DeviceInfo::default()
+        };
+        let hidden = DeviceInfo {
+            id: "3F2A-91C0".to_string(),
+            address: "00:00:00:00:00:00".to_string(),
+            rssi: Some(-80),
+            ..DeviceInfo::default()
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("capture.ndjson");
+        let mut capture = CaptureWriter::open(&path).unwrap();
+        capture.record(&band).unwrap();
+        capture.record(&hidden).unwrap();
+        drop(capture);
+
+        let replay = ReplaySource::open(&path, 1.0).unwrap();
+        assert_eq!(replay.skipped, 0);
+        let replayed: Vec<_> = replay.events.into_iter().map(|(_, d)| d).collect();
+        assert_eq!(replayed.len(), 2);
+        for (original, replayed) in [band, hidden].iter().zip(&replayed) {
+            assert_eq!(replayed.id, original.id);
+            assert_eq!(replayed.address, original.address);
+            assert_eq!(replayed.address_type, original.address_type);
+            assert_eq!(replayed.name, original.name);
+            assert_eq!(replayed.rssi, original.rssi);
+            assert_eq!(replayed.tx_power, original.tx_power);
+            assert_eq!(replayed.manufacturer_data, original.manufacturer_data);
+            assert_eq!(replayed.services, original.services);
+            assert_eq!(replayed.service_data, original.service_data);
+            assert_eq!(replayed.is_connectable, original.is_connectable);
+            assert!(chrono::DateTime::parse_from_rfc3339(&replayed.detected_at).is_ok());
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use std::{error::Error, path::PathBuf};
+
+use crate::structs::PersistedState;
+
+/// Returns the path of the state file: `$XDG_STATE_HOME/btlescan/state.json`, falling back
+/// to `~/.local/state` and, on Windows, `%APPDATA%`.
+pub fn state_file_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state")))
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))?;
+    Some(base.join("btlescan").join("state.json"))
+}
+
+/// Loads the state saved by a previous run. A missing or unreadable state file yields the
+/// default state, so a corrupt file never prevents the application from starting.
+pub fn load_state() -> PersistedState {
+    state_file_path()
+        .and_then(|path| std::fs::read(path).ok())
+        .and_then(|contents| serde_json::from_slice(&contents).ok())
+        .unwrap_or_default()
+}
+
+/// Writes the state to the state file, creating its directory if needed.
+pub fn save_state(state: &PersistedState) -> Result<(), Box<dyn Error>> {
+    let path = state_file_path().ok_or("Unable to locate a directory for the state file")?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_vec_pretty(state)?)?;
+    Ok(())
+}
@@ -1,22 +1,90 @@
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeMap, HashMap},
+    str::FromStr,
+    time::{Duration, Instant},
+};
 
-use btleplug::api::CharPropFlags;
+use btleplug::api::{AddressType, CharPropFlags};
 use uuid::Uuid;
 
-/// A struct to hold the information of a Bluetooth device.
+use crate::utils::{company_name, normalize_address, oui_vendor, reconstruct_advertisement};
+
+/// A struct to hold the information of a Bluetooth device. With the `device-serde` feature
+/// it serializes to the canonical form described in the `serialization` module; the
+/// peripheral handle is not serialized.
 #[derive(Clone, Default)]
-#[allow(dead_code)]
+#[cfg_attr(
+    feature = "device-serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
 pub struct DeviceInfo {
     pub id: String,
-    pub name: String,
-    pub tx_power: String,
+    pub name: Option<String>,
+    pub tx_power: Option<i16>,
     pub address: String,
-    pub rssi: String,
+    #[cfg_attr(
+        feature = "device-serde",
+        serde(with = "crate::serialization::address_type")
+    )]
+    pub address_type: Option<AddressType>,
+    pub rssi: Option<i16>,
+    #[cfg_attr(
+        feature = "device-serde",
+        serde(with = "crate::serialization::manufacturer_data")
+    )]
     pub manufacturer_data: HashMap<u16, Vec<u8>>,
+    #[cfg_attr(
+        feature = "device-serde",
+        serde(with = "crate::serialization::uuid_list")
+    )]
     pub services: Vec<Uuid>,
+    /// Services the device is looking for a central to offer (Service Solicitation).
+    #[cfg_attr(
+        feature = "device-serde",
+        serde(with = "crate::serialization::uuid_list")
+    )]
+    pub solicited_services: Vec<Uuid>,
+    /// When the device was first detected, as an RFC 3339 timestamp.
     pub detected_at: String,
+    /// The start of the device's current presence episode.
+    #[cfg_attr(
+        feature = "device-serde",
+        serde(with = "crate::serialization::timestamp")
+    )]
+    pub first_seen: chrono::DateTime<chrono::Local>,
+    #[cfg_attr(
+        feature = "device-serde",
+        serde(with = "crate::serialization::timestamp")
+    )]
+    pub last_seen: chrono::DateTime<chrono::Local>,
 
+    #[cfg_attr(
+        feature = "device-serde",
+        serde(with = "crate::serialization::service_data")
+    )]
     pub service_data: HashMap<Uuid, Vec<u8>>,
+    #[cfg_attr(feature = "device-serde", serde(rename = "class_of_device"))]
+    pub class: Option<u32>,
+    /// The GAP Appearance the device advertises, where the platform reports it.
+    pub appearance: Option<u16>,
+    #[cfg_attr(
+        feature = "device-serde",
+        serde(with = "crate::serialization::hex_bytes")
+    )]
+    pub raw_advertisement: Vec<u8>,
+    /// Whether `raw_advertisement` was rebuilt from the parsed fields rather than captured
+    /// from the air.
+    #[cfg_attr(
+        feature = "device-serde",
+        serde(rename = "raw_advertisement_reconstructed")
+    )]
+    pub raw_reconstructed: bool,
+    /// Whether the device accepts connections, as the advertising PDU type tells, or
+    /// `Some(true)` once connecting to it succeeded. btleplug does not report the PDU type,
+    /// so live scans only learn it by connecting; captures carry it where they recorded it.
+    pub is_connectable: Option<bool>,
+    #[cfg_attr(feature = "device-serde", serde(skip))]
     pub device: Option<btleplug::platform::Peripheral>,
 }
 
@@ -28,32 +96,592 @@ impl DeviceInfo {
         name: Option<String>,
         tx_power: Option<i16>,
         address: String,
+        address_type: Option<AddressType>,
         rssi: Option<i16>,
         manufacturer_data: HashMap<u16, Vec<u8>>,
         services: Vec<Uuid>,
+        solicited_services: Vec<Uuid>,
         service_data: HashMap<Uuid, Vec<u8>>,
+        class: Option<u32>,
+        appearance: Option<u16>,
         device: btleplug::platform::Peripheral,
     ) -> Self {
+        // btleplug does not expose the raw advertising PDU, so it is rebuilt from the parsed fields.
+        let raw_advertisement = reconstruct_advertisement(
+            name.as_deref(),
+            tx_power,
+            &services,
+            &solicited_services,
+            &service_data,
+            &manufacturer_data,
+        );
         Self {
             id,
-            name: name.unwrap_or_else(|| "Unknown".to_string()),
-            tx_power: tx_power.map_or_else(|| "n/a".to_string(), |tx| tx.to_string()),
+            name: name.filter(|name| !name.trim().is_empty()),
+            tx_power,
             address,
-            rssi: rssi.map_or_else(|| "n/a".to_string(), |rssi| rssi.to_string()),
+            address_type,
+            rssi,
             manufacturer_data,
             services,
-            detected_at: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            solicited_services,
+            detected_at: chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+            first_seen: chrono::Local::now(),
+            last_seen: chrono::Local::now(),
             service_data,
+            class,
+            appearance,
+            raw_advertisement,
+            raw_reconstructed: true,
+            is_connectable: None,
             device: Some(device),
         }
     }
 
-    pub fn get_id(&self) -> String {
-        // Returns the `uuid` or `address` of the device if MacOS or Linux.
-        if cfg!(target_os = "macos") {
-            self.id.clone()
+    /// Returns the best label to display for the device: its advertised name, or a
+    /// fallback chosen by `fallback` when the device does not advertise one.
+    pub fn display_name(&self, fallback: NameFallback) -> String {
+        if let Some(name) = &self.name {
+            return name.clone();
+        }
+        match fallback {
+            NameFallback::Unnamed => "(unnamed)".to_string(),
+            NameFallback::Manufacturer => {
+                company_name(&self.manufacturer_data).unwrap_or_else(|| self.address_or_id())
+            }
+            NameFallback::Address => self.address_or_id(),
+        }
+    }
+
+    /// Returns `true` if the device has not been seen for longer than `timeout`.
+    pub fn is_stale(&self, timeout: Duration) -> bool {
+        (chrono::Local::now() - self.last_seen)
+            .to_std()
+            .is_ok_and(|elapsed| elapsed > timeout)
+    }
+
+    /// Returns how long the device has been continuously present: the time from the start
+    /// of its current presence episode to its latest advertisement.
+    pub fn dwell(&self) -> Duration {
+        (self.last_seen - self.first_seen)
+            .to_std()
+            .unwrap_or_default()
+    }
+
+    /// Returns a label for the type of the device's address.
+    pub fn address_type_label(&self) -> &'static str {
+        match self.address_type {
+            Some(AddressType::Public) => "public",
+            Some(AddressType::Random) => "random",
+            None => "unknown",
+        }
+    }
+
+    /// Returns the vendor the device's public address is assigned to, if it is known.
+    pub fn vendor(&self) -> Option<&'static str> {
+        if self.address_type == Some(AddressType::Random) {
+            return None;
+        }
+        normalize_address(&self.address).and_then(|address| oui_vendor(&address))
+    }
+
+    /// Returns `true` if the device was discovered over classic Bluetooth (BR/EDR).
+    /// Only classic inquiry reports a Class of Device, so its presence marks the transport.
+    pub fn is_classic(&self) -> bool {
+        self.class.is_some()
+    }
+
+    /// Returns a label for the transport the device was discovered over.
+    pub fn device_type_label(&self) -> &'static str {
+        if self.is_classic() {
+            "Classic"
         } else {
-            self.address.clone()
+            "BLE"
+        }
+    }
+
+    /// Returns the identity devices are merged and remembered by under `key`: by default
+    /// its address, or the platform id when the platform hides the address (e.g. macOS
+    /// reports an all-zero placeholder). Under `IdentityKey::Address` a hidden address gives
+    /// an empty identity, and such devices are left out.
+    pub fn get_id(&self, key: IdentityKey) -> String {
+        match key {
+            IdentityKey::Auto => self.address_or_id(),
+            IdentityKey::Address => normalize_address(&self.address).unwrap_or_default(),
+            IdentityKey::Id => self.id.clone(),
+        }
+    }
+
+    /// Returns the address, or the platform id when the platform hides the address, whatever
+    /// the `--identity` key.
+    pub fn address_or_id(&self) -> String {
+        normalize_address(&self.address).unwrap_or_else(|| self.id.clone())
+    }
+
+    /// Returns the identifier to display for the device according to `mode`.
+    pub fn display_identity(&self, mode: IdentityDisplay) -> String {
+        match mode {
+            IdentityDisplay::AddressPreferred => self.address_or_id(),
+            IdentityDisplay::IdPreferred => self.id.clone(),
+        }
+    }
+}
+
+/// A coarse distance band a device is placed in by its RSSI.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ProximityZone {
+    Immediate,
+    Near,
+    Far,
+}
+
+impl ProximityZone {
+    /// Every zone, from the closest to the farthest.
+    pub const ALL: [ProximityZone; 3] = [
+        ProximityZone::Immediate,
+        ProximityZone::Near,
+        ProximityZone::Far,
+    ];
+
+    /// Returns a short lowercase label for the zone.
+    pub fn label(self) -> &'static str {
+        match self {
+            ProximityZone::Immediate => "immediate",
+            ProximityZone::Near => "near",
+            ProximityZone::Far => "far",
+        }
+    }
+}
+
+/// How devices that stop advertising are handled during a session.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum SessionMode {
+    /// Stale devices are removed, so the list only shows devices currently present.
+    #[default]
+    Live,
+    /// Stale devices are kept and marked, so the list is a census of the whole session.
+    Accumulate,
+}
+
+impl SessionMode {
+    /// Returns the name of the mode as given on the command line.
+    pub fn label(self) -> &'static str {
+        match self {
+            SessionMode::Live => "live",
+            SessionMode::Accumulate => "accumulate",
+        }
+    }
+}
+
+impl FromStr for SessionMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "live" => Ok(SessionMode::Live),
+            "accumulate" => Ok(SessionMode::Accumulate),
+            other => Err(format!(
+                "invalid session mode '{}', expected 'live' or 'accumulate'",
+                other
+            )),
+        }
+    }
+}
+
+/// Which devices are dropped first when the device cap is exceeded.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum EvictionPolicy {
+    /// The devices that have gone the longest without advertising.
+    #[default]
+    OldestLastSeen,
+    /// The devices with the weakest signal; devices without an RSSI reading go first.
+    WeakestRssi,
+}
+
+impl FromStr for EvictionPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "oldest" => Ok(EvictionPolicy::OldestLastSeen),
+            "weakest" => Ok(EvictionPolicy::WeakestRssi),
+            other => Err(format!(
+                "invalid eviction policy '{}', expected 'oldest' or 'weakest'",
+                other
+            )),
+        }
+    }
+}
+
+/// The order of the device table.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum SortKey {
+    /// The order devices were first discovered in.
+    #[default]
+    Discovered,
+    /// The most recently seen devices first.
+    LastSeen,
+    /// The strongest signal first; devices without an RSSI reading go last.
+    Rssi,
+}
+
+impl SortKey {
+    /// Returns the next sort key in the cycle.
+    pub fn next(self) -> Self {
+        match self {
+            SortKey::Discovered => SortKey::LastSeen,
+            SortKey::LastSeen => SortKey::Rssi,
+            SortKey::Rssi => SortKey::Discovered,
+        }
+    }
+
+    /// Returns `true` if the key's natural order is descending: the newest or strongest
+    /// first.
+    pub fn descending(self) -> bool {
+        self != SortKey::Discovered
+    }
+
+    /// Returns the name of the sort key, as accepted by `--sort`.
+    pub fn label(self) -> &'static str {
+        match self {
+            SortKey::Discovered => "discovered",
+            SortKey::LastSeen => "last-seen",
+            SortKey::Rssi => "rssi",
+        }
+    }
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "discovered" => Ok(SortKey::Discovered),
+            "last-seen" => Ok(SortKey::LastSeen),
+            "rssi" => Ok(SortKey::Rssi),
+            other => Err(format!(
+                "invalid sort key '{}', expected 'discovered', 'last-seen' or 'rssi'",
+                other
+            )),
+        }
+    }
+}
+
+/// The characters used to draw the signal bar in the RSSI column.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum RssiBarStyle {
+    #[default]
+    Blocks,
+    Ascii,
+    Dots,
+    /// Only the number is shown.
+    Off,
+}
+
+impl RssiBarStyle {
+    /// Returns the characters for a filled and an empty step of the bar.
+    pub fn chars(self) -> Option<(char, char)> {
+        match self {
+            RssiBarStyle::Blocks => Some(('▆', ' ')),
+            RssiBarStyle::Ascii => Some(('#', '.')),
+            RssiBarStyle::Dots => Some(('●', '○')),
+            RssiBarStyle::Off => None,
+        }
+    }
+}
+
+impl FromStr for RssiBarStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "blocks" => Ok(RssiBarStyle::Blocks),
+            "ascii" => Ok(RssiBarStyle::Ascii),
+            "dots" => Ok(RssiBarStyle::Dots),
+            "off" => Ok(RssiBarStyle::Off),
+            other => Err(format!(
+                "invalid RSSI bar style '{}', expected 'blocks', 'ascii', 'dots' or 'off'",
+                other
+            )),
+        }
+    }
+}
+
+/// The key devices are identified by when merging updates, chosen with `--identity`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum IdentityKey {
+    /// The address, or the platform id for devices whose address is hidden.
+    #[default]
+    Auto,
+    /// The address only; devices whose address is hidden are left out.
+    Address,
+    /// The platform id, which on Linux differs per adapter.
+    Id,
+}
+
+impl IdentityKey {
+    /// Returns `true` if devices are left out of the list under this key: those whose
+    /// address is hidden, when keying on the address.
+    pub fn excludes(self, device: &DeviceInfo) -> bool {
+        self == IdentityKey::Address && normalize_address(&device.address).is_none()
+    }
+}
+
+impl FromStr for IdentityKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(IdentityKey::Auto),
+            "address" => Ok(IdentityKey::Address),
+            "id" => Ok(IdentityKey::Id),
+            other => Err(format!(
+                "invalid identity key '{}', expected 'address', 'id' or 'auto'",
+                other
+            )),
+        }
+    }
+}
+
+/// Which identifier is shown in the primary column of the device table.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum IdentityDisplay {
+    /// The address, falling back to the platform id when no address is available.
+    #[default]
+    AddressPreferred,
+    /// The platform id (a UUID on macOS), even when an address is available.
+    IdPreferred,
+}
+
+impl IdentityDisplay {
+    /// Returns the other display mode.
+    pub fn toggle(self) -> Self {
+        match self {
+            IdentityDisplay::AddressPreferred => IdentityDisplay::IdPreferred,
+            IdentityDisplay::IdPreferred => IdentityDisplay::AddressPreferred,
+        }
+    }
+
+    /// Returns the column header for the display mode.
+    pub fn header(self) -> &'static str {
+        match self {
+            IdentityDisplay::AddressPreferred => "Address",
+            IdentityDisplay::IdPreferred => "ID",
+        }
+    }
+}
+
+/// The label shown in place of a name for devices that do not advertise one.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum NameFallback {
+    #[default]
+    Unnamed,
+    Manufacturer,
+    Address,
+}
+
+impl NameFallback {
+    /// Returns the next fallback in the cycle.
+    pub fn next(self) -> Self {
+        match self {
+            NameFallback::Unnamed => NameFallback::Manufacturer,
+            NameFallback::Manufacturer => NameFallback::Address,
+            NameFallback::Address => NameFallback::Unnamed,
+        }
+    }
+
+    /// Returns a short description of the fallback.
+    pub fn label(self) -> &'static str {
+        match self {
+            NameFallback::Unnamed => "unnamed",
+            NameFallback::Manufacturer => "manufacturer",
+            NameFallback::Address => "address",
+        }
+    }
+}
+
+/// Which devices the table shows by the type of their address.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum AddressTypeFilter {
+    #[default]
+    All,
+    /// Addresses assigned by the manufacturer.
+    Public,
+    /// Random addresses that stay the same until the device restarts: the two most
+    /// significant bits are set.
+    RandomStatic,
+    /// Resolvable and non-resolvable private addresses, which devices rotate for privacy.
+    RandomPrivate,
+    /// Devices whose address type the platform does not report.
+    Unknown,
+}
+
+impl AddressTypeFilter {
+    /// Returns the next filter in the cycle.
+    pub fn next(self) -> Self {
+        match self {
+            AddressTypeFilter::All => AddressTypeFilter::Public,
+            AddressTypeFilter::Public => AddressTypeFilter::RandomStatic,
+            AddressTypeFilter::RandomStatic => AddressTypeFilter::RandomPrivate,
+            AddressTypeFilter::RandomPrivate => AddressTypeFilter::Unknown,
+            AddressTypeFilter::Unknown => AddressTypeFilter::All,
+        }
+    }
+
+    /// Returns a short description of the filter.
+    pub fn label(self) -> &'static str {
+        match self {
+            AddressTypeFilter::All => "all",
+            AddressTypeFilter::Public => "public",
+            AddressTypeFilter::RandomStatic => "random static",
+            AddressTypeFilter::RandomPrivate => "random private",
+            AddressTypeFilter::Unknown => "unknown",
+        }
+    }
+
+    /// Returns `true` if the filter lets the device through.
+    pub fn matches(self, device: &DeviceInfo) -> bool {
+        let random_static = || {
+            device
+                .address
+                .split(':')
+                .next()
+                .and_then(|octet| u8::from_str_radix(octet.trim(), 16).ok())
+                .is_some_and(|octet| octet >> 6 == 0b11)
+        };
+        match (self, device.address_type) {
+            (AddressTypeFilter::All, _) => true,
+            (AddressTypeFilter::Public, Some(AddressType::Public)) => true,
+            (AddressTypeFilter::RandomStatic, Some(AddressType::Random)) => random_static(),
+            (AddressTypeFilter::RandomPrivate, Some(AddressType::Random)) => !random_static(),
+            (AddressTypeFilter::Unknown, None) => true,
+            _ => false,
+        }
+    }
+}
+
+/// How the locator signals the proximity of the tracked device.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum ProximityFeedback {
+    #[default]
+    Off,
+    /// Rings the terminal bell and pulses the proximity gauge.
+    Bell,
+    /// Only pulses the proximity gauge.
+    Flash,
+}
+
+impl ProximityFeedback {
+    /// Returns the next feedback mode in the cycle.
+    pub fn next(self) -> Self {
+        match self {
+            ProximityFeedback::Off => ProximityFeedback::Bell,
+            ProximityFeedback::Bell => ProximityFeedback::Flash,
+            ProximityFeedback::Flash => ProximityFeedback::Off,
+        }
+    }
+
+    /// Returns a short description of the feedback mode.
+    pub fn label(self) -> &'static str {
+        match self {
+            ProximityFeedback::Off => "off",
+            ProximityFeedback::Bell => "bell",
+            ProximityFeedback::Flash => "flash",
+        }
+    }
+}
+
+/// The state of the connection to the MQTT broker.
+#[derive(Clone)]
+pub enum MqttStatus {
+    Connecting,
+    Connected,
+    /// The connection failed or dropped with this error; it is retried.
+    Disconnected(String),
+}
+
+impl MqttStatus {
+    /// Returns a short description of the status for the status bar.
+    pub fn label(&self) -> &'static str {
+        match self {
+            MqttStatus::Connecting => "connecting",
+            MqttStatus::Connected => "connected",
+            MqttStatus::Disconnected(_) => "disconnected",
+        }
+    }
+}
+
+/// Where a connection attempt to a device stands, for the connecting indicator.
+#[derive(Clone, Copy)]
+pub struct ConnectProgress {
+    /// The current attempt, starting at 1.
+    pub attempt: u32,
+    pub attempts: u32,
+    /// When the attempt times out or, while `retrying` is set, when the next one starts.
+    pub deadline: Instant,
+    /// Whether the connection is waiting to retry after a failed attempt.
+    pub retrying: bool,
+}
+
+/// A device the locator is locked onto.
+pub struct TrackedDevice {
+    pub identity: String,
+    /// The RSSI smoothed over the device's recent advertisements, in dBm.
+    pub smoothed_rssi: Option<f64>,
+    /// When proximity feedback was last given for the device.
+    pub last_pulse: Option<Instant>,
+}
+
+/// A coarse category for a device, classified from its advertisement or set by the user.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum DeviceCategory {
+    Phone,
+    Laptop,
+    Audio,
+    Wearable,
+    Beacon,
+    Tracker,
+    Sensor,
+    Peripheral,
+    #[default]
+    Unknown,
+}
+
+impl DeviceCategory {
+    /// Every category, in the order they are cycled and listed.
+    pub const ALL: [DeviceCategory; 9] = [
+        DeviceCategory::Phone,
+        DeviceCategory::Laptop,
+        DeviceCategory::Audio,
+        DeviceCategory::Wearable,
+        DeviceCategory::Beacon,
+        DeviceCategory::Tracker,
+        DeviceCategory::Sensor,
+        DeviceCategory::Peripheral,
+        DeviceCategory::Unknown,
+    ];
+
+    /// Returns the next category in the cycle.
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|c| *c == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// Returns a short lowercase label for the category.
+    pub fn label(self) -> &'static str {
+        match self {
+            DeviceCategory::Phone => "phone",
+            DeviceCategory::Laptop => "laptop",
+            DeviceCategory::Audio => "audio",
+            DeviceCategory::Wearable => "wearable",
+            DeviceCategory::Beacon => "beacon",
+            DeviceCategory::Tracker => "tracker",
+            DeviceCategory::Sensor => "sensor",
+            DeviceCategory::Peripheral => "peripheral",
+            DeviceCategory::Unknown => "unknown",
         }
     }
 }
@@ -66,18 +694,467 @@ pub struct Characteristic {
     pub service: Uuid,
 }
 
-/// A struct to hold the information of a GATT Descriptor.
+/// A struct to hold one manufacturer-specific data entry of an advertisement.
+#[derive(serde::Serialize)]
 pub struct ManufacturerData {
-    pub company_code: String,
+    pub company_id: u16,
+    pub company: String,
     pub data: String,
 }
 
-/// A struct to hold data for a CSV file.
+/// A struct to hold a single length/type/value AD structure of an advertisement.
+#[derive(Debug, PartialEq)]
+pub struct AdStructure {
+    pub ad_type: u8,
+    pub data: Vec<u8>,
+}
+
+/// A struct to hold a labeled value decoded from advertisement data.
+#[derive(Clone, serde::Serialize)]
+pub struct DecodedField {
+    pub label: String,
+    pub value: String,
+}
+
+impl DecodedField {
+    /// Creates a new `DecodedField` with the provided label and value.
+    pub fn new(label: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            label: label.into(),
+            value: value.into(),
+        }
+    }
+}
+
+/// What the session statistics keep of a device seen during the session. The entries
+/// outlive the device list, so devices that were evicted or dropped by the cap still count.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SeenDevice {
+    /// Whether any advertisement of the device carried a name.
+    pub named: bool,
+    /// The company of the latest manufacturer data, or `n/a` if none was known.
+    pub manufacturer: String,
+    pub address_type: &'static str,
+    /// The latest RSSI reading.
+    pub rssi: Option<i16>,
+    /// The longest presence episode of the device so far.
+    pub longest_dwell: Duration,
+}
+
+impl SeenDevice {
+    /// Starts the entry of a device seen for the first time.
+    pub fn new(device: &DeviceInfo) -> Self {
+        Self {
+            named: device.name.is_some(),
+            manufacturer: company_name(&device.manufacturer_data)
+                .unwrap_or_else(|| "n/a".to_string()),
+            address_type: device.address_type_label(),
+            rssi: device.rssi,
+            longest_dwell: device.dwell(),
+        }
+    }
+
+    /// Updates the entry with a new observation of the device.
+    pub fn update(&mut self, device: &DeviceInfo) {
+        self.named |= device.name.is_some();
+        if let Some(company) = company_name(&device.manufacturer_data) {
+            self.manufacturer = company;
+        }
+        self.address_type = device.address_type_label();
+        self.rssi = device.rssi.or(self.rssi);
+        self.longest_dwell = self.longest_dwell.max(device.dwell());
+    }
+}
+
+/// A struct to hold the statistics of a scanning session.
+pub struct SessionStats {
+    pub total: usize,
+    pub named: usize,
+    pub unnamed: usize,
+    pub duration: Duration,
+    pub by_manufacturer: Vec<(String, usize)>,
+    pub by_address_type: Vec<(&'static str, usize)>,
+    pub rssi_histogram: Vec<(&'static str, u64)>,
+    pub exposure_rpis: usize,
+    pub trackers: usize,
+    pub longest_dwell: Duration,
+    pub average_dwell: Duration,
+    pub observations: usize,
+}
+
+/// A struct to hold the state that is kept across runs.
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct PersistedState {
+    /// Local aliases keyed by the platform id of the device.
+    pub aliases: HashMap<String, String>,
+    /// Categories set by the user in place of the classified ones, keyed like aliases.
+    pub categories: HashMap<String, DeviceCategory>,
+    /// Free-text notes taken on devices, keyed like aliases.
+    pub notes: HashMap<String, String>,
+}
+
+/// A column of the CSV export.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CsvColumn {
+    Id,
+    /// The name the device advertised, empty when it advertised none.
+    Name,
+    /// The label the table shows: the alias, the advertised name, or the fallback for
+    /// unnamed devices.
+    Label,
+    Alias,
+    Note,
+    TxPower,
+    Address,
+    DeviceType,
+    Rssi,
+    Company,
+    DetectedAt,
+    FirstSeen,
+    LastSeen,
+    Services,
+    ServiceCount,
+    SolicitedServices,
+    DwellSecs,
+    ManufacturerData,
+    Category,
+    Tags,
+    /// Every decoded field, as `label: value` pairs.
+    Decoded,
+    /// The value of the decoded field with this label, e.g. `Ruuvi Temperature`.
+    DecodedField(String),
+}
+
+impl CsvColumn {
+    /// The columns written when none are configured.
+    pub const DEFAULT: [CsvColumn; 16] = [
+        CsvColumn::Id,
+        CsvColumn::Name,
+        CsvColumn::Label,
+        CsvColumn::Alias,
+        CsvColumn::Note,
+        CsvColumn::TxPower,
+        CsvColumn::Address,
+        CsvColumn::DeviceType,
+        CsvColumn::Rssi,
+        CsvColumn::Services,
+        CsvColumn::SolicitedServices,
+        CsvColumn::DwellSecs,
+        CsvColumn::ManufacturerData,
+        CsvColumn::Category,
+        CsvColumn::Tags,
+        CsvColumn::Decoded,
+    ];
+
+    /// Returns the column header, which is also the name used to select the column.
+    pub fn header(&self) -> &str {
+        match self {
+            CsvColumn::Id => "id",
+            CsvColumn::Name => "name",
+            CsvColumn::Label => "label",
+            CsvColumn::Alias => "alias",
+            CsvColumn::Note => "note",
+            CsvColumn::TxPower => "tx_power",
+            CsvColumn::Address => "address",
+            CsvColumn::DeviceType => "device_type",
+            CsvColumn::Rssi => "rssi",
+            CsvColumn::Company => "company",
+            CsvColumn::DetectedAt => "detected_at",
+            CsvColumn::FirstSeen => "first_seen",
+            CsvColumn::LastSeen => "last_seen",
+            CsvColumn::Services => "services",
+            CsvColumn::ServiceCount => "service_count",
+            CsvColumn::SolicitedServices => "solicited_services",
+            CsvColumn::DwellSecs => "dwell_secs",
+            CsvColumn::ManufacturerData => "manufacturer_data",
+            CsvColumn::Category => "category",
+            CsvColumn::Tags => "tags",
+            CsvColumn::Decoded => "decoded",
+            CsvColumn::DecodedField(label) => label,
+        }
+    }
+}
+
+impl FromStr for CsvColumn {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(label) = s.strip_prefix("decoded:") {
+            return Ok(CsvColumn::DecodedField(label.to_string()));
+        }
+        [
+            CsvColumn::Company,
+            CsvColumn::DetectedAt,
+            CsvColumn::FirstSeen,
+            CsvColumn::LastSeen,
+            CsvColumn::ServiceCount,
+        ]
+        .into_iter()
+        .chain(CsvColumn::DEFAULT)
+        .find(|column| column.header() == s)
+        .ok_or_else(|| format!("unknown CSV column '{}'", s))
+    }
+}
+
+/// The version of the JSON export format. Bump it whenever a field is renamed, removed,
+/// or changes meaning, so consumers can detect breaking changes.
+pub const EXPORT_SCHEMA_VERSION: u32 = 1;
+
+/// Running statistics of a device's RSSI readings.
+#[derive(Clone, Copy)]
+pub struct RssiStats {
+    pub min: i16,
+    pub max: i16,
+    sum: i64,
+    pub count: usize,
+}
+
+impl RssiStats {
+    /// Creates statistics holding a single reading.
+    pub fn new(rssi: i16) -> Self {
+        Self {
+            min: rssi,
+            max: rssi,
+            sum: i64::from(rssi),
+            count: 1,
+        }
+    }
+
+    /// Adds a reading.
+    pub fn add(&mut self, rssi: i16) {
+        self.min = self.min.min(rssi);
+        self.max = self.max.max(rssi);
+        self.sum += i64::from(rssi);
+        self.count += 1;
+    }
+
+    /// Returns the mean of the readings.
+    pub fn mean(&self) -> f64 {
+        self.sum as f64 / self.count as f64
+    }
+}
+
+/// A struct to hold the top-level document of a JSON export.
+#[derive(serde::Serialize)]
+pub struct DeviceExport {
+    pub schema_version: u32,
+    pub crate_version: &'static str,
+    pub exported_at: String,
+    pub session: ExportSession,
+    pub devices: Vec<DeviceJson>,
+}
+
+/// A struct to hold the session a JSON export was taken from.
+#[derive(serde::Serialize)]
+pub struct ExportSession {
+    pub started_at: String,
+    /// The address of the adapter that scanned, where the platform exposes it.
+    pub adapter: Option<String>,
+    pub filters: ExportFilters,
+}
+
+/// A struct to hold the filters that were active when a JSON export was taken.
+#[derive(serde::Serialize)]
+pub struct ExportFilters {
+    pub session_mode: &'static str,
+    pub min_rssi: Option<i16>,
+    pub tag: Option<&'static str>,
+    pub category: Option<DeviceCategory>,
+    pub manufacturer: Option<u16>,
+    pub address_type: Option<&'static str>,
+    pub excluded_addresses: Vec<String>,
+}
+
+/// A struct to hold the data of a device in a JSON export.
+#[derive(serde::Serialize)]
+pub struct DeviceJson {
+    pub id: String,
+    pub address: String,
+    pub address_type: &'static str,
+    pub device_type: &'static str,
+    pub class: Option<u32>,
+    pub name: Option<String>,
+    pub alias: Option<String>,
+    pub note: Option<String>,
+    pub tx_power: Option<i16>,
+    pub rssi: Option<i16>,
+    pub rssi_min: Option<i16>,
+    pub rssi_max: Option<i16>,
+    pub rssi_mean: Option<f64>,
+    pub rssi_samples: usize,
+    pub detected_at: String,
+    pub first_seen: String,
+    pub last_seen: String,
+    pub dwell_secs: u64,
+    pub manufacturer_data: Vec<ManufacturerData>,
+    /// Service data payloads as hex, keyed by service UUID.
+    pub service_data: BTreeMap<String, String>,
+    pub services: Vec<String>,
+    pub solicited_services: Vec<String>,
+    pub category: DeviceCategory,
+    pub tags: Vec<&'static str>,
+    pub decoded: Vec<DecodedField>,
+}
+
+/// What the database knows about a device.
+#[derive(Clone, serde::Serialize)]
+pub struct DeviceHistory {
+    pub address: String,
+    pub name: Option<String>,
+    /// Every name the device has advertised, oldest first.
+    pub names: Vec<String>,
+    pub first_seen: String,
+    pub last_seen: String,
+    pub sightings: u64,
+    /// The number of distinct days, in local time, the device was sighted on.
+    pub days_seen: u64,
+    pub max_rssi: Option<i16>,
+}
+
+/// A struct to hold a device and its history, as served by the HTTP API and written by the
+/// single-device export.
 #[derive(serde::Serialize)]
-pub struct DeviceCsv {
+pub struct DeviceDetailJson {
+    #[serde(flatten)]
+    pub device: DeviceJson,
+    /// Names the device advertised before its current one, oldest first.
+    pub previous_names: Vec<String>,
+    /// The RSSI readings kept with --rssi-history, oldest first.
+    pub rssi_history: Vec<RssiSampleJson>,
+    /// What the --db database knew about the device before this session.
+    pub history: Option<DeviceHistory>,
+    /// The advertising payload, as hex.
+    pub raw_advertisement: String,
+    /// Whether the payload was rebuilt from the parsed fields rather than captured.
+    pub raw_reconstructed: bool,
+    /// The GATT characteristics read when the device was last connected to this session.
+    pub characteristics: Vec<CharacteristicJson>,
+}
+
+/// A struct to hold a GATT characteristic in the JSON formats.
+#[derive(serde::Serialize)]
+pub struct CharacteristicJson {
+    pub service: String,
+    pub uuid: String,
+    pub properties: Vec<String>,
+    pub descriptors: Vec<String>,
+}
+
+/// A struct to hold a timestamped RSSI reading.
+#[derive(serde::Serialize)]
+pub struct RssiSampleJson {
+    pub timestamp: String,
+    pub rssi: i16,
+}
+
+/// A struct to hold the top-level document of a session dump written on exit.
+#[derive(serde::Serialize)]
+pub struct SessionExport {
+    pub schema_version: u32,
+    pub crate_version: &'static str,
+    pub started_at: String,
+    pub ended_at: String,
+    pub devices: Vec<SessionDeviceJson>,
+}
+
+/// A struct to hold everything recorded about a device over a whole session.
+#[derive(serde::Serialize)]
+pub struct SessionDeviceJson {
+    pub id: String,
+    pub address: String,
+    pub address_type: &'static str,
+    pub device_type: &'static str,
+    pub class: Option<u32>,
+    pub name: Option<String>,
+    pub tx_power: Option<i16>,
+    pub first_seen: String,
+    pub last_seen: String,
+    pub seen_count: usize,
+    pub rssi_min: Option<i16>,
+    pub rssi_max: Option<i16>,
+    pub rssi_mean: Option<f64>,
+    pub manufacturer_data: Vec<ManufacturerData>,
+    pub services: Vec<String>,
+    pub solicited_services: Vec<String>,
+    pub tags: Vec<&'static str>,
+    pub decoded: Vec<DecodedField>,
+}
+
+/// A struct to hold one line of the event log.
+#[derive(serde::Serialize)]
+pub struct LogEvent {
+    pub timestamp: String,
+    /// `discovered` for the first observation of a device, `updated` afterwards.
+    pub event: &'static str,
     pub id: String,
-    pub name: String,
-    pub tx_power: String,
     pub address: String,
-    pub rssi: String,
+    pub name: Option<String>,
+    pub rssi: Option<i16>,
+    pub changed: Vec<&'static str>,
+    pub decoded: Vec<DecodedField>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(address: &str, id: &str) -> DeviceInfo {
+        DeviceInfo {
+            address: address.to_string(),
+            id: id.to_string(),
+            ..DeviceInfo::default()
+        }
+    }
+
+    #[test]
+    fn get_id_keys_on_the_identity_key() {
+        let visible = device("aa:bb:cc:dd:ee:01", "hci0/dev_AA_BB_CC_DD_EE_01");
+        let hidden = device("00:00:00:00:00:00", "3F2A-91C0");
+        let cases = [
+            (&visible, IdentityKey::Auto, "AA:BB:CC:DD:EE:01"),
+            (&visible, IdentityKey::Address, "AA:BB:CC:DD:EE:01"),
+            (&visible, IdentityKey::Id, "hci0/dev_AA_BB_CC_DD_EE_01"),
+            (&hidden, IdentityKey::Auto, "3F2A-91C0"),
+            (&hidden, IdentityKey::Address, ""),
+            (&hidden, IdentityKey::Id, "3F2A-91C0"),
+        ];
+        for (device, key, identity) in cases {
+            assert_eq!(device.get_id(key), identity, "{:?}", key);
+        }
+    }
+
+    #[test]
+    fn display_name_falls_back_for_unnamed_devices() {
+        let named = DeviceInfo {
+            name: Some("Lamp".to_string()),
+            ..device("aa:bb:cc:dd:ee:01", "hci0/dev_AA_BB_CC_DD_EE_01")
+        };
+        let apple = DeviceInfo {
+            manufacturer_data: HashMap::from([(0x004C, vec![0x10])]),
+            ..device("aa:bb:cc:dd:ee:02", "hci0/dev_AA_BB_CC_DD_EE_02")
+        };
+        let hidden = device("00:00:00:00:00:00", "3F2A-91C0");
+        let cases = [
+            (&named, NameFallback::Unnamed, "Lamp"),
+            (&named, NameFallback::Address, "Lamp"),
+            (&apple, NameFallback::Unnamed, "(unnamed)"),
+            (&apple, NameFallback::Manufacturer, "Apple, Inc."),
+            (&apple, NameFallback::Address, "AA:BB:CC:DD:EE:02"),
+            (&hidden, NameFallback::Manufacturer, "3F2A-91C0"),
+            (&hidden, NameFallback::Address, "3F2A-91C0"),
+        ];
+        for (device, fallback, label) in cases {
+            assert_eq!(device.display_name(fallback), label, "{:?}", fallback);
+        }
+    }
+
+    #[test]
+    fn only_the_address_key_excludes_hidden_addresses() {
+        let hidden = device("00:00:00:00:00:00", "3F2A-91C0");
+        assert!(IdentityKey::Address.excludes(&hidden));
+        assert!(!IdentityKey::Auto.excludes(&hidden));
+        assert!(!IdentityKey::Id.excludes(&hidden));
+        assert!(!IdentityKey::Address.excludes(&device("AA:BB:CC:DD:EE:01", "x")));
+    }
 }
@@ -0,0 +1,159 @@
+use std::{
+    collections::HashMap,
+    io,
+    time::{Duration, Instant},
+};
+
+#[cfg(unix)]
+use std::os::unix::net::UnixDatagram;
+
+/// The socket journald reads structured entries from.
+#[cfg(unix)]
+const JOURNALD_SOCKET: &str = "/run/systemd/journal/socket";
+
+/// The socket the system logger reads syslog messages from.
+#[cfg(unix)]
+const SYSLOG_SOCKET: &str = "/dev/log";
+
+/// The syslog facility of daemons, which messages are logged under.
+const FACILITY_DAEMON: u8 = 3;
+
+/// How many messages one device may send within `RATE_WINDOW`; the rest are dropped.
+const RATE_LIMIT: usize = 5;
+
+/// The window `RATE_LIMIT` applies to.
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// The syslog severity of a presence event.
+#[derive(Clone, Copy)]
+pub enum Priority {
+    Warning = 4,
+    Notice = 5,
+    Info = 6,
+}
+
+/// A presence event to log: a watched device appearing, coming near or disappearing, or a
+/// tracker being detected.
+pub struct PresenceEvent<'a> {
+    /// A short lowercase name of the event, e.g. `appeared`.
+    pub event: &'a str,
+    pub identity: &'a str,
+    pub name: Option<&'a str>,
+    pub rssi: Option<i16>,
+    pub message: &'a str,
+    pub priority: Priority,
+}
+
+/// Sends presence events to the system journal, or to syslog where journald is not running.
+/// Journal entries carry the event, address, name and RSSI as `BTLESCAN_*` fields; syslog
+/// messages append them as `key=value` pairs. Each device may send at most `RATE_LIMIT`
+/// messages per `RATE_WINDOW`.
+pub struct SyslogWriter {
+    #[cfg(unix)]
+    socket: UnixDatagram,
+    journald: bool,
+    sent: HashMap<String, (Instant, usize)>,
+}
+
+impl SyslogWriter {
+    /// Connects to journald, falling back to the syslog socket.
+    #[cfg(unix)]
+    pub fn open() -> io::Result<Self> {
+        let socket = UnixDatagram::unbound()?;
+        let journald = socket.connect(JOURNALD_SOCKET).is_ok();
+        if !journald {
+            socket.connect(SYSLOG_SOCKET)?;
+        }
+        Ok(Self {
+            socket,
+            journald,
+            sent: HashMap::new(),
+        })
+    }
+
+    /// There is no system logger to connect to on this platform.
+    #[cfg(not(unix))]
+    pub fn open() -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "syslog is not available on this platform",
+        ))
+    }
+
+    /// Sends an event, unless its device has used up its rate limit. Fails if the logger
+    /// does not accept it, e.g. after journald was restarted.
+    pub fn send(&mut self, event: &PresenceEvent) -> io::Result<()> {
+        let now = Instant::now();
+        let (window_start, count) = self
+            .sent
+            .entry(event.identity.to_string())
+            .or_insert((now, 0));
+        if now.duration_since(*window_start) >= RATE_WINDOW {
+            *window_start = now;
+            *count = 0;
+        }
+        if *count >= RATE_LIMIT {
+            return Ok(());
+        }
+        *count += 1;
+        let message = if self.journald {
+            journal_entry(event)
+        } else {
+            syslog_message(event)
+        };
+        self.write(message.as_bytes())
+    }
+
+    #[cfg(unix)]
+    fn write(&self, message: &[u8]) -> io::Result<()> {
+        self.socket.send(message).map(|_| ())
+    }
+
+    #[cfg(not(unix))]
+    fn write(&self, _message: &[u8]) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Formats an event in the journal's native protocol, one `FIELD=value` per line. Line
+/// breaks in values would need the binary form of the protocol, so they become spaces.
+fn journal_entry(event: &PresenceEvent) -> String {
+    let mut fields = vec![
+        ("MESSAGE", event.message.to_string()),
+        ("PRIORITY", (event.priority as u8).to_string()),
+        ("SYSLOG_FACILITY", FACILITY_DAEMON.to_string()),
+        ("SYSLOG_IDENTIFIER", "btlescan".to_string()),
+        ("BTLESCAN_EVENT", event.event.to_string()),
+        ("BTLESCAN_ADDRESS", event.identity.to_string()),
+    ];
+    if let Some(name) = event.name {
+        fields.push(("BTLESCAN_NAME", name.to_string()));
+    }
+    if let Some(rssi) = event.rssi {
+        fields.push(("BTLESCAN_RSSI", rssi.to_string()));
+    }
+    fields
+        .into_iter()
+        .map(|(key, value)| format!("{}={}\n", key, value.replace(['\n', '\r'], " ")))
+        .collect()
+}
+
+/// Formats an event as a BSD syslog message with its fields appended as `key=value`.
+fn syslog_message(event: &PresenceEvent) -> String {
+    let mut message = format!(
+        "<{}>{} btlescan[{}]: {} event={} address={}",
+        FACILITY_DAEMON * 8 + event.priority as u8,
+        chrono::Local::now().format("%b %e %H:%M:%S"),
+        std::process::id(),
+        event.message,
+        event.event,
+        event.identity
+    );
+    if let Some(name) = event.name {
+        message.push_str(&format!(" name={:?}", name));
+    }
+    if let Some(rssi) = event.rssi {
+        message.push_str(&format!(" rssi={}", rssi));
+    }
+    message.replace(['\n', '\r'], " ")
+}
@@ -1,40 +1,536 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
 
 use ratatui::layout::Rect;
+use uuid::Uuid;
 
-use crate::{company_codes::COMPANY_CODE, structs::ManufacturerData};
+use crate::{
+    service_names::SERVICE_NAME,
+    structs::{AdStructure, DeviceInfo, ManufacturerData, ProximityZone, SeenDevice, SessionStats},
+    vendor_db,
+};
 
-/// Extracts the manufacturer data from a `HashMap<u16, Vec<u8>>` and returns a tuple with the company name and the manufacturer data as a string.
-/// If the manufacturer data is empty, it returns "n/a" as the company name and the manufacturer data.
-/// If the company code is not found in the `company_codes` module, it returns "n/a" as the company name.
-pub fn extract_manufacturer_data(manufacturer_data: &HashMap<u16, Vec<u8>>) -> ManufacturerData {
-    let mut c = None;
-    let mut m = manufacturer_data
+/// Extracts every manufacturer-specific entry from a `HashMap<u16, Vec<u8>>`, sorted by
+/// company ID. Each entry holds the company name ("n/a" when the code is not found in the
+/// `--company-file` database or the `company_codes` module) and the payload as hex.
+pub fn extract_manufacturer_data(
+    manufacturer_data: &HashMap<u16, Vec<u8>>,
+) -> Vec<ManufacturerData> {
+    let mut entries: Vec<ManufacturerData> = manufacturer_data
         .iter()
-        .map(|(&key, value)| {
-            c = Some(key);
-            let hex_string = value
-                .iter()
-                .map(|byte| format!("{:02X}", byte))
-                .collect::<Vec<String>>()
-                .join(" ");
-            hex_string.to_string()
+        .map(|(&code, value)| ManufacturerData {
+            company_id: code,
+            company: vendor_db::company(code).unwrap_or("n/a").to_string(),
+            data: bytes_to_hex(value),
         })
+        .collect();
+    entries.sort_by_key(|entry| entry.company_id);
+    entries
+}
+
+/// Normalizes a MAC address to uppercase. Returns `None` for an empty address or the
+/// all-zero placeholder that platforms hiding the address report.
+pub fn normalize_address(address: &str) -> Option<String> {
+    let normalized = address.trim().to_uppercase();
+    if normalized.is_empty() || normalized.chars().all(|c| c == '0' || c == ':') {
+        None
+    } else {
+        Some(normalized)
+    }
+}
+
+/// Returns the vendor that the first three octets of a MAC address are assigned to.
+/// Locally administered addresses, which include every random address, carry no assignment.
+pub fn oui_vendor(address: &str) -> Option<&'static str> {
+    let octets: Vec<u8> = address
+        .split(':')
+        .take(3)
+        .map(|octet| u8::from_str_radix(octet, 16))
+        .collect::<Result<_, _>>()
+        .ok()?;
+    if octets.len() < 3 || octets[0] & 0x02 != 0 {
+        return None;
+    }
+    let prefix = u32::from_be_bytes([0, octets[0], octets[1], octets[2]]);
+    vendor_db::vendor(prefix)
+}
+
+/// Returns a label for a service UUID: the assigned name of a SIG service, the short form
+/// of another 16-bit UUID, or the full UUID.
+pub fn service_label(uuid: &Uuid) -> String {
+    match short_uuid(uuid) {
+        Some(short) => SERVICE_NAME
+            .get(&short)
+            .map_or_else(|| format!("0x{:04X}", short), |name| name.to_string()),
+        None => uuid.to_string(),
+    }
+}
+
+/// Capabilities recognized from the services a device advertises, tried in order. Each
+/// entry needs all of its services, which it then accounts for, so combinations come
+/// before the single services they contain.
+const CAPABILITIES: &[(&[u16], &str)] = &[
+    (&[0x181B, 0x181D], "body-composition scale"),
+    (&[0x1816, 0x1818], "cycling power and cadence sensor"),
+    (&[0x1802, 0x1803, 0x1804], "proximity tag"),
+    (&[0x180D], "heart-rate monitor"),
+    (&[0x1809], "thermometer"),
+    (&[0x1810], "blood-pressure monitor"),
+    (&[0x1808], "glucose meter"),
+    (&[0x181F], "continuous glucose monitor"),
+    (&[0x1822], "pulse oximeter"),
+    (&[0x181D], "scale"),
+    (&[0x1816], "speed and cadence sensor"),
+    (&[0x1818], "power meter"),
+    (&[0x1814], "running sensor"),
+    (&[0x1826], "fitness machine"),
+    (&[0x181A], "environmental sensor"),
+    (&[0x1812], "keyboard, mouse or controller (HID)"),
+    (&[0x1819], "location and navigation"),
+    (&[0x1802], "find me"),
+    (&[0x1827], "mesh provisioning"),
+    (&[0x1828], "mesh proxy"),
+    (&[0x184E], "LE Audio"),
+    (&[0x180F], "battery"),
+    (&[0x180A], "device info"),
+    (&[0xFE59], "DFU"),
+];
+
+/// The services every GATT server has, which say nothing about the device.
+const GENERIC_SERVICES: &[u16] = &[0x1800, 0x1801];
+
+/// The legacy Nordic DFU service, which predates its 16-bit UUID.
+const LEGACY_NORDIC_DFU: Uuid = Uuid::from_u128(0x0000_1530_1212_EFDE_1523_785F_EABC_D123);
+
+/// Summarizes what a device can do from the services it advertises, e.g.
+/// "Heart-rate monitor, battery, DFU". Services without a capability contribute their
+/// name or UUID. Returns `None` if there is nothing to summarize.
+pub fn capability_summary(services: &[Uuid]) -> Option<String> {
+    let mut remaining: Vec<&Uuid> = services.iter().collect();
+    let mut parts: Vec<String> = Vec::new();
+    for (required, label) in CAPABILITIES {
+        let present = required.iter().all(|service| {
+            remaining
+                .iter()
+                .any(|uuid| short_uuid(uuid) == Some(*service))
+        });
+        if present {
+            remaining
+                .retain(|uuid| short_uuid(uuid).is_none_or(|short| !required.contains(&short)));
+            parts.push(label.to_string());
+        }
+    }
+    if remaining.contains(&&LEGACY_NORDIC_DFU) {
+        remaining.retain(|uuid| **uuid != LEGACY_NORDIC_DFU);
+        if !parts.iter().any(|part| part == "DFU") {
+            parts.push("DFU".to_string());
+        }
+    }
+    parts.extend(
+        remaining
+            .into_iter()
+            .filter(|uuid| short_uuid(uuid).is_none_or(|short| !GENERIC_SERVICES.contains(&short)))
+            .map(service_label),
+    );
+    let summary = parts.join(", ");
+    let mut chars = summary.chars();
+    let first = chars.next()?;
+    Some(first.to_uppercase().chain(chars).collect())
+}
+
+/// Returns a short tag for `text` that stays the same from run to run: four hex digits of
+/// its FNV-1a hash, folded to 16 bits.
+pub fn short_hash(text: &str) -> String {
+    let hash = text.bytes().fold(0x811c_9dc5u32, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    });
+    format!("{:04x}", (hash >> 16) ^ (hash & 0xffff))
+}
+
+/// Formats bytes as space-separated uppercase hex, e.g. `0A FF 4C`.
+pub fn bytes_to_hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{:02X}", byte))
         .collect::<Vec<String>>()
-        .join(" ");
-    m = if m.is_empty() { "n/a".to_string() } else { m };
-    match c {
-        Some(code) => ManufacturerData {
-            company_code: COMPANY_CODE.get(&code).unwrap_or(&"n/a").to_string(),
-            data: m,
-        },
-        None => ManufacturerData {
-            company_code: "n/a".to_string(),
-            data: m,
-        },
+        .join(" ")
+}
+
+/// Parses bytes written by `bytes_to_hex`. Whitespace between the bytes is optional.
+/// Returns `None` if the text is not an even number of hex digits.
+pub fn hex_to_bytes(hex: &str) -> Option<Vec<u8>> {
+    let digits: Vec<u8> = hex.bytes().filter(|c| !c.is_ascii_whitespace()).collect();
+    if !digits.len().is_multiple_of(2) {
+        return None;
+    }
+    digits
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect()
+}
+
+/// Returns the 16-bit short form of a UUID if it is based on the Bluetooth Base UUID.
+pub fn short_uuid(uuid: &Uuid) -> Option<u16> {
+    let bytes = uuid.as_bytes();
+    let base = Uuid::from_u128(0x0000_0000_0000_1000_8000_0080_5F9B_34FB).into_bytes();
+    if bytes[0..2] == [0, 0] && bytes[4..] == base[4..] {
+        Some(u16::from_be_bytes([bytes[2], bytes[3]]))
+    } else {
+        None
+    }
+}
+
+/// Splits raw advertisement bytes into their length/type/value AD structures.
+/// Parsing stops at a zero length (padding) and a truncated trailing structure keeps
+/// whatever bytes remain.
+pub fn parse_ad_structures(raw: &[u8]) -> Vec<AdStructure> {
+    let mut structures = Vec::new();
+    let mut i = 0;
+    while i < raw.len() {
+        let length = raw[i] as usize;
+        if length == 0 || i + 1 >= raw.len() {
+            break;
+        }
+        let end = usize::min(i + 1 + length, raw.len());
+        structures.push(AdStructure {
+            ad_type: raw[i + 1],
+            data: raw[i + 2..end].to_vec(),
+        });
+        i += 1 + length;
+    }
+    structures
+}
+
+/// Returns the name of an AD type as defined in the Bluetooth Assigned Numbers.
+pub fn ad_type_name(ad_type: u8) -> &'static str {
+    match ad_type {
+        0x01 => "Flags",
+        0x02 => "Incomplete List of 16-bit Service UUIDs",
+        0x03 => "Complete List of 16-bit Service UUIDs",
+        0x04 => "Incomplete List of 32-bit Service UUIDs",
+        0x05 => "Complete List of 32-bit Service UUIDs",
+        0x06 => "Incomplete List of 128-bit Service UUIDs",
+        0x07 => "Complete List of 128-bit Service UUIDs",
+        0x08 => "Shortened Local Name",
+        0x09 => "Complete Local Name",
+        0x0A => "TX Power Level",
+        0x0D => "Class of Device",
+        0x14 => "List of 16-bit Service Solicitation UUIDs",
+        0x15 => "List of 128-bit Service Solicitation UUIDs",
+        0x16 => "Service Data - 16-bit UUID",
+        0x19 => "Appearance",
+        0x1F => "List of 32-bit Service Solicitation UUIDs",
+        0x20 => "Service Data - 32-bit UUID",
+        0x21 => "Service Data - 128-bit UUID",
+        0x2A => "Mesh Message",
+        0x2B => "Mesh Beacon",
+        0xFF => "Manufacturer Specific Data",
+        _ => "Unknown",
+    }
+}
+
+/// Rebuilds advertisement bytes from the fields parsed by the platform.
+/// Entries are emitted in a stable order so the output does not depend on `HashMap` iteration.
+pub fn reconstruct_advertisement(
+    local_name: Option<&str>,
+    tx_power: Option<i16>,
+    services: &[Uuid],
+    solicited_services: &[Uuid],
+    service_data: &HashMap<Uuid, Vec<u8>>,
+    manufacturer_data: &HashMap<u16, Vec<u8>>,
+) -> Vec<u8> {
+    let mut raw = Vec::new();
+    let mut push = |ad_type: u8, data: &[u8]| {
+        let data = &data[..usize::min(data.len(), 254)];
+        raw.push(data.len() as u8 + 1);
+        raw.push(ad_type);
+        raw.extend_from_slice(data);
+    };
+
+    let short: Vec<u8> = services
+        .iter()
+        .filter_map(short_uuid)
+        .flat_map(u16::to_le_bytes)
+        .collect();
+    if !short.is_empty() {
+        push(0x03, &short);
+    }
+    let long: Vec<u8> = services
+        .iter()
+        .filter(|uuid| short_uuid(uuid).is_none())
+        .flat_map(|uuid| {
+            let mut bytes = uuid.into_bytes();
+            bytes.reverse();
+            bytes
+        })
+        .collect();
+    if !long.is_empty() {
+        push(0x07, &long);
+    }
+    let solicited_short: Vec<u8> = solicited_services
+        .iter()
+        .filter_map(short_uuid)
+        .flat_map(u16::to_le_bytes)
+        .collect();
+    if !solicited_short.is_empty() {
+        push(0x14, &solicited_short);
+    }
+    let solicited_long: Vec<u8> = solicited_services
+        .iter()
+        .filter(|uuid| short_uuid(uuid).is_none())
+        .flat_map(|uuid| {
+            let mut bytes = uuid.into_bytes();
+            bytes.reverse();
+            bytes
+        })
+        .collect();
+    if !solicited_long.is_empty() {
+        push(0x15, &solicited_long);
+    }
+    if let Some(name) = local_name {
+        push(0x09, name.as_bytes());
+    }
+    if let Some(tx_power) = tx_power {
+        push(0x0A, &[tx_power as i8 as u8]);
+    }
+
+    let mut sorted_service_data: Vec<_> = service_data.iter().collect();
+    sorted_service_data.sort_by_key(|(uuid, _)| **uuid);
+    for (uuid, data) in sorted_service_data {
+        let mut value = Vec::new();
+        match short_uuid(uuid) {
+            Some(short) => {
+                value.extend_from_slice(&short.to_le_bytes());
+                value.extend_from_slice(data);
+                push(0x16, &value);
+            }
+            None => {
+                let mut bytes = uuid.into_bytes();
+                bytes.reverse();
+                value.extend_from_slice(&bytes);
+                value.extend_from_slice(data);
+                push(0x21, &value);
+            }
+        }
+    }
+
+    let mut sorted_manufacturer_data: Vec<_> = manufacturer_data.iter().collect();
+    sorted_manufacturer_data.sort_by_key(|(code, _)| **code);
+    for (code, data) in sorted_manufacturer_data {
+        let mut value = code.to_le_bytes().to_vec();
+        value.extend_from_slice(data);
+        push(0xFF, &value);
+    }
+    raw
+}
+
+/// Returns the name of the first known company found in the manufacturer data, if any.
+/// Company codes are checked in ascending order so the result is stable.
+pub fn company_name(manufacturer_data: &HashMap<u16, Vec<u8>>) -> Option<String> {
+    let mut codes: Vec<_> = manufacturer_data.keys().collect();
+    codes.sort();
+    codes
+        .into_iter()
+        .find_map(|&code| vendor_db::company(code))
+        .map(|name| name.to_string())
+}
+
+/// Estimates the distance in meters to a transmitter using the log-distance path loss model.
+/// `reference` is the expected RSSI at one meter, and free space (exponent 2) is assumed.
+pub fn estimate_distance(rssi: i16, reference: i16) -> f64 {
+    10f64.powf((reference - rssi) as f64 / 20.0)
+}
+
+/// The RSSI histogram buckets as (label, inclusive lower bound in dBm).
+const RSSI_BUCKETS: [(&str, i16); 7] = [
+    ("<-90", i16::MIN),
+    ("-90", -90),
+    ("-80", -80),
+    ("-70", -70),
+    ("-60", -60),
+    ("-50", -50),
+    (">-40", -40),
+];
+
+/// Formats an RSSI or TX power reading in dBm for display, or `—` where the device
+/// reported none.
+pub fn format_reading(reading: Option<i16>) -> String {
+    reading.map_or_else(|| "—".to_string(), |reading| reading.to_string())
+}
+
+/// The number of signal levels `rssi_level` maps an RSSI to, above zero.
+pub const RSSI_LEVELS: usize = 4;
+
+/// Maps an RSSI in dBm to a signal level from 0 (unusable) to `RSSI_LEVELS` (excellent).
+pub fn rssi_level(rssi: i16) -> usize {
+    match rssi {
+        -55.. => 4,
+        -67..=-56 => 3,
+        -80..=-68 => 2,
+        -90..=-81 => 1,
+        _ => 0,
     }
 }
 
+/// The RSSI, in dBm, at or below which `rssi_percent` reports no signal.
+const RSSI_PERCENT_FLOOR: i16 = -100;
+
+/// The RSSI, in dBm, at or above which `rssi_percent` reports full signal.
+const RSSI_PERCENT_CEILING: i16 = -50;
+
+/// Maps an RSSI in dBm to a signal quality from 0 to 100%: linear between
+/// `RSSI_PERCENT_FLOOR` and `RSSI_PERCENT_CEILING`, that is `2 × (dBm + 100)`, and clamped
+/// outside that range.
+pub fn rssi_percent(rssi: i16) -> u8 {
+    let clamped = rssi.clamp(RSSI_PERCENT_FLOOR, RSSI_PERCENT_CEILING);
+    ((clamped - RSSI_PERCENT_FLOOR) * 100 / (RSSI_PERCENT_CEILING - RSSI_PERCENT_FLOOR)) as u8
+}
+
+/// The weakest RSSI, in dBm, at which a device is in the immediate zone: roughly within
+/// arm's reach for a phone-class transmitter.
+pub const IMMEDIATE_ZONE_RSSI: i16 = -60;
+
+/// The weakest RSSI, in dBm, at which a device is in the near zone: roughly the same room.
+pub const NEAR_ZONE_RSSI: i16 = -80;
+
+/// Places an RSSI in dBm into a proximity zone.
+pub fn proximity_zone(rssi: i16) -> ProximityZone {
+    if rssi >= IMMEDIATE_ZONE_RSSI {
+        ProximityZone::Immediate
+    } else if rssi >= NEAR_ZONE_RSSI {
+        ProximityZone::Near
+    } else {
+        ProximityZone::Far
+    }
+}
+
+/// Returns the proximity zone of a device, or `None` without an RSSI reading.
+pub fn device_zone(device: &DeviceInfo) -> Option<ProximityZone> {
+    device.rssi.map(proximity_zone)
+}
+
+/// Returns how often proximity feedback repeats for a smoothed RSSI: every two seconds at
+/// -100 dBm, speeding up linearly to every 250 ms at -30 dBm and above.
+pub fn pulse_interval(rssi: f64) -> Duration {
+    let closeness = ((rssi + 100.0) / 70.0).clamp(0.0, 1.0);
+    Duration::from_secs_f64(2.0 - closeness * 1.75)
+}
+
+/// Computes the statistics of a scanning session from every device seen during it, keyed
+/// by identity.
+pub fn session_stats(
+    seen: &HashMap<String, SeenDevice>,
+    duration: Duration,
+    exposure_rpis: usize,
+    trackers: usize,
+    observations: usize,
+) -> SessionStats {
+    let named = seen.values().filter(|device| device.named).count();
+
+    let mut manufacturers: HashMap<String, usize> = HashMap::new();
+    let mut address_types: HashMap<&'static str, usize> = HashMap::new();
+    let mut histogram = vec![0u64; RSSI_BUCKETS.len()];
+    let mut no_rssi = 0;
+    for device in seen.values() {
+        *manufacturers
+            .entry(device.manufacturer.clone())
+            .or_default() += 1;
+        *address_types.entry(device.address_type).or_default() += 1;
+        match device.rssi {
+            Some(rssi) => {
+                let bucket = RSSI_BUCKETS
+                    .iter()
+                    .rposition(|(_, lower)| rssi >= *lower)
+                    .unwrap_or(0);
+                histogram[bucket] += 1;
+            }
+            None => no_rssi += 1,
+        }
+    }
+
+    let mut by_manufacturer: Vec<_> = manufacturers.into_iter().collect();
+    by_manufacturer.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let mut by_address_type: Vec<_> = address_types.into_iter().collect();
+    by_address_type.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    let mut rssi_histogram: Vec<_> = RSSI_BUCKETS
+        .iter()
+        .zip(histogram)
+        .map(|((label, _), count)| (*label, count))
+        .collect();
+    rssi_histogram.push(("n/a", no_rssi));
+
+    let longest_dwell = seen
+        .values()
+        .map(|device| device.longest_dwell)
+        .max()
+        .unwrap_or_default();
+    let average_dwell = seen
+        .values()
+        .map(|device| device.longest_dwell)
+        .sum::<Duration>()
+        .checked_div(seen.len() as u32)
+        .unwrap_or_default();
+
+    SessionStats {
+        total: seen.len(),
+        named,
+        unnamed: seen.len() - named,
+        duration,
+        by_manufacturer,
+        by_address_type,
+        rssi_histogram,
+        exposure_rpis,
+        trackers,
+        longest_dwell,
+        average_dwell,
+        observations,
+    }
+}
+
+/// Turns a label or address into a lowercase identifier of letters, digits and
+/// underscores, as Home Assistant expects in unique ids, topics and templates.
+pub fn slug(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Formats a duration as `HH:MM:SS`.
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!(
+        "{:02}:{:02}:{:02}",
+        secs / 3600,
+        (secs / 60) % 60,
+        secs % 60
+    )
+}
+
+/// Describes how long ago `at` was, e.g. `3 days ago`, in the largest whole unit.
+pub fn format_age(at: chrono::DateTime<chrono::FixedOffset>) -> String {
+    let secs = (chrono::Local::now().fixed_offset() - at)
+        .num_seconds()
+        .max(0);
+    let (count, unit) = match secs {
+        0..=59 => return "just now".to_string(),
+        60..=3599 => (secs / 60, "minute"),
+        3600..=86_399 => (secs / 3600, "hour"),
+        _ => (secs / 86_400, "day"),
+    };
+    format!(
+        "{} {}{} ago",
+        count,
+        unit,
+        if count == 1 { "" } else { "s" }
+    )
+}
+
 /// Returns a `Rect` with the provided percentage of the parent `Rect` and centered.
 pub fn centered_rect(percent_x: u16, percent_y: u16, size: Rect) -> Rect {
     let popup_size = Rect {
@@ -48,3 +544,174 @@ pub fn centered_rect(percent_x: u16, percent_y: u16, size: Rect) -> Rect {
         ..popup_size
     }
 }
+
+/// Returns the name of the major device class encoded in a classic Bluetooth
+/// Class of Device value.
+pub fn major_device_class(class: u32) -> &'static str {
+    match (class >> 8) & 0x1F {
+        0x00 => "Miscellaneous",
+        0x01 => "Computer",
+        0x02 => "Phone",
+        0x03 => "Network Access Point",
+        0x04 => "Audio/Video",
+        0x05 => "Peripheral",
+        0x06 => "Imaging",
+        0x07 => "Wearable",
+        0x08 => "Toy",
+        0x09 => "Health",
+        0x1F => "Uncategorized",
+        _ => "Reserved",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use btleplug::api::bleuuid::uuid_from_u16;
+
+    use super::*;
+
+    #[test]
+    fn advertisement_splits_into_its_ad_structures() {
+        let raw = [
+            0x02, 0x01, 0x06, // Flags
+            0x05, 0x09, b'L', b'a', b'm', b'p', // Complete Local Name
+            0x03, 0x42, 0xAA, 0xBB, // an unassigned type
+            0x02, 0x0A, 0xF4, // TX Power Level
+            0x00, 0x00, // padding
+        ];
+        let structures = parse_ad_structures(&raw);
+        assert_eq!(
+            structures,
+            [
+                AdStructure {
+                    ad_type: 0x01,
+                    data: vec![0x06]
+                },
+                AdStructure {
+                    ad_type: 0x09,
+                    data: b"Lamp".to_vec()
+                },
+                AdStructure {
+                    ad_type: 0x42,
+                    data: vec![0xAA, 0xBB]
+                },
+                AdStructure {
+                    ad_type: 0x0A,
+                    data: vec![0xF4]
+                },
+            ]
+        );
+        let names: Vec<_> = structures
+            .iter()
+            .map(|structure| ad_type_name(structure.ad_type))
+            .collect();
+        assert_eq!(
+            names,
+            ["Flags", "Complete Local Name", "Unknown", "TX Power Level"]
+        );
+    }
+
+    #[test]
+    fn truncated_ad_structure_keeps_the_remaining_bytes() {
+        assert_eq!(
+            parse_ad_structures(&[0x02, 0x01, 0x06, 0x05, 0xFF, 0x4C]),
+            [
+                AdStructure {
+                    ad_type: 0x01,
+                    data: vec![0x06]
+                },
+                AdStructure {
+                    ad_type: 0xFF,
+                    data: vec![0x4C]
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn manufacturer_entries_are_sorted_and_named() {
+        /// Returns the entries as `(company ID, company, data)` tuples.
+        fn entries(manufacturer_data: &[(u16, Vec<u8>)]) -> Vec<(u16, String, String)> {
+            extract_manufacturer_data(&manufacturer_data.iter().cloned().collect())
+                .into_iter()
+                .map(|entry| (entry.company_id, entry.company, entry.data))
+                .collect()
+        }
+
+        assert!(entries(&[]).is_empty());
+        assert_eq!(
+            entries(&[(0x004C, vec![0x02, 0x15])]),
+            [(0x004C, "Apple, Inc.".to_string(), "02 15".to_string())]
+        );
+        assert_eq!(
+            entries(&[
+                (0xFFFF, vec![]),
+                (0x0075, vec![0x42, 0x04]),
+                (0x0006, vec![0x01]),
+            ]),
+            [
+                (0x0006, "Microsoft".to_string(), "01".to_string()),
+                (
+                    0x0075,
+                    "Samsung Electronics Co. Ltd.".to_string(),
+                    "42 04".to_string()
+                ),
+                (0xFFFF, "n/a".to_string(), String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn capabilities_are_summarized_from_services() {
+        fn summary(services: &[u16]) -> Option<String> {
+            let uuids: Vec<Uuid> = services.iter().map(|&short| uuid_from_u16(short)).collect();
+            capability_summary(&uuids)
+        }
+
+        for (services, expected) in [
+            (
+                &[0x180D, 0x180F, 0xFE59][..],
+                "Heart-rate monitor, battery, DFU",
+            ),
+            // A combination takes its services, so they are not listed again.
+            (&[0x181D, 0x181B], "Body-composition scale"),
+            (&[0x181D], "Scale"),
+            (&[0x1800, 0x1801, 0x181A], "Environmental sensor"),
+            // Services without a capability are listed by name, or by short UUID.
+            (&[0x1805, 0xABCD], "Current Time, 0xABCD"),
+        ] {
+            assert_eq!(
+                summary(services).as_deref(),
+                Some(expected),
+                "{:04X?}",
+                services
+            );
+        }
+        assert_eq!(summary(&[]), None);
+        assert_eq!(summary(&[0x1800, 0x1801]), None);
+
+        // The legacy Nordic DFU service counts as DFU once, and custom UUIDs are shown whole.
+        let custom = Uuid::from_u128(0x6E40_0001_B5A3_F393_E0A9_E50E_24DC_CA9E);
+        assert_eq!(
+            capability_summary(&[LEGACY_NORDIC_DFU, uuid_from_u16(0xFE59), custom]).as_deref(),
+            Some("DFU, 6e400001-b5a3-f393-e0a9-e50e24dcca9e")
+        );
+    }
+
+    #[test]
+    fn rssi_percent_is_linear_and_clamped() {
+        for (rssi, percent) in [
+            (i16::MIN, 0),
+            (-120, 0),
+            (-100, 0),
+            (-99, 2),
+            (-75, 50),
+            (-51, 98),
+            (-50, 100),
+            (-20, 100),
+            (i16::MAX, 100),
+        ] {
+            assert_eq!(rssi_percent(rssi), percent, "{} dBm", rssi);
+        }
+    }
+}
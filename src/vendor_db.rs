@@ -0,0 +1,220 @@
+use std::{collections::HashMap, error::Error, path::Path, sync::OnceLock};
+
+use crate::{company_codes::COMPANY_CODE, oui_codes::OUI_VENDOR};
+
+/// Vendors loaded with `--oui-file`, consulted before the embedded table.
+static OUI_OVERRIDES: OnceLock<HashMap<u32, String>> = OnceLock::new();
+
+/// Company names loaded with `--company-file`, consulted before the embedded table.
+static COMPANY_OVERRIDES: OnceLock<HashMap<u16, String>> = OnceLock::new();
+
+/// A database read from a file, with a warning for each line that could not be used.
+type Loaded<K> = Result<(HashMap<K, String>, Vec<String>), Box<dyn Error>>;
+
+/// Returns the vendor an OUI, the first three octets of an address, is assigned to.
+pub fn vendor(prefix: u32) -> Option<&'static str> {
+    OUI_OVERRIDES
+        .get()
+        .and_then(|vendors| vendors.get(&prefix))
+        .map(String::as_str)
+        .or_else(|| OUI_VENDOR.get(&prefix).copied())
+}
+
+/// Returns the name of the company a Bluetooth SIG company identifier is assigned to.
+pub fn company(code: u16) -> Option<&'static str> {
+    COMPANY_OVERRIDES
+        .get()
+        .and_then(|companies| companies.get(&code))
+        .map(String::as_str)
+        .or_else(|| COMPANY_CODE.get(&code).copied())
+}
+
+/// Makes `vendor` look OUIs up in the given table first. Only the first call has an effect.
+pub fn install_vendors(vendors: HashMap<u32, String>) {
+    let _ = OUI_OVERRIDES.set(vendors);
+}
+
+/// Makes `company` look identifiers up in the given table first. Only the first call has
+/// an effect.
+pub fn install_companies(companies: HashMap<u16, String>) {
+    let _ = COMPANY_OVERRIDES.set(companies);
+}
+
+/// Reads an OUI database in one of the formats the IEEE and Wireshark publish it in:
+///
+/// - the IEEE's `oui.txt`, whose `00-00-F0   (hex)   Samsung Electronics` lines are used,
+/// - the IEEE's `oui.csv`, with a `Registry,Assignment,Organization Name,...` header,
+/// - Wireshark's `manuf`, with `00:00:F0<TAB>Samsung<TAB>Samsung Electronics` lines whose
+///   short name is used. Assignments narrower than an OUI (`/28`, `/36`) are skipped.
+///
+/// Returns the vendors keyed by OUI and a warning for each line that could not be used.
+pub fn load_vendors(path: &Path) -> Loaded<u32> {
+    let contents = std::fs::read_to_string(path)?;
+    let mut vendors = HashMap::new();
+    let mut warnings = Vec::new();
+    let ieee_text = contents.lines().any(|line| line.contains("(hex)"));
+    let ieee_csv = contents
+        .lines()
+        .find(|line| !line.trim().is_empty())
+        .is_some_and(|line| line.starts_with("Registry,"));
+    for (index, line) in contents.lines().enumerate() {
+        let warn = |message: String| format!("{}:{}: {}", path.display(), index + 1, message);
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let entry = if ieee_text {
+            let Some((prefix, name)) = trimmed.split_once("(hex)") else {
+                continue;
+            };
+            (prefix.trim().to_string(), name.trim().to_string())
+        } else if ieee_csv {
+            if index == 0 {
+                continue;
+            }
+            match csv_fields(line) {
+                Some(fields) if fields.len() >= 3 => (fields[1].clone(), fields[2].clone()),
+                _ => {
+                    warnings.push(warn(
+                        "expected Registry,Assignment,Organization Name".to_string(),
+                    ));
+                    continue;
+                }
+            }
+        } else {
+            let mut fields = trimmed.split('\t').map(str::trim);
+            let (Some(prefix), Some(name)) = (fields.next(), fields.next()) else {
+                warnings.push(warn("expected <prefix><TAB><vendor>".to_string()));
+                continue;
+            };
+            if prefix.contains('/') {
+                continue;
+            }
+            (prefix.to_string(), name.to_string())
+        };
+        let (prefix, name) = entry;
+        match parse_oui(&prefix) {
+            Some(oui) if !name.is_empty() => {
+                vendors.insert(oui, name);
+            }
+            Some(_) => warnings.push(warn(format!("no vendor for {}", prefix))),
+            None => warnings.push(warn(format!("invalid OUI '{}'", prefix))),
+        }
+    }
+    if vendors.is_empty() {
+        warnings.push(format!("{}: no OUI assignments found", path.display()));
+    }
+    Ok((vendors, warnings))
+}
+
+/// Reads a company identifier database: the Bluetooth SIG's `company_identifiers.yaml`,
+/// with `- value: 0x004C` and `name: 'Apple, Inc.'` lines, or a CSV file of
+/// `identifier,name` lines, the identifier in decimal or `0x` hexadecimal, optionally
+/// under a header. Returns the names keyed by identifier and a warning for each line that
+/// could not be used.
+pub fn load_companies(path: &Path) -> Loaded<u16> {
+    let contents = std::fs::read_to_string(path)?;
+    let mut companies = HashMap::new();
+    let mut warnings = Vec::new();
+    let yaml = contents
+        .lines()
+        .any(|line| line.trim_start().starts_with("- value:"));
+    let mut pending: Option<u16> = None;
+    for (index, line) in contents.lines().enumerate() {
+        let warn = |message: String| format!("{}:{}: {}", path.display(), index + 1, message);
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if yaml {
+            if let Some(value) = trimmed.strip_prefix("- value:") {
+                pending = parse_company_code(value.trim());
+                if pending.is_none() {
+                    warnings.push(warn(format!(
+                        "invalid company identifier '{}'",
+                        value.trim()
+                    )));
+                }
+            } else if let Some(name) = trimmed.strip_prefix("name:") {
+                match pending.take() {
+                    Some(code) => {
+                        companies.insert(code, unquote(name.trim()));
+                    }
+                    None => warnings.push(warn("name without a value".to_string())),
+                }
+            }
+            continue;
+        }
+        let Some(fields) = csv_fields(line).filter(|fields| fields.len() >= 2) else {
+            warnings.push(warn("expected <identifier>,<name>".to_string()));
+            continue;
+        };
+        match parse_company_code(&fields[0]) {
+            Some(code) if !fields[1].is_empty() => {
+                companies.insert(code, fields[1].clone());
+            }
+            Some(code) => warnings.push(warn(format!("no name for 0x{:04X}", code))),
+            // A header names its columns rather than giving an identifier.
+            None if index == 0 => {}
+            None => warnings.push(warn(format!("invalid company identifier '{}'", fields[0]))),
+        }
+    }
+    if companies.is_empty() {
+        warnings.push(format!("{}: no company identifiers found", path.display()));
+    }
+    Ok((companies, warnings))
+}
+
+/// Parses an OUI written as six hex digits, optionally separated by `:`, `-` or `.`.
+fn parse_oui(prefix: &str) -> Option<u32> {
+    let digits: String = prefix
+        .chars()
+        .filter(|c| !matches!(c, ':' | '-' | '.'))
+        .collect();
+    if digits.len() != 6 {
+        return None;
+    }
+    u32::from_str_radix(&digits, 16).ok()
+}
+
+/// Parses a company identifier in decimal or `0x` hexadecimal.
+fn parse_company_code(value: &str) -> Option<u16> {
+    match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    }
+}
+
+/// Splits a CSV line into its trimmed fields, or returns `None` if it is malformed.
+fn csv_fields(line: &str) -> Option<Vec<String>> {
+    let record = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(line.as_bytes())
+        .records()
+        .next()?
+        .ok()?;
+    Some(record.iter().map(str::to_string).collect())
+}
+
+/// Removes the quotes around a YAML scalar, undoing the doubled quotes that escape
+/// a single quote.
+fn unquote(value: &str) -> String {
+    if let Some(inner) = value
+        .strip_prefix('\'')
+        .and_then(|value| value.strip_suffix('\''))
+    {
+        inner.replace("''", "'")
+    } else if let Some(inner) = value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+    {
+        inner.replace("\\\"", "\"")
+    } else {
+        value.to_string()
+    }
+}
@@ -1,4 +1,4 @@
-use crossterm::event::{self, Event, KeyCode};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
 use ratatui::backend::Backend;
 use ratatui::layout::Alignment;
 use ratatui::text::Span;
@@ -7,126 +7,554 @@ use ratatui::{
     layout::{Constraint, Direction, Layout},
     Terminal,
 };
-use std::error::Error;
+use std::io::{self, Write};
 use std::sync::atomic::Ordering;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::error::TryRecvError;
 
 use crate::app::{App, DeviceData};
-use crate::structs::DeviceInfo;
-use crate::utils::centered_rect;
+use crate::commands::{self, Command, ExportTarget};
+use crate::error::BluscanError;
+use crate::pause_changes::PauseChanges;
+use crate::structs::{DeviceInfo, ProximityFeedback};
+use crate::utils::{centered_rect, session_stats};
+use crate::widgets::ad_structure_table::ad_structure_table;
+use crate::widgets::category_popup::category_popup;
+use crate::widgets::chip_bar::chip_bar;
+use crate::widgets::command_line::command_line;
+use crate::widgets::compare_view::{compare_view, ComparedDevice};
 use crate::widgets::detail_table::detail_table;
 use crate::widgets::device_table::device_table;
 use crate::widgets::info_table::info_table;
 use crate::widgets::inspect_overlay::inspect_overlay;
+use crate::widgets::rssi_gauge::{proximity_gauge, rssi_gauge};
+use crate::widgets::services_overlay::services_overlay;
+use crate::widgets::stats_view::stats_view;
+use crate::widgets::watchdog_warning::watchdog_warning;
 
-/// Displays the detected Bluetooth devices in a table and handles the user input.
-/// The user can navigate the table, pause the scanning, and quit the application.
-/// The detected devices are received through the provided `mpsc::Receiver`.
+/// How often the interface is redrawn while the terminal window is not focused.
+const UNFOCUSED_REDRAW_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How long the interface waits for input before it looks for scan results again.
+const INPUT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Where the interface reads key presses and focus changes from.
+pub trait InputEvents {
+    /// Waits up to `timeout` for the next event. Returns `None` if none arrived.
+    fn next(&mut self, timeout: Duration) -> io::Result<Option<Event>>;
+}
+
+/// Reads the events of the terminal the interface is drawn on.
+pub struct TerminalEvents;
+
+impl InputEvents for TerminalEvents {
+    fn next(&mut self, timeout: Duration) -> io::Result<Option<Event>> {
+        if event::poll(timeout)? {
+            event::read().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Displays the detected Bluetooth devices in a table and handles the user input read
+/// from `input`. The user can navigate the table, pause the scanning, and quit the
+/// application. The detected devices are received through the app's channel.
 pub async fn viewer<B: Backend>(
     terminal: &mut Terminal<B>,
     app: &mut App,
-) -> Result<(), Box<dyn Error>> {
+    input: &mut impl InputEvents,
+) -> Result<(), BluscanError> {
     app.table_state.select(Some(0));
+    let mut last_draw: Option<Instant> = None;
 
     loop {
-        // Draw UI
-        terminal.draw(|f| {
-            app.frame_count = f.count();
-            let chunks = Layout::default()
-                .direction(Direction::Vertical)
-                .margin(1)
-                .constraints(
-                    [
-                        Constraint::Percentage(70),
-                        Constraint::Percentage(20),
-                        Constraint::Percentage(10),
-                    ]
-                    .as_ref(),
-                )
-                .split(f.size());
-
-            let device_binding = &DeviceInfo::default();
-            let selected_device = app
-                .devices
-                .get(app.table_state.selected().unwrap_or(0))
-                .unwrap_or(device_binding);
-
-            // Draw the device table
-            let device_table = device_table(app.table_state.selected(), &app.devices);
-            f.render_stateful_widget(device_table, chunks[0], &mut app.table_state);
-
-            // Draw the detail table
-            let detail_table = detail_table(selected_device);
-            f.render_widget(detail_table, chunks[1]);
-
-            // Draw the info table
-            app.frame_count += 1;
-            let info_table: ratatui::widgets::Table<'_> = info_table(
-                app.pause_status.load(Ordering::SeqCst),
-                &app.is_loading,
-                &app.frame_count,
-            );
-            f.render_widget(info_table, chunks[2]);
-
-            // Draw the inspect overlay
-            if app.inspect_view {
-                let area = centered_rect(60, 60, f.size());
-                let inspect_overlay = inspect_overlay(
-                    &app.selected_characteristics,
-                    app.inspect_overlay_scroll,
-                    area.height,
+        // Draw UI, slowly while the terminal is in the background
+        let draw_due =
+            app.focused || last_draw.is_none_or(|at| at.elapsed() >= UNFOCUSED_REDRAW_INTERVAL);
+        if draw_due {
+            last_draw = Some(Instant::now());
+            terminal.draw(|f| {
+                app.frame_count = f.count();
+                let chunks = Layout::default()
+                    .direction(Direction::Vertical)
+                    .margin(1)
+                    .constraints(
+                        [
+                            Constraint::Length(1),
+                            Constraint::Percentage(if app.compact { 90 } else { 60 }),
+                            Constraint::Percentage(if app.compact { 0 } else { 30 }),
+                            Constraint::Length(1),
+                            Constraint::Fill(1),
+                        ]
+                        .as_ref(),
+                    )
+                    .split(f.size());
+
+                let pause_highlights = app.pause_highlights();
+                let device_binding = &DeviceInfo::default();
+                let visible_devices = app.visible_devices();
+                let selected_device = visible_devices
+                    .get(app.table_state.selected().unwrap_or(0))
+                    .copied()
+                    .unwrap_or(device_binding);
+                // Kept for the services overlay, which is drawn once the list is released.
+                let advertised_services = selected_device.services.clone();
+                let gatt_services = app.gatt_services(selected_device);
+
+                // Draw the manufacturer chip bar
+                let manufacturers = app.top_manufacturers();
+                let chips = chip_bar(&manufacturers, app.chip_cursor, app.manufacturer_filter);
+                f.render_widget(chips, chunks[0]);
+
+                // Draw the device table
+                let categories: Vec<_> = visible_devices
+                    .iter()
+                    .map(|device| app.category_for(device))
+                    .collect();
+                let aliases: Vec<_> = visible_devices
+                    .iter()
+                    .map(|device| app.alias_for(device))
+                    .collect();
+                let device_table = device_table(
+                    app.table_state.selected(),
+                    &visible_devices,
+                    &categories,
+                    &app.marked_devices,
+                    &app.renamed_devices,
+                    &pause_highlights,
+                    app.name_fallback,
+                    app.identity_display,
+                    app.identity_key,
+                    app.show_vendor,
+                    app.rssi_percent,
+                    app.flag_duplicate_names,
+                    &aliases,
+                    app.striped.then_some(app.stripe_color),
+                    app.rssi_bar,
+                    &app.zone_counts(),
+                    app.group_by_zone,
                 );
-                f.render_widget(Clear, area);
-                f.render_widget(inspect_overlay, area);
-            }
+                let mut table_state = app.table_state.clone();
+                f.render_stateful_widget(device_table, chunks[1], &mut table_state);
 
-            // Draw the error overlay
-            if app.error_view {
-                let error_message_clone = app.error_message.clone();
-                let area = centered_rect(60, 10, f.size());
-                let error_block = Paragraph::new(Span::from(error_message_clone))
-                    .alignment(Alignment::Center)
-                    .block(Block::default().borders(Borders::ALL).title("Notification"));
-                f.render_widget(Clear, area);
-                f.render_widget(error_block, area);
-            }
-        })?;
+                // Draw the detail table, or the raw advertisement breakdown when toggled, unless
+                // the detail pane is hidden in compact mode
+                if !app.compact {
+                    if app.raw_view {
+                        let ad_structure_table = ad_structure_table(selected_device);
+                        f.render_widget(ad_structure_table, chunks[2]);
+                    } else if let Some(pinned) = app.pinned_device() {
+                        let compared = |device| ComparedDevice {
+                            device,
+                            alias: app.alias_for(device),
+                            category: app.category_for(device),
+                        };
+                        let (pinned_table, selected_table) = compare_view(
+                            &compared(pinned),
+                            &compared(selected_device),
+                            app.name_fallback,
+                            app.rssi_percent,
+                        );
+                        let halves = Layout::default()
+                            .direction(Direction::Horizontal)
+                            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                            .split(chunks[2]);
+                        f.render_widget(pinned_table, halves[0]);
+                        f.render_widget(selected_table, halves[1]);
+                    } else {
+                        let detail_table = detail_table(
+                            selected_device,
+                            app.name_fallback,
+                            app.rssi_percent,
+                            app.alias_for(selected_device),
+                            app.note_for(selected_device),
+                            app.name_history
+                                .get(&selected_device.get_id(app.identity_key))
+                                .map_or(&[], Vec::as_slice),
+                            app.db_history
+                                .get(&selected_device.get_id(app.identity_key))
+                                .and_then(Option::as_ref),
+                            &gatt_services,
+                            &app.decoders,
+                        );
+                        f.render_widget(detail_table, chunks[2]);
+                    }
+                }
+                app.table_state = table_state;
+
+                // Draw the proximity gauge while tracking a device, otherwise the RSSI threshold
+                if let Some(tracked) = &app.tracked {
+                    let name = app.tracked_device().map_or_else(
+                        || tracked.identity.clone(),
+                        |device| device.display_name(app.name_fallback),
+                    );
+                    let gauge = proximity_gauge(
+                        &name,
+                        tracked.smoothed_rssi,
+                        app.proximity_feedback,
+                        app.pulse_active(),
+                    );
+                    f.render_widget(gauge, chunks[3]);
+                } else {
+                    let hidden = app
+                        .devices
+                        .iter()
+                        .filter(|device| app.below_min_rssi(device))
+                        .count();
+                    f.render_widget(rssi_gauge(app.min_rssi, hidden), chunks[3]);
+                }
+
+                // Draw the info table
+                app.frame_count += 1;
+                let info_table: ratatui::widgets::Table<'_> = info_table(
+                    app.pause_status.load(Ordering::SeqCst),
+                    &app.is_loading,
+                    &app.frame_count,
+                    app.name_fallback,
+                    app.marked_devices.len(),
+                    app.tag_filter,
+                    app.category_filter,
+                    app.address_type_filter,
+                    app.capped_indicator(),
+                    (!app.scan_config.services().is_empty()).then_some(app.service_filter_enabled),
+                    app.reconfiguring,
+                    app.mqtt_status.as_ref(),
+                    &app.sort_label(),
+                    app.search.as_deref(),
+                    app.named_only,
+                    app.connect_progress,
+                    app.inventory.as_ref(),
+                    app.ws_clients,
+                    app.idle_paused,
+                    app.replay_speed.map(|speed| (speed, app.replay_finished)),
+                    app.capture.is_some(),
+                    &app.channel_health.label(),
+                    app.pause_changes.as_ref().map(PauseChanges::summary),
+                    app.status_indicator(),
+                );
+                // The command line takes the place of the status bar while it is in use
+                if app.command_input.is_some() || app.command_error.is_some() {
+                    let line =
+                        command_line(app.command_input.as_deref(), app.command_error.as_deref());
+                    f.render_widget(line, chunks[4]);
+                } else {
+                    f.render_widget(info_table, chunks[4]);
+                }
+
+                // Warn over the device table when the scan produces nothing at all
+                if let Some(elapsed) = app.watchdog_tripped() {
+                    let area = centered_rect(70, 60, chunks[1]);
+                    let warning = watchdog_warning(elapsed, &app.scan_filter_descriptions());
+                    f.render_widget(Clear, area);
+                    f.render_widget(warning, area);
+                }
+
+                // Draw the session statistics over the device and detail tables
+                if app.stats_view {
+                    let area = chunks[1].union(chunks[2]);
+                    let stats = session_stats(
+                        &app.session_seen,
+                        app.session_start.elapsed(),
+                        app.exposure_rpis.len(),
+                        app.trackers.len(),
+                        app.observations,
+                    );
+                    let (stats_table, rssi_chart) = stats_view(&stats);
+                    let halves = Layout::default()
+                        .direction(Direction::Horizontal)
+                        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                        .split(area);
+                    f.render_widget(Clear, area);
+                    f.render_widget(stats_table, halves[0]);
+                    f.render_widget(rssi_chart, halves[1]);
+                }
+
+                // Draw the inspect overlay
+                if app.inspect_view {
+                    let area = centered_rect(60, 60, f.size());
+                    let inspect_overlay = inspect_overlay(
+                        &app.selected_characteristics,
+                        app.inspect_overlay_scroll,
+                        area.height,
+                    );
+                    f.render_widget(Clear, area);
+                    f.render_widget(inspect_overlay, area);
+                }
+
+                // Draw the services overlay
+                if app.services_view {
+                    let area = centered_rect(60, 60, f.size());
+                    let services_overlay = services_overlay(
+                        &advertised_services,
+                        &gatt_services,
+                        app.services_scroll,
+                        area.height,
+                    );
+                    f.render_widget(Clear, area);
+                    f.render_widget(services_overlay, area);
+                }
+
+                // Draw the category filter popup
+                if let Some(selected) = app.category_popup {
+                    let area = centered_rect(30, 50, f.size());
+                    let popup =
+                        category_popup(selected, &app.category_counts(), app.category_filter);
+                    f.render_widget(Clear, area);
+                    f.render_widget(popup, area);
+                }
+
+                // Draw the alias input
+                if let Some(alias) = &app.alias_input {
+                    let area = centered_rect(40, 10, f.size());
+                    let alias_block = Paragraph::new(Span::from(format!("{}▏", alias))).block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .title("Alias (enter → save, esc → cancel, empty → remove)"),
+                    );
+                    f.render_widget(Clear, area);
+                    f.render_widget(alias_block, area);
+                }
+
+                // Draw the note input
+                if let Some(note) = &app.note_input {
+                    let area = centered_rect(60, 10, f.size());
+                    let note_block = Paragraph::new(Span::from(format!("{}▏", note))).block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .title("Note (enter → save, esc → cancel, empty → remove)"),
+                    );
+                    f.render_widget(Clear, area);
+                    f.render_widget(note_block, area);
+                }
+
+                // Draw the search input
+                if let Some(search) = &app.search_input {
+                    let area = centered_rect(40, 10, f.size());
+                    let search_block = Paragraph::new(Span::from(format!("{}▏", search))).block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .title("Search (enter → apply, esc → cancel, empty → show all)"),
+                    );
+                    f.render_widget(Clear, area);
+                    f.render_widget(search_block, area);
+                }
+
+                // Draw the error overlay
+                if app.error_view {
+                    let error_message_clone = app.error_message.clone();
+                    let area = centered_rect(60, 10, f.size());
+                    let error_block = Paragraph::new(Span::from(error_message_clone))
+                        .alignment(Alignment::Center)
+                        .block(Block::default().borders(Borders::ALL).title("Notification"));
+                    f.render_widget(Clear, area);
+                    f.render_widget(error_block, area);
+                }
+            })?;
+        }
 
         // Event handling
-        if event::poll(Duration::from_millis(100))? {
-            if let Event::Key(key) = event::read()? {
+        if let Some(event) = input.next(INPUT_POLL_INTERVAL)? {
+            match event {
+                Event::FocusLost => app.focused = false,
+                Event::FocusGained => app.focused = true,
+                _ => {}
+            }
+            if let Event::Key(key) = event {
+                // Windows also reports key releases, which would act on every key twice.
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                if app.wake() {
+                    continue;
+                }
+                app.command_error = None;
+                if let Some(command) = app.command_input.as_mut() {
+                    match key.code {
+                        KeyCode::Char(c) => command.push(c),
+                        // Deleting past the start closes the prompt, as in vi.
+                        KeyCode::Backspace if command.is_empty() => app.command_input = None,
+                        KeyCode::Backspace => {
+                            command.pop();
+                        }
+                        KeyCode::Tab => app.complete_command_input(),
+                        KeyCode::Esc => app.command_input = None,
+                        KeyCode::Enter => {
+                            let line = app.command_input.take().unwrap_or_default();
+                            match commands::parse(&line) {
+                                Ok(Command::Quit) => {
+                                    app.stop_scan().await;
+                                    break;
+                                }
+                                Ok(command) => run_command(app, command).await,
+                                Err(e) => app.command_error = Some(e),
+                            }
+                        }
+                        _ => {}
+                    }
+                    continue;
+                }
+                if let Some(alias) = app.alias_input.as_mut() {
+                    match key.code {
+                        KeyCode::Char(c) => alias.push(c),
+                        KeyCode::Backspace => {
+                            alias.pop();
+                        }
+                        KeyCode::Esc => app.alias_input = None,
+                        KeyCode::Enter => {
+                            if let Err(e) = app.commit_alias_input() {
+                                app.error_message = format!("Unable to save alias: {}", e);
+                                app.error_view = true;
+                            }
+                        }
+                        _ => {}
+                    }
+                    continue;
+                }
+                if let Some(note) = app.note_input.as_mut() {
+                    match key.code {
+                        KeyCode::Char(c) => note.push(c),
+                        KeyCode::Backspace => {
+                            note.pop();
+                        }
+                        KeyCode::Esc => app.note_input = None,
+                        KeyCode::Enter => {
+                            if let Err(e) = app.commit_note_input() {
+                                app.error_message = format!("Unable to save note: {}", e);
+                                app.error_view = true;
+                            }
+                        }
+                        _ => {}
+                    }
+                    continue;
+                }
+                if let Some(search) = app.search_input.as_mut() {
+                    match key.code {
+                        KeyCode::Char(c) => search.push(c),
+                        KeyCode::Backspace => {
+                            search.pop();
+                        }
+                        KeyCode::Esc => app.search_input = None,
+                        KeyCode::Enter => app.commit_search_input(),
+                        _ => {}
+                    }
+                    continue;
+                }
+                if app.category_popup.is_some() {
+                    match key.code {
+                        KeyCode::Down | KeyCode::Char('j') => app.move_category_popup(1),
+                        KeyCode::Up | KeyCode::Char('k') => app.move_category_popup(-1),
+                        KeyCode::Enter => app.select_category_popup(),
+                        KeyCode::Esc | KeyCode::Char('g') => app.category_popup = None,
+                        _ => {}
+                    }
+                    continue;
+                }
                 match key.code {
                     KeyCode::Char('q') => {
+                        app.stop_scan().await;
                         break;
                     }
-                    KeyCode::Char('s') => {
-                        let current_state = app.pause_status.load(Ordering::SeqCst);
-                        app.pause_status.store(!current_state, Ordering::SeqCst);
+                    KeyCode::Char('s') => app.toggle_pause(),
+                    KeyCode::Char(' ') => {
+                        app.toggle_mark();
                     }
-                    KeyCode::Char('e') => {
-                        app.error_message = match app.get_devices_csv() {
-                            Ok(success_message) => success_message,
-                            Err(e) => e.to_string(),
-                        };
-                        app.error_view = true;
+                    KeyCode::Char('c') => {
+                        app.marked_devices.clear();
+                    }
+                    KeyCode::Char('a') => {
+                        app.start_alias_input();
+                    }
+                    KeyCode::Char('A') => {
+                        app.start_note_input();
+                    }
+                    KeyCode::Char('T') => app.cycle_address_type_filter(),
+                    KeyCode::Char('i') => {
+                        app.identity_display = app.identity_display.toggle();
+                    }
+                    KeyCode::Char('n') => {
+                        app.name_fallback = app.name_fallback.next();
+                    }
+                    KeyCode::Char('f') => {
+                        app.cycle_tag_filter();
+                    }
+                    KeyCode::Char('g') => {
+                        app.open_category_popup();
+                    }
+                    KeyCode::Char('o') => {
+                        if let Err(e) = app.cycle_category_override() {
+                            app.error_message = format!("Unable to save category: {}", e);
+                            app.error_view = true;
+                        }
+                    }
+                    KeyCode::Char('u') => app.acknowledge_rename(),
+                    KeyCode::Char('d') => app.toggle_compare_pin(),
+                    KeyCode::Esc => app.compare_pin = None,
+                    KeyCode::Char('l') => {
+                        app.toggle_tracking();
+                    }
+                    KeyCode::Char('b') => {
+                        app.proximity_feedback = app.proximity_feedback.next();
+                    }
+                    KeyCode::Char('+') => app.adjust_min_rssi(1),
+                    KeyCode::Char('-') => app.adjust_min_rssi(-1),
+                    KeyCode::Char(']') => app.adjust_min_rssi(5),
+                    KeyCode::Char('[') => app.adjust_min_rssi(-5),
+                    KeyCode::Char('p') => {
+                        app.group_by_zone = !app.group_by_zone;
+                        app.table_state.select(Some(0));
                     }
+                    KeyCode::Left => app.move_chip_cursor(-1),
+                    KeyCode::Right => app.move_chip_cursor(1),
+                    KeyCode::Char('m') => {
+                        app.toggle_chip();
+                    }
+                    KeyCode::Char('v') => {
+                        app.toggle_service_filter();
+                    }
+                    KeyCode::Char('x') => {
+                        app.disconnect_all();
+                    }
+                    KeyCode::Char('I') => {
+                        app.toggle_inventory();
+                    }
+                    KeyCode::Char('R') => {
+                        app.toggle_capture();
+                    }
+                    KeyCode::Char('z') => {
+                        app.striped = !app.striped;
+                    }
+                    KeyCode::Char('t') => {
+                        app.stats_view = !app.stats_view;
+                    }
+                    KeyCode::Char('r') => {
+                        app.raw_view = !app.raw_view;
+                    }
+                    KeyCode::Char('e') => app.export(ExportTarget::Csv),
+                    KeyCode::Char('H') => app.export(ExportTarget::RssiHistory),
+                    KeyCode::Char('J') => app.export(ExportTarget::Json),
+                    KeyCode::Char('D') => app.export(ExportTarget::Device),
+                    KeyCode::Char('Y') => app.export(ExportTarget::Text),
                     KeyCode::Enter => {
                         if app.error_view {
                             app.error_view = false;
+                        } else if app.services_view {
+                            app.services_view = false;
                         } else if app.inspect_view {
                             app.inspect_view = false;
                         } else {
-                            app.is_loading = true;
                             app.connect().await;
                         }
                     }
                     KeyCode::Down | KeyCode::Char('j') => {
-                        if app.inspect_view {
+                        if app.services_view {
+                            app.services_scroll += 1;
+                        } else if app.inspect_view {
                             app.inspect_overlay_scroll += 1;
-                        } else if !app.devices.is_empty() {
+                        } else if !app.visible_devices().is_empty() {
                             let next = match app.table_state.selected() {
                                 Some(selected) => {
-                                    if selected >= app.devices.len() - 1 {
+                                    if selected >= app.visible_devices().len() - 1 {
                                         0
                                     } else {
                                         selected + 1
@@ -138,14 +566,16 @@ pub async fn viewer<B: Backend>(
                         }
                     }
                     KeyCode::Up | KeyCode::Char('k') => {
-                        if app.inspect_view {
+                        if app.services_view {
+                            app.services_scroll = app.services_scroll.saturating_sub(1);
+                        } else if app.inspect_view {
                             app.inspect_overlay_scroll =
                                 app.inspect_overlay_scroll.saturating_sub(1);
-                        } else {
+                        } else if !app.visible_devices().is_empty() {
                             let previous = match app.table_state.selected() {
                                 Some(selected) => {
                                     if selected == 0 {
-                                        app.devices.len() - 1
+                                        app.visible_devices().len() - 1
                                     } else {
                                         selected - 1
                                     }
@@ -155,20 +585,76 @@ pub async fn viewer<B: Backend>(
                             app.table_state.select(Some(previous));
                         }
                     }
+                    KeyCode::Char('S') => app.cycle_sort(),
+                    KeyCode::Char('/') => app.start_search_input(),
+                    KeyCode::Char(':') => app.command_input = Some(String::new()),
+                    KeyCode::Char('N') => app.named_only = !app.named_only,
+                    KeyCode::Char('C') => app.compact = !app.compact,
+                    KeyCode::Char('V') => app.show_vendor = !app.show_vendor,
+                    KeyCode::Char('P') => app.rssi_percent = !app.rssi_percent,
+                    KeyCode::Char('L') => {
+                        app.services_view = !app.services_view;
+                        app.services_scroll = 0;
+                    }
+                    KeyCode::Char('F') => app.toggle_last_seen_sort(),
                     _ => {}
                 }
             }
         }
+        app.remember_selection();
 
         // Check for updates
-        if let Ok(new_device) = app.rx.try_recv() {
+        loop {
+            let new_device = match app.rx.try_recv() {
+                Ok(new_device) => new_device,
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return Err(BluscanError::ChannelClosed),
+            };
             match new_device {
-                DeviceData::DeviceInfo(device) => app.devices.push(device),
+                DeviceData::DeviceBatch {
+                    devices,
+                    observations,
+                } => {
+                    app.channel_health.record_batch(devices.len(), observations);
+                    app.merge_batch(devices, observations);
+                }
                 DeviceData::Characteristics(characteristics) => {
                     app.selected_characteristics = characteristics;
+                    app.connect_succeeded();
                     app.inspect_view = true;
                     app.is_loading = false;
+                    app.connect_progress = None;
+                }
+                DeviceData::Connecting(progress) => app.connect_progress = Some(progress),
+                DeviceData::ConnectFailed(error) => app.connect_failed(error),
+                DeviceData::ScanReconfigured(result) => {
+                    app.reconfiguring = false;
+                    if let Err(e) = result {
+                        app.error_message = format!("Unable to restart the scan: {}", e);
+                        app.error_view = true;
+                    }
+                }
+                DeviceData::InventoryReading(identity) => {
+                    if let Some(inventory) = &mut app.inventory {
+                        inventory.current = Some(identity);
+                    }
+                }
+                DeviceData::InventoryEntry(entry) => app.record_inventory_entry(*entry),
+                DeviceData::InventoryDone => app.finish_inventory(),
+                DeviceData::ReplayFinished { replayed, skipped } => {
+                    app.finish_replay(replayed, skipped)
                 }
+                DeviceData::WatchAlert(alert) => {
+                    app.error_message = alert;
+                    app.error_view = true;
+                }
+                DeviceData::WsClient { peer, connected } => app.ws_client(peer, connected),
+                DeviceData::MqttStatus(status) => app.mqtt_status = Some(status),
+                DeviceData::Disconnected {
+                    disconnected,
+                    failed,
+                } => app.disconnected(disconnected, failed),
+                DeviceData::ScanFailed(e) => return Err(e),
                 DeviceData::Error(error) => {
                     app.error_message = error;
                     app.error_view = true;
@@ -180,6 +666,274 @@ pub async fn viewer<B: Backend>(
                 app.table_state.select(Some(0));
             }
         }
+        if app.channel_health.finish_pass() {
+            app.error_message = format!(
+                "Scan results are piling up ({} batches between two redraws). Raise --coalesce-ms to send fewer, larger batches.",
+                app.channel_health.max_backlog()
+            );
+            app.error_view = true;
+        }
+
+        // The gauge pulses with every bell, so terminals with the bell disabled or
+        // unsupported still show the feedback.
+        if app.proximity_pulse() && app.proximity_feedback == ProximityFeedback::Bell {
+            let mut stdout = std::io::stdout();
+            stdout.write_all(b"\x07")?;
+            stdout.flush()?;
+        }
+
+        app.answer_api_queries();
+        app.flush_capture(false);
+        app.evict_stale();
+        app.enforce_device_cap();
+        app.publish_removed();
+        app.check_watch_list();
+        app.check_idle();
+        app.anchor_selection();
+        let chip_count = app.top_manufacturers().len();
+        app.chip_cursor = app.chip_cursor.min(chip_count.saturating_sub(1));
+        let visible_count = app.visible_devices().len();
+        if app.table_state.selected().unwrap_or(0) >= visible_count && visible_count > 0 {
+            app.table_state.select(Some(visible_count - 1));
+        }
     }
     Ok(())
 }
+
+/// Runs a command typed at the `:` prompt. `:quit` is handled by the viewer, which it ends.
+async fn run_command(app: &mut App, command: Command) {
+    match command {
+        Command::Sort(key, reversed) => {
+            app.sort_key = key;
+            app.sort_reversed = reversed;
+        }
+        Command::Filter(search) => app.search = search,
+        Command::Export(target) => app.export(target),
+        Command::Connect => app.connect().await,
+        Command::Mark => app.toggle_mark(),
+        Command::Clear => app.marked_devices.clear(),
+        Command::Pause => app.set_paused(true),
+        Command::Resume => app.set_paused(false),
+        Command::Help => {
+            app.error_message = commands::help();
+            app.error_view = true;
+        }
+        Command::Quit => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, VecDeque};
+
+    use crossterm::event::{KeyEvent, KeyModifiers};
+    use ratatui::backend::TestBackend;
+
+    use super::*;
+    use crate::{
+        cli::Args,
+        decoders::DecoderRegistry,
+        scanner::ScanConfig,
+        source::MockSource,
+        structs::{PersistedState, TrackedDevice},
+    };
+
+    /// Plays back key presses, each after a poll without input, so the viewer has merged
+    /// the results the source has sent and evicted devices in between. Quits once the
+    /// script runs out.
+    struct ScriptedInput {
+        keys: VecDeque<KeyCode>,
+        idle: bool,
+    }
+
+    impl ScriptedInput {
+        fn keys(keys: &str) -> Self {
+            Self {
+                keys: keys.chars().map(KeyCode::Char).collect(),
+                idle: true,
+            }
+        }
+
+        fn then(mut self, key: KeyCode) -> Self {
+            self.keys.push_back(key);
+            self
+        }
+    }
+
+    impl InputEvents for ScriptedInput {
+        fn next(&mut self, timeout: Duration) -> io::Result<Option<Event>> {
+            self.idle = !self.idle;
+            if !self.idle {
+                std::thread::sleep(timeout);
+                return Ok(None);
+            }
+            let key = self.keys.pop_front().unwrap_or(KeyCode::Char('q'));
+            Ok(Some(Event::Key(KeyEvent::new(key, KeyModifiers::NONE))))
+        }
+    }
+
+    fn device(address: &str, name: &str, rssi: i16, seen_secs_ago: i64) -> DeviceInfo {
+        let seen = chrono::Local::now() - chrono::Duration::seconds(seen_secs_ago);
+        DeviceInfo {
+            id: address.to_string(),
+            address: address.to_string(),
+            name: Some(name.to_string()),
+            rssi: Some(rssi),
+            first_seen: seen,
+            last_seen: seen,
+            ..DeviceInfo::default()
+        }
+    }
+
+    /// Runs the viewer on a test terminal, fed by `devices` in one batch, until `input`
+    /// quits. Returns the app and the last frame drawn.
+    async fn run(args: Args, devices: Vec<DeviceInfo>, input: ScriptedInput) -> (App, String) {
+        run_with(args, devices, input, |_| {}).await
+    }
+
+    /// Like `run`, with `setup` applied to the app before the scan starts.
+    async fn run_with(
+        args: Args,
+        devices: Vec<DeviceInfo>,
+        mut input: ScriptedInput,
+        setup: impl FnOnce(&mut App),
+    ) -> (App, String) {
+        let mut app = App::new(
+            &args,
+            ScanConfig::default(),
+            DecoderRegistry::with_builtins(HashMap::new()),
+        );
+        // Aliases, notes and categories saved by a previous run would change what the
+        // searches and filters match.
+        app.state = PersistedState::default();
+        setup(&mut app);
+        app.scan_with(Box::new(MockSource::new().batch(Duration::ZERO, devices)));
+        let mut terminal = Terminal::new(TestBackend::new(160, 40)).unwrap();
+        viewer(&mut terminal, &mut app, &mut input).await.unwrap();
+        let buffer = terminal.backend().buffer();
+        let screen = buffer
+            .content
+            .chunks(buffer.area.width.into())
+            .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n");
+        (app, screen)
+    }
+
+    fn visible_names(app: &App) -> Vec<&str> {
+        app.visible_devices()
+            .iter()
+            .filter_map(|device| device.name.as_deref())
+            .collect()
+    }
+
+    fn lamp_kettle_tag() -> Vec<DeviceInfo> {
+        vec![
+            device("AA:BB:CC:DD:EE:01", "Lamp", -70, 5),
+            device("AA:BB:CC:DD:EE:02", "Kettle", -40, 1),
+            device("AA:BB:CC:DD:EE:03", "Tag", -55, 3),
+        ]
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn arrow_keys_move_the_selection() {
+        let input = ScriptedInput::keys("")
+            .then(KeyCode::Down)
+            .then(KeyCode::Down);
+        let (app, screen) = run(Args::default(), lamp_kettle_tag(), input).await;
+        assert_eq!(app.table_state.selected(), Some(2));
+        assert_eq!(
+            app.selected_device()
+                .and_then(|device| device.name.as_deref()),
+            Some("Tag")
+        );
+        for name in ["Lamp", "Kettle", "Tag"] {
+            assert!(screen.contains(name), "{} is not drawn:\n{}", name, screen);
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn search_filters_the_table() {
+        let input = ScriptedInput::keys("/ket").then(KeyCode::Enter);
+        let (app, screen) = run(Args::default(), lamp_kettle_tag(), input).await;
+        assert_eq!(app.search.as_deref(), Some("ket"));
+        assert_eq!(visible_names(&app), ["Kettle"]);
+        assert!(!screen.contains("Lamp"), "{}", screen);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn sort_key_cycles_through_last_seen_and_rssi() {
+        let (app, _) = run(Args::default(), lamp_kettle_tag(), ScriptedInput::keys("S")).await;
+        assert_eq!(app.sort_key.label(), "last-seen");
+        assert_eq!(visible_names(&app), ["Kettle", "Tag", "Lamp"]);
+
+        let (app, _) = run(
+            Args::default(),
+            lamp_kettle_tag(),
+            ScriptedInput::keys("SS"),
+        )
+        .await;
+        assert_eq!(app.sort_key.label(), "rssi");
+        assert_eq!(visible_names(&app), ["Kettle", "Tag", "Lamp"]);
+
+        let (app, _) = run(Args::default(), lamp_kettle_tag(), ScriptedInput::keys("")).await;
+        assert_eq!(visible_names(&app), ["Lamp", "Kettle", "Tag"]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn stale_devices_are_evicted_in_live_mode() {
+        let mut devices = lamp_kettle_tag();
+        devices.push(device("AA:BB:CC:DD:EE:04", "Gone", -60, 120));
+        let (app, screen) = run(Args::default(), devices, ScriptedInput::keys("")).await;
+        assert_eq!(visible_names(&app), ["Lamp", "Kettle", "Tag"]);
+        assert!(!screen.contains("Gone"), "{}", screen);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn session_stats_count_the_devices_eviction_removed() {
+        let mut devices = lamp_kettle_tag();
+        devices.push(DeviceInfo {
+            name: None,
+            ..device("AA:BB:CC:DD:EE:04", "", -60, 120)
+        });
+        let args = Args {
+            max_devices: Some(2),
+            ..Args::default()
+        };
+        let (app, _) = run(args, devices, ScriptedInput::keys("")).await;
+        assert_eq!(app.devices.len(), 2);
+        let stats = session_stats(&app.session_seen, Duration::ZERO, 0, 0, app.observations);
+        assert_eq!((stats.total, stats.named, stats.unnamed), (4, 3, 1));
+        assert_eq!(stats.observations, 4);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn device_cap_evicts_the_oldest_devices() {
+        let args = Args {
+            max_devices: Some(2),
+            ..Args::default()
+        };
+        let (app, _) = run(args, lamp_kettle_tag(), ScriptedInput::keys("")).await;
+        assert_eq!(visible_names(&app), ["Kettle", "Tag"]);
+        assert_eq!(app.capped_evictions, 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn device_cap_keeps_the_tracked_device() {
+        let args = Args {
+            max_devices: Some(2),
+            ..Args::default()
+        };
+        let (app, _) = run_with(args, lamp_kettle_tag(), ScriptedInput::keys(""), |app| {
+            app.tracked = Some(TrackedDevice {
+                identity: "AA:BB:CC:DD:EE:01".to_string(),
+                smoothed_rssi: None,
+                last_pulse: None,
+            });
+        })
+        .await;
+        // The Lamp is the oldest, so the Tag is evicted in its place.
+        assert_eq!(visible_names(&app), ["Lamp", "Kettle"]);
+        assert_eq!(app.capped_evictions, 1);
+    }
+}
@@ -0,0 +1,91 @@
+use std::{collections::HashMap, error::Error};
+
+use futures::future;
+
+use crate::{
+    app::{App, DeviceData, STALE_TIMEOUT},
+    cli::{Args, WaitForArgs},
+    decoders::DecoderRegistry,
+    scanner::ScanConfig,
+    source::ReplaySource,
+    structs::DeviceInfo,
+};
+
+/// How a `btlescan wait-for` run ended.
+pub enum WaitOutcome {
+    /// A matching device was seen and printed.
+    Found,
+    /// The timeout passed, or the replay ended, without a match.
+    TimedOut,
+    /// The wait was interrupted with Ctrl+C.
+    Interrupted,
+}
+
+/// Runs `btlescan wait-for`: scans, or replays, until a device matching the arguments is
+/// seen and prints it as one line of JSON.
+pub async fn run(args: &WaitForArgs) -> Result<WaitOutcome, Box<dyn Error>> {
+    let mut app = App::new(
+        &Args::default(),
+        ScanConfig::default(),
+        DecoderRegistry::with_builtins(HashMap::new()),
+    );
+    match &args.replay {
+        Some(path) => {
+            let source = ReplaySource::open(path, args.replay_speed)
+                .map_err(|e| format!("unable to read {}: {}", path.display(), e))?;
+            app.replay(source, args.replay_speed);
+        }
+        None => app.scan().await,
+    }
+    let deadline = async {
+        match args.timeout {
+            Some(timeout) => tokio::time::sleep(timeout).await,
+            None => future::pending().await,
+        }
+    };
+    let interrupted = tokio::signal::ctrl_c();
+    tokio::pin!(deadline, interrupted);
+    let outcome = loop {
+        let data = tokio::select! {
+            data = app.rx.recv() => data,
+            _ = &mut interrupted => break WaitOutcome::Interrupted,
+            _ = &mut deadline => break WaitOutcome::TimedOut,
+        };
+        match data {
+            Some(DeviceData::DeviceBatch {
+                devices,
+                observations,
+            }) => app.merge_batch(devices, observations),
+            Some(DeviceData::Error(error)) => eprintln!("error: {}", error),
+            Some(DeviceData::ScanFailed(e)) => return Err(e.into()),
+            Some(DeviceData::ReplayFinished { .. }) | None => break WaitOutcome::TimedOut,
+            Some(_) => {}
+        }
+        let found = app
+            .devices
+            .iter()
+            .find(|device| !device.is_stale(STALE_TIMEOUT) && matches(args, device));
+        if let Some(device) = found {
+            println!("{}", serde_json::to_string(&app.device_json(device))?);
+            break WaitOutcome::Found;
+        }
+    };
+    app.stop_scan().await;
+    Ok(outcome)
+}
+
+/// Returns `true` if the device is the one waited for and, with `--min-rssi`, was heard at
+/// least that strongly.
+fn matches(args: &WaitForArgs, device: &DeviceInfo) -> bool {
+    let identified = match (&args.address, &args.name) {
+        (Some(address), _) => {
+            device.address_or_id() == *address || device.id.to_uppercase() == *address
+        }
+        (None, Some(name)) => device.name.as_ref() == Some(name),
+        (None, None) => false,
+    };
+    identified
+        && args
+            .min_rssi
+            .is_none_or(|threshold| device.rssi.is_some_and(|rssi| rssi >= threshold))
+}
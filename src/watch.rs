@@ -0,0 +1,167 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    app::STALE_TIMEOUT,
+    structs::{DeviceInfo, IdentityKey, NameFallback},
+    utils::{format_reading, normalize_address},
+};
+
+/// The shortest time between two alerts about the same device, so a device flapping at the
+/// edge of range does not raise an alert on every update.
+const ALERT_COOLDOWN: Duration = Duration::from_secs(60);
+
+/// What a watch-list alert reports about a device.
+#[derive(Clone, Copy, PartialEq)]
+pub enum AlertKind {
+    Appeared,
+    Near,
+    Disappeared,
+}
+
+impl AlertKind {
+    /// Returns a short lowercase label for the kind of alert.
+    pub fn label(self) -> &'static str {
+        match self {
+            AlertKind::Appeared => "appeared",
+            AlertKind::Near => "near",
+            AlertKind::Disappeared => "disappeared",
+        }
+    }
+}
+
+/// An alert about a watched device.
+pub struct WatchAlert {
+    pub kind: AlertKind,
+    pub identity: String,
+    /// The device's name, when it is in the list and advertises one.
+    pub name: Option<String>,
+    pub rssi: Option<i16>,
+    pub message: String,
+}
+
+/// Follows the devices given with `--watch` and reports when one appears, disappears, or
+/// comes closer than the `--watch-rssi` threshold.
+pub struct WatchList {
+    /// The watched addresses or platform ids, normalized to uppercase.
+    entries: HashSet<String>,
+    rssi_threshold: Option<i16>,
+    present: HashSet<String>,
+    near: HashSet<String>,
+    last_alert: HashMap<String, Instant>,
+}
+
+impl WatchList {
+    /// Creates a watch list of the given addresses or platform ids.
+    pub fn new(entries: impl IntoIterator<Item = String>, rssi_threshold: Option<i16>) -> Self {
+        Self {
+            entries: entries
+                .into_iter()
+                .filter_map(|entry| normalize_address(&entry))
+                .collect(),
+            rssi_threshold,
+            present: HashSet::new(),
+            near: HashSet::new(),
+            last_alert: HashMap::new(),
+        }
+    }
+
+    /// Returns `true` if no device is watched.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns `true` if the device is on the watch list, by address or platform id.
+    pub fn contains(&self, device: &DeviceInfo) -> bool {
+        self.entries.contains(&device.address_or_id())
+            || self.entries.contains(&device.id.to_uppercase())
+    }
+
+    /// Compares the device list with the previous call and returns an alert for each watched
+    /// device that appeared, disappeared, or crossed the RSSI threshold, unless that device
+    /// raised an alert within the cooldown. Devices are told apart by their identity under
+    /// `key`, and messages name them by their alias in `aliases`, keyed by identity, where
+    /// they have one.
+    pub fn check(
+        &mut self,
+        devices: &[DeviceInfo],
+        key: IdentityKey,
+        name_fallback: NameFallback,
+        aliases: &HashMap<String, String>,
+    ) -> Vec<WatchAlert> {
+        let mut alerts = Vec::new();
+        let mut present = HashSet::new();
+        let mut near = HashSet::new();
+        for device in devices {
+            if !self.contains(device) || device.is_stale(STALE_TIMEOUT) {
+                continue;
+            }
+            let identity = device.get_id(key);
+            let label = format!(
+                "{} ({}, {} dBm)",
+                aliases
+                    .get(&identity)
+                    .cloned()
+                    .unwrap_or_else(|| device.display_name(name_fallback)),
+                identity,
+                format_reading(device.rssi)
+            );
+            let rssi = device.rssi;
+            if self
+                .rssi_threshold
+                .is_some_and(|threshold| rssi.is_some_and(|rssi| rssi >= threshold))
+            {
+                near.insert(identity.clone());
+            }
+            let alert = |kind, message| WatchAlert {
+                kind,
+                identity: identity.clone(),
+                name: device.name.clone(),
+                rssi,
+                message,
+            };
+            if !self.present.contains(&identity) {
+                alerts.push(alert(
+                    AlertKind::Appeared,
+                    format!("Watched device appeared: {}", label),
+                ));
+            } else if near.contains(&identity) && !self.near.contains(&identity) {
+                alerts.push(alert(
+                    AlertKind::Near,
+                    format!("Watched device is near: {}", label),
+                ));
+            }
+            present.insert(identity);
+        }
+        for identity in self.present.difference(&present) {
+            alerts.push(WatchAlert {
+                kind: AlertKind::Disappeared,
+                identity: identity.clone(),
+                name: None,
+                rssi: None,
+                message: match aliases.get(identity) {
+                    Some(alias) => format!("Watched device disappeared: {} ({})", alias, identity),
+                    None => format!("Watched device disappeared: {}", identity),
+                },
+            });
+        }
+        self.present = present;
+        self.near = near;
+        alerts
+            .into_iter()
+            .filter(|alert| {
+                let due = self
+                    .last_alert
+                    .get(&alert.identity)
+                    .is_none_or(|at| at.elapsed() >= ALERT_COOLDOWN);
+                if due {
+                    self.last_alert
+                        .insert(alert.identity.clone(), Instant::now());
+                }
+                due
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,49 @@
+use ratatui::{
+    layout::Constraint,
+    style::{Color, Style},
+    widgets::{Block, Borders, Row, Table},
+};
+
+use crate::{
+    structs::DeviceInfo,
+    utils::{ad_type_name, bytes_to_hex, parse_ad_structures},
+};
+
+/// Creates a table with the advertisement of a selected device split into its AD structures.
+pub fn ad_structure_table(selected_device: &DeviceInfo) -> Table<'_> {
+    let rows: Vec<Row> = parse_ad_structures(&selected_device.raw_advertisement)
+        .into_iter()
+        .map(|structure| {
+            Row::new(vec![
+                format!("0x{:02X}", structure.ad_type),
+                ad_type_name(structure.ad_type).to_string(),
+                (structure.data.len() + 1).to_string(),
+                bytes_to_hex(&structure.data),
+            ])
+        })
+        .collect();
+
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(6),
+            Constraint::Length(42),
+            Constraint::Length(4),
+            Constraint::Fill(1),
+        ],
+    )
+    .header(
+        Row::new(vec!["Type", "Name", "Len", "Value"]).style(Style::default().fg(Color::Yellow)),
+    )
+    .block(
+        Block::default()
+            .title(if selected_device.raw_reconstructed {
+                "Advertisement Structures (reconstructed from parsed fields)".to_owned()
+            } else {
+                "Advertisement Structures".to_owned()
+            })
+            .borders(Borders::ALL),
+    );
+
+    table
+}
@@ -0,0 +1,55 @@
+use ratatui::{
+    layout::Constraint,
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, Row, Table},
+};
+
+use crate::structs::DeviceCategory;
+
+/// Creates the category filter popup: an "All" entry followed by every category, each with
+/// the number of devices in it. `selected` indexes the entries, with `0` being "All".
+pub fn category_popup(
+    selected: usize,
+    counts: &[(DeviceCategory, usize)],
+    active: Option<DeviceCategory>,
+) -> Table<'static> {
+    let total: usize = counts.iter().map(|(_, count)| count).sum();
+    let entries = std::iter::once((None, total)).chain(
+        counts
+            .iter()
+            .map(|(category, count)| (Some(*category), *count)),
+    );
+    let rows: Vec<Row> = entries
+        .enumerate()
+        .map(|(i, (category, count))| {
+            let marker = if category == active { "●" } else { " " };
+            let row = Row::new(vec![
+                marker.to_string(),
+                category.map_or("all", DeviceCategory::label).to_string(),
+                count.to_string(),
+            ]);
+            if i == selected {
+                row.style(Style::default().add_modifier(Modifier::REVERSED))
+            } else {
+                row
+            }
+        })
+        .collect();
+
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(1),
+            Constraint::Fill(1),
+            Constraint::Length(5),
+        ],
+    )
+    .header(Row::new(vec!["", "Category", "Count"]).style(Style::default().fg(Color::Yellow)))
+    .block(
+        Block::default()
+            .title("Filter by Category (enter → select, esc → close)")
+            .borders(Borders::ALL),
+    );
+
+    table
+}
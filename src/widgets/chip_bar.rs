@@ -0,0 +1,48 @@
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::Paragraph,
+};
+
+use crate::vendor_db;
+
+/// The longest manufacturer name shown on a chip before it is cut short.
+const MAX_CHIP_NAME: usize = 12;
+
+/// Creates the manufacturer chip bar: one chip per manufacturer with its company code and
+/// device count. The chip under the cursor is reversed and the active filter is green.
+pub fn chip_bar(
+    manufacturers: &[(u16, usize)],
+    cursor: usize,
+    active: Option<u16>,
+) -> Paragraph<'static> {
+    let mut spans = vec![Span::styled(
+        "Manufacturers ←/→ m: ",
+        Style::default().fg(Color::DarkGray),
+    )];
+    if manufacturers.is_empty() {
+        spans.push(Span::styled("none", Style::default().fg(Color::DarkGray)));
+    }
+    for (i, (code, count)) in manufacturers.iter().enumerate() {
+        let name: String = vendor_db::company(*code)
+            .unwrap_or("Unknown")
+            .chars()
+            .take(MAX_CHIP_NAME)
+            .collect();
+        let mut style = if active == Some(*code) {
+            Style::default().fg(Color::Black).bg(Color::Green)
+        } else {
+            Style::default().fg(Color::Yellow)
+        };
+        if i == cursor {
+            style = style.add_modifier(Modifier::REVERSED);
+        }
+        spans.push(Span::styled(
+            format!("[{:04X} {} {}]", code, name.trim_end(), count),
+            style,
+        ));
+        spans.push(Span::raw(" "));
+    }
+
+    Paragraph::new(Line::from(spans))
+}
@@ -0,0 +1,24 @@
+use ratatui::{
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::Paragraph,
+};
+
+/// Creates the command line that replaces the status bar: the command being typed after
+/// `:`, or why the last command failed, in red.
+pub fn command_line(input: Option<&str>, error: Option<&str>) -> Paragraph<'static> {
+    let line = match (input, error) {
+        (Some(input), _) => Line::from(vec![
+            Span::raw(format!(":{}▏", input)),
+            Span::styled(
+                "  (tab → complete, enter → run, esc → cancel)",
+                Style::default().fg(Color::DarkGray),
+            ),
+        ]),
+        (None, Some(error)) => {
+            Line::styled(format!("error: {}", error), Style::default().fg(Color::Red))
+        }
+        (None, None) => Line::default(),
+    };
+    Paragraph::new(line)
+}
@@ -0,0 +1,133 @@
+use ratatui::{
+    layout::Constraint,
+    style::{Color, Style},
+    widgets::{Block, Borders, Row, Table},
+};
+
+use crate::{
+    decoders::device_tags,
+    structs::{DeviceCategory, DeviceInfo, NameFallback},
+    utils::{bytes_to_hex, extract_manufacturer_data, format_reading, rssi_percent, service_label},
+};
+
+/// A device as the comparison shows it, with the values the detail pane would look up.
+pub struct ComparedDevice<'a> {
+    pub device: &'a DeviceInfo,
+    pub alias: Option<&'a str>,
+    pub category: DeviceCategory,
+}
+
+/// Creates two tables comparing the pinned device with the selected one, field by field.
+/// Fields only one of them has are listed on both sides, and fields whose values differ
+/// are highlighted.
+pub fn compare_view(
+    pinned: &ComparedDevice,
+    selected: &ComparedDevice,
+    name_fallback: NameFallback,
+    rssi_percent: bool,
+) -> (Table<'static>, Table<'static>) {
+    let pinned_fields = fields(pinned, name_fallback, rssi_percent);
+    let selected_fields = fields(selected, name_fallback, rssi_percent);
+    let mut labels: Vec<&String> = pinned_fields.iter().map(|(label, _)| label).collect();
+    for (label, _) in &selected_fields {
+        if !labels.contains(&label) {
+            labels.push(label);
+        }
+    }
+    let value = |fields: &[(String, String)], label: &String| {
+        fields
+            .iter()
+            .find(|(field, _)| field == label)
+            .map_or_else(|| "-".to_owned(), |(_, value)| value.clone())
+    };
+    let (mut pinned_rows, mut selected_rows) = (Vec::new(), Vec::new());
+    for label in labels {
+        let (old, new) = (value(&pinned_fields, label), value(&selected_fields, label));
+        let style = if old == new {
+            Style::default()
+        } else {
+            Style::default().fg(Color::Yellow)
+        };
+        pinned_rows.push(Row::new(vec![label.clone(), old]).style(style));
+        selected_rows.push(Row::new(vec![label.clone(), new]).style(style));
+    }
+    let table = |rows: Vec<Row<'static>>, title: String| {
+        Table::new(rows, [Constraint::Length(24), Constraint::Fill(1)])
+            .block(Block::default().title(title).borders(Borders::ALL))
+    };
+    (
+        table(
+            pinned_rows,
+            format!(
+                "Pinned: {} (esc → unpin)",
+                pinned.device.display_name(name_fallback)
+            ),
+        ),
+        table(
+            selected_rows,
+            format!("Selected: {}", selected.device.display_name(name_fallback)),
+        ),
+    )
+}
+
+/// Lists the compared fields of a device as label and value.
+fn fields(
+    compared: &ComparedDevice,
+    name_fallback: NameFallback,
+    percent: bool,
+) -> Vec<(String, String)> {
+    let device = compared.device;
+    let mut fields = vec![
+        ("Name:".to_owned(), device.display_name(name_fallback)),
+        (
+            "Alias:".to_owned(),
+            compared.alias.unwrap_or("-").to_owned(),
+        ),
+        ("Address:".to_owned(), device.address_or_id()),
+        (
+            "Address Type:".to_owned(),
+            device.address_type_label().to_owned(),
+        ),
+        ("Type:".to_owned(), device.device_type_label().to_owned()),
+        ("Category:".to_owned(), compared.category.label().to_owned()),
+        (
+            "Vendor:".to_owned(),
+            device.vendor().unwrap_or("n/a").to_owned(),
+        ),
+        (
+            "Signal:".to_owned(),
+            match device.rssi {
+                Some(rssi) if percent => format!("{}%", rssi_percent(rssi)),
+                Some(rssi) => format!("{} dBm", rssi),
+                None => "—".to_owned(),
+            },
+        ),
+        ("TX Power:".to_owned(), format_reading(device.tx_power)),
+        (
+            "Services:".to_owned(),
+            if device.services.is_empty() {
+                "n/a".to_owned()
+            } else {
+                device
+                    .services
+                    .iter()
+                    .map(service_label)
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            },
+        ),
+    ];
+    for entry in extract_manufacturer_data(&device.manufacturer_data) {
+        fields.push((
+            format!("Manufacturer 0x{:04X}:", entry.company_id),
+            format!("{}: {}", entry.company, entry.data),
+        ));
+    }
+    let mut service_data: Vec<_> = device.service_data.iter().collect();
+    service_data.sort_by_key(|(uuid, _)| **uuid);
+    for (uuid, data) in service_data {
+        fields.push((format!("{}:", service_label(uuid)), bytes_to_hex(data)));
+    }
+    fields.push(("Tags:".to_owned(), device_tags(device).join(" ")));
+    fields
+}
@@ -2,32 +2,200 @@ use ratatui::{
     layout::Constraint,
     widgets::{Block, Borders, Row, Table},
 };
+use uuid::Uuid;
 
-use crate::{structs::DeviceInfo, utils::extract_manufacturer_data};
+use crate::{
+    decoders::{reference_rssi, DecoderRegistry},
+    structs::{DeviceHistory, DeviceInfo, NameFallback},
+    utils::{
+        self, capability_summary, estimate_distance, extract_manufacturer_data, format_age,
+        format_duration, major_device_class, service_label,
+    },
+};
+
+/// How many service names a long service list is summarized with.
+const SERVICE_SUMMARY_LENGTH: usize = 3;
 
 /// Creates a table with more detailed information about a selected device.
-pub fn detail_table(selected_device: &DeviceInfo) -> Table {
-    let services_binding = selected_device.services.len().to_string();
-    let manufacturer_data = extract_manufacturer_data(&selected_device.manufacturer_data);
-    let table = Table::new(
-        vec![
-            Row::new(vec![
-                "Detected At:".to_owned(),
-                selected_device.detected_at.clone(),
-            ]),
-            Row::new(vec!["Services:".to_owned(), services_binding]),
-            Row::new(vec![
-                "Company Code ID:".to_owned(),
-                manufacturer_data.company_code,
-            ]),
-            Row::new(vec![
-                "Manufacturer Data:".to_owned(),
-                manufacturer_data.data,
-            ]),
-        ],
-        [Constraint::Length(20), Constraint::Length(80)],
-    )
-    .block(
+/// `note` is the note taken on the device, `previous_names` holds the names the device
+/// advertised before, oldest first,
+/// `history` what the device database knew about it before this session, and
+/// `gatt_services` the services found by connecting to it. With `rssi_percent`, the signal
+/// is given in percent as well as in dBm.
+#[allow(clippy::too_many_arguments)]
+pub fn detail_table<'a>(
+    selected_device: &'a DeviceInfo,
+    name_fallback: NameFallback,
+    rssi_percent: bool,
+    alias: Option<&str>,
+    note: Option<&str>,
+    previous_names: &[String],
+    history: Option<&DeviceHistory>,
+    gatt_services: &[Uuid],
+    decoders: &DecoderRegistry,
+) -> Table<'a> {
+    // Long lists are cut short; the services overlay lists them in full.
+    let service_labels = |services: &[Uuid]| {
+        let labels: Vec<String> = services
+            .iter()
+            .take(SERVICE_SUMMARY_LENGTH)
+            .map(service_label)
+            .collect();
+        match services.len() {
+            0 => "n/a".to_owned(),
+            count if count > SERVICE_SUMMARY_LENGTH => {
+                format!("{} services: {}, … (L → list)", count, labels.join(", "))
+            }
+            _ => labels.join(", "),
+        }
+    };
+    let mut rows = vec![
+        Row::new(vec![
+            "Name:".to_owned(),
+            selected_device.display_name(name_fallback),
+        ]),
+        Row::new(vec!["Alias:".to_owned(), alias.unwrap_or("-").to_owned()]),
+        Row::new(vec!["Note:".to_owned(), note.unwrap_or("-").to_owned()]),
+        Row::new(vec![
+            "Previous Names:".to_owned(),
+            if previous_names.is_empty() {
+                "-".to_owned()
+            } else {
+                previous_names
+                    .iter()
+                    .rev()
+                    .map(String::as_str)
+                    .collect::<Vec<&str>>()
+                    .join(" ← ")
+            },
+        ]),
+        Row::new(vec![
+            "History:".to_owned(),
+            history.map_or_else(
+                || "-".to_owned(),
+                |history| {
+                    format!(
+                        "first seen {}, previously seen {} times on {} day{}, last on {}",
+                        chrono::DateTime::parse_from_rfc3339(&history.first_seen)
+                            .map_or_else(|_| history.first_seen.clone(), format_age),
+                        history.sightings,
+                        history.days_seen,
+                        if history.days_seen == 1 { "" } else { "s" },
+                        chrono::DateTime::parse_from_rfc3339(&history.last_seen).map_or_else(
+                            |_| history.last_seen.clone(),
+                            |at| at.format("%Y-%m-%d %H:%M").to_string()
+                        )
+                    )
+                },
+            ),
+        ]),
+        Row::new(vec![
+            "Detected At:".to_owned(),
+            chrono::DateTime::parse_from_rfc3339(&selected_device.detected_at).map_or_else(
+                |_| selected_device.detected_at.clone(),
+                |at| at.format("%Y-%m-%d %H:%M:%S").to_string(),
+            ),
+        ]),
+        Row::new(vec![
+            "Last Seen:".to_owned(),
+            selected_device
+                .last_seen
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string(),
+        ]),
+        Row::new(vec![
+            "Dwell:".to_owned(),
+            format!(
+                "{} (since {})",
+                format_duration(selected_device.dwell()),
+                selected_device.first_seen.format("%H:%M:%S")
+            ),
+        ]),
+        Row::new(vec![
+            "Type:".to_owned(),
+            selected_device.device_type_label().to_owned(),
+        ]),
+        Row::new(vec![
+            "Connectable:".to_owned(),
+            match selected_device.is_connectable {
+                Some(true) => "yes",
+                Some(false) => "no, connecting is disabled",
+                None => "unknown",
+            }
+            .to_owned(),
+        ]),
+        Row::new(vec![
+            "Vendor:".to_owned(),
+            selected_device.vendor().unwrap_or("n/a").to_owned(),
+        ]),
+        Row::new(vec![
+            "Capabilities:".to_owned(),
+            capability_summary(&selected_device.services).unwrap_or_else(|| "n/a".to_owned()),
+        ]),
+        Row::new(vec![
+            "Services:".to_owned(),
+            service_labels(&selected_device.services),
+        ]),
+        Row::new(vec![
+            "Solicited Services:".to_owned(),
+            service_labels(&selected_device.solicited_services),
+        ]),
+    ];
+    if !gatt_services.is_empty() {
+        rows.push(Row::new(vec![
+            "GATT Services:".to_owned(),
+            service_labels(gatt_services),
+        ]));
+    }
+    // Classic devices are found by inquiry rather than advertising, so they carry a Class
+    // of Device instead of manufacturer data.
+    match selected_device.class {
+        Some(class) => rows.push(Row::new(vec![
+            "Device Class:".to_owned(),
+            format!("{} (0x{:06X})", major_device_class(class), class),
+        ])),
+        None => {
+            let manufacturer_data = extract_manufacturer_data(&selected_device.manufacturer_data);
+            if manufacturer_data.is_empty() {
+                rows.push(Row::new(vec![
+                    "Manufacturer Data:".to_owned(),
+                    "n/a".to_owned(),
+                ]));
+            }
+            // An advertisement may carry several manufacturer-specific structures.
+            for entry in manufacturer_data {
+                rows.push(Row::new(vec![
+                    format!("Manufacturer 0x{:04X}:", entry.company_id),
+                    format!("{}: {}", entry.company, entry.data),
+                ]));
+            }
+        }
+    }
+    if let Some(rssi) = selected_device.rssi {
+        rows.push(Row::new(vec![
+            "Signal:".to_owned(),
+            if rssi_percent {
+                format!("{}% ({} dBm)", utils::rssi_percent(rssi), rssi)
+            } else {
+                format!("{} dBm", rssi)
+            },
+        ]));
+    }
+    // Beacons calibrate their reference RSSI at one meter, which is more reliable than the
+    // advertised TX power measured at the antenna (about 41 dB louder than at one meter).
+    let reference = reference_rssi(selected_device)
+        .map(i16::from)
+        .or_else(|| selected_device.tx_power.map(|tx| tx - 41));
+    if let (Some(reference), Some(rssi)) = (reference, selected_device.rssi) {
+        rows.push(Row::new(vec![
+            "Distance:".to_owned(),
+            format!("~{:.1} m", estimate_distance(rssi, reference)),
+        ]));
+    }
+    for field in decoders.decode(selected_device) {
+        rows.push(Row::new(vec![format!("{}:", field.label), field.value]));
+    }
+    let table = Table::new(rows, [Constraint::Length(24), Constraint::Fill(1)]).block(
         Block::default()
             .title("More Details".to_owned())
             .borders(Borders::ALL),
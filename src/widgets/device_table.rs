@@ -1,52 +1,204 @@
+use std::collections::{HashMap, HashSet};
+
 use ratatui::{
     layout::Constraint,
     style::{Color, Modifier, Style},
     widgets::{Block, Borders, Row, Table},
 };
 
-use crate::structs::DeviceInfo;
+use crate::{
+    app::STALE_TIMEOUT,
+    decoders::device_tags,
+    pause_changes::PauseChange,
+    structs::{
+        DeviceCategory, DeviceInfo, IdentityDisplay, IdentityKey, NameFallback, ProximityZone,
+        RssiBarStyle,
+    },
+    utils::{device_zone, format_reading, rssi_level, rssi_percent, short_hash, RSSI_LEVELS},
+};
+
+/// Counts the listed devices advertising each name, leaving out those shown by their alias.
+fn name_counts<'a>(
+    devices: &[&'a DeviceInfo],
+    aliases: &[Option<&str>],
+) -> HashMap<&'a str, usize> {
+    let mut counts = HashMap::new();
+    for (i, device) in devices.iter().enumerate() {
+        if let (None, Some(name)) = (aliases.get(i).copied().flatten(), &device.name) {
+            *counts.entry(name.as_str()).or_insert(0) += 1;
+        }
+    }
+    counts
+}
+
+/// Formats the RSSI column: the number, or the percentage with `percent`, right-aligned,
+/// followed by a signal bar so the bars line up whatever the width of the number.
+fn rssi_cell(reading: Option<i16>, style: RssiBarStyle, percent: bool) -> String {
+    let rssi = match reading.filter(|_| percent) {
+        Some(reading) => format!("{}%", rssi_percent(reading)),
+        None => format_reading(reading),
+    };
+    let Some((filled, empty)) = style.chars() else {
+        return rssi;
+    };
+    let level = reading.map_or(0, rssi_level);
+    let bar: String = (0..RSSI_LEVELS)
+        .map(|i| if i < level { filled } else { empty })
+        .collect();
+    format!("{:>4} {}", rssi, bar)
+}
 
-/// Creates a table with the detected BTLE devices.
-pub fn device_table(selected: Option<usize>, devices: &[DeviceInfo]) -> Table {
+/// Creates a table with the detected Bluetooth devices. `categories` and `aliases` hold the
+/// category and alias of each device, in the same order as `devices`. When `grouped` is set, the devices are
+/// expected in zone order and a zone column labels the first row of each group. The title
+/// shows the number of devices in each zone. Devices in `renamed_devices` are highlighted
+/// until their name change is acknowledged, and devices in `pause_highlights` while their
+/// change since the last pause is fresh: green if they appeared, yellow if they changed.
+/// With `show_vendor`, the vendor of a public
+/// address follows it in the address column, cut short by the column width rather than
+/// widening it. With `rssi_percent`, the RSSI column shows the signal quality in percent.
+/// A name more than one listed device advertises is followed by a short hash of each
+/// device's identity, and with `flag_duplicates` marked with ⧉.
+#[allow(clippy::too_many_arguments)]
+pub fn device_table(
+    selected: Option<usize>,
+    devices: &[&DeviceInfo],
+    categories: &[DeviceCategory],
+    marked_devices: &HashSet<String>,
+    renamed_devices: &HashSet<String>,
+    pause_highlights: &HashMap<String, PauseChange>,
+    name_fallback: NameFallback,
+    identity_display: IdentityDisplay,
+    identity_key: IdentityKey,
+    show_vendor: bool,
+    rssi_percent: bool,
+    flag_duplicates: bool,
+    aliases: &[Option<&str>],
+    stripe: Option<Color>,
+    rssi_bar: RssiBarStyle,
+    zone_counts: &[(ProximityZone, usize)],
+    grouped: bool,
+) -> Table<'static> {
     let selected_style = Style::default().add_modifier(Modifier::REVERSED);
+    let name_counts = name_counts(devices, aliases);
     let rows: Vec<Row> = devices
         .iter()
         .enumerate()
         .map(|(i, device)| {
+            // The stripe only sets the background, so it combines with the foreground
+            // styling; the selected row uses its own style so the cursor stays visible.
+            let base = match stripe {
+                Some(color) if i % 2 == 1 => Style::default().bg(color),
+                _ => Style::default(),
+            };
+            let identity = device.get_id(identity_key);
+            let renamed = renamed_devices.contains(&identity);
             let style = if selected == Some(i) {
                 selected_style
+            } else if device.is_stale(STALE_TIMEOUT) {
+                base.fg(Color::DarkGray)
+            } else if renamed {
+                base.fg(Color::LightMagenta)
+            } else {
+                match pause_highlights.get(&identity) {
+                    Some(PauseChange::Appeared) => base.fg(Color::LightGreen),
+                    Some(PauseChange::Changed) => base.fg(Color::LightYellow),
+                    None => base,
+                }
+            };
+            let mark = if marked_devices.contains(&identity) {
+                "[x]"
             } else {
-                Style::default()
+                "[ ]"
             };
-            Row::new(vec![
-                device.get_id(),
-                device.name.clone(),
-                device.tx_power.clone(),
-                device.rssi.clone(),
-            ])
-            .style(style)
+            let zone = device_zone(device);
+            let zone_label = if i == 0 || devices.get(i - 1).map(|d| device_zone(d)) != Some(zone) {
+                zone.map_or("n/a", ProximityZone::label)
+            } else {
+                ""
+            };
+            let mut identity_cell = device.display_identity(identity_display);
+            if let Some(vendor) = device.vendor().filter(|_| show_vendor) {
+                identity_cell = format!("{} ({})", identity_cell, vendor);
+            }
+            let mut cells = vec![
+                mark.to_string(),
+                identity_cell,
+                format!(
+                    "{}{}",
+                    if renamed { "↻ " } else { "" },
+                    // The advertised name follows an alias where there is one, and the
+                    // detail pane keeps it either way.
+                    match (aliases.get(i).copied().flatten(), &device.name) {
+                        (Some(alias), Some(name)) => format!("✎ {} ({})", alias, name),
+                        (Some(alias), None) => format!("✎ {}", alias),
+                        (None, Some(name)) if name_counts.get(name.as_str()) > Some(&1) => {
+                            format!(
+                                "{}{} #{}",
+                                if flag_duplicates { "⧉ " } else { "" },
+                                name,
+                                short_hash(&identity)
+                            )
+                        }
+                        (None, _) => device.display_name(name_fallback),
+                    }
+                ),
+                device.device_type_label().to_string(),
+                categories
+                    .get(i)
+                    .copied()
+                    .unwrap_or_default()
+                    .label()
+                    .to_string(),
+                format_reading(device.tx_power),
+                rssi_cell(device.rssi, rssi_bar, rssi_percent),
+                device_tags(device).join(" "),
+            ];
+            if grouped {
+                cells.insert(0, zone_label.to_string());
+            }
+            Row::new(cells).style(style)
         })
         .collect();
 
-    let table = Table::new(
-        rows,
-        [
-            Constraint::Length(40),
-            Constraint::Length(30),
-            Constraint::Length(10),
-            Constraint::Length(10),
-        ],
-    )
-    .header(
-        Row::new(vec!["Identifier", "Name", "TX Power", "RSSI"])
-            .style(Style::default().fg(Color::Yellow)),
-    )
-    .block(
-        Block::default()
-            .title("Detected Devices")
-            .borders(Borders::ALL),
-    )
-    .highlight_style(selected_style);
+    let mut widths = vec![
+        Constraint::Length(3),
+        Constraint::Length(40),
+        Constraint::Length(30),
+        Constraint::Length(8),
+        Constraint::Length(10),
+        Constraint::Length(10),
+        Constraint::Length(10),
+        Constraint::Length(12),
+    ];
+    let mut header = vec![
+        "",
+        identity_display.header(),
+        "Name",
+        "Type",
+        "Category",
+        "TX Power",
+        if rssi_percent { "Signal" } else { "RSSI" },
+        "Tags",
+    ];
+    if grouped {
+        widths.insert(0, Constraint::Length(9));
+        header.insert(0, "Zone");
+    }
+    let zones = zone_counts
+        .iter()
+        .map(|(zone, count)| format!("{} {}", zone.label(), count))
+        .collect::<Vec<String>>()
+        .join(" · ");
+
+    let table = Table::new(rows, widths)
+        .header(Row::new(header).style(Style::default().fg(Color::Yellow)))
+        .block(
+            Block::default()
+                .title(format!("Detected Devices ({})", zones))
+                .borders(Borders::ALL),
+        )
+        .highlight_style(selected_style);
 
     table
 }
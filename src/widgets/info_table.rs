@@ -4,14 +4,119 @@ use ratatui::{
     widgets::{Row, Table},
 };
 
+use std::time::Instant;
+
+use crate::{
+    inventory::Inventory,
+    structs::{AddressTypeFilter, ConnectProgress, DeviceCategory, MqttStatus, NameFallback},
+};
+
 /// Creates a table with information about the application and the user input.
-pub fn info_table(signal: bool, is_loading: &bool, frame_count: &usize) -> Table<'static> {
+#[allow(clippy::too_many_arguments)]
+pub fn info_table(
+    signal: bool,
+    is_loading: &bool,
+    frame_count: &usize,
+    name_fallback: NameFallback,
+    marked_count: usize,
+    tag_filter: Option<&str>,
+    category_filter: Option<DeviceCategory>,
+    address_type_filter: AddressTypeFilter,
+    capped: Option<usize>,
+    service_filter: Option<bool>,
+    reconfiguring: bool,
+    mqtt: Option<&MqttStatus>,
+    sort: &str,
+    search: Option<&str>,
+    named_only: bool,
+    connect: Option<ConnectProgress>,
+    inventory: Option<&Inventory>,
+    ws_clients: Option<usize>,
+    idle_paused: bool,
+    replay: Option<(f64, bool)>,
+    recording: bool,
+    channel: &str,
+    pause_summary: Option<String>,
+    status: Option<&str>,
+) -> Table<'static> {
     let spinner = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
     let index = frame_count % spinner.len();
     let info_text = format!(
-        "[q → exit] [e → export csv] [up/down → navigate] [enter → open/close] {}",
-        if *is_loading {
+        "{}{}{}{}{}[q → exit] [e/J → export csv/json{}] [D → export device] [Y → copy as text] [H → export rssi history] [space → mark] [d → pin to compare] [c → clear marks] [a → alias] [A → note] [u → acknowledge rename] [f → filter: {}] [g → category: {}] [T → address type: {}] [o → set category] [l → track] [b → proximity feedback] [+/- [/] → min rssi] [S → sort: {}] [/ → search: {}] [: → command] [N → named only: {}] [C → compact] [V → vendor] [P → signal %] [L → services] [F → activity feed] [p → group by zone] [z → stripes] [r → raw ad] [t → stats] [i → id/address] [n → unnamed: {}] [up/down → navigate] [enter → open/close] [x → disconnect all] [I → inventory] [R → record] [channel: {}] {}{}{}{}",
+        replay.map_or_else(String::new, |(speed, finished)| format!(
+            "[REPLAY {}x{}] ",
+            speed,
+            if finished { ", finished" } else { "" }
+        )),
+        if recording { "[● REC] " } else { "" },
+        status.map_or_else(String::new, |status| format!("[{}] ", status)),
+        pause_summary.map_or_else(String::new, |summary| format!(
+            "[since pause: {}] ",
+            summary
+        )),
+        capped.map_or_else(String::new, |evicted| format!(
+            "[capped: {} dropped] ",
+            evicted
+        )),
+        if marked_count > 0 {
+            format!(" ({} marked)", marked_count)
+        } else {
+            String::new()
+        },
+        tag_filter.unwrap_or("all"),
+        category_filter.map_or("all", DeviceCategory::label),
+        address_type_filter.label(),
+        sort,
+        search.unwrap_or("off"),
+        if named_only { "on" } else { "off" },
+        name_fallback.label(),
+        channel,
+        service_filter.map_or_else(String::new, |enabled| format!(
+            "[v → service filter: {}] ",
+            if enabled { "on" } else { "off" }
+        )),
+        mqtt.map_or_else(String::new, |status| match status {
+            MqttStatus::Disconnected(error) => format!("[mqtt: disconnected ({})] ", error),
+            _ => format!("[mqtt: {}] ", status.label()),
+        }),
+        ws_clients.map_or_else(String::new, |clients| format!(
+            "[ws: {} client(s)] ",
+            clients
+        )),
+        if reconfiguring {
+            format!("[reconfiguring scan... {}]", spinner[index])
+        } else if let Some(inventory) = inventory {
+            format!(
+                "[inventory {}/{}{}, {} failed {}]",
+                inventory.entries.len(),
+                inventory.total,
+                inventory
+                    .current
+                    .as_ref()
+                    .map_or_else(String::new, |identity| format!(": {}", identity)),
+                inventory.failed(),
+                spinner[index]
+            )
+        } else if let Some(connect) = connect.filter(|_| *is_loading) {
+            let remaining = connect
+                .deadline
+                .saturating_duration_since(Instant::now())
+                .as_secs();
+            if connect.retrying {
+                format!(
+                    "[attempt {}/{} failed, retrying in {}s {}]",
+                    connect.attempt, connect.attempts, remaining, spinner[index]
+                )
+            } else {
+                format!(
+                    "[connecting {}/{}... {}s {}]",
+                    connect.attempt, connect.attempts, remaining, spinner[index]
+                )
+            }
+        } else if *is_loading {
             format!("[loading... {}]", spinner[index])
+        } else if idle_paused {
+            "[auto-paused (idle), press any key to resume]".to_string()
         } else if signal {
             "[s → start scan]".to_string()
         } else {
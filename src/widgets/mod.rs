@@ -1,4 +1,13 @@
+pub mod ad_structure_table;
+pub mod category_popup;
+pub mod chip_bar;
+pub mod command_line;
+pub mod compare_view;
 pub mod detail_table;
 pub mod device_table;
 pub mod info_table;
 pub mod inspect_overlay;
+pub mod rssi_gauge;
+pub mod services_overlay;
+pub mod stats_view;
+pub mod watchdog_warning;
@@ -0,0 +1,68 @@
+use ratatui::{
+    style::{Color, Style},
+    symbols,
+    widgets::LineGauge,
+};
+
+use crate::structs::ProximityFeedback;
+
+/// The weakest and strongest RSSI values the gauge spans, in dBm.
+const RSSI_RANGE: (i16, i16) = (-100, -30);
+
+/// Creates a gauge showing the minimum RSSI threshold and how many devices it hides.
+pub fn rssi_gauge(min_rssi: Option<i16>, hidden: usize) -> LineGauge<'static> {
+    let (ratio, label) = match min_rssi {
+        Some(threshold) => {
+            let clamped = threshold.clamp(RSSI_RANGE.0, RSSI_RANGE.1);
+            (
+                (clamped - RSSI_RANGE.0) as f64 / (RSSI_RANGE.1 - RSSI_RANGE.0) as f64,
+                format!("Min RSSI {} dBm ({} hidden) ", threshold, hidden),
+            )
+        }
+        None => (0.0, "Min RSSI off ".to_string()),
+    };
+
+    LineGauge::default()
+        .ratio(ratio)
+        .label(label)
+        .style(Style::default().fg(Color::DarkGray))
+        .gauge_style(Style::default().fg(Color::Yellow).bg(Color::DarkGray))
+        .line_set(symbols::line::THICK)
+}
+
+/// Creates the proximity gauge shown while the locator is locked onto a device. The gauge
+/// follows the smoothed RSSI, and its colors are inverted while a feedback pulse is active.
+pub fn proximity_gauge(
+    name: &str,
+    smoothed_rssi: Option<f64>,
+    feedback: ProximityFeedback,
+    pulse: bool,
+) -> LineGauge<'static> {
+    let (ratio, reading) = match smoothed_rssi {
+        Some(rssi) => {
+            let (weakest, strongest) = (RSSI_RANGE.0 as f64, RSSI_RANGE.1 as f64);
+            (
+                (rssi.clamp(weakest, strongest) - weakest) / (strongest - weakest),
+                format!("{:.0} dBm", rssi),
+            )
+        }
+        None => (0.0, "no signal".to_string()),
+    };
+    let (fg, bg) = if pulse {
+        (Color::DarkGray, Color::Green)
+    } else {
+        (Color::Green, Color::DarkGray)
+    };
+
+    LineGauge::default()
+        .ratio(ratio)
+        .label(format!(
+            "Tracking {} {} (feedback {}) ",
+            name,
+            reading,
+            feedback.label()
+        ))
+        .style(Style::default().fg(Color::DarkGray))
+        .gauge_style(Style::default().fg(fg).bg(bg))
+        .line_set(symbols::line::THICK)
+}
@@ -0,0 +1,51 @@
+use ratatui::{
+    layout::Constraint,
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, Row, Table},
+};
+use uuid::Uuid;
+
+use crate::utils::service_label;
+
+/// Provides an overlay listing every service of the selected device with its resolved
+/// name: the advertised ones, then those discovered over GATT after connecting. `scroll`
+/// is the number of rows scrolled past.
+pub fn services_overlay(
+    advertised: &[Uuid],
+    gatt: &[Uuid],
+    scroll: usize,
+    height: u16,
+) -> Table<'static> {
+    let mut rows: Vec<Row> = Vec::new();
+    for (title, services) in [("Advertised", advertised), ("GATT", gatt)] {
+        if services.is_empty() {
+            continue;
+        }
+        rows.push(
+            Row::new(vec![format!("{} ({})", title, services.len())])
+                .style(Style::default().add_modifier(Modifier::BOLD)),
+        );
+        for service in services {
+            let label = service_label(service);
+            rows.push(Row::new(vec![if label == service.to_string() {
+                format!("  ↳ {}", service)
+            } else {
+                format!("  ↳ {} ({})", label, service)
+            }]));
+        }
+    }
+
+    let visible_rows_count = height.saturating_sub(2) as usize;
+    let visible_rows: Vec<Row> = rows
+        .into_iter()
+        .skip(scroll)
+        .take(visible_rows_count)
+        .collect();
+
+    Table::new(visible_rows, [Constraint::Percentage(100)]).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Services")
+            .border_style(Style::default().fg(Color::Yellow)),
+    )
+}
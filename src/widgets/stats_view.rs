@@ -0,0 +1,89 @@
+use ratatui::{
+    layout::Constraint,
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Bar, BarChart, BarGroup, Block, Borders, Row, Table},
+};
+
+use crate::{structs::SessionStats, utils::format_duration};
+
+/// Creates a table summarizing the session and a bar chart of the RSSI distribution.
+pub fn stats_view(stats: &SessionStats) -> (Table<'static>, BarChart<'static>) {
+    let heading = Style::default().add_modifier(Modifier::BOLD);
+    let mut rows = vec![
+        Row::new(vec![
+            "Session Duration:".to_owned(),
+            format_duration(stats.duration),
+        ]),
+        Row::new(vec!["Unique Devices:".to_owned(), stats.total.to_string()]),
+        Row::new(vec![
+            "Advertisements:".to_owned(),
+            stats.observations.to_string(),
+        ]),
+        Row::new(vec!["Named:".to_owned(), stats.named.to_string()]),
+        Row::new(vec!["Unnamed:".to_owned(), stats.unnamed.to_string()]),
+        Row::new(vec![
+            "Exposure Notification RPIs:".to_owned(),
+            stats.exposure_rpis.to_string(),
+        ]),
+        Row::new(vec![
+            "Distinct Trackers:".to_owned(),
+            stats.trackers.to_string(),
+        ]),
+        Row::new(vec![
+            "Longest Dwell:".to_owned(),
+            format_duration(stats.longest_dwell),
+        ]),
+        Row::new(vec![
+            "Average Dwell:".to_owned(),
+            format_duration(stats.average_dwell),
+        ]),
+        Row::new(vec!["".to_owned()]),
+        Row::new(vec!["Address Types".to_owned()]).style(heading),
+    ];
+    for (address_type, count) in &stats.by_address_type {
+        rows.push(Row::new(vec![
+            format!("  {}", address_type),
+            count.to_string(),
+        ]));
+    }
+    rows.push(Row::new(vec!["".to_owned()]));
+    rows.push(Row::new(vec!["Manufacturers".to_owned()]).style(heading));
+    for (manufacturer, count) in &stats.by_manufacturer {
+        rows.push(Row::new(vec![
+            format!("  {}", manufacturer),
+            count.to_string(),
+        ]));
+    }
+
+    let table = Table::new(rows, [Constraint::Fill(1), Constraint::Length(10)]).block(
+        Block::default()
+            .title("Session Statistics")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow)),
+    );
+
+    let bars: Vec<Bar> = stats
+        .rssi_histogram
+        .iter()
+        .map(|(label, count)| {
+            Bar::default()
+                .value(*count)
+                .label(Line::from(label.to_string()))
+        })
+        .collect();
+    let chart = BarChart::default()
+        .block(
+            Block::default()
+                .title("RSSI Distribution (dBm)")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Yellow)),
+        )
+        .data(BarGroup::default().bars(&bars))
+        .bar_width(5)
+        .bar_gap(1)
+        .bar_style(Style::default().fg(Color::Green))
+        .value_style(Style::default().fg(Color::Black).bg(Color::Green));
+
+    (table, chart)
+}
@@ -0,0 +1,44 @@
+use std::time::Duration;
+
+use ratatui::{
+    layout::Alignment,
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Block, Borders, Paragraph, Wrap},
+};
+
+/// Creates the warning shown when the scan has produced no results for `elapsed`, listing
+/// the likely causes. `filters` describes the active scan filters, if any.
+pub fn watchdog_warning(elapsed: Duration, filters: &[String]) -> Paragraph<'static> {
+    let mut lines = vec![
+        Line::styled(
+            format!(
+                "No scan results for {} seconds. The scan may not be working:",
+                elapsed.as_secs()
+            ),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        Line::from(""),
+        Line::from("• Bluetooth permissions: on Linux, check that your user may use BlueZ"),
+        Line::from("  (e.g. the bluetooth group); on macOS, allow Bluetooth for the terminal."),
+        Line::from("• Adapter state: the adapter may be off, blocked by rfkill or asleep;"),
+        Line::from("  try `bluetoothctl power on`."),
+    ];
+    if !filters.is_empty() {
+        lines.push(Line::from(format!(
+            "• Filters: only devices matching {} are reported.",
+            filters.join(" and ")
+        )));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from("This clears as soon as results arrive."));
+    Paragraph::new(lines)
+        .alignment(Alignment::Left)
+        .wrap(Wrap { trim: false })
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("No devices seen")
+                .border_style(Style::default().fg(Color::Red)),
+        )
+}
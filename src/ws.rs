@@ -0,0 +1,145 @@
+use std::{
+    collections::{HashMap, HashSet},
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use futures::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::{mpsc::UnboundedSender, Notify},
+};
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::app::DeviceData;
+
+/// Streams device updates to WebSocket clients. Each client first receives a snapshot of
+/// every device, then an update message whenever a device changes and a removal message
+/// when it leaves the list. A client that reads slower than updates arrive only gets the
+/// latest state of each device: older pending updates are replaced rather than queued.
+#[derive(Clone)]
+pub struct WsHub {
+    state: Arc<Mutex<HubState>>,
+}
+
+#[derive(Default)]
+struct HubState {
+    /// The latest JSON of every device in the list, for the snapshots.
+    devices: HashMap<String, Value>,
+    clients: Vec<Arc<Client>>,
+}
+
+/// The messages waiting to be sent to one client, at most one per device.
+#[derive(Default)]
+struct Client {
+    pending: Mutex<HashMap<String, Value>>,
+    wake: Notify,
+}
+
+impl WsHub {
+    /// Accepts WebSocket clients on `listener` in the background. Clients connecting and
+    /// disconnecting are reported as `DeviceData::WsClient`.
+    pub fn start(listener: TcpListener, tx: UnboundedSender<DeviceData>) -> Self {
+        let hub = Self {
+            state: Arc::default(),
+        };
+        let server = hub.clone();
+        tokio::spawn(async move {
+            loop {
+                let Ok((stream, peer)) = listener.accept().await else {
+                    continue;
+                };
+                tokio::spawn(server.clone().serve(stream, peer, tx.clone()));
+            }
+        });
+        hub
+    }
+
+    /// Sends the current JSON of a device to every client.
+    pub fn publish_device(&self, identity: &str, device: Value) {
+        let mut state = self.state.lock().unwrap();
+        state.devices.insert(identity.to_string(), device.clone());
+        state.broadcast(identity, json!({ "type": "update", "device": device }));
+    }
+
+    /// Tells every client about the devices that are no longer in `present`.
+    pub fn remove_absent(&self, present: &HashSet<String>) {
+        let mut state = self.state.lock().unwrap();
+        let absent: Vec<String> = state
+            .devices
+            .keys()
+            .filter(|identity| !present.contains(*identity))
+            .cloned()
+            .collect();
+        for identity in absent {
+            state.devices.remove(&identity);
+            let message = json!({ "type": "removed", "address": identity });
+            state.broadcast(&identity, message);
+        }
+    }
+
+    /// Streams updates to one client until it disconnects.
+    async fn serve(self, stream: TcpStream, peer: SocketAddr, tx: UnboundedSender<DeviceData>) {
+        let Ok(mut socket) = tokio_tungstenite::accept_async(stream).await else {
+            return;
+        };
+        let client = Arc::new(Client::default());
+        // The snapshot is taken under the same lock as the registration, so no update
+        // can fall between the two.
+        let snapshot = {
+            let mut state = self.state.lock().unwrap();
+            state.clients.push(client.clone());
+            json!({ "type": "snapshot", "devices": state.devices.values().collect::<Vec<_>>() })
+        };
+        let _ = tx.send(DeviceData::WsClient {
+            peer,
+            connected: true,
+        });
+        let mut open = socket
+            .send(Message::text(snapshot.to_string()))
+            .await
+            .is_ok();
+        while open {
+            tokio::select! {
+                _ = client.wake.notified() => {
+                    let pending = std::mem::take(&mut *client.pending.lock().unwrap());
+                    for message in pending.into_values() {
+                        if socket.send(Message::text(message.to_string())).await.is_err() {
+                            open = false;
+                            break;
+                        }
+                    }
+                }
+                message = socket.next() => match message {
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => open = false,
+                    // Pings are answered by the library; anything else is ignored.
+                    Some(Ok(_)) => {}
+                }
+            }
+        }
+        self.state
+            .lock()
+            .unwrap()
+            .clients
+            .retain(|other| !Arc::ptr_eq(other, &client));
+        let _ = tx.send(DeviceData::WsClient {
+            peer,
+            connected: false,
+        });
+    }
+}
+
+impl HubState {
+    /// Queues a message about a device for every client, replacing the one still pending.
+    fn broadcast(&self, identity: &str, message: Value) {
+        for client in &self.clients {
+            client
+                .pending
+                .lock()
+                .unwrap()
+                .insert(identity.to_string(), message.clone());
+            client.wake.notify_one();
+        }
+    }
+}
@@ -0,0 +1,284 @@
+//! Snapshots of the command line's help and usage errors, and runs of the subcommands that
+//! work without an adapter by replaying `tests/fixtures/capture.jsonl`. Run with
+//! `BTLESCAN_UPDATE_SNAPSHOTS=1` to rewrite the files under `tests/cmd` after changing the
+//! options.
+
+use std::{fs, path::Path};
+
+use assert_cmd::Command;
+
+/// Compares `actual` with the snapshot `name` under `tests/cmd`, or rewrites the snapshot
+/// when `BTLESCAN_UPDATE_SNAPSHOTS` is set.
+fn assert_snapshot(name: &str, actual: &[u8]) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/cmd")
+        .join(name);
+    let actual = String::from_utf8_lossy(actual);
+    if std::env::var_os("BTLESCAN_UPDATE_SNAPSHOTS").is_some() {
+        fs::write(&path, actual.as_bytes()).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("unable to read {}: {}", path.display(), e));
+    assert_eq!(actual, expected, "{} differs", path.display());
+}
+
+/// The path of a fixture under `tests/fixtures`.
+fn fixture(name: &str) -> String {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+        .display()
+        .to_string()
+}
+
+fn btlescan(args: &[&str]) -> std::process::Output {
+    Command::cargo_bin("btlescan")
+        .unwrap()
+        .args(args)
+        .env_remove("COLUMNS")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn help() {
+    let output = btlescan(&["--help"]);
+    assert!(output.status.success());
+    assert_snapshot("help.stdout", &output.stdout);
+}
+
+#[test]
+fn scan_help() {
+    let output = btlescan(&["scan", "--help"]);
+    assert!(output.status.success());
+    assert_snapshot("scan-help.stdout", &output.stdout);
+}
+
+#[test]
+fn replay_help() {
+    let output = btlescan(&["replay", "--help"]);
+    assert!(output.status.success());
+    assert_snapshot("replay-help.stdout", &output.stdout);
+}
+
+#[test]
+fn version() {
+    let output = btlescan(&["--version"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        format!("btlescan {}\n", env!("CARGO_PKG_VERSION"))
+    );
+}
+
+#[test]
+fn conflicting_options_are_a_usage_error() {
+    let output = btlescan(&["--headless", "--oneshot"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert_snapshot("headless-oneshot.stderr", &output.stderr);
+}
+
+#[test]
+fn unknown_option_is_a_usage_error() {
+    let output = btlescan(&["--bogus"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert_snapshot("unknown-option.stderr", &output.stderr);
+}
+
+#[test]
+fn invalid_value_is_a_usage_error() {
+    let output = btlescan(&["--sort", "rssi:up"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert_snapshot("invalid-sort.stderr", &output.stderr);
+}
+
+#[test]
+fn replay_requires_a_path() {
+    let output = btlescan(&["replay"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert_snapshot("replay-without-path.stderr", &output.stderr);
+}
+
+#[test]
+fn grouped_options_require_their_main_option() {
+    for (args, missing) in [
+        (&["--mqtt-username", "user"][..], "--mqtt-url <URL>"),
+        (
+            &["--influx-url", "http://localhost:8086"][..],
+            "--influx-bucket <NAME>",
+        ),
+        (&["--log-rotate-gzip"][..], "--log-file <PATH>|--log <PATH>"),
+        (&["--watch-rssi", "-60"][..], "--watch <ADDRESS>"),
+        (
+            &["--notify", "00002a37-0000-1000-8000-00805f9b34fb"][..],
+            "--auto <ADDRESS>",
+        ),
+    ] {
+        let output = btlescan(args);
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains(missing), "{:?}: {}", args, stderr);
+    }
+}
+
+#[test]
+fn auto_rejects_an_invalid_target() {
+    let output = btlescan(&[
+        "--auto",
+        "00:00:00:00:00:00",
+        "--notify",
+        "00002a37-0000-1000-8000-00805f9b34fb",
+        "--log",
+        "hr.log",
+    ]);
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("invalid value '00:00:00:00:00:00' for '--auto <ADDRESS>'"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn unreadable_file_is_a_usage_error() {
+    let output = btlescan(&["--headless", "--oui-file", "/nonexistent/oui.txt"]);
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.starts_with("error: unable to read /nonexistent/oui.txt: "),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+#[cfg(not(feature = "mqtt"))]
+fn integration_left_out_of_the_build_is_a_usage_error() {
+    let output = btlescan(&["--headless", "--mqtt-url", "mqtt://localhost"]);
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("--mqtt-url needs btlescan built with the `mqtt` feature"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn oneshot_prints_the_replayed_devices_as_a_table() {
+    let capture = fixture("capture.jsonl");
+    let output = btlescan(&["replay", &capture, "--oneshot", "--duration", "1"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 4, "{}", stdout);
+    assert!(
+        lines[0].starts_with("Address            Name"),
+        "{}",
+        stdout
+    );
+    assert!(
+        lines[2].starts_with("AA:BB:CC:DD:EE:01  Kitchen Sensor"),
+        "{}",
+        stdout
+    );
+    assert!(
+        lines[3].starts_with("AA:BB:CC:DD:EE:02  (unnamed)"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn oneshot_json_prints_the_export() {
+    let capture = fixture("capture.jsonl");
+    let output = btlescan(&["replay", &capture, "--oneshot", "--json", "--duration", "1"]);
+    assert!(output.status.success(), "{:?}", output);
+    let export: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let addresses: Vec<&str> = export["devices"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|device| device["address"].as_str().unwrap())
+        .collect();
+    assert_eq!(addresses, ["AA:BB:CC:DD:EE:01", "AA:BB:CC:DD:EE:02"]);
+}
+
+#[test]
+fn wait_for_exits_with_0_and_prints_the_match() {
+    let capture = fixture("capture.jsonl");
+    let output = btlescan(&[
+        "wait-for",
+        "--address",
+        "aa:bb:cc:dd:ee:01",
+        "--replay",
+        &capture,
+    ]);
+    assert_eq!(output.status.code(), Some(0), "{:?}", output);
+    let device: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(device["name"], "Kitchen Sensor");
+}
+
+#[test]
+fn wait_for_exits_with_1_when_the_replay_ends_without_a_match() {
+    let capture = fixture("capture.jsonl");
+    for args in [
+        &["--name", "Kitchen Sensor", "--min-rssi", "-40"][..],
+        &["--address", "AA:BB:CC:DD:EE:03"][..],
+    ] {
+        let output = btlescan(&[&["wait-for", "--replay", &capture][..], args].concat());
+        assert_eq!(output.status.code(), Some(1), "{:?}", args);
+        assert!(output.stdout.is_empty(), "{:?}", args);
+    }
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn wait_for_exits_with_130_when_interrupted() {
+    use std::{process::Stdio, thread, time::Duration};
+
+    use assert_cmd::cargo::CommandCargoExt;
+
+    // Replayed this slowly, the second device would appear after several minutes.
+    let mut child = std::process::Command::cargo_bin("btlescan")
+        .unwrap()
+        .args(["wait-for", "--address", "AA:BB:CC:DD:EE:02", "--replay"])
+        .arg(fixture("capture.jsonl"))
+        .args(["--replay-speed", "0.0001"])
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+    thread::sleep(Duration::from_secs(1));
+    // SAFETY: kill has no memory effects; the child has not been waited on, so its id is
+    // still its own.
+    unsafe {
+        libc::kill(child.id() as libc::pid_t, libc::SIGINT);
+    }
+    assert_eq!(child.wait().unwrap().code(), Some(130));
+}
+
+#[test]
+fn invalid_scan_options_are_rejected_before_the_terminal_is_taken_over() {
+    for (args, message) in [
+        (
+            &["--min-rssi", "50"][..],
+            "error: invalid minimum RSSI 50 dBm: expected between -127 and 20\n",
+        ),
+        (
+            &["--coalesce-ms", "60000"][..],
+            "error: the coalescing window (60s) must not be longer than the expiry (30s)\n",
+        ),
+        (
+            &["--service", "00000000-0000-0000-0000-000000000000"][..],
+            "error: the nil UUID is not a service UUID\n",
+        ),
+    ] {
+        let output = btlescan(args);
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+        // The interface never started, so nothing was drawn and the screen was not cleared.
+        assert!(output.stdout.is_empty(), "{:?}", args);
+        assert_eq!(String::from_utf8_lossy(&output.stderr), message);
+    }
+}
@@ -0,0 +1,212 @@
+//! Runs `Scanner` on scripted `MockSource` batches and checks the updates it reports, and
+//! checks the configurations `ScanConfig::builder` rejects.
+
+use std::time::Duration;
+
+use btlescan::{
+    structs::IdentityKey, DeviceInfo, DeviceUpdate, MockSource, ScanConfig, ScanConfigError,
+    Scanner,
+};
+use futures::StreamExt;
+use uuid::Uuid;
+
+const HEART_RATE: Uuid = Uuid::from_u128(0x0000180d_0000_1000_8000_00805f9b34fb);
+
+fn device(address: &str, rssi: i16) -> DeviceInfo {
+    DeviceInfo {
+        id: format!("hci0/dev_{}", address.replace(':', "_")),
+        address: address.to_string(),
+        rssi: Some(rssi),
+        ..DeviceInfo::default()
+    }
+}
+
+/// Describes an update as `<kind> <identity>`.
+fn describe(update: &DeviceUpdate) -> String {
+    match update {
+        DeviceUpdate::Discovered(device) => format!("discovered {}", device.address),
+        DeviceUpdate::Updated(device) => format!("updated {}", device.address),
+        DeviceUpdate::Lost(identity) => format!("lost {}", identity),
+        DeviceUpdate::Failed(e) => format!("failed {}", e),
+    }
+}
+
+/// Collects every update the scanner reports until `source` runs out.
+async fn updates(config: ScanConfig, source: MockSource) -> Vec<String> {
+    let scanner = Scanner::with_source(config, move || source.clone());
+    scanner
+        .events()
+        .await
+        .map(|update| describe(&update))
+        .collect()
+        .await
+}
+
+#[tokio::test]
+async fn repeated_advertisements_are_dropped() {
+    let lamp = device("AA:BB:CC:DD:EE:01", -50);
+    let closer = DeviceInfo {
+        rssi: Some(-45),
+        ..lamp.clone()
+    };
+    let source = MockSource::new()
+        .batch(Duration::ZERO, vec![lamp.clone(), lamp.clone()])
+        .batch(Duration::ZERO, vec![closer]);
+    assert_eq!(
+        updates(ScanConfig::default(), source).await,
+        ["discovered AA:BB:CC:DD:EE:01", "updated AA:BB:CC:DD:EE:01"]
+    );
+}
+
+#[tokio::test]
+async fn repeated_advertisements_are_kept_without_dedupe() {
+    let lamp = device("AA:BB:CC:DD:EE:01", -50);
+    let source = MockSource::new().batch(Duration::ZERO, vec![lamp.clone(), lamp]);
+    let config = ScanConfig::builder().dedupe(false).build().unwrap();
+    assert_eq!(
+        updates(config, source).await,
+        ["discovered AA:BB:CC:DD:EE:01", "updated AA:BB:CC:DD:EE:01"]
+    );
+}
+
+#[tokio::test]
+async fn weak_and_unmatched_devices_are_filtered_out() {
+    let strong = DeviceInfo {
+        services: vec![HEART_RATE],
+        ..device("AA:BB:CC:DD:EE:01", -60)
+    };
+    let weak = DeviceInfo {
+        services: vec![HEART_RATE],
+        ..device("AA:BB:CC:DD:EE:02", -80)
+    };
+    let unmatched = device("AA:BB:CC:DD:EE:03", -40);
+    let source = MockSource::new().batch(Duration::ZERO, vec![strong, weak, unmatched]);
+    let config = ScanConfig::builder()
+        .min_rssi(-70)
+        .services([HEART_RATE])
+        .build()
+        .unwrap();
+    assert_eq!(
+        updates(config, source).await,
+        ["discovered AA:BB:CC:DD:EE:01"]
+    );
+}
+
+#[tokio::test]
+async fn address_key_leaves_out_hidden_addresses() {
+    let hidden = DeviceInfo {
+        id: "3F2A-91C0".to_string(),
+        ..device("00:00:00:00:00:00", -50)
+    };
+    let visible = device("AA:BB:CC:DD:EE:01", -50);
+    let source = MockSource::new().batch(Duration::ZERO, vec![hidden, visible]);
+    let config = ScanConfig::builder()
+        .identity(IdentityKey::Address)
+        .build()
+        .unwrap();
+    assert_eq!(
+        updates(config, source).await,
+        ["discovered AA:BB:CC:DD:EE:01"]
+    );
+}
+
+#[tokio::test]
+async fn id_key_tells_apart_the_adapters_hearing_a_device() {
+    let first = device("AA:BB:CC:DD:EE:01", -50);
+    let second = DeviceInfo {
+        id: "hci1/dev_AA_BB_CC_DD_EE_01".to_string(),
+        ..first.clone()
+    };
+    let source = MockSource::new().batch(Duration::ZERO, vec![first.clone(), second.clone()]);
+    let config = ScanConfig::builder()
+        .identity(IdentityKey::Id)
+        .build()
+        .unwrap();
+    assert_eq!(
+        updates(config, source).await,
+        [
+            "discovered AA:BB:CC:DD:EE:01",
+            "discovered AA:BB:CC:DD:EE:01"
+        ]
+    );
+
+    let source = MockSource::new().batch(Duration::ZERO, vec![first, second]);
+    assert_eq!(
+        updates(ScanConfig::default(), source).await,
+        ["discovered AA:BB:CC:DD:EE:01"]
+    );
+}
+
+#[tokio::test]
+async fn silent_devices_are_lost_after_the_expiry() {
+    let lamp = device("AA:BB:CC:DD:EE:01", -50);
+    let kettle = device("AA:BB:CC:DD:EE:02", -60);
+    let source = MockSource::new()
+        .batch(Duration::ZERO, vec![lamp])
+        .batch(Duration::from_millis(2500), vec![kettle]);
+    let config = ScanConfig::builder()
+        .expiry(Duration::from_secs(1))
+        .window(Duration::ZERO)
+        .build()
+        .unwrap();
+    assert_eq!(
+        updates(config, source).await,
+        [
+            "discovered AA:BB:CC:DD:EE:01",
+            "lost AA:BB:CC:DD:EE:01",
+            "discovered AA:BB:CC:DD:EE:02"
+        ]
+    );
+}
+
+#[test]
+fn invalid_configurations_are_rejected() {
+    let second = Duration::from_secs(1);
+    let day = Duration::from_secs(24 * 60 * 60);
+    for (builder, error) in [
+        (
+            ScanConfig::builder().expiry(second / 2),
+            ScanConfigError::Expiry(second / 2),
+        ),
+        (
+            ScanConfig::builder().expiry(day + second),
+            ScanConfigError::Expiry(day + second),
+        ),
+        (
+            ScanConfig::builder().expiry(second * 2).window(second * 3),
+            ScanConfigError::WindowExceedsExpiry {
+                window: second * 3,
+                expiry: second * 2,
+            },
+        ),
+        (
+            ScanConfig::builder().services([HEART_RATE, Uuid::nil()]),
+            ScanConfigError::NilService,
+        ),
+        (
+            ScanConfig::builder().min_rssi(-128),
+            ScanConfigError::MinRssi(-128),
+        ),
+        (
+            ScanConfig::builder().min_rssi(21),
+            ScanConfigError::MinRssi(21),
+        ),
+    ] {
+        assert_eq!(builder.build().unwrap_err(), error);
+    }
+}
+
+#[test]
+fn limits_of_the_valid_ranges_are_accepted() {
+    for builder in [
+        ScanConfig::builder()
+            .expiry(Duration::from_secs(1))
+            .window(Duration::from_secs(1)),
+        ScanConfig::builder().expiry(Duration::from_secs(24 * 60 * 60)),
+        ScanConfig::builder().min_rssi(-127),
+        ScanConfig::builder().min_rssi(20),
+        ScanConfig::builder().services([HEART_RATE]),
+    ] {
+        builder.build().unwrap();
+    }
+}
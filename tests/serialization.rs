@@ -0,0 +1,114 @@
+//! Pins the canonical serialized form of `DeviceInfo` to `tests/fixtures/device.json`. Run
+//! with `BTLESCAN_UPDATE_SNAPSHOTS=1` to rewrite the fixture after a deliberate change; a
+//! change that older files cannot be read with needs `FORMAT_VERSION` raised as well.
+
+#![cfg(feature = "device-serde")]
+
+use std::{collections::HashMap, fs, path::Path};
+
+use btleplug::api::AddressType;
+use btlescan::{
+    serialization::{Envelope, FORMAT_VERSION},
+    DeviceInfo,
+};
+use chrono::{DateTime, Local, Utc};
+use uuid::Uuid;
+
+const HEART_RATE: Uuid = Uuid::from_u128(0x0000180d_0000_1000_8000_00805f9b34fb);
+
+fn fixture_path() -> std::path::PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/device.json")
+}
+
+/// A local time `secs` seconds after 2024-05-01T12:00:00Z.
+fn at(secs: i64) -> DateTime<Local> {
+    (DateTime::parse_from_rfc3339("2024-05-01T12:00:00Z").unwrap()
+        + chrono::Duration::seconds(secs))
+    .with_timezone(&Local)
+}
+
+fn band() -> DeviceInfo {
+    DeviceInfo {
+        id: "hci0/dev_AA_BB_CC_DD_EE_01".to_string(),
+        name: Some("Band".to_string()),
+        tx_power: Some(-4),
+        address: "AA:BB:CC:DD:EE:01".to_string(),
+        address_type: Some(AddressType::Random),
+        rssi: Some(-58),
+        manufacturer_data: HashMap::from([(0x0075, vec![0x42, 0x04]), (0x004c, vec![0x10])]),
+        services: vec![HEART_RATE],
+        solicited_services: Vec::new(),
+        detected_at: "2024-05-01T12:00:00+00:00".to_string(),
+        first_seen: at(0),
+        last_seen: at(95),
+        service_data: HashMap::from([(HEART_RATE, vec![0x06, 0x48])]),
+        class: Some(0x240404),
+        appearance: Some(0x00C1),
+        raw_advertisement: vec![0x02, 0x01, 0x06],
+        raw_reconstructed: true,
+        is_connectable: Some(true),
+        ..DeviceInfo::default()
+    }
+}
+
+/// Rewrites the timestamps of a serialized device in UTC, so the fixture does not depend
+/// on the time zone.
+fn in_utc(mut envelope: serde_json::Value) -> serde_json::Value {
+    for field in ["first_seen", "last_seen"] {
+        let timestamp = &mut envelope["data"][field];
+        let utc = DateTime::parse_from_rfc3339(timestamp.as_str().unwrap())
+            .unwrap()
+            .with_timezone(&Utc)
+            .to_rfc3339();
+        *timestamp = utc.into();
+    }
+    envelope
+}
+
+#[test]
+fn device_serializes_to_the_golden_file() {
+    let envelope = serde_json::to_value(Envelope::new("device", band())).unwrap();
+    let actual = serde_json::to_string_pretty(&in_utc(envelope)).unwrap() + "\n";
+    if std::env::var_os("BTLESCAN_UPDATE_SNAPSHOTS").is_some() {
+        fs::write(fixture_path(), &actual).unwrap();
+        return;
+    }
+    assert_eq!(actual, fs::read_to_string(fixture_path()).unwrap());
+}
+
+#[test]
+fn golden_file_reads_back() {
+    let envelope: Envelope<DeviceInfo> =
+        serde_json::from_str(&fs::read_to_string(fixture_path()).unwrap()).unwrap();
+    assert_eq!(envelope.kind, "device");
+    assert_eq!(envelope.version, FORMAT_VERSION);
+    let (read, expected) = (envelope.data, band());
+    assert_eq!(read.id, expected.id);
+    assert_eq!(read.name, expected.name);
+    assert_eq!(read.tx_power, expected.tx_power);
+    assert_eq!(read.address, expected.address);
+    assert_eq!(read.address_type, expected.address_type);
+    assert_eq!(read.rssi, expected.rssi);
+    assert_eq!(read.manufacturer_data, expected.manufacturer_data);
+    assert_eq!(read.services, expected.services);
+    assert_eq!(read.detected_at, expected.detected_at);
+    assert_eq!(read.first_seen, expected.first_seen);
+    assert_eq!(read.last_seen, expected.last_seen);
+    assert_eq!(read.service_data, expected.service_data);
+    assert_eq!(read.class, expected.class);
+    assert_eq!(read.appearance, expected.appearance);
+    assert_eq!(read.raw_advertisement, expected.raw_advertisement);
+    assert_eq!(read.raw_reconstructed, expected.raw_reconstructed);
+    assert_eq!(read.is_connectable, expected.is_connectable);
+}
+
+#[test]
+fn missing_fields_take_their_defaults() {
+    let envelope: Envelope<DeviceInfo> = serde_json::from_str(
+        r#"{"kind": "device", "version": 1, "data": {"address": "AA:BB:CC:DD:EE:01"}}"#,
+    )
+    .unwrap();
+    assert_eq!(envelope.data.address, "AA:BB:CC:DD:EE:01");
+    assert!(envelope.data.name.is_none());
+    assert!(envelope.data.services.is_empty());
+}